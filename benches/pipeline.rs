@@ -0,0 +1,99 @@
+// Benchmarks for the stages of checking a document: tokenizing, filtering, querying the
+// checker and applying edits.
+// Run with `cargo bench`, optionally followed by `-- <filter>` to run only the benchmarks
+// whose name matches the filter.
+use criterion::{criterion_group, criterion_main, Criterion};
+use spelgud::bench::{Backend, File, Filter, FilterOptions, Options, Program};
+
+// A chapter of a novel.
+const CHAPTER: &str = include_str!("corpus/chapter.txt");
+// A large Markdown document.
+const README: &str = include_str!("../README.md");
+// Source code, of which only comments and strings are checked.
+const SOURCE: &str = include_str!("../src/spell/builtin.rs");
+
+fn pipeline(c: &mut Criterion) {
+    // Repeat the README so it is as large as a long document, e.g. a book chapter.
+    let readme = README.repeat(10);
+    let corpora = [
+        ("chapter", CHAPTER, Filter::Text),
+        ("readme", readme.as_str(), Filter::Markdown),
+        ("source", SOURCE, source_filter()),
+    ];
+
+    for (name, text, _) in &corpora {
+        c.bench_function(&format!("tokenize/{name}"), |b| {
+            b.iter(|| {
+                for line in text.lines() {
+                    std::hint::black_box(spelgud::bench::words(line));
+                }
+            })
+        });
+    }
+
+    for (name, text, filter) in &corpora {
+        let file = File::new(text.to_string(), filter.clone()).unwrap();
+        c.bench_function(&format!("filter/{name}"), |b| {
+            b.iter(|| file.checked_text(&FilterOptions::default()))
+        });
+    }
+
+    for program in [Program::Aspell, Program::Builtin] {
+        let prog = format!("{program:?}").to_lowercase();
+        for (cache, known_words) in [("cold", 0), ("warm", Options::default().known_words)] {
+            let mut opts = Options {
+                known_words,
+                ..Default::default()
+            };
+            opts.codespell.enabled = false;
+            let mut backend = match Backend::new(std::slice::from_ref(&program), &[], &opts) {
+                Ok(backend) if !backend.status().is_empty() => backend,
+                _ => {
+                    println!("check/{prog}: skipped, {prog} is not available");
+                    break;
+                }
+            };
+            for (name, text, filter) in &corpora {
+                let mut file = File::new(text.to_string(), filter.clone()).unwrap();
+                let lines = file.changed_lines(&FilterOptions::default()).unwrap();
+                let lines: Vec<_> = lines.iter().map(|(l, c, t)| (*l, *c, t.as_str())).collect();
+                c.bench_function(&format!("check/{prog}/{cache}/{name}"), |b| {
+                    b.iter(|| backend.diags_at(lines.iter().copied()).unwrap())
+                });
+            }
+        }
+    }
+
+    for (name, text, filter) in &corpora {
+        let mut file = File::new(text.to_string(), filter.clone()).unwrap();
+        let lines = u32::try_from(text.lines().count()).unwrap();
+        let mut n = 0;
+        // Type a character on a different line each time, as when editing throughout a file,
+        // and find the lines to check again.
+        let opts = FilterOptions::default();
+        let checked = file.changed_lines(&opts).unwrap();
+        file.update(&checked, vec![]);
+        c.bench_function(&format!("edit/{name}"), |b| {
+            b.iter(|| {
+                n = (n + 7919) % lines;
+                let pos = lsp_types::Position::new(n, 0);
+                let change = lsp_types::TextDocumentContentChangeEvent {
+                    range: Some(lsp_types::Range::new(pos, pos)),
+                    range_length: None,
+                    text: "x".into(),
+                };
+                file.edit(vec![change]).unwrap();
+                let checked = file.changed_lines(&opts).unwrap();
+                file.update(&checked, vec![]);
+            })
+        });
+    }
+}
+
+fn source_filter() -> Filter {
+    let uri = lsp_types::Url::parse("file:///builtin.rs").unwrap();
+    Filter::detect("rust", &uri, &FilterOptions::default())
+}
+
+criterion_group!(benches, pipeline);
+criterion_main!(benches);
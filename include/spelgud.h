@@ -0,0 +1,80 @@
+/*
+ * The spelgud checker as a C library, built by `cargo build --release` as
+ * libspelgud.so (libspelgud.dylib on macOS, spelgud.dll on Windows).
+ *
+ *     spelgud_results *results = spelgud_check("# Helo\n", "markdown");
+ *     if (results->error) {
+ *         fprintf(stderr, "%s\n", results->error);
+ *     }
+ *     for (size_t i = 0; i < results->count; i++) {
+ *         spelgud_misspelling *m = &results->misspellings[i];
+ *         printf("%zu: %s\n", m->line, m->word);
+ *     }
+ *     spelgud_results_free(results);
+ *
+ * All strings are UTF-8.
+ */
+#ifndef SPELGUD_H
+#define SPELGUD_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct {
+    char *word;
+    /* The zero-based line the word is on. */
+    size_t line;
+    /* The byte range of the word in the checked text. */
+    size_t start;
+    size_t end;
+    /* Suggested corrections, best first. */
+    char **suggestions;
+    size_t suggestion_count;
+} spelgud_misspelling;
+
+typedef struct {
+    spelgud_misspelling *misspellings;
+    size_t count;
+    /* Why checking failed, or NULL if it didn't. */
+    char *error;
+} spelgud_results;
+
+/*
+ * Check text in the language with the given id, as sent by editors, e.g. "markdown"
+ * or "rust", so only its prose is checked. If lang is NULL the text is checked as
+ * plain text. The checker is started on the first call and reused by later ones.
+ * The results must be freed with spelgud_results_free.
+ */
+spelgud_results *spelgud_check(const char *text, const char *lang);
+
+/*
+ * The words in text that would be checked by spelgud_check, whether or not they are
+ * spelled correctly, with no suggestions. The results must be freed with
+ * spelgud_results_free.
+ */
+spelgud_results *spelgud_tokenize(const char *text, const char *lang);
+
+void spelgud_results_free(spelgud_results *results);
+
+typedef struct {
+    char **suggestions;
+    size_t count;
+    char *error;
+} spelgud_suggestions;
+
+/*
+ * Suggest corrections for a word, best first. The suggestions must be freed with
+ * spelgud_suggestions_free.
+ */
+spelgud_suggestions *spelgud_suggest(const char *word);
+
+void spelgud_suggestions_free(spelgud_suggestions *suggestions);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,15 @@
+#!/usr/bin/env python3
+# Front-code a hunspell word list for embedding in spelgud: each word is written as the number
+# of leading characters it shares with the previous word, followed by the rest of the word.
+# Usage: scripts/compress-dictionary.py < en_US.dic > src/spell/en_US.dic.fc
+import sys
+
+lines = sys.stdin.read().splitlines()
+print(lines[0])
+prev = ""
+for word in sorted(lines[1:]):
+    n = 0
+    while n < min(len(word), len(prev), 9) and word[n] == prev[n]:
+        n += 1
+    print(f"{n}{word[n:]}")
+    prev = word
@@ -0,0 +1,557 @@
+// Checking files from the command line, outside of an editor, e.g. in scripts and CI.
+use crate::workspace::{self, Workspace};
+use crate::{git, Config, Result};
+use lsp_types::Url;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+// How errors are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    // `file:line:column: error (fixes)`, for people and editors.
+    Text,
+    // A JSON object for each error on its own line, for other tools.
+    Json,
+    // A SARIF log, for GitHub code scanning and other tools that read SARIF.
+    Sarif,
+    // Workflow commands that annotate the errors when run in GitHub Actions.
+    Github,
+}
+
+// The names of the formats, as given to --format.
+pub const FORMATS: &[&str] = &["text", "json", "sarif", "github"];
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Format, String> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "sarif" => Ok(Format::Sarif),
+            "github" => Ok(Format::Github),
+            _ => Err(format!(
+                "Unknown format {s}, expected text, json, sarif or github"
+            )),
+        }
+    }
+}
+
+// How to run the check subcommand.
+pub struct Options {
+    pub format: Format,
+    // The most errors there can be for the check to pass.
+    pub max_errors: usize,
+    // Print nothing, only reporting whether the check passed through the exit code.
+    pub quiet: bool,
+    // Check the copies of files staged for the next commit, rather than those on disk.
+    pub staged: bool,
+    // Only report errors in lines added or changed since this git revision.
+    pub since: Option<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            format: Format::Text,
+            max_errors: 0,
+            quiet: false,
+            staged: false,
+            since: None,
+        }
+    }
+}
+
+// An error as printed in the JSON format.
+#[derive(serde::Serialize)]
+struct Record<'a> {
+    path: &'a str,
+    // Zero-based, as in LSP.
+    range: lsp_types::Range,
+    word: &'a str,
+    suggestions: &'a [String],
+}
+
+// An error found in a file.
+pub struct Problem {
+    // The path of the file, as given or found under a directory that was given.
+    pub path: String,
+    pub diagnostic: lsp_types::Diagnostic,
+    pub fixes: Vec<String>,
+}
+
+// A workspace for checking files from the command line, rooted in the current directory.
+pub fn workspace() -> Result<Workspace> {
+    let root = std::fs::canonicalize(std::env::current_dir()?)?;
+    let mut workspace = Workspace::new(Config::load(Some(&root), None)?)?;
+    workspace.set_root(&root);
+    Ok(workspace)
+}
+
+// Check the given files, and the files the scan would check in the given directories,
+// returning the errors found in each file in order.
+pub fn check(paths: &[PathBuf], opts: &Options) -> Result<Vec<Problem>> {
+    let mut files = match opts.staged {
+        true => staged(paths)?,
+        false => read(paths)?,
+    };
+    let Some(base) = &opts.since else {
+        return check_files(&mut workspace()?, files);
+    };
+    // Only check the changed files, and only report errors in their changed lines.
+    let changed = git::changed_lines(base, opts.staged, paths)?;
+    files.retain(|(path, _)| {
+        let path = path.strip_prefix(".").unwrap_or(path);
+        changed.contains_key(path)
+    });
+    let mut problems = check_files(&mut workspace()?, files)?;
+    problems.retain(|problem| {
+        let line = problem.diagnostic.range.start.line;
+        git::is_changed(&changed, std::path::Path::new(&problem.path), line)
+    });
+    Ok(problems)
+}
+
+// As `check`, but loading the files on disk into the given workspace.
+pub fn check_with(workspace: &mut Workspace, paths: &[PathBuf]) -> Result<Vec<Problem>> {
+    check_files(workspace, read(paths)?)
+}
+
+// The path given to check the text read from stdin.
+pub const STDIN: &str = "-";
+
+// The given files, and the files the scan would check in the given directories,
+// with their text.
+fn read(paths: &[PathBuf]) -> Result<Vec<(PathBuf, String)>> {
+    let scan = crate::scan::Options::default();
+    let mut files = vec![];
+    for path in paths {
+        match path.is_dir() {
+            true => files.extend(crate::scan::files(path, &scan)),
+            false => files.push(path.clone()),
+        }
+    }
+    files
+        .into_iter()
+        .map(|path| {
+            if path == Path::new(STDIN) {
+                let mut text = String::new();
+                std::io::stdin().read_to_string(&mut text)?;
+                return Ok((path, text));
+            }
+            let text = std::fs::read_to_string(&path)
+                .map_err(|err| format!("Cannot read {}: {err}", path.display()))?;
+            Ok((path, text))
+        })
+        .collect()
+}
+
+// The files staged for the next commit that are under the given paths and that the scan
+// would check, with their staged text. Unstaged changes are left out, so e.g. a pre-commit
+// hook checks exactly what is being committed.
+fn staged(paths: &[PathBuf]) -> Result<Vec<(PathBuf, String)>> {
+    let root = std::env::current_dir()?;
+    let scan = crate::scan::Options::default();
+    let mut args = vec![
+        "diff",
+        "--cached",
+        "--name-only",
+        "--relative",
+        "-z",
+        // Deleted files have nothing to check.
+        "--diff-filter=d",
+        "--",
+    ];
+    for path in paths {
+        args.push(path.to_str().ok_or(format!("Invalid path {path:?}"))?);
+    }
+    git::run(&args)?
+        .split('\0')
+        .filter(|name| !name.is_empty())
+        .map(PathBuf::from)
+        .filter(|path| crate::scan::matches(&root, &root.join(path), &scan))
+        .map(|path| {
+            let text = git::run(&["show", &format!(":./{}", path.display())])?;
+            Ok((path, text))
+        })
+        .collect()
+}
+
+// Check the given files, whose text may differ from that on disk.
+fn check_files(workspace: &mut Workspace, files: Vec<(PathBuf, String)>) -> Result<Vec<Problem>> {
+    let mut opened = vec![];
+    for (path, text) in files {
+        if path == Path::new(STDIN) {
+            // Checked as plain text, as it has no file name to tell its language by.
+            let uri = Url::parse("untitled:stdin")?;
+            workspace.open(uri.clone(), "", text)?;
+            opened.push(("<stdin>".into(), uri));
+            continue;
+        }
+        let absolute = match std::fs::canonicalize(&path) {
+            Ok(path) => path,
+            // Staged files may have been deleted since.
+            Err(_) => std::path::absolute(&path)?,
+        };
+        let uri =
+            Url::from_file_path(absolute).map_err(|_| format!("Not a file: {}", path.display()))?;
+        workspace.open(uri.clone(), "", text)?;
+        opened.push((path, uri));
+    }
+    problems(workspace, opened)
+}
+
+// Check the messages of the commits in the given git range, e.g. "main..HEAD",
+// returning the errors found in each, whose path is the commit's abbreviated hash.
+pub fn commits(range: &str) -> Result<Vec<Problem>> {
+    let mut workspace = workspace()?;
+    let mut opened = vec![];
+    for (hash, message) in git::commits(range)? {
+        let uri = Url::parse(&format!("untitled:commit-{hash}"))?;
+        workspace.open(uri.clone(), "git-commit", message)?;
+        opened.push((PathBuf::from(hash), uri));
+    }
+    problems(&mut workspace, opened)
+}
+
+// Wait for the given opened files to be checked, and return the errors found in each.
+fn problems(workspace: &mut Workspace, opened: Vec<(PathBuf, Url)>) -> Result<Vec<Problem>> {
+    workspace.check_all()?;
+
+    let mut problems = vec![];
+    for (path, uri) in opened {
+        for diagnostic in workspace.diagnostics(&uri) {
+            problems.push(Problem {
+                path: path
+                    .strip_prefix(".")
+                    .unwrap_or(&path)
+                    .display()
+                    .to_string(),
+                fixes: workspace.fixes(&uri, &diagnostic)?,
+                diagnostic,
+            });
+        }
+    }
+    Ok(problems)
+}
+
+// Print the errors in the given format.
+pub fn write(out: &mut impl Write, problems: &[Problem], format: Format) -> Result<()> {
+    match format {
+        Format::Text => {
+            for problem in problems {
+                let start = problem.diagnostic.range.start;
+                let fixes = match problem.fixes.is_empty() {
+                    true => String::new(),
+                    false => format!(" ({})", problem.fixes.join(", ")),
+                };
+                writeln!(
+                    out,
+                    "{}:{}:{}: {}{fixes}",
+                    problem.path,
+                    start.line + 1,
+                    start.character + 1,
+                    problem.diagnostic.message
+                )?;
+            }
+        }
+        Format::Json => {
+            for problem in problems {
+                let record = Record {
+                    path: &problem.path,
+                    range: problem.diagnostic.range,
+                    word: &problem.diagnostic.message,
+                    suggestions: &problem.fixes,
+                };
+                writeln!(out, "{}", serde_json::to_string(&record)?)?;
+            }
+        }
+        Format::Sarif => writeln!(out, "{}", serde_json::to_string_pretty(&sarif(problems))?)?,
+        Format::Github => {
+            for problem in problems {
+                writeln!(out, "{}", github(problem))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// A GitHub Actions workflow command annotating the error, e.g.
+// `::error file=README.md,line=1,col=6,endLine=1,endColumn=9,title=Misspelled word::...`.
+fn github(problem: &Problem) -> String {
+    let diag = &problem.diagnostic;
+    let level = match diag.severity {
+        Some(lsp_types::DiagnosticSeverity::WARNING) => "warning",
+        Some(lsp_types::DiagnosticSeverity::INFORMATION)
+        | Some(lsp_types::DiagnosticSeverity::HINT) => "notice",
+        _ => "error",
+    };
+    let (title, mut message) = match workspace::is_spelling(diag) {
+        true => ("Misspelled word", format!("{} is misspelled", diag.message)),
+        false => ("Spelling error", diag.message.clone()),
+    };
+    if !problem.fixes.is_empty() {
+        message.push_str(&format!(". Did you mean {}?", problem.fixes.join(", ")));
+    }
+    // Values are escaped so they can't be mistaken for the command's syntax.
+    let escape = |s: &str| {
+        s.replace('%', "%25")
+            .replace('\r', "%0D")
+            .replace('\n', "%0A")
+    };
+    let property = |s: &str| escape(s).replace(':', "%3A").replace(',', "%2C");
+    format!(
+        "::{level} file={},line={},col={},endLine={},endColumn={},title={}::{}",
+        property(&problem.path),
+        diag.range.start.line + 1,
+        diag.range.start.character + 1,
+        diag.range.end.line + 1,
+        diag.range.end.character + 1,
+        property(title),
+        escape(&message),
+    )
+}
+
+// A SARIF log of the errors. Each misspelled word has its own rule, so e.g. code scanning
+// alerts for a word that is spelled correctly can be dismissed together.
+fn sarif(problems: &[Problem]) -> serde_json::Value {
+    let mut rules: Vec<(String, String)> = problems
+        .iter()
+        .map(|problem| rule(&problem.diagnostic))
+        .collect();
+    rules.sort();
+    rules.dedup();
+    let results: Vec<serde_json::Value> = problems
+        .iter()
+        .map(|problem| {
+            let diag = &problem.diagnostic;
+            let (id, _) = rule(diag);
+            let original = workspace::original(diag);
+            let artifact = serde_json::json!({"uri": problem.path});
+            let region = serde_json::json!({
+                "startLine": diag.range.start.line + 1,
+                "startColumn": diag.range.start.character + 1,
+                "endLine": diag.range.end.line + 1,
+                "endColumn": diag.range.end.character + 1,
+            });
+            let fixes: Vec<serde_json::Value> = problem
+                .fixes
+                .iter()
+                .map(|fix| {
+                    serde_json::json!({
+                        "description": {"text": format!("Change {original} to {fix}")},
+                        "artifactChanges": [{
+                            "artifactLocation": artifact,
+                            "replacements": [{
+                                "deletedRegion": region,
+                                "insertedContent": {"text": fix},
+                            }],
+                        }],
+                    })
+                })
+                .collect();
+            let message = match workspace::is_spelling(diag) {
+                true => format!("{} is misspelled", diag.message),
+                false => diag.message.clone(),
+            };
+            serde_json::json!({
+                "ruleId": id,
+                "ruleIndex": rules.iter().position(|(rule, _)| *rule == id),
+                "level": match diag.severity {
+                    Some(lsp_types::DiagnosticSeverity::WARNING) => "warning",
+                    Some(lsp_types::DiagnosticSeverity::INFORMATION)
+                    | Some(lsp_types::DiagnosticSeverity::HINT) => "note",
+                    _ => "error",
+                },
+                "message": {"text": message},
+                "locations": [{
+                    "physicalLocation": {"artifactLocation": artifact, "region": region},
+                }],
+                "fixes": fixes,
+            })
+        })
+        .collect();
+    let rules: Vec<serde_json::Value> = rules
+        .into_iter()
+        .map(|(id, description)| {
+            serde_json::json!({
+                "id": id,
+                "shortDescription": {"text": description},
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "spelgud",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                },
+            },
+            // Columns count characters, not UTF-16 code units as SARIF assumes by default.
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }],
+    })
+}
+
+// The id and description of the SARIF rule for a diagnostic.
+fn rule(diag: &lsp_types::Diagnostic) -> (String, String) {
+    if workspace::is_spelling(diag) {
+        let word = &diag.message;
+        return (
+            format!("spelling/{word}"),
+            format!("Misspelled word: {word}"),
+        );
+    }
+    match &diag.code {
+        Some(lsp_types::NumberOrString::String(code)) => (code.clone(), code.clone()),
+        Some(lsp_types::NumberOrString::Number(code)) => (code.to_string(), code.to_string()),
+        None => ("spelgud".into(), "Spelling error".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn problems() -> Vec<Problem> {
+        let range = |line, start, end| {
+            lsp_types::Range::new(
+                lsp_types::Position::new(line, start),
+                lsp_types::Position::new(line, end),
+            )
+        };
+        vec![
+            Problem {
+                path: "docs/guide.md".into(),
+                diagnostic: lsp_types::Diagnostic {
+                    range: range(0, 5, 8),
+                    message: "duz".into(),
+                    ..Default::default()
+                },
+                fixes: vec!["does".into(), "due".into()],
+            },
+            Problem {
+                path: "notes.txt".into(),
+                diagnostic: lsp_types::Diagnostic {
+                    range: range(2, 0, 4),
+                    message: "Thiss".into(),
+                    ..Default::default()
+                },
+                fixes: vec![],
+            },
+        ]
+    }
+
+    fn written(format: Format) -> String {
+        let mut out = vec![];
+        write(&mut out, &problems(), format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write_text() {
+        assert_eq!(
+            written(Format::Text),
+            "docs/guide.md:1:6: duz (does, due)\nnotes.txt:3:1: Thiss\n"
+        );
+    }
+
+    #[test]
+    fn test_write_github() {
+        assert_eq!(
+            written(Format::Github),
+            "::error file=docs/guide.md,line=1,col=6,endLine=1,endColumn=9,title=Misspelled word\
+             ::duz is misspelled. Did you mean does, due?\n\
+             ::error file=notes.txt,line=3,col=1,endLine=3,endColumn=5,title=Misspelled word\
+             ::Thiss is misspelled\n"
+        );
+
+        let problem = Problem {
+            path: "a,b:c.md".into(),
+            diagnostic: lsp_types::Diagnostic {
+                message: "100% wrong\nreally".into(),
+                code: Some(lsp_types::NumberOrString::String("RULE".into())),
+                severity: Some(lsp_types::DiagnosticSeverity::WARNING),
+                ..Default::default()
+            },
+            fixes: vec![],
+        };
+        assert_eq!(
+            github(&problem),
+            "::warning file=a%2Cb%3Ac.md,line=1,col=1,endLine=1,endColumn=1,title=Spelling error\
+             ::100%25 wrong%0Areally"
+        );
+    }
+
+    #[test]
+    fn test_write_sarif() {
+        let log: serde_json::Value = serde_json::from_str(&written(Format::Sarif)).unwrap();
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        assert_eq!(
+            run["tool"]["driver"]["rules"],
+            serde_json::json!([
+                {"id": "spelling/Thiss", "shortDescription": {"text": "Misspelled word: Thiss"}},
+                {"id": "spelling/duz", "shortDescription": {"text": "Misspelled word: duz"}},
+            ])
+        );
+        let result = &run["results"][0];
+        assert_eq!(result["ruleId"], "spelling/duz");
+        assert_eq!(result["ruleIndex"], 1);
+        assert_eq!(result["level"], "error");
+        assert_eq!(result["message"]["text"], "duz is misspelled");
+        let region = serde_json::json!({
+            "startLine": 1,
+            "startColumn": 6,
+            "endLine": 1,
+            "endColumn": 9,
+        });
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "docs/guide.md");
+        assert_eq!(location["region"], region);
+        let fixes = result["fixes"].as_array().unwrap();
+        assert_eq!(fixes.len(), 2);
+        assert_eq!(fixes[0]["description"]["text"], "Change duz to does");
+        let replacement = &fixes[0]["artifactChanges"][0]["replacements"][0];
+        assert_eq!(replacement["deletedRegion"], region);
+        assert_eq!(replacement["insertedContent"]["text"], "does");
+        assert_eq!(run["results"][1]["fixes"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_write_json() {
+        let records: Vec<serde_json::Value> = written(Format::Json)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            records,
+            [
+                serde_json::json!({
+                    "path": "docs/guide.md",
+                    "range": {
+                        "start": {"line": 0, "character": 5},
+                        "end": {"line": 0, "character": 8},
+                    },
+                    "word": "duz",
+                    "suggestions": ["does", "due"],
+                }),
+                serde_json::json!({
+                    "path": "notes.txt",
+                    "range": {
+                        "start": {"line": 2, "character": 0},
+                        "end": {"line": 2, "character": 4},
+                    },
+                    "word": "Thiss",
+                    "suggestions": [],
+                }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Spell checking text without a language server, for tools such as static site generators
+//! and documentation builders that want spelgud's filtering and checking in-process.
+//!
+//! ```no_run
+//! let mut checker = spelgud::checker::Checker::new()?;
+//! let text = "# Helo\n\nSome `mispelled` code.\n";
+//! for misspelling in checker.check_as(text, "markdown")? {
+//!     println!("{}: {} {:?}", misspelling.line, misspelling.word, misspelling.suggestions);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::filter::Filter;
+use crate::{spell, Config, Result};
+use std::ops::Range;
+use std::path::Path;
+
+/// Checks the spelling of text, using the first available spell checker as the language
+/// server would.
+pub struct Checker {
+    backend: spell::Backend,
+    filter: crate::filter::Options,
+}
+
+/// A misspelled word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Misspelling {
+    pub word: String,
+    /// The zero-based line the word is on.
+    pub line: usize,
+    /// The byte range of the word in the checked text.
+    pub range: Range<usize>,
+    /// Suggested corrections, best first.
+    pub suggestions: Vec<String>,
+}
+
+/// A word in checked text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Word {
+    pub word: String,
+    /// The zero-based line the word is on.
+    pub line: usize,
+    /// The byte range of the word in the text.
+    pub range: Range<usize>,
+}
+
+impl Checker {
+    /// Create a checker with the default options.
+    pub fn new() -> Result<Checker> {
+        Checker::with_options(serde_json::json!({}))
+    }
+
+    /// Create a checker with the options an editor would pass as `initializationOptions`,
+    /// e.g. `{"dictionary": "en_GB"}`, as described in the README.
+    pub fn with_options(options: serde_json::Value) -> Result<Checker> {
+        let conf = Config::load(None, Some(options))?;
+        Ok(Checker {
+            backend: spell::Backend::new(&conf.programs, &conf.secondary, &conf.spell)?,
+            filter: conf.filter,
+        })
+    }
+
+    // Use the given checker, e.g. a test double, with the default options.
+    #[cfg(test)]
+    pub(crate) fn with_checker(checker: Box<dyn spell::Checker>) -> Checker {
+        let opts = spell::Options::default();
+        Checker {
+            backend: spell::Backend::with_checker("test", checker, &opts),
+            filter: Default::default(),
+        }
+    }
+
+    /// Check plain text.
+    pub fn check(&mut self, text: &str) -> Result<Vec<Misspelling>> {
+        self.check_with(text, &Filter::Text)
+    }
+
+    /// Check text in the language with the given id, as sent by editors, e.g. "markdown"
+    /// or "rust", so only its prose is checked, e.g. not code blocks or identifiers.
+    pub fn check_as(&mut self, text: &str, language_id: &str) -> Result<Vec<Misspelling>> {
+        let uri = lsp_types::Url::parse("untitled:text")?;
+        let filter = Filter::detect(language_id, &uri, &self.filter);
+        self.check_with(text, &filter)
+    }
+
+    /// Check the file at `path`, whose language is detected from its extension.
+    pub fn check_file(&mut self, path: &Path) -> Result<Vec<Misspelling>> {
+        let text = std::fs::read_to_string(path)?;
+        let absolute = std::path::absolute(path)?;
+        let uri = lsp_types::Url::from_file_path(&absolute)
+            .map_err(|_| format!("Not a file: {}", path.display()))?;
+        let filter = Filter::detect("", &uri, &self.filter);
+        self.check_with(&text, &filter)
+    }
+
+    /// Suggest corrections for a word.
+    pub fn suggest(&mut self, word: &str) -> Result<Vec<String>> {
+        self.backend.suggest(word)
+    }
+
+    /// The words that would be checked in text in the language with the given id, as for
+    /// [`check_as`](Checker::check_as), whether or not they are spelled correctly.
+    pub fn tokenize(&self, text: &str, language_id: &str) -> Result<Vec<Word>> {
+        let uri = lsp_types::Url::parse("untitled:text")?;
+        let filter = Filter::detect(language_id, &uri, &self.filter);
+        let checked = filter.apply(text, &self.filter);
+        let positions = Positions::new(text);
+        let mut words = vec![];
+        for (line, checked) in crate::file::lines(&checked).enumerate() {
+            for (start, word) in crate::token::words(checked) {
+                let range = positions.range(line, start, start + word.chars().count());
+                words.push(Word {
+                    word: text[range.clone()].to_string(),
+                    line,
+                    range,
+                });
+            }
+        }
+        Ok(words)
+    }
+
+    fn check_with(&mut self, text: &str, filter: &Filter) -> Result<Vec<Misspelling>> {
+        let checked = filter.apply(text, &self.filter);
+        let lines: Vec<&str> = crate::file::lines(&checked)
+            .map(|line| line.trim_end_matches(['\r', '\n']))
+            .collect();
+        let inputs = lines
+            .iter()
+            .enumerate()
+            .map(|(n, line)| Ok((u32::try_from(n)?, 0, *line)))
+            .collect::<Result<Vec<_>>>()?;
+        let positions = Positions::new(text);
+        let document = spell::rank::pairs(lines.iter().copied());
+        let mut found = vec![];
+        for diag in self.backend.diags_at(inputs)? {
+            let line = usize::try_from(diag.range.start.line)?;
+            let range = positions.range(
+                line,
+                diag.range.start.character as usize,
+                diag.range.end.character as usize,
+            );
+            let fixes = match &diag.data {
+                Some(data) => serde_json::from_value::<spell::DiagnosticData>(data.clone())?.fixes,
+                None => vec![],
+            };
+            let suggestions = match fixes.is_empty() {
+                // Checkers may only find misspellings, leaving suggestions until they are needed.
+                true => {
+                    let context = spell::rank::Context::at(
+                        lines[line],
+                        diag.range.start.character as usize,
+                        Some(&document),
+                    );
+                    self.backend.suggest_in(&diag.message, &context)?
+                }
+                false => fixes,
+            };
+            found.push(Misspelling {
+                word: text[range.clone()].to_string(),
+                line,
+                range,
+                suggestions,
+            });
+        }
+        Ok(found)
+    }
+}
+
+// Converts the character positions of words in filtered text to byte ranges in the
+// original. Filtering keeps every character in place, so positions in the checked text
+// are also those in the original, though their byte offsets may differ.
+struct Positions<'a> {
+    text: &'a str,
+    // The byte offset of the start of each line.
+    starts: Vec<usize>,
+}
+
+impl Positions<'_> {
+    fn new(text: &str) -> Positions<'_> {
+        let starts = crate::file::lines(text)
+            .scan(0, |start, line| {
+                let this = *start;
+                *start += line.len();
+                Some(this)
+            })
+            .collect();
+        Positions { text, starts }
+    }
+
+    // The byte range of the characters from `start` to `end` on the given line.
+    fn range(&self, line: usize, start: usize, end: usize) -> Range<usize> {
+        let offset = self.starts[line];
+        let byte = |character: usize| {
+            self.text[offset..]
+                .char_indices()
+                .nth(character)
+                .map_or(self.text.len(), |(i, _)| offset + i)
+        };
+        byte(start)..byte(end)
+    }
+}
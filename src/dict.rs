@@ -0,0 +1,172 @@
+// A client for DICT servers (RFC 2229), such as a local dictd serving WordNet,
+// used to show the definition of the word under the cursor on hover.
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Options {
+    pub host: String,
+    pub port: u16,
+    // The database to search, e.g. "wn" for WordNet, or "*" for all of them.
+    pub database: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            host: "localhost".into(),
+            port: 2628,
+            database: "*".into(),
+        }
+    }
+}
+
+// A definition of a word from one of the server's databases.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Definition {
+    // The description of the database, e.g. "WordNet (r) 3.0 (2006)".
+    pub source: String,
+    pub text: String,
+}
+
+// Look up the definitions of a word. Returns an empty list if the word is not found.
+pub fn define(opts: &Options, word: &str) -> Result<Vec<Definition>> {
+    let stream = TcpStream::connect((opts.host.as_str(), opts.port))?;
+    stream.set_read_timeout(Some(std::time::Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    expect(&mut reader, "220")?;
+    // Quote the word, as it may contain spaces or apostrophes.
+    let word = word.replace(['"', '\\'], "");
+    write!(writer, "DEFINE {} \"{word}\"\r\n", opts.database)?;
+    let definitions = read_definitions(&mut reader);
+    // The connection is no longer needed, so failing to close it cleanly is not an error.
+    writer.write_all(b"QUIT\r\n").ok();
+    definitions
+}
+
+fn read_definitions(reader: &mut impl BufRead) -> Result<Vec<Definition>> {
+    let mut definitions = vec![];
+    loop {
+        let line = read_line(reader)?;
+        let (code, rest) = line.split_once(' ').unwrap_or((&line, ""));
+        match code {
+            // No match
+            "552" => return Ok(definitions),
+            // Count of definitions that follow
+            "150" => {}
+            // A definition: 151 "word" database "description"
+            "151" => {
+                let source = rest.rsplit('"').nth(1).unwrap_or_default().to_string();
+                let mut text = String::new();
+                loop {
+                    let line = read_line(reader)?;
+                    if line == "." {
+                        break;
+                    }
+                    // Lines starting with a period are escaped by doubling it.
+                    let line = line.strip_prefix('.').unwrap_or(&line);
+                    text.push_str(line);
+                    text.push('\n');
+                }
+                definitions.push(Definition {
+                    source,
+                    text: text.trim_end().to_string(),
+                });
+            }
+            "250" => return Ok(definitions),
+            _ => Err(format!("Unexpected response from DICT server: {line}"))?,
+        }
+    }
+}
+
+fn read_line(reader: &mut impl BufRead) -> Result<String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        Err("DICT server closed the connection")?;
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn expect(reader: &mut impl BufRead, code: &str) -> Result<()> {
+    let line = read_line(reader)?;
+    if !line.starts_with(code) {
+        Err(format!("Unexpected response from DICT server: {line}"))?;
+    }
+    Ok(())
+}
+
+// Format definitions as markdown for a hover.
+pub fn markdown(word: &str, definitions: &[Definition]) -> String {
+    let mut out = format!("**{word}**\n");
+    for def in definitions {
+        out.push_str(&format!(
+            "\n*{}*\n\n```text\n{}\n```\n",
+            def.source, def.text
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_read_definitions() {
+        let response = "150 1 definitions retrieved\r\n\
+                        151 \"spell\" wn \"WordNet (r) 3.0 (2006)\"\r\n\
+                        spell\r\n    n 1: a verbal formula\r\n..ellipsis\r\n.\r\n\
+                        250 ok\r\n";
+        assert_eq!(
+            read_definitions(&mut response.as_bytes()).unwrap(),
+            vec![Definition {
+                source: "WordNet (r) 3.0 (2006)".into(),
+                text: "spell\n    n 1: a verbal formula\n.ellipsis".into(),
+            }]
+        );
+        assert_eq!(
+            read_definitions(&mut "552 no match\r\n".as_bytes()).unwrap(),
+            vec![]
+        );
+        assert!(read_definitions(&mut "".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_define() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let opts = Options {
+            host: "127.0.0.1".into(),
+            port: listener.local_addr().unwrap().port(),
+            database: "wn".into(),
+        };
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            write!(writer, "220 dictd ready\r\n").unwrap();
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            write!(
+                writer,
+                "150 1 definitions\r\n151 \"word\" wn \"WordNet\"\r\nword\r\n.\r\n250 ok\r\n"
+            )
+            .unwrap();
+            request
+        });
+        let defs = define(&opts, "word").unwrap();
+        assert_eq!(server.join().unwrap(), "DEFINE wn \"word\"\r\n");
+        assert_eq!(
+            defs,
+            vec![Definition {
+                source: "WordNet".into(),
+                text: "word".into()
+            }]
+        );
+    }
+}
@@ -0,0 +1,342 @@
+// A C interface to the checker, so editors and tools not written in Rust can link the
+// checking core directly. It is declared in include/spelgud.h; see there for its use.
+use crate::checker::{Checker, Misspelling};
+use std::ffi::{c_char, CStr, CString};
+use std::sync::Mutex;
+
+#[repr(C)]
+pub struct SpelgudMisspelling {
+    pub word: *mut c_char,
+    pub line: usize,
+    // The byte range of the word in the checked text.
+    pub start: usize,
+    pub end: usize,
+    pub suggestions: *mut *mut c_char,
+    pub suggestion_count: usize,
+}
+
+#[repr(C)]
+pub struct SpelgudResults {
+    pub misspellings: *mut SpelgudMisspelling,
+    pub count: usize,
+    // Why checking failed, or null if it didn't.
+    pub error: *mut c_char,
+}
+
+#[repr(C)]
+pub struct SpelgudSuggestions {
+    pub suggestions: *mut *mut c_char,
+    pub count: usize,
+    pub error: *mut c_char,
+}
+
+// The checker is started on first use and kept for later calls, as starting one may
+// mean starting a spell checker program.
+static CHECKER: Mutex<Option<Checker>> = Mutex::new(None);
+
+// Check `text` in the language with the given id, e.g. "markdown", or as plain text if
+// `lang` is null. The results must be freed with `spelgud_results_free`.
+//
+// Safety: `text` and `lang` must be null or point to nul-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn spelgud_check(
+    text: *const c_char,
+    lang: *const c_char,
+) -> *mut SpelgudResults {
+    let found = call(|| {
+        let text = arg(text, "text")?;
+        match optional_arg(lang)? {
+            Some(lang) => with_checker(|checker| checker.check_as(text, lang)),
+            None => with_checker(|checker| checker.check(text)),
+        }
+    });
+    results(found.map(|found| found.into_iter().map(misspelling).collect()))
+}
+
+// The words in `text` that would be checked, as for `spelgud_check`, without
+// suggestions. The results must be freed with `spelgud_results_free`.
+//
+// Safety: as for `spelgud_check`.
+#[no_mangle]
+pub unsafe extern "C" fn spelgud_tokenize(
+    text: *const c_char,
+    lang: *const c_char,
+) -> *mut SpelgudResults {
+    let words = call(|| {
+        let text = arg(text, "text")?;
+        let lang = optional_arg(lang)?.unwrap_or("plaintext");
+        with_checker(|checker| checker.tokenize(text, lang))
+    });
+    results(words.map(|words| {
+        words
+            .into_iter()
+            .map(|word| {
+                misspelling(Misspelling {
+                    word: word.word,
+                    line: word.line,
+                    range: word.range,
+                    suggestions: vec![],
+                })
+            })
+            .collect()
+    }))
+}
+
+// Suggest corrections for `word`. The suggestions must be freed with
+// `spelgud_suggestions_free`.
+//
+// Safety: `word` must be null or point to a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn spelgud_suggest(word: *const c_char) -> *mut SpelgudSuggestions {
+    let suggestions = call(|| {
+        let word = arg(word, "word")?;
+        with_checker(|checker| checker.suggest(word))
+    });
+    let suggestions = match suggestions {
+        Ok(suggestions) => {
+            let (suggestions, count) = into_raw(suggestions.iter().map(|s| string(s)).collect());
+            SpelgudSuggestions {
+                suggestions,
+                count,
+                error: std::ptr::null_mut(),
+            }
+        }
+        Err(err) => SpelgudSuggestions {
+            suggestions: std::ptr::null_mut(),
+            count: 0,
+            error: string(&err),
+        },
+    };
+    Box::into_raw(Box::new(suggestions))
+}
+
+// Free results returned by `spelgud_check` or `spelgud_tokenize`.
+//
+// Safety: `results` must be null or returned by one of them, and not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn spelgud_results_free(results: *mut SpelgudResults) {
+    if results.is_null() {
+        return;
+    }
+    let results = Box::from_raw(results);
+    for misspelling in from_raw(results.misspellings, results.count).iter() {
+        drop(CString::from_raw(misspelling.word));
+        free_strings(misspelling.suggestions, misspelling.suggestion_count);
+    }
+    free_string(results.error);
+}
+
+// Free suggestions returned by `spelgud_suggest`.
+//
+// Safety: `suggestions` must be null or returned by `spelgud_suggest`, and not already
+// freed.
+#[no_mangle]
+pub unsafe extern "C" fn spelgud_suggestions_free(suggestions: *mut SpelgudSuggestions) {
+    if suggestions.is_null() {
+        return;
+    }
+    let suggestions = Box::from_raw(suggestions);
+    free_strings(suggestions.suggestions, suggestions.count);
+    free_string(suggestions.error);
+}
+
+// Call `f`, returning any error or panic as a message, as neither can cross into C.
+fn call<T>(f: impl FnOnce() -> crate::Result<T>) -> Result<T, String> {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(result) => result.map_err(|err| err.to_string()),
+        Err(_) => Err("The checker panicked".into()),
+    }
+}
+
+fn with_checker<T>(f: impl FnOnce(&mut Checker) -> crate::Result<T>) -> crate::Result<T> {
+    let mut checker = CHECKER.lock().unwrap_or_else(|err| err.into_inner());
+    let checker = match checker.as_mut() {
+        Some(checker) => checker,
+        None => checker.insert(Checker::new()?),
+    };
+    f(checker)
+}
+
+unsafe fn arg<'a>(s: *const c_char, name: &str) -> crate::Result<&'a str> {
+    Ok(optional_arg(s)?.ok_or(format!("{name} is null"))?)
+}
+
+unsafe fn optional_arg<'a>(s: *const c_char) -> crate::Result<Option<&'a str>> {
+    match s.is_null() {
+        true => Ok(None),
+        false => Ok(Some(CStr::from_ptr(s).to_str()?)),
+    }
+}
+
+fn results(found: Result<Vec<SpelgudMisspelling>, String>) -> *mut SpelgudResults {
+    let results = match found {
+        Ok(found) => {
+            let (misspellings, count) = into_raw(found);
+            SpelgudResults {
+                misspellings,
+                count,
+                error: std::ptr::null_mut(),
+            }
+        }
+        Err(err) => SpelgudResults {
+            misspellings: std::ptr::null_mut(),
+            count: 0,
+            error: string(&err),
+        },
+    };
+    Box::into_raw(Box::new(results))
+}
+
+fn misspelling(found: Misspelling) -> SpelgudMisspelling {
+    let suggestions = found.suggestions.iter().map(|s| string(s)).collect();
+    let (suggestions, suggestion_count) = into_raw(suggestions);
+    SpelgudMisspelling {
+        word: string(&found.word),
+        line: found.line,
+        start: found.range.start,
+        end: found.range.end,
+        suggestions,
+        suggestion_count,
+    }
+}
+
+// A C string, without any nul characters, which C strings can't contain.
+fn string(s: &str) -> *mut c_char {
+    CString::new(s.replace('\0', ""))
+        .unwrap_or_default()
+        .into_raw()
+}
+
+unsafe fn free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+unsafe fn free_strings(strings: *mut *mut c_char, count: usize) {
+    for s in from_raw(strings, count).iter() {
+        free_string(*s);
+    }
+}
+
+// Pass ownership of a vector to C as a pointer and length, null if it's empty.
+fn into_raw<T>(items: Vec<T>) -> (*mut T, usize) {
+    match items.len() {
+        0 => (std::ptr::null_mut(), 0),
+        len => (Box::into_raw(items.into_boxed_slice()).cast(), len),
+    }
+}
+
+// Take back ownership of a vector passed to C by `into_raw`.
+unsafe fn from_raw<T>(items: *mut T, len: usize) -> Box<[T]> {
+    match items.is_null() {
+        true => Box::default(),
+        false => Box::from_raw(std::ptr::slice_from_raw_parts_mut(items, len)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spell::fakes::Fake;
+    use pretty_assertions::assert_eq;
+
+    // Check with a checker that reports the misspellings in these tests, rather than whatever
+    // spell checker is installed. Tests run at once share the checker, so each sets the same.
+    fn fake_checker() {
+        let fake = Fake(vec!["Helo", "helo", "mispelled"]);
+        let mut checker = CHECKER.lock().unwrap_or_else(|err| err.into_inner());
+        *checker = Some(Checker::with_checker(Box::new(fake)));
+    }
+
+    // The words found and their positions, each with at least one suggestion.
+    unsafe fn read(results: &SpelgudResults) -> Vec<(String, usize, usize, usize)> {
+        assert!(
+            results.error.is_null(),
+            "{:?}",
+            CStr::from_ptr(results.error)
+        );
+        let misspellings = match results.count {
+            0 => &[][..],
+            n => std::slice::from_raw_parts(results.misspellings, n),
+        };
+        misspellings
+            .iter()
+            .map(|m| {
+                assert!(m.suggestion_count > 0);
+                let suggestions = std::slice::from_raw_parts(m.suggestions, m.suggestion_count);
+                assert!(!CStr::from_ptr(suggestions[0]).is_empty());
+                (
+                    CStr::from_ptr(m.word).to_string_lossy().into_owned(),
+                    m.line,
+                    m.start,
+                    m.end,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_check() {
+        fake_checker();
+        let text = CString::new("# Helo\n\nSome `mispelled` code, mispelled.\n").unwrap();
+        let lang = CString::new("markdown").unwrap();
+        unsafe {
+            let results = spelgud_check(text.as_ptr(), lang.as_ptr());
+            assert_eq!(
+                read(&*results),
+                [("Helo".into(), 0, 2, 6), ("mispelled".into(), 2, 31, 40)]
+            );
+            spelgud_results_free(results);
+
+            let results = spelgud_check(text.as_ptr(), std::ptr::null());
+            assert_eq!(read(&*results).len(), 3);
+            spelgud_results_free(results);
+        }
+    }
+
+    #[test]
+    fn test_tokenize() {
+        fake_checker();
+        let text = CString::new("# Helo\n\nSome `mispelled` code.\n").unwrap();
+        let lang = CString::new("markdown").unwrap();
+        unsafe {
+            let results = spelgud_tokenize(text.as_ptr(), lang.as_ptr());
+            assert!((*results).error.is_null());
+            let words: Vec<_> =
+                std::slice::from_raw_parts((*results).misspellings, (*results).count)
+                    .iter()
+                    .map(|m| (CStr::from_ptr(m.word).to_str().unwrap(), m.line, m.start))
+                    .collect();
+            assert_eq!(words, [("Helo", 0, 2), ("Some", 2, 8), ("code", 2, 25)]);
+            spelgud_results_free(results);
+        }
+    }
+
+    #[test]
+    fn test_suggest() {
+        fake_checker();
+        let word = CString::new("helo").unwrap();
+        unsafe {
+            let suggestions = spelgud_suggest(word.as_ptr());
+            assert!((*suggestions).error.is_null());
+            assert!((*suggestions).count > 0);
+            spelgud_suggestions_free(suggestions);
+        }
+    }
+
+    #[test]
+    fn test_error() {
+        unsafe {
+            let results = spelgud_check(std::ptr::null(), std::ptr::null());
+            assert_eq!((*results).count, 0);
+            assert_eq!(CStr::from_ptr((*results).error), c"text is null");
+            spelgud_results_free(results);
+            spelgud_results_free(std::ptr::null_mut());
+
+            let suggestions = spelgud_suggest(std::ptr::null());
+            assert_eq!(CStr::from_ptr((*suggestions).error), c"word is null");
+            spelgud_suggestions_free(suggestions);
+        }
+    }
+}
@@ -1,72 +1,361 @@
+use crate::filter::{self, Filter};
+use ropey::Rope;
+use std::borrow::Cow;
+
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 pub struct File {
+    // A rope, so edits to large files don't copy the text after them, and lines are found
+    // without scanning the text. As in LSP, lines end at \n, \r\n or \r.
+    text: Rope,
+    filter: Filter,
+    // Each line as it was last checked, or None if it has been edited since.
+    checked: Vec<Option<Checked>>,
+    // Incremented whenever the text is edited or previous checks are invalidated.
+    generation: u64,
+}
+
+// A line's checked text, and the diagnostics found in it.
+struct Checked {
     text: String,
+    diags: Vec<lsp_types::Diagnostic>,
 }
 
 impl File {
-    pub fn new(text: String) -> Result<File> {
-        Ok(File { text })
+    pub fn new(text: String, filter: Filter) -> Result<File> {
+        Ok(File {
+            text: Rope::from(text),
+            filter,
+            checked: vec![],
+            generation: 0,
+        })
     }
 
     pub fn edit(&mut self, changes: Vec<lsp_types::TextDocumentContentChangeEvent>) -> Result<()> {
+        self.generation += 1;
         for change in changes {
             let range = change
                 .range
                 .ok_or("No range in change notification {change:?}")?;
-            let mut lines = self.text.split_inclusive("\n").peekable();
-            // First count bytes in all lines preceding the edit.
-            let start_byte = lines
-                .by_ref()
-                .take(range.start.line.try_into()?)
-                .map(str::len)
-                .sum::<usize>();
-            // Now add bytes up to the character within the start line.
-            let start_offset = lines
-                .peek()
-                .map(|line| char_to_byte(&line, range.start.character))
-                .unwrap_or(0);
-            let start_byte = start_byte + start_offset;
-            // Now count bytes in all lines following the edit.
-            let end_byte = start_byte
-                + lines
-                    .by_ref()
-                    .take((range.end.line - range.start.line).try_into()?)
-                    .map(str::len)
-                    .sum::<usize>();
-            // Now add bytes up to the character within the end line.
-            let end_offset = lines
-                .peek()
-                .map(|line| char_to_byte(&line, range.end.character))
-                .unwrap_or(0);
-            let end_byte = end_byte + end_offset - start_offset;
+            let start_byte = self.offset(range.start)?;
+            let end_byte = self.offset(range.end)?.max(start_byte);
 
             log::trace!(
                 "Computing change {start_byte}..{end_byte} with text {}",
                 change.text
             );
 
-            self.text.replace_range(start_byte..end_byte, &change.text);
+            let start = self.text.byte_to_char(start_byte);
+            self.text.remove(start..self.text.byte_to_char(end_byte));
+            self.text.insert(start, &change.text);
+
+            // The lines spanned by the change are replaced by the lines of its text.
+            let breaks = lines(&change.text)
+                .filter(|line| line.ends_with(['\r', '\n']))
+                .count();
+            let end = usize::try_from(range.end.line)?
+                .saturating_add(1)
+                .min(self.checked.len());
+            let start = usize::try_from(range.start.line)?.min(end);
+            self.checked
+                .splice(start..end, std::iter::repeat_with(|| None).take(breaks + 1));
         }
         log::trace!("Edited text to: {}", self.text);
 
         Ok(())
     }
 
-    pub fn text(&self) -> &str {
-        self.text.as_str()
+    pub fn text(&self) -> String {
+        self.text.to_string()
+    }
+
+    // The byte offset of a position in UTF-8 bytes, as the client counts them given the
+    // server's capabilities. Positions past the end of a line or the file are clamped to
+    // its end, and positions within a character to its start.
+    fn offset(&self, pos: lsp_types::Position) -> Result<usize> {
+        let line = usize::try_from(pos.line)?;
+        if line >= self.text.len_lines() {
+            return Ok(self.text.len_bytes());
+        }
+        let start = self.text.line_to_byte(line);
+        let len = self.text.line(line).len_bytes();
+        let offset = start + usize::try_from(pos.character)?.min(len);
+        Ok(self.text.char_to_byte(self.text.byte_to_char(offset)))
+    }
+
+    // A range in characters, as diagnostics count them, as a range in UTF-8 bytes, as the
+    // client counts them given the server's capabilities, e.g. "é" is two bytes but one
+    // character.
+    pub fn utf8_range(&self, range: lsp_types::Range) -> lsp_types::Range {
+        let convert = |pos: lsp_types::Position| match self.line(pos.line as usize) {
+            Some(text) => lsp_types::Position::new(
+                pos.line,
+                u32::try_from(char_to_byte(&text, pos.character)).unwrap_or(pos.character),
+            ),
+            None => pos,
+        };
+        lsp_types::Range::new(convert(range.start), convert(range.end))
+    }
+
+    // A range in UTF-8 bytes from the client as a range in characters.
+    pub fn char_range(&self, range: lsp_types::Range) -> lsp_types::Range {
+        lsp_types::Range::new(
+            self.char_position(range.start),
+            self.char_position(range.end),
+        )
+    }
+
+    // A position in UTF-8 bytes from the client as a position in characters. A position
+    // within a character is taken as its start.
+    pub fn char_position(&self, pos: lsp_types::Position) -> lsp_types::Position {
+        let Some(text) = self.line(pos.line as usize) else {
+            return pos;
+        };
+        let chars = text
+            .char_indices()
+            .take_while(|(i, c)| i + c.len_utf8() <= pos.character as usize)
+            .count();
+        lsp_types::Position::new(pos.line, u32::try_from(chars).unwrap_or(pos.character))
+    }
+
+    // The text of a line, including its line ending.
+    fn line(&self, line: usize) -> Option<Cow<'_, str>> {
+        (line < self.text.len_lines()).then(|| self.text.line(line).into())
+    }
+
+    // The number of lines, as counted by `lines`, which doesn't count an empty last line.
+    fn line_count(&self) -> usize {
+        let count = self.text.len_lines();
+        count - usize::from(self.text.line(count - 1).len_bytes() == 0)
+    }
+
+    // The text with all regions that should not be spell checked blanked out.
+    pub fn checked_text(&self, opts: &filter::Options) -> String {
+        self.filter.apply(&self.text(), opts)
+    }
+
+    // Whether each line can be filtered on its own, so only lines that have changed need be
+    // copied out of the rope. Other filters need the whole document, e.g. to know whether a
+    // line is in a code block, as does a directive that turns checking off past its line.
+    fn filters_by_line(&self) -> bool {
+        self.filter.is_line_local()
+            && !self
+                .text
+                .lines()
+                .any(|line| filter::spans_lines(&Cow::from(line)))
+    }
+
+    // Forget the results of previous checks, so every line is checked again,
+    // e.g. after adding a word to the dictionary.
+    pub fn invalidate(&mut self) {
+        self.generation += 1;
+        self.checked.clear();
+    }
+
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    // The checked text of the lines that have changed since they were last checked, with the
+    // line and character they start at. Their previous results are forgotten, so none are
+    // kept for text that is no longer there.
+    pub fn changed_lines(&mut self, opts: &filter::Options) -> Result<Vec<(u32, u32, String)>> {
+        let mut changed = vec![];
+        if self.filters_by_line() {
+            for n in 0..self.line_count() {
+                if let (Some(None) | None, Some(line)) = (self.checked.get(n), self.line(n)) {
+                    let line = self.filter.apply(&line, opts);
+                    changed.push((n, line.trim_end_matches(['\r', '\n']).to_string()));
+                }
+            }
+        } else {
+            let checked = self.checked_text(opts);
+            for (n, line) in lines(&checked).enumerate() {
+                let line = line.trim_end_matches(['\r', '\n']);
+                if !self.is_checked(n, line) {
+                    changed.push((n, line.to_string()));
+                }
+            }
+        }
+        changed
+            .into_iter()
+            .map(|(n, line)| {
+                if let Some(checked) = self.checked.get_mut(n) {
+                    *checked = None;
+                }
+                Ok((n.try_into()?, 0, line))
+            })
+            .collect()
+    }
+
+    // Store the diagnostics found in the given lines, some or all of those returned by
+    // `changed_lines`, and return them along with those previously found in other lines.
+    // Diagnostics in other lines, e.g. grammar errors spanning several lines, are added to
+    // those lines if they have been checked.
+    pub fn update(
+        &mut self,
+        lines_checked: &[(u32, u32, String)],
+        diags: Vec<lsp_types::Diagnostic>,
+    ) -> Vec<lsp_types::Diagnostic> {
+        self.checked.resize_with(self.line_count(), || None);
+        for (n, line) in self.checked.iter_mut().enumerate() {
+            // Unchanged lines may have moved, due to lines added or removed above.
+            if let Some(line) = line {
+                let diags = std::mem::take(&mut line.diags);
+                line.diags = diags.into_iter().map(|d| move_to_line(d, n)).collect();
+            }
+        }
+        for (line, _, text) in lines_checked {
+            if let Some(checked) = usize::try_from(*line)
+                .ok()
+                .and_then(|n| self.checked.get_mut(n))
+            {
+                *checked = Some(Checked {
+                    text: text.clone(),
+                    diags: vec![],
+                });
+            }
+        }
+        for diag in diags {
+            if let Some(Some(line)) = usize::try_from(diag.range.start.line)
+                .ok()
+                .and_then(|n| self.checked.get_mut(n))
+            {
+                line.diags.push(diag);
+            }
+        }
+        self.checked
+            .iter()
+            .flatten()
+            .flat_map(|line| line.diags.iter().cloned())
+            .collect()
+    }
+
+    // Whether the line was checked with the same text, and hasn't been edited since.
+    fn is_checked(&self, n: usize, text: &str) -> bool {
+        matches!(self.checked.get(n), Some(Some(line)) if line.text == text)
+    }
+
+    // The words within identifiers that should be checked, with their line and character.
+    pub fn identifiers(&self) -> Result<Vec<(u32, u32, String)>> {
+        let text = self.text();
+        let mut words = vec![];
+        let mut lines = lines(&text).enumerate().peekable();
+        let mut line_start = 0;
+        for range in self.filter.identifiers(&text) {
+            while let Some((_, line)) = lines.next_if(|(_, l)| line_start + l.len() <= range.start)
+            {
+                line_start += line.len();
+            }
+            let line = lines.peek().map_or(0, |(n, _)| *n);
+            let character = text[line_start..range.start].chars().count();
+            words.push((
+                line.try_into()?,
+                character.try_into()?,
+                text[range].to_string(),
+            ));
+        }
+        Ok(words)
+    }
+
+    // The number of words that are checked, i.e. outside of code, markup and so on.
+    pub fn word_count(&self, opts: &filter::Options) -> usize {
+        self.words(opts).len()
+    }
+
+    // The words that are checked, in order, once for each time they appear.
+    pub fn words(&self, opts: &filter::Options) -> Vec<String> {
+        let checked = self.checked_text(opts);
+        lines(&checked)
+            .flat_map(|line| crate::token::words(line).into_iter())
+            .map(|(_, word)| word.to_string())
+            .collect()
+    }
+
+    // The diagnostics found by the last check of each line, in order.
+    pub fn diagnostics(&self) -> impl Iterator<Item = &lsp_types::Diagnostic> {
+        self.checked.iter().flatten().flat_map(|line| &line.diags)
+    }
+
+    // The words the last check of each line accepted, with their line and character.
+    pub fn accepted_words(&self) -> Vec<(u32, u32, &str)> {
+        let mut accepted = vec![];
+        for (line, checked) in self.checked.iter().enumerate() {
+            let (Some(checked), Ok(line)) = (checked, u32::try_from(line)) else {
+                continue;
+            };
+            let misspelled: Vec<&lsp_types::Range> = checked
+                .diags
+                .iter()
+                .filter(|diag| diag.code.is_none())
+                .map(|diag| &diag.range)
+                .collect();
+            for (start, word) in crate::token::words(&checked.text) {
+                let start = u32::try_from(start).unwrap_or(u32::MAX);
+                if !misspelled
+                    .iter()
+                    .any(|range| range.start.character == start)
+                {
+                    accepted.push((line, start, word));
+                }
+            }
+        }
+        accepted
+    }
+
+    // The misspelled words found by the last check, once for each time they were found.
+    // Other errors, e.g. from grammar checks, have a rule code and aren't included.
+    pub fn misspellings(&self) -> Vec<&str> {
+        self.diagnostics()
+            .filter(|diag| diag.code.is_none())
+            .map(|diag| diag.message.as_str())
+            .collect()
+    }
+
+    // The spelling error reported at the given line and character, if any.
+    pub fn misspelling_at(&self, line: u32, character: u32) -> Option<&lsp_types::Diagnostic> {
+        let checked = self.checked.get(usize::try_from(line).ok()?)?.as_ref()?;
+        checked.diags.iter().find(|diag| {
+            // Other errors, e.g. from grammar checks, have a rule code.
+            diag.code.is_none()
+                && (diag.range.start.character..=diag.range.end.character).contains(&character)
+        })
+    }
+
+    // The word at the given line and character, with the character range it spans.
+    pub fn word_at(&self, line: u32, character: u32) -> Option<(lsp_types::Range, String)> {
+        let text = self.line(line.try_into().ok()?)?;
+        let character = usize::try_from(character).ok()?;
+        let (start, word) = crate::token::words(&text)
+            .into_iter()
+            .find(|(start, word)| (*start..=start + word.chars().count()).contains(&character))?;
+        let start = u32::try_from(start).ok()?;
+        let end = start + u32::try_from(word.chars().count()).ok()?;
+        let range = lsp_types::Range {
+            start: lsp_types::Position {
+                line,
+                character: start,
+            },
+            end: lsp_types::Position {
+                line,
+                character: end,
+            },
+        };
+        Some((range, word.to_string()))
     }
 
     pub fn symbols(&self, uri: &lsp_types::Url) -> Result<Vec<lsp_types::SymbolInformation>> {
         #[allow(deprecated)]
-        Ok(self
-            .text()
-            .lines()
+        Ok(lines(&self.text())
             .enumerate()
             .map(|(line, text)| {
                 let line = line.try_into().unwrap();
                 lsp_types::SymbolInformation {
-                    name: text.into(),
+                    name: text.trim_end_matches(['\r', '\n']).into(),
                     kind: lsp_types::SymbolKind::STRING,
                     tags: None,
                     deprecated: None,
@@ -84,6 +373,34 @@ impl File {
     }
 }
 
+// Split text into lines, keeping their line endings.
+// As in LSP, a line may be terminated by \n, \r\n or \r.
+pub fn lines(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let end = match rest.find(['\r', '\n']) {
+            Some(i) if rest[i..].starts_with("\r\n") => i + 2,
+            Some(i) => i + 1,
+            None => rest.len(),
+        };
+        let (line, tail) = rest.split_at(end);
+        rest = tail;
+        Some(line)
+    })
+}
+
+// Move a diagnostic found in an earlier check to the line its text is now on.
+fn move_to_line(mut diag: lsp_types::Diagnostic, line: usize) -> lsp_types::Diagnostic {
+    let line = u32::try_from(line).unwrap_or(u32::MAX);
+    let span = diag.range.end.line - diag.range.start.line;
+    diag.range.start.line = line;
+    diag.range.end.line = line.saturating_add(span);
+    diag
+}
+
 fn char_to_byte(line: &str, char: u32) -> usize {
     line.chars()
         .take(char.try_into().unwrap())
@@ -100,7 +417,7 @@ mod tests {
     #[test]
     fn test_symbols() {
         let text = "One two three\nFour five six\n";
-        let file = File::new(text.to_string()).unwrap();
+        let file = File::new(text.to_string(), Filter::Text).unwrap();
         let uri = lsp_types::Url::parse("file://example.txt").unwrap();
         assert_eq!(
             file.symbols(&uri).unwrap(),
@@ -151,10 +468,54 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_identifiers() {
+        let lang = crate::syntax::Language {
+            syntax: crate::syntax::Syntax::builtin("rust").unwrap(),
+            identifiers: true,
+            ..Default::default()
+        };
+        let text = "fn main() {\n  let välue = getThing();\n}\n";
+        let file = File::new(text.to_string(), Filter::Code(lang)).unwrap();
+        assert_eq!(
+            file.identifiers().unwrap(),
+            vec![
+                (0, 3, "main".into()),
+                (1, 6, "välue".into()),
+                (1, 17, "Thing".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_word_at() {
+        let file = File::new("One twö\nthree's, four".into(), Filter::Text).unwrap();
+        let word = |line, character| file.word_at(line, character).map(|(_, w)| w);
+        assert_eq!(word(0, 0).as_deref(), Some("One"));
+        assert_eq!(word(0, 3).as_deref(), Some("One"));
+        assert_eq!(word(0, 6).as_deref(), Some("twö"));
+        assert_eq!(word(1, 2).as_deref(), Some("three's"));
+        assert_eq!(word(1, 8).as_deref(), None);
+        assert_eq!(word(2, 0).as_deref(), None);
+        assert_eq!(
+            file.word_at(1, 10).unwrap().0,
+            lsp_types::Range {
+                start: lsp_types::Position {
+                    line: 1,
+                    character: 9
+                },
+                end: lsp_types::Position {
+                    line: 1,
+                    character: 13
+                },
+            }
+        );
+    }
+
     #[test]
     fn test_edit() {
         let text = "yn";
-        let mut file = File::new(text.into()).unwrap();
+        let mut file = File::new(text.into(), Filter::Text).unwrap();
         assert_eq!(file.text, text);
 
         let change = |(start_line, start_char), (end_line, end_char), text: &str| {
@@ -250,6 +611,8 @@ mod tests {
             .join("\n")
         );
 
+        assert_eq!(file.text.len_lines(), 7);
+
         file.edit(vec![change((3, 13), (4, 0), "5;\n")]).unwrap();
         assert_eq!(
             file.text,
@@ -264,6 +627,73 @@ mod tests {
             ]
             .join("\n")
         );
+        assert_eq!(file.text.line(3), "string str = 5;\n");
+
+        // Joining lines, and an edit past the end of the file.
+        file.edit(vec![
+            change((1, 13), (3, 3), ""),
+            change((9, 0), (9, 0), "x"),
+        ])
+        .unwrap();
+        assert_eq!(
+            file.text,
+            [
+                "syntax = \"proto3\";",
+                "message Foo {ing str = 5;",
+                "bytes b = 4;",
+                "}",
+                "x"
+            ]
+            .join("\n")
+        );
+        assert_eq!(file.text.len_lines(), 5);
+    }
+
+    #[test]
+    fn test_lines() {
+        assert_eq!(
+            lines("a\nb\r\nc\rd").collect::<Vec<_>>(),
+            vec!["a\n", "b\r\n", "c\r", "d"]
+        );
+        assert_eq!(lines("").count(), 0);
+    }
+
+    #[test]
+    fn test_edit_crlf() {
+        let mut file = File::new("one\r\ntwo\r\nthree".into(), Filter::Text).unwrap();
+        let change = lsp_types::TextDocumentContentChangeEvent {
+            range: Some(lsp_types::Range {
+                start: lsp_types::Position {
+                    line: 1,
+                    character: 1,
+                },
+                end: lsp_types::Position {
+                    line: 2,
+                    character: 2,
+                },
+            }),
+            range_length: None,
+            text: "o\r\nfou".into(),
+        };
+        file.edit(vec![change]).unwrap();
+        assert_eq!(file.text, "one\r\nto\r\nfouree");
+        assert_eq!(file.text.line_to_byte(1), 5);
+        assert_eq!(file.text.line_to_byte(2), 9);
+    }
+
+    #[test]
+    fn test_identifiers_crlf() {
+        let lang = crate::syntax::Language {
+            syntax: crate::syntax::Syntax::builtin("rust").unwrap(),
+            identifiers: true,
+            ..Default::default()
+        };
+        let text = "fn main() {\r\n  let value = 1;\r}\r\n";
+        let file = File::new(text.to_string(), Filter::Code(lang)).unwrap();
+        assert_eq!(
+            file.identifiers().unwrap(),
+            vec![(0, 3, "main".into()), (1, 6, "value".into())]
+        );
     }
 
     #[test]
@@ -275,7 +705,7 @@ mod tests {
             "",
         ]
         .join("\n");
-        let mut file = File::new(text.clone()).unwrap();
+        let mut file = File::new(text.clone(), Filter::Text).unwrap();
         assert_eq!(file.text, text);
 
         let change = |(start_line, start_char), (end_line, end_char), text: &str| {
@@ -295,7 +725,8 @@ mod tests {
             }
         };
 
-        file.edit(vec![change((1, 8), (1, 15), "thing")]).unwrap();
+        // "𐐀" is four bytes.
+        file.edit(vec![change((1, 8), (1, 18), "thing")]).unwrap();
         assert_eq!(
             file.text,
             [
@@ -306,5 +737,157 @@ mod tests {
             ]
             .join("\n")
         );
+
+        // A column within a character is taken as its start.
+        file.edit(vec![change((1, 8), (1, 8), "𐐀")]).unwrap();
+        file.edit(vec![change((1, 10), (1, 12), "")]).unwrap();
+        assert_eq!(
+            file.text,
+            [
+                "syntax = \"proto3\";",
+                "import \"thing.proto\";",
+                "import \"other.proto\";",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_utf8_range() {
+        let file = File::new(
+            "plain
+“Café” 😀 teh
+"
+            .into(),
+            Filter::Text,
+        )
+        .unwrap();
+        let range = |line, start, end| {
+            lsp_types::Range::new(
+                lsp_types::Position::new(line, start),
+                lsp_types::Position::new(line, end),
+            )
+        };
+        // "teh" starts after 9 characters, which are 17 bytes.
+        assert_eq!(file.utf8_range(range(1, 9, 12)), range(1, 17, 20));
+        assert_eq!(file.char_range(range(1, 17, 20)), range(1, 9, 12));
+        assert_eq!(file.utf8_range(range(0, 0, 5)), range(0, 0, 5));
+        // A column within a character is rounded down to its start.
+        assert_eq!(
+            file.char_position(lsp_types::Position::new(1, 2)).character,
+            0
+        );
+        assert_eq!(file.utf8_range(range(5, 1, 2)), range(5, 1, 2));
+    }
+
+    #[test]
+    fn test_update() {
+        let diag = |line| lsp_types::Diagnostic {
+            range: lsp_types::Range {
+                start: lsp_types::Position { line, character: 4 },
+                end: lsp_types::Position { line, character: 7 },
+            },
+            message: "teh".into(),
+            ..Default::default()
+        };
+        let opts = filter::Options::default();
+        let mut file = File::new("one\ntwo teh\nthree".into(), Filter::Text).unwrap();
+        let lines = file.changed_lines(&opts).unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(file.update(&lines, vec![diag(1)]), vec![diag(1)]);
+        assert!(file.changed_lines(&opts).unwrap().is_empty());
+
+        // Insert a line at the start, moving the misspelling down.
+        let start = lsp_types::Position::new(0, 0);
+        file.edit(vec![lsp_types::TextDocumentContentChangeEvent {
+            range: Some(lsp_types::Range { start, end: start }),
+            range_length: None,
+            text: "zero\n".into(),
+        }])
+        .unwrap();
+        let lines = file.changed_lines(&opts).unwrap();
+        assert_eq!(lines, vec![(0, 0, "zero".into()), (1, 0, "one".into())]);
+        assert_eq!(file.update(&lines, vec![]), vec![diag(2)]);
+
+        // Lines whose checked text changes are checked again, even if not edited.
+        file.edit(vec![lsp_types::TextDocumentContentChangeEvent {
+            range: Some(lsp_types::Range::new(
+                lsp_types::Position::new(1, 0),
+                lsp_types::Position::new(1, 3),
+            )),
+            range_length: None,
+            text: "spelgud:disable-next-line".into(),
+        }])
+        .unwrap();
+        let lines = file.changed_lines(&opts).unwrap();
+        assert_eq!(lines, vec![(1, 0, " ".repeat(25)), (2, 0, " ".repeat(7))]);
+        assert_eq!(file.update(&lines, vec![]), vec![]);
+
+        file.invalidate();
+        let lines = file.changed_lines(&opts).unwrap();
+        assert_eq!(lines.len(), 4);
+
+        // Lines can be checked a few at a time, with the rest left unchecked until later.
+        assert_eq!(file.update(&lines[..2], vec![]), vec![]);
+        let lines = file.changed_lines(&opts).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(file.update(&lines, vec![diag(3)]), vec![diag(3)]);
+        assert!(file.changed_lines(&opts).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_changed_lines() {
+        let opts = filter::Options::default();
+        let insert = |file: &mut File, line, text: &str| {
+            let pos = lsp_types::Position::new(line, 0);
+            let change = lsp_types::TextDocumentContentChangeEvent {
+                range: Some(lsp_types::Range::new(pos, pos)),
+                range_length: None,
+                text: text.into(),
+            };
+            file.edit(vec![change]).unwrap();
+        };
+        let numbers = |lines: Vec<(u32, u32, String)>| -> Vec<u32> {
+            lines.into_iter().map(|(n, _, _)| n).collect()
+        };
+
+        // Plain text is filtered a line at a time, so only edited lines are checked again.
+        let mut file = File::new("one\ntwo\nthree\n".into(), Filter::Text).unwrap();
+        let lines = file.changed_lines(&opts).unwrap();
+        file.update(&lines, vec![]);
+        insert(&mut file, 1, "x");
+        let lines = file.changed_lines(&opts).unwrap();
+        assert_eq!(lines, vec![(1, 0, "xtwo".into())]);
+        file.update(&lines, vec![]);
+        // An empty last line isn't a line.
+        insert(&mut file, 3, "four");
+        assert_eq!(numbers(file.changed_lines(&opts).unwrap()), [3]);
+
+        // Other filters need the whole document, e.g. to know a line is now in a code block.
+        let mut file = File::new("Some txt\n\nMore txt\n".into(), Filter::Markdown).unwrap();
+        let lines = file.changed_lines(&opts).unwrap();
+        file.update(&lines, vec![]);
+        insert(&mut file, 1, "```\n");
+        assert_eq!(numbers(file.changed_lines(&opts).unwrap()), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_misspelling_at() {
+        let diag = lsp_types::Diagnostic {
+            range: lsp_types::Range {
+                start: lsp_types::Position::new(1, 4),
+                end: lsp_types::Position::new(1, 7),
+            },
+            message: "teh".into(),
+            ..Default::default()
+        };
+        let mut file = File::new("one\ntwo teh\nthree".into(), Filter::Text).unwrap();
+        let lines = file.changed_lines(&filter::Options::default()).unwrap();
+        file.update(&lines, vec![diag.clone()]);
+        assert_eq!(file.misspelling_at(1, 5), Some(&diag));
+        assert_eq!(file.misspelling_at(1, 2), None);
+        assert_eq!(file.misspelling_at(0, 5), None);
+        assert_eq!(file.misspelling_at(5, 5), None);
     }
 }
@@ -0,0 +1,320 @@
+// Filters decide which parts of a document are prose that should be spell checked.
+// Skipped regions are blanked out with spaces rather than removed, so the lines and
+// character offsets reported by the checker still line up with the original text.
+mod commit;
+mod directive;
+mod email;
+mod frontmatter;
+mod html;
+mod latex;
+mod markdown;
+mod notebook;
+mod rst;
+
+use crate::syntax::{Language, Syntax};
+use lsp_types::Url;
+use std::collections::HashMap;
+
+pub use frontmatter::Frontmatter;
+
+// User-configurable options that control how documents are filtered.
+#[derive(serde::Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Options {
+    pub frontmatter: Frontmatter,
+    // Comment and string syntax for languages, keyed by language id.
+    pub languages: HashMap<String, Language>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    Text,
+    Markdown,
+    Latex,
+    Bibtex,
+    Html,
+    Rst,
+    // An email message, where quoted text and signatures are skipped.
+    Email,
+    // A Jupyter notebook, where only markdown cells are checked.
+    Notebook,
+    // A git commit message, where comments and trailers are skipped.
+    Commit,
+    // Source code, where only comments and/or strings are checked.
+    Code(Language),
+}
+
+impl Filter {
+    // Pick a filter based on the language id provided by the client,
+    // falling back to the file extension if the client did not provide one.
+    pub fn detect(language_id: &str, uri: &Url, opts: &Options) -> Filter {
+        let language_id = match (language_id, extension(uri)) {
+            ("", Some(ext)) => opts
+                .languages
+                .iter()
+                .find(|(_, lang)| lang.extensions.iter().any(|e| e == ext))
+                .map(|(id, _)| id.as_str())
+                .or_else(|| language_for(ext))
+                .unwrap_or_default(),
+            ("", None) if is_commit_message(uri) => "git-commit",
+            (id, _) => id,
+        };
+        let lang = opts.languages.get(language_id).cloned().unwrap_or_default();
+        if lang.syntax != Syntax::default() {
+            return Filter::Code(lang);
+        }
+        match language_id {
+            "markdown" => Filter::Markdown,
+            "latex" | "tex" => Filter::Latex,
+            "bibtex" => Filter::Bibtex,
+            "html" | "xhtml" | "xml" => Filter::Html,
+            "restructuredtext" | "rst" => Filter::Rst,
+            "mail" | "email" => Filter::Email,
+            "jupyter" => Filter::Notebook,
+            "git-commit" | "gitcommit" => Filter::Commit,
+            // Some clients open notebooks as plain JSON
+            "json" if extension(uri) == Some("ipynb") => Filter::Notebook,
+            id => match Syntax::builtin(id) {
+                Some(syntax) => Filter::Code(Language { syntax, ..lang }),
+                None => Filter::Text,
+            },
+        }
+    }
+
+    // Return a copy of the text with all non-prose regions blanked out.
+    pub fn apply(&self, text: &str, opts: &Options) -> String {
+        let mut ranges = match self {
+            Filter::Text => vec![],
+            Filter::Markdown => {
+                let mut ranges = frontmatter::skip(text, &opts.frontmatter);
+                ranges.extend(markdown::skip(text));
+                ranges
+            }
+            Filter::Latex => latex::skip(text),
+            Filter::Bibtex => latex::skip_bibtex(text),
+            Filter::Html => html::skip(text),
+            Filter::Rst => rst::skip(text),
+            Filter::Email => email::skip(text),
+            Filter::Notebook => notebook::skip(text),
+            Filter::Commit => commit::skip(text),
+            Filter::Code(lang) => {
+                let regions = lang.syntax.regions(text).into_iter();
+                let regions = regions.filter(|r| lang.check.includes(r.kind));
+                complement(regions.map(|r| r.range), text.len())
+            }
+        };
+        ranges.extend(directive::skip(text));
+        ranges.sort_by_key(|r| r.start);
+        mask(text, &ranges)
+    }
+
+    // Whether each line can be filtered on its own, as nothing but directives (see
+    // `spans_lines`) carries from one line to the next.
+    pub fn is_line_local(&self) -> bool {
+        *self == Filter::Text
+    }
+
+    // Return the byte ranges of the words in identifiers, if identifiers should be checked.
+    pub fn identifiers(&self, text: &str) -> Vec<std::ops::Range<usize>> {
+        match self {
+            Filter::Code(lang) if lang.identifiers => {
+                let disabled = directive::skip(text);
+                let mut words = lang.syntax.identifiers(text);
+                words.retain(|w| !disabled.iter().any(|d| d.start < w.end && w.start < d.end));
+                words
+            }
+            _ => vec![],
+        }
+    }
+}
+
+// Whether a directive in the line turns checking off past the end of the line.
+pub fn spans_lines(line: &str) -> bool {
+    directive::spans_lines(line)
+}
+
+// The extension of the file name at the end of the URI's path, which may not be a
+// hierarchical path, e.g. "untitled:Untitled-1.md". The path of a notebook cell is that
+// of its notebook, which says nothing about the cell's language.
+fn extension(uri: &Url) -> Option<&str> {
+    if uri.scheme() == "vscode-notebook-cell" {
+        return None;
+    }
+    let name = uri.path().rsplit('/').next()?;
+    name.rsplit_once('.').map(|(_, ext)| ext)
+}
+
+// Whether the URI is of a file git opens to edit a commit, merge or tag message.
+fn is_commit_message(uri: &Url) -> bool {
+    let name = uri.path().rsplit('/').next();
+    matches!(name, Some("COMMIT_EDITMSG" | "MERGE_MSG" | "TAG_EDITMSG"))
+}
+
+// Map a file extension to the language id a client would likely send for it.
+pub fn language_for(extension: &str) -> Option<&'static str> {
+    Some(match extension {
+        "md" | "markdown" => "markdown",
+        "tex" | "ltx" | "sty" | "cls" => "latex",
+        "bib" => "bibtex",
+        "html" | "htm" | "xhtml" => "html",
+        "xml" => "xml",
+        "rst" | "rest" => "restructuredtext",
+        "eml" => "mail",
+        "ipynb" => "jupyter",
+        ext => return Syntax::language_id(ext),
+    })
+}
+
+// Given sorted, non-overlapping ranges to keep, return the ranges to skip.
+fn complement(
+    keep: impl Iterator<Item = std::ops::Range<usize>>,
+    len: usize,
+) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = vec![];
+    let mut start = 0;
+    for r in keep {
+        ranges.push(start..r.start);
+        start = r.end;
+    }
+    ranges.push(start..len);
+    ranges
+}
+
+// Replace every character within the given byte ranges, which must be sorted by start, with a space.
+// Newlines are preserved, so line numbers are unaffected.
+fn mask(text: &str, ranges: &[std::ops::Range<usize>]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut ranges = ranges.iter().peekable();
+    for (i, c) in text.char_indices() {
+        while ranges.next_if(|r| r.end <= i).is_some() {}
+        let skip = ranges.peek().is_some_and(|r| r.start <= i);
+        out.push(if skip && c != '\n' && c != '\r' {
+            ' '
+        } else {
+            c
+        });
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_detect() {
+        let uri = |s| Url::parse(s).unwrap();
+        let detect = |id, u| Filter::detect(id, &uri(u), &Options::default());
+        assert_eq!(detect("markdown", "file:///a/README"), Filter::Markdown);
+        assert_eq!(detect("", "file:///a/README.md"), Filter::Markdown);
+        assert_eq!(detect("plaintext", "file:///a/README.md"), Filter::Text);
+        assert_eq!(detect("", "file:///a/notes"), Filter::Text);
+        assert_eq!(detect("mail", "file:///tmp/mutt-host-1000"), Filter::Email);
+        assert_eq!(detect("", "file:///a/draft.eml"), Filter::Email);
+        assert_eq!(detect("git-commit", "file:///a/msg"), Filter::Commit);
+        assert_eq!(detect("", "file:///a/.git/COMMIT_EDITMSG"), Filter::Commit);
+        assert_eq!(detect("", "untitled:Untitled-1.md"), Filter::Markdown);
+        assert_eq!(detect("markdown", "untitled:Untitled-1"), Filter::Markdown);
+        assert_eq!(detect("", "untitled:Untitled-1"), Filter::Text);
+        assert_eq!(
+            detect("", "vscode-notebook-cell:/a/nb.ipynb#W0"),
+            Filter::Text
+        );
+        assert_eq!(
+            detect("markdown", "vscode-notebook-cell:/a/nb.ipynb#W0"),
+            Filter::Markdown
+        );
+        assert_eq!(
+            detect("", "vscode-remote://ssh-remote+host/a/README.md"),
+            Filter::Markdown
+        );
+        assert_eq!(
+            detect("", "file:///a/main.rs"),
+            Filter::Code(Language {
+                syntax: Syntax::builtin("rust").unwrap(),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            detect("python", "file:///a/script"),
+            Filter::Code(Language {
+                syntax: Syntax::builtin("python").unwrap(),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_code() {
+        let filter = Filter::detect(
+            "rust",
+            &Url::parse("file:///a/main.rs").unwrap(),
+            &Options::default(),
+        );
+        assert_eq!(
+            filter.apply(
+                "// Retrun x\nfn f(x: u8) -> &str { \"wrod\" }",
+                &Options::default()
+            ),
+            "   Retrun x\n                       wrod   "
+        );
+    }
+
+    #[test]
+    fn test_configured_language() {
+        let opts: Options = serde_json::from_value(serde_json::json!({
+            "languages": {
+                "haskell": {
+                    "extensions": ["hs"],
+                    "line_comments": ["--"],
+                    "block_comments": [["{-", "-}"]],
+                    "strings": [["\"", "\""]],
+                },
+                "rust": {
+                    "line_comments": ["//"],
+                },
+            }
+        }))
+        .unwrap();
+        let uri = Url::parse("file:///a/Main.hs").unwrap();
+        let filter = Filter::detect("", &uri, &opts);
+        assert_eq!(filter, Filter::detect("haskell", &uri, &opts));
+        assert_eq!(
+            filter.apply("x = 1 -- a coment\n{- blok -} \"strng\"", &opts),
+            "         a coment\n   blok     strng "
+        );
+
+        // Configuration overrides the built-in syntax
+        let filter = Filter::detect("rust", &uri, &opts);
+        assert_eq!(filter.apply("// yes\n\"no\"", &opts), "   yes\n    ");
+    }
+
+    #[test]
+    fn test_mode() {
+        let opts: Options = serde_json::from_value(serde_json::json!({
+            "languages": {
+                "javascript": { "check": "strings" },
+                "go": { "check": "comments" },
+            }
+        }))
+        .unwrap();
+        let uri = Url::parse("file:///a/main").unwrap();
+        let check = |id, text| Filter::detect(id, &uri, &opts).apply(text, &opts);
+        assert_eq!(
+            check("javascript", "// getUsrName\nalert('Helo')"),
+            "             \n       Helo  "
+        );
+        assert_eq!(
+            check("go", "// getUsrName\nfmt.Print(\"Helo\")"),
+            "   getUsrName\n                 "
+        );
+    }
+
+    #[test]
+    fn test_mask() {
+        assert_eq!(mask("one two\nthree", &[]), "one two\nthree");
+        assert_eq!(mask("one two\nthree", &[4..7, 8..10]), "one    \n  ree");
+        assert_eq!(mask("añb ñ", &[1..3, 5..7]), "a b  ");
+    }
+}
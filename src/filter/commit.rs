@@ -0,0 +1,113 @@
+// A filter for git commit messages, such as COMMIT_EDITMSG or those in `git log`.
+// Comments, the diff shown by `git commit --verbose` and trailers such as
+// `Signed-off-by: Name <email>` are skipped.
+use std::ops::Range;
+
+// The line above the diff shown by `git commit --verbose`.
+const SCISSORS: &str = "# ------------------------ >8 ------------------------";
+
+// Return the byte ranges of the message that should not be spell checked.
+pub fn skip(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    // The lines of the paragraph being read, and whether each is a trailer.
+    let mut paragraph = vec![];
+    // Whether the paragraph is the first, i.e. the subject, which is never made of trailers.
+    let mut subject = true;
+    // Whether the last line was blank, so the next starts a paragraph.
+    let mut blank = false;
+    let mut start = 0;
+    for line in text.split_inclusive('\n') {
+        let end = start + line.len();
+        let trimmed = line.trim_end_matches(['\r', '\n']);
+        if trimmed == SCISSORS {
+            ranges.push(start..text.len());
+            break;
+        }
+        if line.starts_with('#') {
+            ranges.push(start..end);
+        } else if trimmed.trim().is_empty() {
+            blank = true;
+        } else {
+            if blank && !paragraph.is_empty() {
+                subject = false;
+                paragraph.clear();
+            }
+            blank = false;
+            paragraph.push((start..end, is_trailer(trimmed)));
+        }
+        start = end;
+    }
+    // Trailers are only recognized in the last paragraph.
+    if !subject && paragraph.iter().all(|(_, trailer)| *trailer) {
+        ranges.extend(paragraph.into_iter().map(|(range, _)| range));
+        ranges.sort_by_key(|r| r.start);
+    }
+    ranges
+}
+
+// Whether the line is a trailer such as `Signed-off-by: Name <email>`.
+fn is_trailer(line: &str) -> bool {
+    let Some((token, value)) = line.split_once(": ") else {
+        return false;
+    };
+    let valid = !token.is_empty() && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    valid && !value.trim().is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filter::{Filter, Options};
+    use pretty_assertions::assert_eq;
+
+    fn check(text: &[&str], expected: &[&str]) {
+        let text = text.join("\n");
+        let expected = expected.join("\n");
+        assert_eq!(Filter::Commit.apply(&text, &Options::default()), expected);
+    }
+
+    #[test]
+    fn test_commit() {
+        check(
+            &[
+                "Fix teh parser",
+                "",
+                "It brok on empty input.",
+                "Note: this is not a trailer.",
+                "",
+                "Signed-off-by: Smoe One <smoe@exmple.com>",
+                "Reviewed-by: Othr <othr@exmple.com>",
+                "# Plese enter the commit message",
+                "# ------------------------ >8 ------------------------",
+                "diff --git a/mian.rs b/mian.rs",
+                "",
+            ],
+            &[
+                "Fix teh parser",
+                "",
+                "It brok on empty input.",
+                "Note: this is not a trailer.",
+                "",
+                "                                         ",
+                "                                   ",
+                "                                ",
+                "                                                      ",
+                "                              ",
+                "",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_trailers() {
+        check(
+            &["Fix teh parser", "", "Fixes: #12", "Acked-by: Smoe", "", ""],
+            &["Fix teh parser", "", "          ", "              ", "", ""],
+        );
+        // Only the last paragraph can be trailers, and the subject never is.
+        check(&["Fixes: teh parser"], &["Fixes: teh parser"]);
+        check(
+            &["# Coment", "Fixes: teh parser", ""],
+            &["        ", "Fixes: teh parser", ""],
+        );
+    }
+}
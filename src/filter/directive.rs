@@ -0,0 +1,148 @@
+// Inline markers that let authors turn checking off for part of a document, e.g.
+// `spelgud:disable` ... `spelgud:enable`, `spelgud:disable-line` or `spelgud:disable-next-line`.
+// Markers are recognized anywhere, so they can be placed in whatever comment syntax the file uses.
+use std::ops::Range;
+
+const PREFIX: &str = "spelgud:";
+
+// Return the byte ranges of `text` that have been disabled by a marker.
+// The markers themselves are always skipped.
+pub fn skip(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    // The start of the current disabled region, if any.
+    let mut disabled: Option<usize> = None;
+    let mut next_line = false;
+    let mut start = 0;
+    for line in text.split_inclusive('\n') {
+        let end = start + line.len();
+        let mut skip_line = std::mem::take(&mut next_line);
+        for (offset, marker) in markers(line) {
+            let marker_start = start + offset;
+            match marker {
+                "disable" => {
+                    disabled.get_or_insert(marker_start);
+                }
+                "enable" => {
+                    if let Some(from) = disabled.take() {
+                        ranges.push(from..marker_start);
+                    }
+                }
+                "disable-line" => skip_line = true,
+                "disable-next-line" => next_line = true,
+                _ => continue,
+            }
+            ranges.push(marker_start..marker_start + PREFIX.len() + marker.len());
+        }
+        if skip_line {
+            ranges.push(start..end);
+        }
+        start = end;
+    }
+    if let Some(from) = disabled {
+        ranges.push(from..text.len());
+    }
+    ranges.sort_by_key(|r| r.start);
+    ranges
+}
+
+// Whether a marker in the line turns checking off for other lines.
+pub fn spans_lines(line: &str) -> bool {
+    markers(line).any(|(_, marker)| matches!(marker, "disable" | "disable-next-line"))
+}
+
+// Find each `spelgud:<name>` marker in the line, returning its offset and name.
+fn markers(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    line.match_indices(PREFIX).map(move |(i, _)| {
+        let rest = &line[i + PREFIX.len()..];
+        let len = rest
+            .find(|c: char| !c.is_ascii_lowercase() && c != '-')
+            .unwrap_or(rest.len());
+        (i, &rest[..len])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filter::{Filter, Options};
+    use pretty_assertions::assert_eq;
+
+    fn check(filter: Filter, text: &[&str], expected: &[&str]) {
+        let text = text.join("\n");
+        let expected = expected.join("\n");
+        assert_eq!(filter.apply(&text, &Options::default()), expected);
+    }
+
+    #[test]
+    fn test_region() {
+        check(
+            Filter::Text,
+            &[
+                "Befor",
+                "spelgud:disable",
+                "qwerty asdfgh",
+                "spelgud:enable",
+                "Aftr",
+            ],
+            &[
+                "Befor",
+                "               ",
+                "             ",
+                "              ",
+                "Aftr",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_unterminated() {
+        check(
+            Filter::Text,
+            &["Befor", "<!-- spelgud:disable -->", "zxcv"],
+            &["Befor", "<!--                    ", "    "],
+        );
+    }
+
+    #[test]
+    fn test_line() {
+        check(
+            Filter::Text,
+            &[
+                "Chek",
+                "qwerty  # spelgud:disable-line",
+                "# spelgud:disable-next-line",
+                "asdfgh",
+                "Chek",
+            ],
+            &[
+                "Chek",
+                "                              ",
+                "#                          ",
+                "      ",
+                "Chek",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_code() {
+        let filter = Filter::detect(
+            "rust",
+            &lsp_types::Url::parse("file:///a/main.rs").unwrap(),
+            &Options::default(),
+        );
+        check(
+            filter,
+            &["// spelgud:disable-next-line", "// ┌─┐ hjkl", "// Chek"],
+            &["                            ", "           ", "   Chek"],
+        );
+    }
+
+    #[test]
+    fn test_unknown() {
+        check(
+            Filter::Text,
+            &["See spelgud:README for detials"],
+            &["See spelgud:README for detials"],
+        );
+    }
+}
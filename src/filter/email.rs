@@ -0,0 +1,121 @@
+// A filter for email messages, such as the drafts opened by mutt, aerc or himalaya.
+// Only the new body text is checked: headers (other than the subject), quoted replies
+// and the signature are skipped.
+use std::ops::Range;
+
+// Return the byte ranges of the message that should not be spell checked.
+pub fn skip(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    let mut start = 0;
+    let mut lines = text.split_inclusive('\n').peekable();
+
+    // Headers run until the first blank line, if the message starts with one.
+    if lines.peek().is_some_and(|line| header_name(line).is_some()) {
+        // Whether the current header's value should be checked.
+        // Indented lines continue the value of the preceding header.
+        let mut checked = false;
+        for line in lines.by_ref() {
+            let end = start + line.len();
+            if line.trim().is_empty() {
+                start = end;
+                break;
+            }
+            if line.starts_with([' ', '\t']) {
+                if !checked {
+                    ranges.push(start..end);
+                }
+            } else {
+                match header_name(line) {
+                    Some(name) if name.eq_ignore_ascii_case("subject") => {
+                        checked = true;
+                        ranges.push(start..start + name.len() + 1);
+                    }
+                    _ => {
+                        checked = false;
+                        ranges.push(start..end);
+                    }
+                }
+            }
+            start = end;
+        }
+    }
+
+    for line in lines {
+        let end = start + line.len();
+        // Everything after the signature delimiter is skipped.
+        if line.trim_end_matches(['\r', '\n']) == "-- " {
+            ranges.push(start..text.len());
+            break;
+        }
+        if line.starts_with('>') {
+            ranges.push(start..end);
+        }
+        start = end;
+    }
+    ranges
+}
+
+// If the line is a header such as `From: someone`, return the header name.
+fn header_name(line: &str) -> Option<&str> {
+    let (name, _) = line.split_once(':')?;
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    valid.then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filter::{Filter, Options};
+    use pretty_assertions::assert_eq;
+
+    fn check(text: &[&str], expected: &[&str]) {
+        let text = text.join("\n");
+        let expected = expected.join("\n");
+        assert_eq!(Filter::Email.apply(&text, &Options::default()), expected);
+    }
+
+    #[test]
+    fn test_email() {
+        check(
+            &[
+                "From: Smoe One <smoe@exmple.com>",
+                "To: othr@exmple.com,",
+                "  thrid@exmple.com",
+                "Subject: Re: Meeting agnda",
+                "",
+                "On Tue, Bob wrote:",
+                "> Can we mete tomorow?",
+                ">> Erlier qoute",
+                "",
+                "Shure, see you thn.",
+                "",
+                "-- ",
+                "Smoe One",
+                "Senoir Enginer",
+            ],
+            &[
+                "                                ",
+                "                    ",
+                "                  ",
+                "         Re: Meeting agnda",
+                "",
+                "On Tue, Bob wrote:",
+                "                      ",
+                "               ",
+                "",
+                "Shure, see you thn.",
+                "",
+                "   ",
+                "        ",
+                "              ",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_no_headers() {
+        check(
+            &["Helo there,", "> qouted", "Bye -- for now"],
+            &["Helo there,", "        ", "Bye -- for now"],
+        );
+    }
+}
@@ -0,0 +1,126 @@
+// Handling for YAML (---) or TOML (+++) frontmatter at the start of a document.
+// Frontmatter is mostly metadata such as dates, tags and layouts, which produce noisy diagnostics.
+use std::ops::Range;
+
+#[derive(serde::Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Frontmatter {
+    // Check the frontmatter like any other text.
+    #[default]
+    Check,
+    // Skip the frontmatter entirely.
+    Skip,
+    // Check only the values of the listed fields, e.g. title and description.
+    Fields(Vec<String>),
+}
+
+// Return the byte ranges of the frontmatter that should not be spell checked.
+pub fn skip(text: &str, mode: &Frontmatter) -> Vec<Range<usize>> {
+    if *mode == Frontmatter::Check {
+        return vec![];
+    }
+    let Some(end) = block_end(text) else {
+        return vec![];
+    };
+    let Frontmatter::Fields(fields) = mode else {
+        return vec![Range { start: 0, end }];
+    };
+
+    let mut ranges = vec![];
+    let mut start = 0;
+    // Whether the current field should be checked.
+    // Indented lines continue the value of the preceding field.
+    let mut checked = false;
+    for line in text[..end].split_inclusive('\n') {
+        let end = start + line.len();
+        if !line.starts_with([' ', '\t']) {
+            match line.find([':', '=']) {
+                Some(sep) if fields.iter().any(|f| f == line[..sep].trim()) => {
+                    checked = true;
+                    ranges.push(start..start + sep + 1);
+                }
+                _ => {
+                    checked = false;
+                    ranges.push(start..end);
+                }
+            }
+        } else if !checked {
+            ranges.push(start..end);
+        }
+        start = end;
+    }
+    ranges
+}
+
+// If the text begins with a frontmatter block, return the byte offset of its end.
+fn block_end(text: &str) -> Option<usize> {
+    let mut lines = text.split_inclusive('\n');
+    let delim = lines.next()?.trim_end();
+    if delim != "---" && delim != "+++" {
+        return None;
+    }
+    let mut end = text.find('\n')? + 1;
+    for line in lines {
+        end += line.len();
+        let line = line.trim_end();
+        // YAML documents may also be terminated by "..."
+        if line == delim || (delim == "---" && line == "...") {
+            return Some(end);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{Filter, Options};
+    use pretty_assertions::assert_eq;
+
+    const TEXT: &str = "---\n\
+                        title: A ttile\n\
+                        datePublished: 2024-01-01\n\
+                        description: >\n  a lnog description\n\
+                        tags:\n  - rustlang\n\
+                        ---\n\
+                        Body txt";
+
+    fn check(mode: Frontmatter, expected: &str) {
+        let opts = Options {
+            frontmatter: mode,
+            ..Default::default()
+        };
+        assert_eq!(Filter::Markdown.apply(TEXT, &opts), expected);
+    }
+
+    #[test]
+    fn test_check() {
+        check(Frontmatter::Check, TEXT);
+    }
+
+    #[test]
+    fn test_skip() {
+        check(
+            Frontmatter::Skip,
+            "   \n              \n                         \n              \n                    \n     \n            \n   \nBody txt",
+        );
+    }
+
+    #[test]
+    fn test_fields() {
+        check(
+            Frontmatter::Fields(vec!["title".into(), "description".into()]),
+            "   \n       A ttile\n                         \n             >\n  a lnog description\n     \n            \n   \nBody txt",
+        );
+    }
+
+    #[test]
+    fn test_no_frontmatter() {
+        assert!(skip("# Title\n---\n", &Frontmatter::Skip).is_empty());
+        assert!(skip("---\nunterminated\n", &Frontmatter::Skip).is_empty());
+        assert_eq!(
+            skip("+++\ntitle = 'x'\n+++\nbody", &Frontmatter::Skip).first(),
+            Some(&(0..20))
+        );
+    }
+}
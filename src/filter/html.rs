@@ -0,0 +1,133 @@
+// A scanner for HTML and XML that checks only text nodes.
+// Tags (including their attributes), comments, processing instructions,
+// entities and the contents of <script> and <style> are skipped.
+// This is used for every checker, rather than aspell's --mode=html, as checkers are given
+// a line at a time and an aspell process is shared by documents of every language.
+use std::ops::Range;
+
+// Elements whose content is not prose.
+const RAW_ELEMENTS: &[&str] = &["script", "style"];
+
+// Return the byte ranges of `text` that should not be spell checked.
+pub fn skip(text: &str) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let mut ranges = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let skipped = match bytes[i] {
+            b'<' => tag(text, i),
+            b'&' => entity(text, i),
+            b']' if text[i..].starts_with("]]>") => Some(i..i + 3),
+            _ => None,
+        };
+        match skipped {
+            Some(r) => {
+                i = r.end;
+                ranges.push(r);
+            }
+            None => i += 1,
+        }
+    }
+    ranges
+}
+
+// Skip from `start` through the end of the first occurrence of `pat`.
+// If `pat` does not occur, skip to the end of the text.
+fn through(text: &str, start: usize, pat: &str) -> usize {
+    text[start..]
+        .find(pat)
+        .map_or(text.len(), |i| start + i + pat.len())
+}
+
+fn tag(text: &str, start: usize) -> Option<Range<usize>> {
+    let rest = &text[start..];
+    if rest.starts_with("<!--") {
+        return Some(start..through(text, start + 4, "-->"));
+    }
+    if rest.starts_with("<![CDATA[") {
+        // CDATA is text content, skip only the delimiters.
+        return Some(start..start + "<![CDATA[".len());
+    }
+    let name: String = rest[1..]
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || matches!(c, '-' | ':' | '_'))
+        .collect();
+    let is_tag = !name.is_empty() || rest[1..].starts_with(['/', '!', '?']);
+    if !is_tag {
+        // A bare '<', as in "a < b"
+        return None;
+    }
+    let end = tag_end(text, start);
+    let name = name.to_lowercase();
+    if RAW_ELEMENTS.contains(&name.as_str()) && !text[..end].ends_with("/>") {
+        let close = text[end..].find(&format!("</{name}"));
+        return Some(start..close.map_or(text.len(), |i| tag_end(text, end + i)));
+    }
+    Some(start..end)
+}
+
+// Find the end of a tag, ignoring any '>' within quoted attribute values.
+fn tag_end(text: &str, start: usize) -> usize {
+    let mut quote = None;
+    for (i, b) in text.bytes().enumerate().skip(start) {
+        match (quote, b) {
+            (None, b'"' | b'\'') => quote = Some(b),
+            (Some(q), b) if q == b => quote = None,
+            (None, b'>') => return i + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+// Entities look like &nbsp; &#160; or &#xA0;
+fn entity(text: &str, start: usize) -> Option<Range<usize>> {
+    let rest = &text[start + 1..];
+    let len = rest
+        .bytes()
+        .take(32)
+        .take_while(|b| b.is_ascii_alphanumeric() || *b == b'#')
+        .count();
+    (len > 0 && rest[len..].starts_with(';')).then_some(start..start + len + 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filter::{Filter, Options};
+    use pretty_assertions::assert_eq;
+
+    fn check(text: &str, expected: &str) {
+        assert_eq!(Filter::Html.apply(text, &Options::default()), expected);
+    }
+
+    #[test]
+    fn test_tags() {
+        check(
+            "<p class=\"wrnig\">Some <b>txt</b></p>",
+            "                 Some    txt        ",
+        );
+        check(
+            "<a title='x>y' href=\"/lnk\">here</a>",
+            "                           here    ",
+        );
+        check("if a < b then", "if a < b then");
+        check(
+            "<?xml version=\"1.0\"?>\n<!-- a coment -->\n<doc>tekst</doc>",
+            "                     \n                 \n     tekst      ",
+        );
+    }
+
+    #[test]
+    fn test_raw_elements() {
+        check(
+            "<script>let wrod = 1;</script>ok<style>.clss {}</style>",
+            "                              ok                       ",
+        );
+    }
+
+    #[test]
+    fn test_entities() {
+        check("a&nbsp;b &#160; c&amp;d & e", "a      b        c     d & e");
+        check("<![CDATA[some txet]]>", "         some txet   ");
+    }
+}
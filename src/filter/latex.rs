@@ -0,0 +1,312 @@
+// Scanners for LaTeX and BibTeX that skip commands, math, comments and
+// references, leaving the prose (including the arguments of formatting commands like \textbf).
+use std::ops::Range;
+
+// Commands whose arguments are identifiers or paths rather than prose.
+const ARG_COMMANDS: &[&str] = &[
+    "autoref",
+    "bibliography",
+    "bibliographystyle",
+    "cite",
+    "citep",
+    "citet",
+    "cref",
+    "Cref",
+    "documentclass",
+    "eqref",
+    "href",
+    "include",
+    "includegraphics",
+    "input",
+    "label",
+    "nocite",
+    "pageref",
+    "ref",
+    "url",
+    "usepackage",
+];
+
+// Environments whose entire body should be skipped.
+const SKIP_ENVIRONMENTS: &[&str] = &[
+    "align",
+    "align*",
+    "displaymath",
+    "eqnarray",
+    "eqnarray*",
+    "equation",
+    "equation*",
+    "gather",
+    "gather*",
+    "lstlisting",
+    "math",
+    "minted",
+    "multline",
+    "multline*",
+    "verbatim",
+    "verbatim*",
+];
+
+// BibTeX fields whose values are prose.
+const PROSE_FIELDS: &[&str] = &[
+    "abstract",
+    "annote",
+    "booktitle",
+    "journal",
+    "note",
+    "series",
+    "title",
+];
+
+// Return the byte ranges of a LaTeX document that should not be spell checked.
+pub fn skip(text: &str) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let mut ranges = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let skipped = match bytes[i] {
+            b'%' => Some(i..line_end(text, i)),
+            b'$' => Some(math(text, i)),
+            b'\\' => Some(command(text, i)),
+            _ => None,
+        };
+        match skipped {
+            Some(r) => {
+                i = r.end;
+                ranges.push(r);
+            }
+            None => i += 1,
+        }
+    }
+    ranges
+}
+
+fn line_end(text: &str, start: usize) -> usize {
+    text[start..].find('\n').map_or(text.len(), |i| start + i)
+}
+
+// Skip from `start` through the end of the first occurrence of `pat`.
+// If `pat` does not occur, skip to the end of the text.
+fn through(text: &str, start: usize, pat: &str) -> usize {
+    text[start..]
+        .find(pat)
+        .map_or(text.len(), |i| start + i + pat.len())
+}
+
+// Inline math ($...$) or display math ($$...$$).
+fn math(text: &str, start: usize) -> Range<usize> {
+    if text[start..].starts_with("$$") {
+        return start..through(text, start + 2, "$$");
+    }
+    let bytes = text.as_bytes();
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'$' => return start..i + 1,
+            _ => i += 1,
+        }
+    }
+    start..start + 1
+}
+
+fn command(text: &str, start: usize) -> Range<usize> {
+    let rest = &text[start + 1..];
+    let name_len = rest.bytes().take_while(|b| b.is_ascii_alphabetic()).count();
+    if name_len == 0 {
+        // A control symbol such as \% or \\, or the start of math with \( or \[.
+        return match rest.chars().next() {
+            Some('(') => start..through(text, start, "\\)"),
+            Some('[') => start..through(text, start, "\\]"),
+            Some(c) => start..start + 1 + c.len_utf8(),
+            None => start..start + 1,
+        };
+    }
+    let name = &rest[..name_len];
+    let mut end = start + 1 + name_len;
+    if text[end..].starts_with('*') {
+        end += 1;
+    }
+    match name {
+        "begin" => {
+            let env_end = group(text, end, b'{', b'}');
+            let env = text[end..env_end].trim_matches(&['{', '}'][..]);
+            if SKIP_ENVIRONMENTS.contains(&env) {
+                start..through(text, env_end, &format!("\\end{{{env}}}"))
+            } else {
+                start..groups(text, env_end)
+            }
+        }
+        "end" => start..group(text, end, b'{', b'}'),
+        name if ARG_COMMANDS.contains(&name) => start..groups(text, end),
+        // The arguments of other commands (e.g. \emph{...}) are prose, skip only the options.
+        _ => start..group(text, end, b'[', b']'),
+    }
+}
+
+// Skip any number of consecutive [options] and {arguments}.
+fn groups(text: &str, mut start: usize) -> usize {
+    loop {
+        let end = group(text, group(text, start, b'[', b']'), b'{', b'}');
+        if end == start {
+            return end;
+        }
+        start = end;
+    }
+}
+
+// If a bracketed group begins at `start`, return the index just past its end.
+// Otherwise return `start`.
+fn group(text: &str, start: usize, open: u8, close: u8) -> usize {
+    let bytes = text.as_bytes();
+    if bytes.get(start) != Some(&open) {
+        return start;
+    }
+    let mut depth = 0;
+    for (i, &b) in bytes.iter().enumerate().skip(start) {
+        if b == open {
+            depth += 1;
+        } else if b == close {
+            depth -= 1;
+            if depth == 0 {
+                return i + 1;
+            }
+        }
+    }
+    text.len()
+}
+
+// Return the byte ranges of a BibTeX database that should not be spell checked.
+// Entry types, keys, field names and non-prose fields (author, url, ...) are skipped.
+// Text outside of entries is treated as a comment by BibTeX, and is also skipped.
+pub fn skip_bibtex(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    let mut i = 0;
+    while let Some(at) = text[i..].find('@').map(|at| at + i) {
+        ranges.push(i..at);
+        let Some(open) = text[at..].find(['{', '(']).map(|open| open + at) else {
+            i = at;
+            break;
+        };
+        let close = if text.as_bytes()[open] == b'{' {
+            b'}'
+        } else {
+            b')'
+        };
+        let end = group(text, open, text.as_bytes()[open], close);
+        // Skip the entry type and key, e.g. `@article{key,`
+        let mut field = text[open..end].find(',').map_or(end, |c| open + c + 1);
+        ranges.push(at..field);
+        while field < end {
+            let Some(eq) = text[field..end].find('=').map(|eq| eq + field) else {
+                ranges.push(field..end);
+                break;
+            };
+            let name = text[field..eq].trim().to_lowercase();
+            let value_end = field_end(text, eq + 1, end);
+            ranges.push(field..eq + 1);
+            if PROSE_FIELDS.contains(&name.as_str()) {
+                let value = &text[eq + 1..value_end];
+                ranges.extend(
+                    skip(value)
+                        .into_iter()
+                        .map(|r| r.start + eq + 1..r.end + eq + 1),
+                );
+            } else {
+                ranges.push(eq + 1..value_end);
+            }
+            field = value_end + 1;
+        }
+        i = end;
+    }
+    ranges.push(i..text.len());
+    ranges
+}
+
+// Find the comma that terminates a field value, or the end of the entry.
+fn field_end(text: &str, start: usize, end: usize) -> usize {
+    let mut depth = 0;
+    let mut quoted = false;
+    for (i, b) in text[start..end].bytes().enumerate() {
+        match b {
+            b'{' => depth += 1,
+            b'}' if depth > 0 => depth -= 1,
+            b'"' if depth == 0 => quoted = !quoted,
+            b',' if depth == 0 && !quoted => return start + i,
+            // The closing brace of the entry
+            b'}' | b')' if depth == 0 && !quoted => return start + i,
+            _ => {}
+        }
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filter::{Filter, Options};
+    use pretty_assertions::assert_eq;
+
+    fn check(filter: Filter, text: &str, expected: &str) {
+        assert_eq!(filter.apply(text, &Options::default()), expected);
+    }
+
+    #[test]
+    fn test_commands() {
+        check(
+            Filter::Latex,
+            "Some \\textbf{bold} and \\emph[x]{emphsis}.",
+            "Some        {bold} and         {emphsis}.",
+        );
+        check(
+            Filter::Latex,
+            "See \\ref{sec:intro} and \\cite[p.~3]{knuth84}, 100\\% sure\\\\",
+            "See                 and                     , 100   sure  ",
+        );
+        check(
+            Filter::Latex,
+            "text % a coment\n\\section*{Intro}",
+            "text           \n         {Intro}",
+        );
+    }
+
+    #[test]
+    fn test_math() {
+        check(
+            Filter::Latex,
+            "Let $x = \\alpha$ and $$y$$ or \\(z\\).",
+            "Let              and       or      .",
+        );
+        check(
+            Filter::Latex,
+            "A\n\\begin{equation}\nx = y\n\\end{equation}\nB",
+            "A\n                \n     \n              \nB",
+        );
+        check(
+            Filter::Latex,
+            "\\begin{itemize}[noitemsep]\n\\item Frist\n\\end{itemize}",
+            "                          \n      Frist\n             ",
+        );
+    }
+
+    #[test]
+    fn test_bibtex() {
+        let text = [
+            "% comment",
+            "@article{knuth84,",
+            "  author = {Donald Knuth},",
+            "  title = {Literate \\emph{Programing}},",
+            "  year = 1984",
+            "}",
+        ]
+        .join("\n");
+        let expected = [
+            "         ",
+            "                 ",
+            "                         ,",
+            "          {Literate      {Programing}},",
+            "             ",
+            "}",
+        ]
+        .join("\n");
+        check(Filter::Bibtex, &text, &expected);
+    }
+}
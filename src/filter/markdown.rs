@@ -0,0 +1,184 @@
+// A lightweight Markdown scanner that finds regions which are not prose:
+// fenced code blocks, inline code spans, link destinations, reference labels,
+// reference definitions and autolinks. Link text and image alt text are still checked.
+use std::ops::Range;
+
+// Return the byte ranges of `text` that should not be spell checked.
+pub fn skip(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    let mut fence: Option<(u8, usize)> = None;
+    let mut start = 0;
+    for line in text.split_inclusive('\n') {
+        let end = start + line.len();
+        let trimmed = line.trim_start_matches(' ');
+        let indent = line.len() - trimmed.len();
+        match fence {
+            Some((ch, len)) => {
+                ranges.push(start..end);
+                if indent < 4 && fence_len(trimmed, ch).is_some_and(|n| n >= len) {
+                    fence = None;
+                }
+            }
+            None if indent < 4 => {
+                if let Some((ch, len)) = open_fence(trimmed) {
+                    fence = Some((ch, len));
+                    ranges.push(start..end);
+                } else if is_reference_definition(trimmed) {
+                    ranges.push(start..end);
+                } else {
+                    inline(line, start, &mut ranges);
+                }
+            }
+            None => inline(line, start, &mut ranges),
+        }
+        start = end;
+    }
+    ranges
+}
+
+fn fence_len(line: &str, ch: u8) -> Option<usize> {
+    let n = line.bytes().take_while(|&b| b == ch).count();
+    (n >= 3).then_some(n)
+}
+
+fn open_fence(line: &str) -> Option<(u8, usize)> {
+    [b'`', b'~']
+        .into_iter()
+        .find_map(|ch| fence_len(line, ch).map(|n| (ch, n)))
+}
+
+// A reference definition looks like `[label]: destination "optional title"`.
+fn is_reference_definition(line: &str) -> bool {
+    let Some(rest) = line.strip_prefix('[') else {
+        return false;
+    };
+    match rest.find(']') {
+        Some(i) => i > 0 && rest[i + 1..].starts_with(':'),
+        None => false,
+    }
+}
+
+// Find the skipped ranges within a single line, which begins at byte `offset` of the text.
+fn inline(line: &str, offset: usize, ranges: &mut Vec<Range<usize>>) {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let skipped = match bytes[i] {
+            b'`' => code_span(bytes, i),
+            // The destination of a link or image: [text](dest) or ![alt](dest)
+            b']' if bytes.get(i + 1) == Some(&b'(') => closing(bytes, i + 1, b'(', b')'),
+            // The label of a reference link: [text][label]
+            b']' if bytes.get(i + 1) == Some(&b'[') => closing(bytes, i + 1, b'[', b']'),
+            b'<' => autolink(line, i),
+            _ => None,
+        };
+        match skipped {
+            Some(r) => {
+                i = r.end;
+                ranges.push(offset + r.start..offset + r.end);
+            }
+            None => i += 1,
+        }
+    }
+}
+
+// A code span is opened by a run of backticks, and closed by a run of the same length.
+fn code_span(bytes: &[u8], start: usize) -> Option<Range<usize>> {
+    let ticks = |i: usize| bytes[i..].iter().take_while(|&&b| b == b'`').count();
+    let len = ticks(start);
+    let mut i = start + len;
+    while i < bytes.len() {
+        if bytes[i] == b'`' {
+            let n = ticks(i);
+            if n == len {
+                return Some(start..i + n);
+            }
+            i += n;
+        } else {
+            i += 1;
+        }
+    }
+    // An unmatched run of backticks is literal text, skip just the run itself.
+    Some(start..start + len)
+}
+
+// Find the bracket matching the one at `start`, allowing for nested brackets.
+fn closing(bytes: &[u8], start: usize, open: u8, close: u8) -> Option<Range<usize>> {
+    let mut depth = 0;
+    for (i, &b) in bytes.iter().enumerate().skip(start) {
+        if b == open {
+            depth += 1;
+        } else if b == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(start..i + 1);
+            }
+        }
+    }
+    None
+}
+
+// An autolink looks like <https://example.com> or <mailto:foo@example.com>.
+fn autolink(line: &str, start: usize) -> Option<Range<usize>> {
+    let rest = &line[start + 1..];
+    let end = rest.find('>')?;
+    let link = &rest[..end];
+    let (scheme, _) = link.split_once(':')?;
+    let valid = !scheme.is_empty()
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric())
+        && !link.contains(char::is_whitespace);
+    valid.then_some(start..start + end + 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filter::{Filter, Options};
+    use pretty_assertions::assert_eq;
+
+    fn check(text: &str, expected: &str) {
+        assert_eq!(Filter::Markdown.apply(text, &Options::default()), expected);
+    }
+
+    #[test]
+    fn test_inline_code() {
+        check("run `cargo tset` now", "run              now");
+        check("a ``co`de`` b", "a           b");
+        check("unmatched ` tick", "unmatched   tick");
+    }
+
+    #[test]
+    fn test_links() {
+        check(
+            "see [the dcos](https://exmple.com/x_(y)) here",
+            "see [the dcos]                           here",
+        );
+        check(
+            "an ![imge](img/pictur.png \"titel\")",
+            "an ![imge]                        ",
+        );
+        check("a [refrence][lbel] link", "a [refrence]       link");
+        check("visit <https://exmple.com>", "visit                     ");
+        check("not <a tag> link", "not <a tag> link");
+    }
+
+    #[test]
+    fn test_reference_definitions() {
+        check(
+            "text\n[lbel]: https://exmple.com \"titel\"\n  [x]: y\nmore",
+            "text\n                                  \n        \nmore",
+        );
+        check("[not a def] here", "[not a def] here");
+    }
+
+    #[test]
+    fn test_fenced_code() {
+        check(
+            "prose\n```rust\nlet x = cde;\n```\nmore",
+            "prose\n       \n            \n   \nmore",
+        );
+        check(
+            "~~~~\nasdf\n~~~\nstill cde\n~~~~\nout",
+            "    \n    \n   \n         \n    \nout",
+        );
+    }
+}
@@ -0,0 +1,258 @@
+// Jupyter notebooks are JSON documents in which each cell holds its source as a string,
+// or a list of strings. Only the sources of markdown cells are checked, using the Markdown filter.
+// Since the client edits the raw JSON, regions are located within the JSON text itself.
+use std::ops::Range;
+
+// A parsed JSON value that records the location of each string's content.
+enum Json {
+    Object(Vec<(Range<usize>, Json)>),
+    Array(Vec<Json>),
+    String(Range<usize>),
+    Other,
+}
+
+// Return the byte ranges of the notebook that should not be spell checked.
+// If the notebook cannot be parsed, nothing is checked.
+pub fn skip(text: &str) -> Vec<Range<usize>> {
+    let mut keep = vec![false; text.len()];
+    let mut parser = Parser { text, pos: 0 };
+    if let Some(Json::Object(root)) = parser.value() {
+        for cell in root
+            .iter()
+            .filter(|(k, _)| &text[k.clone()] == "cells")
+            .flat_map(|(_, v)| match v {
+                Json::Array(cells) => cells.as_slice(),
+                _ => &[],
+            })
+        {
+            if let Some(source) = markdown_source(text, cell) {
+                check_source(text, &source, &mut keep);
+            }
+        }
+    }
+
+    let mut ranges = vec![];
+    let mut start = None;
+    for (i, &k) in keep.iter().enumerate() {
+        match (k, start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                ranges.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        ranges.push(s..text.len());
+    }
+    ranges
+}
+
+// If the cell is a markdown cell, return the ranges of the strings making up its source.
+fn markdown_source(text: &str, cell: &Json) -> Option<Vec<Range<usize>>> {
+    let Json::Object(fields) = cell else {
+        return None;
+    };
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(k, _)| &text[k.clone()] == name)
+            .map(|(_, v)| v)
+    };
+    match field("cell_type")? {
+        Json::String(s) if &text[s.clone()] == "markdown" => {}
+        _ => return None,
+    }
+    match field("source")? {
+        Json::String(s) => Some(vec![s.clone()]),
+        Json::Array(lines) => Some(
+            lines
+                .iter()
+                .filter_map(|l| match l {
+                    Json::String(s) => Some(s.clone()),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+// Decode the source strings of a cell, run the Markdown filter over the result,
+// and mark the raw characters that should be checked.
+// Escape sequences are never checked, as their raw text differs from what they represent.
+fn check_source(text: &str, source: &[Range<usize>], keep: &mut [bool]) {
+    let mut decoded = String::new();
+    // For each byte of the decoded text, the offset of the raw byte it came from,
+    // or None if it came from an escape sequence.
+    let mut offsets = vec![];
+    for range in source {
+        let raw = &text[range.clone()];
+        let mut chars = raw.char_indices();
+        while let Some((i, c)) = chars.next() {
+            if c != '\\' {
+                decoded.push(c);
+                offsets.extend((0..c.len_utf8()).map(|n| Some(range.start + i + n)));
+                continue;
+            }
+            let c = match chars.next().map(|(_, c)| c) {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                    u32::from_str_radix(&hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .unwrap_or(' ')
+                }
+                Some(c) => c,
+                None => break,
+            };
+            decoded.push(c);
+            offsets.extend((0..c.len_utf8()).map(|_| None));
+        }
+    }
+
+    let mut skipped = super::markdown::skip(&decoded).into_iter().peekable();
+    for (i, offset) in offsets.into_iter().enumerate() {
+        while skipped.next_if(|r| r.end <= i).is_some() {}
+        let skip = skipped.peek().is_some_and(|r| r.start <= i);
+        if let (Some(offset), false) = (offset, skip) {
+            keep[offset] = true;
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&mut self) -> Option<u8> {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn expect(&mut self, b: u8) -> Option<()> {
+        (self.peek()? == b).then(|| self.pos += 1)
+    }
+
+    fn value(&mut self) -> Option<Json> {
+        match self.peek()? {
+            b'{' => {
+                self.pos += 1;
+                let mut fields = vec![];
+                if self.expect(b'}').is_some() {
+                    return Some(Json::Object(fields));
+                }
+                loop {
+                    let Json::String(key) = self.value()? else {
+                        return None;
+                    };
+                    self.expect(b':')?;
+                    fields.push((key, self.value()?));
+                    if self.expect(b',').is_none() {
+                        self.expect(b'}')?;
+                        return Some(Json::Object(fields));
+                    }
+                }
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut items = vec![];
+                if self.expect(b']').is_some() {
+                    return Some(Json::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    if self.expect(b',').is_none() {
+                        self.expect(b']')?;
+                        return Some(Json::Array(items));
+                    }
+                }
+            }
+            b'"' => {
+                let start = self.pos + 1;
+                let mut escaped = false;
+                for (i, b) in self.text.bytes().enumerate().skip(start) {
+                    match b {
+                        _ if escaped => escaped = false,
+                        b'\\' => escaped = true,
+                        b'"' => {
+                            self.pos = i + 1;
+                            return Some(Json::String(start..i));
+                        }
+                        _ => {}
+                    }
+                }
+                None
+            }
+            _ => {
+                // A number, boolean or null
+                let rest = &self.text[self.pos..];
+                let len = rest
+                    .find(|c: char| matches!(c, ',' | '}' | ']') || c.is_whitespace())
+                    .unwrap_or(rest.len());
+                self.pos += len;
+                (len > 0).then_some(Json::Other)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filter::{Filter, Options};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_notebook() {
+        let text = r##"{
+ "cells": [
+  {
+   "cell_type": "markdown",
+   "metadata": {},
+   "source": ["# A titel\n", "Run `cod` to \"qoute\""]
+  },
+  {
+   "cell_type": "code",
+   "execution_count": 1,
+   "source": ["# a coment\n", "prnt(1)"]
+  },
+  {"cell_type": "markdown", "source": "Singel string"}
+ ],
+ "nbformat": 4
+}"##;
+        let expected = [
+            "",
+            "",
+            "",
+            "",
+            "",
+            "               # A titel      Run       to   qoute",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "                                       Singel string",
+            "",
+            "",
+            "",
+        ];
+        let actual = Filter::Notebook.apply(text, &Options::default());
+        let actual: Vec<&str> = actual.lines().map(str::trim_end).collect();
+        let expected: Vec<&str> = expected.iter().map(|l| l.trim_end()).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let text = "{\"cells\": [{\"cell_type\": \"markdown\", \"source\": \"oops";
+        assert_eq!(Filter::Notebook.apply(text, &Options::default()).trim(), "");
+    }
+}
@@ -0,0 +1,201 @@
+// A scanner for reStructuredText that skips directives, comments, target definitions,
+// literal blocks, roles and inline literals, while checking body text.
+// The bodies of admonitions and other prose directives (note, warning, ...) are still checked.
+use std::ops::Range;
+
+// Directives whose body is code or other non-prose content.
+const CODE_DIRECTIVES: &[&str] = &[
+    "automodule",
+    "autosummary",
+    "code",
+    "code-block",
+    "csv-table",
+    "doctest",
+    "graphviz",
+    "highlight",
+    "include",
+    "literalinclude",
+    "math",
+    "raw",
+    "sourcecode",
+    "testcode",
+    "testoutput",
+    "toctree",
+];
+
+// Return the byte ranges of `text` that should not be spell checked.
+pub fn skip(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    // While set, skip blank lines and lines indented further than this.
+    let mut block: Option<usize> = None;
+    let mut start = 0;
+    for line in text.split_inclusive('\n') {
+        let end = start + line.len();
+        let content = line.trim_start();
+        let indent = line.len() - content.len();
+        let blank = content.trim().is_empty();
+        if let Some(level) = block {
+            if blank || indent > level {
+                ranges.push(start..end);
+                start = end;
+                continue;
+            }
+            block = None;
+        }
+
+        if let Some(markup) = content.strip_prefix("..") {
+            // Explicit markup: a directive, target, substitution, footnote or comment.
+            ranges.push(start..end);
+            match directive(markup) {
+                // The body of a prose directive is checked like any other text.
+                Some(name) if !CODE_DIRECTIVES.contains(&name) => {}
+                _ => block = Some(indent),
+            }
+        } else {
+            // A paragraph ending in :: introduces a literal block.
+            if content.trim_end().ends_with("::") {
+                block = Some(indent);
+            }
+            inline(line, start, &mut ranges);
+        }
+        start = end;
+    }
+    ranges
+}
+
+// If the explicit markup is a directive like `.. name:: args`, return the name.
+fn directive(markup: &str) -> Option<&str> {
+    let (name, _) = markup.split_once("::")?;
+    let name = name.trim();
+    // A substitution definition (.. |name| replace:: text) is not a plain directive.
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'));
+    valid.then_some(name)
+}
+
+// Find the skipped ranges within a single line, which begins at byte `offset` of the text.
+fn inline(line: &str, offset: usize, ranges: &mut Vec<Range<usize>>) {
+    let bytes = line.as_bytes();
+    let indent = line.len() - line.trim_start().len();
+    let mut i = 0;
+    // A field list marker at the start of a line, e.g. `:param name:`.
+    if bytes.get(indent) == Some(&b':') {
+        if let Some(end) = line[indent + 1..].find(": ").map(|e| indent + e + 2) {
+            ranges.push(offset + indent..offset + end);
+            i = end;
+        }
+    }
+    while i < bytes.len() {
+        let skipped = match bytes[i] {
+            b'`' if bytes.get(i + 1) == Some(&b'`') => {
+                Some(i..through(line, i + 2, "``").unwrap_or(i + 2))
+            }
+            // A link target within interpreted text: `text <https://...>`_
+            b'<' => line[i..]
+                .find(">`")
+                .filter(|end| !line[i..i + end].contains('`'))
+                .map(|end| i..i + end + 1),
+            b':' => role(line, i),
+            b'|' => through(line, i + 1, "|")
+                .filter(|&end| end > i + 2)
+                .map(|end| i..end),
+            _ => None,
+        };
+        match skipped {
+            Some(r) => {
+                i = r.end;
+                ranges.push(offset + r.start..offset + r.end);
+            }
+            None => i += 1,
+        }
+    }
+}
+
+// Return the offset just past the first occurrence of `pat` at or after `start`.
+fn through(line: &str, start: usize, pat: &str) -> Option<usize> {
+    line[start..].find(pat).map(|i| start + i + pat.len())
+}
+
+// A role and its content, e.g. :ref:`label` or :py:func:`name`.
+fn role(line: &str, start: usize) -> Option<Range<usize>> {
+    let rest = &line[start + 1..];
+    let name_len = rest.find(":`").filter(|&n| n > 0).filter(|&n| {
+        rest[..n]
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '.' | '+'))
+    })?;
+    let content = start + 1 + name_len + 2;
+    let end = through(line, content, "`").unwrap_or(line.len());
+    Some(start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filter::{Filter, Options};
+    use pretty_assertions::assert_eq;
+
+    fn check(text: &[&str], expected: &[&str]) {
+        let text = text.join("\n");
+        let expected = expected.join("\n");
+        assert_eq!(Filter::Rst.apply(&text, &Options::default()), expected);
+    }
+
+    #[test]
+    fn test_directives() {
+        check(
+            &[
+                "Intro txt.",
+                "",
+                ".. code-block:: python",
+                "   :linenos:",
+                "",
+                "   prnt('hi')",
+                "",
+                ".. note::",
+                "",
+                "   A noet.",
+                "",
+                ".. _my-lable:",
+                ".. a coment",
+                "   continued",
+                "Done.",
+            ],
+            &[
+                "Intro txt.",
+                "",
+                "                      ",
+                "            ",
+                "",
+                "             ",
+                "",
+                "         ",
+                "",
+                "   A noet.",
+                "",
+                "             ",
+                "           ",
+                "            ",
+                "Done.",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_literal_blocks() {
+        check(
+            &["Exmple::", "", "    let x = y;", "", "Aftr."],
+            &["Exmple::", "", "              ", "", "Aftr."],
+        );
+    }
+
+    #[test]
+    fn test_inline() {
+        check(
+            &["Use ``fnc()`` and :py:func:`my_fnc` or `Lnk <https://x.io/pth>`_ |sbst|."],
+            &["Use           and                   or `Lnk                   `_       ."],
+        );
+        check(&[":param nme: The nmae"], &["            The nmae"]);
+    }
+}
@@ -0,0 +1,212 @@
+// Fixing errors interactively from the command line, like `aspell check`.
+use crate::check::{self, Problem};
+use crate::file::File;
+use crate::filter::Filter;
+use crate::workspace;
+use crate::Result;
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+// What to do about an error.
+enum Action {
+    Replace(String),
+    Ignore,
+    AddWord,
+    Quit,
+}
+
+// Go through the errors in the given files and directories, asking how to fix each, and
+// save each file with the chosen fixes once all of its errors have been dealt with.
+// Errors are highlighted with terminal escape codes if `highlight` is set, or else brackets.
+pub fn fix(
+    paths: &[PathBuf],
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+    highlight: bool,
+) -> Result<()> {
+    if paths.iter().any(|path| path == Path::new(check::STDIN)) {
+        Err("Cannot fix text read from stdin, which is needed for the answers")?;
+    }
+    let mut workspace = check::workspace()?;
+    let problems = check::check_with(&mut workspace, paths)?;
+    let mut added = HashSet::new();
+    for problems in problems.chunk_by(|a, b| a.path == b.path) {
+        let path = &problems[0].path;
+        let text = std::fs::read_to_string(path)?;
+        let mut edits = vec![];
+        let mut quit = false;
+        for problem in problems {
+            // Words added to the dictionary aren't errors anymore.
+            if added.contains(&problem.diagnostic.message) {
+                continue;
+            }
+            match ask(problem, &text, input, out, highlight)? {
+                Action::Replace(fix) => edits.push((problem.diagnostic.range, fix)),
+                Action::Ignore => {}
+                Action::AddWord => {
+                    workspace.add_word(&problem.diagnostic.message)?;
+                    added.insert(problem.diagnostic.message.clone());
+                }
+                Action::Quit => {
+                    quit = true;
+                    break;
+                }
+            }
+        }
+        save(path, text, edits, out)?;
+        if quit {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// Show the error in context, and ask what to do about it until given a valid answer.
+fn ask(
+    problem: &Problem,
+    text: &str,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+    highlight: bool,
+) -> Result<Action> {
+    let diag = &problem.diagnostic;
+    let start = diag.range.start;
+    let spelling = workspace::is_spelling(diag);
+    writeln!(
+        out,
+        "{}:{}:{}: {}",
+        problem.path,
+        start.line + 1,
+        start.character + 1,
+        diag.message
+    )?;
+    if let Some(line) = text.lines().nth(usize::try_from(start.line)?) {
+        writeln!(out, "    {}", highlighted(line, diag.range, highlight))?;
+    }
+    let mut choices: Vec<String> = problem
+        .fixes
+        .iter()
+        .enumerate()
+        .map(|(i, fix)| format!("{}) {fix}", i + 1))
+        .collect();
+    choices.push("r) replace".into());
+    choices.push("i) ignore".into());
+    if spelling {
+        choices.push("a) add to dictionary".into());
+    }
+    choices.push("q) quit".into());
+    writeln!(out, "  {}", choices.join("  "))?;
+    loop {
+        write!(out, "> ")?;
+        out.flush()?;
+        let Some(answer) = read_line(input)? else {
+            return Ok(Action::Quit);
+        };
+        match answer.as_str() {
+            "r" => {
+                write!(out, "Replace with: ")?;
+                out.flush()?;
+                match read_line(input)? {
+                    Some(fix) if !fix.is_empty() => return Ok(Action::Replace(fix)),
+                    Some(_) => continue,
+                    None => return Ok(Action::Quit),
+                }
+            }
+            "i" => return Ok(Action::Ignore),
+            "a" if spelling => return Ok(Action::AddWord),
+            "q" => return Ok(Action::Quit),
+            answer => {
+                let fix = answer
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| problem.fixes.get(n.checked_sub(1)?));
+                match fix {
+                    Some(fix) => return Ok(Action::Replace(fix.clone())),
+                    None => writeln!(out, "Unexpected answer {answer:?}")?,
+                }
+            }
+        }
+    }
+}
+
+// Read a line of input without its line ending, or None at the end of the input.
+fn read_line(input: &mut impl BufRead) -> Result<Option<String>> {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
+
+// The line with the part in the range highlighted.
+fn highlighted(line: &str, range: lsp_types::Range, highlight: bool) -> String {
+    let start = usize::try_from(range.start.character).unwrap_or_default();
+    let end = match range.end.line == range.start.line {
+        true => usize::try_from(range.end.character).unwrap_or_default(),
+        false => usize::MAX,
+    };
+    let before: String = line.chars().take(start).collect();
+    let word: String = line.chars().skip(start).take(end - start).collect();
+    let after: String = line.chars().skip(end).collect();
+    match highlight {
+        true => format!("{before}\x1b[7m{word}\x1b[0m{after}"),
+        false => format!("{before}[{word}]{after}"),
+    }
+}
+
+// Replace the text in each range, and save the file if anything changed.
+fn save(
+    path: &str,
+    text: String,
+    mut edits: Vec<(lsp_types::Range, String)>,
+    out: &mut impl Write,
+) -> Result<()> {
+    if edits.is_empty() {
+        return Ok(());
+    }
+    // Make the last edit first, so the ranges of the others are unchanged.
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse((range.start.line, range.start.character)));
+    let count = edits.len();
+    let mut file = File::new(text, Filter::Text)?;
+    file.edit(
+        edits
+            .into_iter()
+            .map(|(range, text)| lsp_types::TextDocumentContentChangeEvent {
+                range: Some(range),
+                range_length: None,
+                text,
+            })
+            .collect(),
+    )?;
+    std::fs::write(path, file.text())?;
+    writeln!(out, "Saved {count} fixes to {path}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_highlighted() {
+        let range = |start, end| {
+            lsp_types::Range::new(
+                lsp_types::Position::new(0, start),
+                lsp_types::Position::new(0, end),
+            )
+        };
+        let line = "Thé duz not work.";
+        assert_eq!(highlighted(line, range(4, 7), false), "Thé [duz] not work.");
+        assert_eq!(
+            highlighted(line, range(0, 3), true),
+            "\x1b[7mThé\x1b[0m duz not work."
+        );
+        let lines = lsp_types::Range::new(
+            lsp_types::Position::new(0, 12),
+            lsp_types::Position::new(1, 2),
+        );
+        assert_eq!(highlighted(line, lines, false), "Thé duz not [work.]");
+    }
+}
@@ -0,0 +1,149 @@
+// Running git, e.g. to check what is staged for a commit.
+use crate::Result;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+// The lines of each file under the given paths that were added or changed since `base`,
+// as zero-based ranges. Files that aren't tracked by git are new, so all of their lines are
+// included. If `staged` is set, the staged copies of files are compared instead of those on disk.
+pub fn changed_lines(
+    base: &str,
+    staged: bool,
+    paths: &[PathBuf],
+) -> Result<HashMap<PathBuf, Vec<Range<u32>>>> {
+    let mut args = vec![
+        "diff",
+        "--unified=0",
+        "--no-color",
+        "--no-prefix",
+        "--relative",
+    ];
+    if staged {
+        args.push("--cached");
+    }
+    args.extend([base, "--"]);
+    let mut untracked = vec!["ls-files", "--others", "--exclude-standard", "-z", "--"];
+    for path in paths {
+        let path = path.to_str().ok_or(format!("Invalid path {path:?}"))?;
+        args.push(path);
+        untracked.push(path);
+    }
+    let mut changed = parse_diff(&run(&args)?);
+    if !staged {
+        for path in run(&untracked)?.split('\0').filter(|p| !p.is_empty()) {
+            let all = std::iter::once(0..u32::MAX).collect();
+            changed.insert(PathBuf::from(path), all);
+        }
+    }
+    Ok(changed)
+}
+
+// The lines added in each file by a diff with no context lines.
+fn parse_diff(diff: &str) -> HashMap<PathBuf, Vec<Range<u32>>> {
+    let mut changed: HashMap<PathBuf, Vec<Range<u32>>> = HashMap::new();
+    let mut file = None;
+    for line in diff.lines() {
+        if let Some(path) = line.strip_prefix("+++ ") {
+            // Deleted files are diffed against /dev/null.
+            file = (path != "/dev/null").then(|| PathBuf::from(path));
+        } else if let (Some(hunk), Some(file)) = (line.strip_prefix("@@ "), &file) {
+            // e.g. "@@ -10,2 +12,3 @@", where the new lines are 12 to 14.
+            let Some(added) = hunk.split(' ').find_map(|s| s.strip_prefix('+')) else {
+                continue;
+            };
+            let (start, count) = added.split_once(',').unwrap_or((added, "1"));
+            let (Ok(start), Ok(count)) = (start.parse::<u32>(), count.parse::<u32>()) else {
+                continue;
+            };
+            if count > 0 {
+                let start = start.saturating_sub(1);
+                changed
+                    .entry(file.clone())
+                    .or_default()
+                    .push(start..start + count);
+            }
+        }
+    }
+    changed
+}
+
+// Whether `path`, relative to the current directory, is one of the `changed` files, and
+// the line is one of its changed lines.
+pub fn is_changed(changed: &HashMap<PathBuf, Vec<Range<u32>>>, path: &Path, line: u32) -> bool {
+    let path = path.strip_prefix(".").unwrap_or(path);
+    changed
+        .get(path)
+        .is_some_and(|lines| lines.iter().any(|lines| lines.contains(&line)))
+}
+
+// The abbreviated hash and message of each commit in the range, e.g. "main..HEAD", oldest first.
+pub fn commits(range: &str) -> Result<Vec<(String, String)>> {
+    run(&["rev-list", "--reverse", "--abbrev-commit", range, "--"])?
+        .lines()
+        .map(|hash| Ok((hash.to_string(), run(&["log", "-1", "--format=%B", hash])?)))
+        .collect()
+}
+
+// Run git in the current directory, returning what it printed.
+pub fn run(args: &[&str]) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|err| format!("Cannot run git: {err}"))?;
+    if !output.status.success() {
+        Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))?;
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_diff() {
+        let diff = "\
+diff --git docs/guide.md docs/guide.md
+index 1111111..2222222 100644
+--- docs/guide.md
++++ docs/guide.md
+@@ -3 +3 @@ Intro
+-Teh start.
++The start.
+@@ -10,2 +12,3 @@
+-old
+-lines
++new
++lines
++here
+@@ -20 +22,0 @@
+-removed
+diff --git new.md new.md
+new file mode 100644
+--- /dev/null
++++ new.md
+@@ -0,0 +1,2 @@
++All
++new
+diff --git gone.md gone.md
+deleted file mode 100644
+--- gone.md
++++ /dev/null
+@@ -1 +0,0 @@
+-Gone
+";
+        let changed = parse_diff(diff);
+        assert_eq!(changed.len(), 2);
+        assert_eq!(changed[Path::new("docs/guide.md")], [2..3, 11..14]);
+        assert_eq!(changed[Path::new("new.md")].first(), Some(&(0..2)));
+        assert!(is_changed(&changed, Path::new("./docs/guide.md"), 12));
+        assert!(!is_changed(&changed, Path::new("docs/guide.md"), 14));
+        assert!(!is_changed(&changed, Path::new("other.md"), 0));
+    }
+}
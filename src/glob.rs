@@ -0,0 +1,50 @@
+// Matching paths against glob patterns, such as "**/*.md".
+// "*" matches any characters except "/", "**" matches any number of whole directories,
+// and "?" matches a single character other than "/".
+
+// Whether `path`, relative to the workspace root and separated by "/", matches `pattern`.
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    matches_at(&pattern, &path)
+}
+
+fn matches_at(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            // Match no directories, or skip past the next "/" and try again.
+            matches_at(rest, path)
+                || path
+                    .iter()
+                    .position(|&c| c == '/')
+                    .is_some_and(|i| matches_at(pattern, &path[i + 1..]))
+        }
+        ['*', '*'] => true,
+        ['*', rest @ ..] => (0..=path.len())
+            .take_while(|&i| i == 0 || path[i - 1] != '/')
+            .any(|i| matches_at(rest, &path[i..])),
+        ['?', rest @ ..] => path.first().is_some_and(|&c| c != '/') && matches_at(rest, &path[1..]),
+        [c, rest @ ..] => path.first() == Some(c) && matches_at(rest, &path[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("*.md", "README.md"));
+        assert!(!matches("*.md", "docs/README.md"));
+        assert!(matches("**/*.md", "README.md"));
+        assert!(matches("**/*.md", "docs/guide/README.md"));
+        assert!(!matches("**/*.md", "README.txt"));
+        assert!(matches("docs/**", "docs/guide/README.md"));
+        assert!(matches("**/target/**", "target/"));
+        assert!(matches("**/target/**", "crate/target/debug/out.txt"));
+        assert!(!matches("**/target/**", "targets/out.txt"));
+        assert!(matches("notes-?.txt", "notes-1.txt"));
+        assert!(!matches("notes-?.txt", "notes-10.txt"));
+    }
+}
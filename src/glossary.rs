@@ -0,0 +1,297 @@
+// A glossary of discouraged terms and the terms to use instead, e.g. "e-mail" and "email",
+// given in the configuration or in CSV or TOML files, of proper nouns that must be written
+// with the right case, e.g. "GitHub", and of words never to use, e.g. "lorem". Each term
+// becomes a style rule. Also bundles an optional set of rules for inclusive language.
+use crate::style;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const INCLUSIVE: &str = include_str!("inclusive.csv");
+
+// Options for the bundled inclusive language rules.
+#[derive(serde::Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Inclusive {
+    pub enabled: bool,
+    // Terms not to report, e.g. "sanity check".
+    pub disabled: Vec<String>,
+}
+
+// A style rule for each discouraged term. Terms are found whatever their case, and a
+// preferred term with capitals, e.g. "GitHub", is offered exactly as written.
+pub fn rules(terms: &BTreeMap<String, String>) -> Result<Vec<style::Rule>> {
+    terms
+        .iter()
+        .map(|(term, preferred)| {
+            let opts = style::Options {
+                name: Some("glossary".into()),
+                text: Some(term.clone()),
+                message: format!("Use '{preferred}' instead of '$0'"),
+                replace: vec![preferred.clone()],
+                match_case: Some(!preferred.chars().any(char::is_uppercase)),
+                ..Default::default()
+            };
+            Ok(style::Rule::try_from(opts)?)
+        })
+        .collect()
+}
+
+// A style rule for each term in the bundled inclusive language list, unless disabled,
+// suggesting its alternatives.
+pub fn inclusive(opts: &Inclusive) -> Result<Vec<style::Rule>> {
+    if !opts.enabled {
+        return Ok(vec![]);
+    }
+    csv(INCLUSIVE)?
+        .into_iter()
+        .filter(|fields| {
+            !opts
+                .disabled
+                .iter()
+                .any(|d| d.eq_ignore_ascii_case(&fields[0]))
+        })
+        .map(|mut fields| {
+            let term = fields.remove(0);
+            let quoted: Vec<String> = fields.iter().map(|alt| format!("'{alt}'")).collect();
+            let opts = style::Options {
+                name: Some("inclusive".into()),
+                text: Some(term),
+                message: format!("Consider {} instead of '$0'", quoted.join(" or ")),
+                replace: fields,
+                severity: style::Severity::Information,
+                ..Default::default()
+            };
+            Ok(style::Rule::try_from(opts)?)
+        })
+        .collect()
+}
+
+// A style rule for each proper noun, reporting it written with the wrong case, e.g.
+// "Github" for "GitHub".
+pub fn proper_nouns(nouns: &[String]) -> Result<Vec<style::Rule>> {
+    nouns
+        .iter()
+        .map(|noun| {
+            let opts = style::Options {
+                name: Some("casing".into()),
+                text: Some(noun.clone()),
+                message: format!("Write '$0' as '{noun}'"),
+                replace: vec![noun.clone()],
+                match_case: Some(false),
+                ..Default::default()
+            };
+            Ok(style::Rule::try_from(opts)?)
+        })
+        .collect()
+}
+
+// A style rule for each forbidden word, reporting it as an error with the given message,
+// or a generic one if the message is empty.
+pub fn forbidden(words: &BTreeMap<String, String>) -> Result<Vec<style::Rule>> {
+    words
+        .iter()
+        .map(|(word, message)| {
+            let message = match message.as_str() {
+                "" => "'$0' is not allowed".to_string(),
+                message => message.to_string(),
+            };
+            let opts = style::Options {
+                name: Some("forbidden".into()),
+                text: Some(word.clone()),
+                message,
+                severity: style::Severity::Error,
+                ..Default::default()
+            };
+            Ok(style::Rule::try_from(opts)?)
+        })
+        .collect()
+}
+
+// The terms in a glossary file. A ".toml" file maps each discouraged term to the preferred
+// one, e.g. `repo = "repository"`. Any other file is CSV, with a discouraged term and the
+// preferred one on each line, e.g. `repo,repository`. Lines starting with "#" are comments.
+pub fn load(path: &Path) -> Result<BTreeMap<String, String>> {
+    let text = std::fs::read_to_string(path)?;
+    if path.extension().is_some_and(|ext| ext == "toml") {
+        return Ok(toml::from_str(&text)?);
+    }
+    csv(&text)?
+        .into_iter()
+        .map(|fields| match <[String; 2]>::try_from(fields) {
+            Ok([term, preferred]) => Ok((term, preferred)),
+            Err(fields) => {
+                Err(format!("Expected a term and its replacement: {}", fields.join(",")).into())
+            }
+        })
+        .collect()
+}
+
+// The fields of each line of CSV with at least two fields, e.g. a term followed by its
+// alternatives. Lines starting with "#" are comments.
+fn csv(text: &str) -> Result<Vec<Vec<String>>> {
+    let mut lines = vec![];
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<String> = line
+            .split(',')
+            .map(|field| field.trim().trim_matches('"').to_string())
+            .collect();
+        if fields.len() < 2 {
+            Err(format!("Line {} has no comma: {line}", n + 1))?;
+        }
+        lines.push(fields);
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn terms(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(term, preferred)| (term.to_string(), preferred.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_rules() {
+        let rules = rules(&terms(&[("e-mail", "email"), ("Github", "GitHub")])).unwrap();
+        let found: Vec<(String, String)> =
+            style::check(&rules, 0, 0, "E-mail me on github, or GitHub.")
+                .into_iter()
+                .map(|diag| {
+                    let data: crate::spell::DiagnosticData =
+                        serde_json::from_value(diag.data.unwrap()).unwrap();
+                    (diag.message, data.fixes.join(","))
+                })
+                .collect();
+        assert_eq!(
+            found,
+            [
+                ("Use 'GitHub' instead of 'github'".into(), "GitHub".into()),
+                ("Use 'email' instead of 'E-mail'".into(), "Email".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_proper_nouns() {
+        let nouns = ["GitHub".into(), "macOS".into(), "Visual Studio Code".into()];
+        let rules = proper_nouns(&nouns).unwrap();
+        let text = "Github and MacOS, not GitHub or macOS, in visual studio code.";
+        let found: Vec<(u32, String)> = style::check(&rules, 0, 0, text)
+            .into_iter()
+            .map(|diag| (diag.range.start.character, diag.message))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (0, "Write 'Github' as 'GitHub'".into()),
+                (11, "Write 'MacOS' as 'macOS'".into()),
+                (
+                    42,
+                    "Write 'visual studio code' as 'Visual Studio Code'".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_forbidden() {
+        let words = terms(&[
+            ("lorem", ""),
+            ("AcmeDB", "AcmeDB was renamed to Acme Store"),
+        ]);
+        let rules = forbidden(&words).unwrap();
+        let diags = style::check(&rules, 0, 0, "Lorem ipsum, stored in acmedb.");
+        let found: Vec<(u32, &str)> = diags
+            .iter()
+            .map(|diag| (diag.range.start.character, diag.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (23, "AcmeDB was renamed to Acme Store"),
+                (0, "'Lorem' is not allowed"),
+            ]
+        );
+        assert_eq!(
+            diags[0].severity,
+            Some(lsp_types::DiagnosticSeverity::ERROR)
+        );
+    }
+
+    #[test]
+    fn test_inclusive() {
+        let found = |opts: &Inclusive, text| {
+            let rules = inclusive(opts).unwrap();
+            style::check(&rules, 0, 0, text)
+                .into_iter()
+                .map(|diag| {
+                    let data: crate::spell::DiagnosticData =
+                        serde_json::from_value(diag.data.unwrap()).unwrap();
+                    (diag.message, data.fixes)
+                })
+                .collect::<Vec<_>>()
+        };
+        let text = "Add it to the Whitelist after a sanity check.";
+        assert_eq!(found(&Inclusive::default(), text), []);
+        let enabled = Inclusive {
+            enabled: true,
+            disabled: vec![],
+        };
+        assert_eq!(
+            found(&enabled, text),
+            [
+                (
+                    "Consider 'allowlist' instead of 'Whitelist'".into(),
+                    vec!["Allowlist".into()]
+                ),
+                (
+                    "Consider 'confidence check' or 'quick check' instead of 'sanity check'".into(),
+                    vec!["confidence check".into(), "quick check".into()]
+                ),
+            ]
+        );
+        let disabled = Inclusive {
+            enabled: true,
+            disabled: vec!["Sanity check".into()],
+        };
+        assert_eq!(found(&disabled, text).len(), 1);
+        // Every bundled term makes a valid rule.
+        assert_eq!(
+            csv(INCLUSIVE).unwrap().len(),
+            inclusive(&enabled).unwrap().len()
+        );
+    }
+
+    #[test]
+    fn test_load() {
+        let tmp = tempfile::tempdir().unwrap();
+        let csv = tmp.path().join("glossary.csv");
+        std::fs::write(&csv, "# Terms\ne-mail,email\n\"repo\", \"repository\"\n").unwrap();
+        assert_eq!(
+            load(&csv).unwrap(),
+            terms(&[("e-mail", "email"), ("repo", "repository")])
+        );
+        let toml = tmp.path().join("glossary.toml");
+        std::fs::write(&toml, "\"e-mail\" = \"email\"\nrepo = \"repository\"\n").unwrap();
+        assert_eq!(
+            load(&toml).unwrap(),
+            terms(&[("e-mail", "email"), ("repo", "repository")])
+        );
+        std::fs::write(&csv, "e-mail email\n").unwrap();
+        assert_eq!(
+            load(&csv).unwrap_err().to_string(),
+            "Line 1 has no comma: e-mail email"
+        );
+    }
+}
@@ -0,0 +1,153 @@
+// Excluding files from spelgud with `.spelgudignore` files, which use the syntax of
+// `.gitignore` and can be put in the workspace root or any directory under it.
+use crate::glob;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub const FILE_NAME: &str = ".spelgudignore";
+
+pub struct Ignore {
+    root: PathBuf,
+    // The rules in each directory's ignore file, read when first needed.
+    rules: HashMap<PathBuf, Vec<Rule>>,
+}
+
+struct Rule {
+    // The glob matched against paths relative to the directory of the ignore file.
+    glob: String,
+    // Whether the rule started with "!", so it includes paths excluded by earlier rules.
+    negate: bool,
+    // Whether the rule ended with "/", so it only matches directories.
+    dir_only: bool,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Rule> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negate, line) = match line.strip_prefix('!') {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        // A pattern containing a "/" is relative to the ignore file's directory,
+        // while others match a name at any depth.
+        let glob = match line.strip_prefix('/') {
+            Some(line) => line.to_string(),
+            None if line.contains('/') => line.to_string(),
+            None => format!("**/{line}"),
+        };
+        (!line.is_empty()).then_some(Rule {
+            glob,
+            negate,
+            dir_only,
+        })
+    }
+
+    fn matches(&self, rel: &str, is_dir: bool) -> bool {
+        (is_dir || !self.dir_only) && glob::matches(&self.glob, rel)
+    }
+}
+
+impl Ignore {
+    pub fn new(root: &Path) -> Ignore {
+        Ignore {
+            root: root.to_path_buf(),
+            rules: HashMap::new(),
+        }
+    }
+
+    // Whether the file or directory at `path` is ignored, either itself or because a
+    // directory it is in is. Paths outside of the root are never ignored.
+    pub fn ignored(&mut self, path: &Path) -> bool {
+        let Ok(rel) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let parts: Vec<String> = rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        (1..=parts.len()).any(|i| {
+            let is_dir = i < parts.len() || path.is_dir();
+            self.matches(&parts[..i], is_dir)
+        })
+    }
+
+    // Whether the rules ignore the path made of `parts`, regardless of its directories.
+    // As with git, the last matching rule wins, and rules in deeper directories come later.
+    fn matches(&mut self, parts: &[String], is_dir: bool) -> bool {
+        let mut ignored = false;
+        let mut dir = self.root.clone();
+        for depth in 0..parts.len() {
+            let rel = parts[depth..].join("/");
+            for rule in self.rules(&dir) {
+                if rule.matches(&rel, is_dir) {
+                    ignored = !rule.negate;
+                }
+            }
+            dir.push(&parts[depth]);
+        }
+        ignored
+    }
+
+    fn rules(&mut self, dir: &Path) -> &[Rule] {
+        self.rules.entry(dir.to_path_buf()).or_insert_with(|| {
+            std::fs::read_to_string(dir.join(FILE_NAME))
+                .map(|text| text.lines().filter_map(Rule::parse).collect())
+                .unwrap_or_default()
+        })
+    }
+
+    // Read the ignore files again when next needed, e.g. after one changed.
+    pub fn forget(&mut self) {
+        self.rules.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignored() {
+        let tmp = tempfile::tempdir().unwrap();
+        for path in [
+            "README.md",
+            "CHANGELOG.md",
+            "drafts/idea.md",
+            "docs/guide.md",
+            "docs/old.md",
+            "docs/api/old.md",
+            "docs/api/index.md",
+            "notes/build.log",
+            "notes/keep.log",
+        ] {
+            let path = tmp.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "text").unwrap();
+        }
+        let root = "# Not ready yet\ndrafts/\n/CHANGELOG.md\n*.log\n!keep.log\n";
+        std::fs::write(tmp.path().join(FILE_NAME), root).unwrap();
+        std::fs::write(tmp.path().join("docs").join(FILE_NAME), "old.md\n").unwrap();
+        std::fs::write(tmp.path().join("docs/api").join(FILE_NAME), "!old.md\n").unwrap();
+
+        let mut ignore = Ignore::new(tmp.path());
+        let mut ignored = |path: &str| ignore.ignored(&tmp.path().join(path));
+        assert!(!ignored("README.md"));
+        assert!(ignored("CHANGELOG.md"));
+        assert!(ignored("drafts"));
+        assert!(ignored("drafts/idea.md"));
+        assert!(!ignored("docs/guide.md"));
+        assert!(ignored("docs/old.md"));
+        assert!(!ignored("docs/api/old.md"));
+        assert!(!ignored("docs/api/index.md"));
+        assert!(ignored("notes/build.log"));
+        assert!(!ignored("notes/keep.log"));
+        assert!(!ignored("../elsewhere.md"));
+    }
+}
@@ -0,0 +1,182 @@
+// Setting up a new project with `spelgud init`, which writes a commented configuration
+// file listing the languages found in the project, and an empty word list.
+use crate::{scan, Result, CONFIG_FILE};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+// The project's word list, which `init` creates empty.
+pub const WORDLIST: &str = ".spelgud-words.txt";
+
+// A language found in the project.
+#[derive(Debug, PartialEq, Eq)]
+struct Language {
+    id: &'static str,
+    // The extensions of its files, e.g. "md".
+    extensions: BTreeSet<String>,
+    // How many of its files there are.
+    files: usize,
+}
+
+// Write the configuration file and word list in `root`, returning the files written.
+// An existing configuration file is never overwritten.
+pub fn init(root: &Path) -> Result<Vec<PathBuf>> {
+    let config = root.join(CONFIG_FILE);
+    if config.exists() {
+        Err(format!("{} already exists", config.display()))?;
+    }
+    std::fs::write(&config, config_text(&detect(root)))?;
+    let mut written = vec![config];
+    let wordlist = root.join(WORDLIST);
+    if !wordlist.exists() {
+        std::fs::write(&wordlist, "")?;
+        written.push(wordlist);
+    }
+    Ok(written)
+}
+
+// The languages of the files under `root` that aren't excluded from the scan by default,
+// most common first.
+fn detect(root: &Path) -> Vec<Language> {
+    let opts = scan::Options {
+        include: vec!["**/*".into()],
+        max_files: usize::MAX,
+        ..Default::default()
+    };
+    let mut found: HashMap<&'static str, Language> = HashMap::new();
+    for path in scan::files(root, &opts) {
+        let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+            continue;
+        };
+        let id = match crate::filter::language_for(ext) {
+            Some(id) => id,
+            None if ext == "txt" => "plaintext",
+            None => continue,
+        };
+        let language = found.entry(id).or_insert_with(|| Language {
+            id,
+            extensions: BTreeSet::new(),
+            files: 0,
+        });
+        language.extensions.insert(ext.to_string());
+        language.files += 1;
+    }
+    let mut found: Vec<Language> = found.into_values().collect();
+    found.sort_by(|a, b| b.files.cmp(&a.files).then_with(|| a.id.cmp(b.id)));
+    found
+}
+
+fn config_text(languages: &[Language]) -> String {
+    let (found, include) = match languages {
+        [] => (
+            "No files in a known language were found, so the defaults are used.".to_string(),
+            scan::Options::default().include,
+        ),
+        languages => (
+            format!(
+                "Languages found: {}.",
+                languages
+                    .iter()
+                    .map(|lang| match lang.files {
+                        1 => format!("{} (1 file)", lang.id),
+                        n => format!("{} ({n} files)", lang.id),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            languages
+                .iter()
+                .flat_map(|lang| &lang.extensions)
+                .map(|ext| format!("**/*.{ext}"))
+                .collect(),
+        ),
+    };
+    // JSON strings are valid TOML strings.
+    let strings = |values: &[String]| {
+        values
+            .iter()
+            .map(|value| serde_json::Value::from(value.as_str()).to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    format!(
+        "\
+# Configuration for spelgud. Options given by an editor override those set here.
+# Every option is described in spelgud's README.
+
+# The dictionary to check with, e.g. \"en_GB\". The locale's language is used by default.
+# dictionary = \"en_US\"
+
+# Files of words to accept in this project, one per line.
+wordlists = [{}]
+
+# Check these files when the server starts, not only those that are opened.
+# {found}
+[scan]
+include = [{}]
+",
+        strings(&[WORDLIST.to_string()]),
+        strings(&include),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_init() {
+        let tmp = tempfile::tempdir().unwrap();
+        for path in [
+            "README.md",
+            "docs/guide.md",
+            "docs/notes.markdown",
+            "src/main.rs",
+            "src/image.png",
+            "target/debug/build.rs",
+        ] {
+            let path = tmp.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "text").unwrap();
+        }
+
+        let written = init(tmp.path()).unwrap();
+        assert_eq!(
+            written,
+            [tmp.path().join(CONFIG_FILE), tmp.path().join(WORDLIST)]
+        );
+        let text = std::fs::read_to_string(tmp.path().join(CONFIG_FILE)).unwrap();
+        assert!(
+            text.contains("# Languages found: markdown (3 files), rust (1 file).\n"),
+            "{text}"
+        );
+        assert_eq!(
+            toml::from_str::<serde_json::Value>(&text).unwrap(),
+            serde_json::json!({
+                "wordlists": [WORDLIST],
+                "scan": {"include": ["**/*.markdown", "**/*.md", "**/*.rs"]},
+            })
+        );
+        assert_eq!(
+            std::fs::read_to_string(tmp.path().join(WORDLIST)).unwrap(),
+            ""
+        );
+
+        // Existing configuration isn't overwritten.
+        std::fs::write(tmp.path().join(CONFIG_FILE), "").unwrap();
+        assert!(init(tmp.path()).is_err());
+        assert_eq!(
+            std::fs::read_to_string(tmp.path().join(CONFIG_FILE)).unwrap(),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_no_languages() {
+        let text = config_text(&[]);
+        assert_eq!(
+            toml::from_str::<serde_json::Value>(&text).unwrap()["scan"]["include"],
+            serde_json::json!(["**/*.md", "**/*.txt"])
+        );
+    }
+}
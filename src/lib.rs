@@ -1,10 +1,52 @@
+mod check;
+pub mod checker;
+mod dict;
+mod ffi;
 mod file;
+mod filter;
+mod fix;
+mod git;
+mod glob;
+mod glossary;
+mod ignore;
+mod init;
+mod logging;
+#[cfg(feature = "async")]
+mod nonblocking;
+#[cfg(feature = "python")]
+mod python;
+mod readability;
+mod scan;
 mod spell;
+mod style;
+mod syntax;
+mod token;
+mod typography;
+mod watch;
+mod worker;
 mod workspace;
 
-use lsp_types::notification::DidChangeTextDocument;
+/// Spell checking backends, for crates that implement their own [`backend::Checker`] and
+/// pass it to [`run_with_checker`].
+pub mod backend {
+    pub use crate::spell::{Checker, Misspelling};
+}
+
+// Internals used by the benchmarks in benches/, which are not part of the API.
+#[doc(hidden)]
+pub mod bench {
+    pub use crate::file::File;
+    pub use crate::filter::{Filter, Options as FilterOptions};
+    pub use crate::spell::{Backend, Options, Program};
+    pub use crate::token::words;
+}
+
+use lsp_types::notification::{DidChangeTextDocument, DidChangeWatchedFiles};
 use lsp_types::request::CodeActionRequest;
+use lsp_types::request::CodeLensRequest;
 use lsp_types::request::Completion;
+use lsp_types::request::ExecuteCommand;
+use lsp_types::request::HoverRequest;
 use lsp_types::CodeAction;
 use lsp_types::CodeActionKind;
 use lsp_types::CodeActionParams;
@@ -31,11 +73,193 @@ use lsp_types::{
 };
 use std::error::Error;
 
+#[cfg(feature = "async")]
+pub use nonblocking::run_async;
+
 pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
 
+// Called when something happens that a server waiting on an async runtime should handle.
+type Wake = std::sync::Arc<dyn Fn() + Send + Sync>;
+
+// The command run by the "Add to dictionary" code action, with the word as its argument.
+const ADD_WORD_COMMAND: &str = "spelgud.addWord";
+// The command run by the "Ignore" code action, which accepts the word until the server exits.
+const IGNORE_WORD_COMMAND: &str = "spelgud.ignoreWord";
+// Download the dictionary for the language given as the argument.
+const INSTALL_DICTIONARY_COMMAND: &str = "spelgud.installDictionary";
+// Report the most common misspellings in the workspace. The argument, if any, is how many.
+const TYPOS_COMMAND: &str = "spelgud.typos";
+// How many misspellings are reported by the typos command by default.
+const TYPOS_LIMIT: usize = 20;
+
+// A custom request for the server's state, e.g. for a statusline or bug report.
+enum StatusRequest {}
+
+impl Request for StatusRequest {
+    type Params = ();
+    type Result = workspace::Status;
+    const METHOD: &'static str = "spelgud/status";
+}
+
+// A custom request for counts of words and spelling errors in each file and overall.
+enum StatisticsRequest {}
+
+impl Request for StatisticsRequest {
+    type Params = ();
+    type Result = workspace::Statistics;
+    const METHOD: &'static str = "spelgud/statistics";
+}
+
 #[derive(serde::Deserialize, Debug)]
+#[serde(default)]
 struct Config {
     programs: Vec<spell::Program>,
+    // Checkers run in addition to the first available one in `programs`.
+    secondary: Vec<spell::Program>,
+    #[serde(flatten)]
+    spell: spell::Options,
+    // If set, also check grammar and style using a LanguageTool server.
+    languagetool: Option<spell::languagetool::Options>,
+    // Style issues to report alongside spelling errors, such as "utilize" for "use".
+    rules: Vec<style::Rule>,
+    // Discouraged terms and the terms to use instead, e.g. "e-mail" and "email".
+    glossary: std::collections::BTreeMap<String, String>,
+    // Files of more glossary terms, in CSV or TOML.
+    glossary_files: Vec<std::path::PathBuf>,
+    // Words to always report, e.g. placeholder text, and the message to report them with.
+    forbidden: std::collections::BTreeMap<String, String>,
+    // Whether to suggest alternatives to non-inclusive terms, e.g. "allowlist".
+    inclusive: glossary::Inclusive,
+    // If set, flag punctuation in prose that doesn't match this style.
+    typography: Option<typography::Style>,
+    // If set, show definitions from a DICT server on hover.
+    dict: Option<dict::Options>,
+    // Whether to show the readability of each section of prose as a code lens.
+    readability: bool,
+    // How long a file must go unchanged before it is checked, so checks don't run
+    // on every keystroke.
+    debounce_ms: u64,
+    // When more lines than this need checking, diagnostics are published after every
+    // this many lines, rather than once the whole file is checked.
+    stream_lines: usize,
+    // Unload files that haven't been used for this long, reloading them from disk if they
+    // are used again. 0 keeps every file loaded.
+    evict_after_secs: u64,
+    // The least time between publishing diagnostics for the same file.
+    publish_interval_ms: u64,
+    // The most diagnostics to publish for a file.
+    max_diagnostics_per_file: usize,
+    // If set, check the matching files in the workspace at startup, whether open or not.
+    scan: Option<scan::Options>,
+    log: logging::Options,
+    #[serde(flatten)]
+    filter: filter::Options,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            programs: vec![
+                spell::Program::Aspell,
+                spell::Program::Ispell,
+                spell::Program::Hunspell,
+                spell::Program::Builtin,
+            ],
+            secondary: vec![],
+            spell: Default::default(),
+            languagetool: None,
+            rules: vec![],
+            glossary: Default::default(),
+            glossary_files: vec![],
+            forbidden: Default::default(),
+            inclusive: Default::default(),
+            typography: None,
+            dict: None,
+            readability: false,
+            debounce_ms: 300,
+            stream_lines: 1000,
+            evict_after_secs: 600,
+            publish_interval_ms: 500,
+            max_diagnostics_per_file: 1000,
+            scan: None,
+            log: Default::default(),
+            filter: Default::default(),
+        }
+    }
+}
+
+// The project's configuration file, in the workspace root.
+pub const CONFIG_FILE: &str = ".spelgud.toml";
+
+impl Config {
+    // The configuration for a workspace: that in the project's configuration file, if there
+    // is one, overridden by the options given by the editor.
+    fn load(root: Option<&std::path::Path>, options: Option<serde_json::Value>) -> Result<Config> {
+        let file = root.map(|root| root.join(CONFIG_FILE));
+        let mut value = match file.filter(|file| file.is_file()) {
+            // Read as JSON, so it can be merged with the editor's options.
+            Some(file) => toml::from_str(&std::fs::read_to_string(&file)?)
+                .map_err(|err| format!("{}: {err}", file.display()))?,
+            None => serde_json::json!({}),
+        };
+        if let Some(options) = options {
+            merge(&mut value, options);
+        }
+        let mut conf: Config = serde_json::from_value(value)?;
+        if let Some(root) = root {
+            for path in conf
+                .spell
+                .wordlists
+                .iter_mut()
+                .chain(&mut conf.spell.jargon)
+                .chain(&mut conf.spell.dictionaries)
+                .chain(&mut conf.glossary_files)
+            {
+                *path = root.join(&path);
+            }
+            for path in [&mut conf.spell.frequencies, &mut conf.spell.pairs]
+                .into_iter()
+                .flatten()
+            {
+                *path = root.join(&path);
+            }
+            if let Some(path) = &mut conf.log.file {
+                *path = root.join(&path);
+            }
+        }
+        // Terms in the configuration take precedence over those in files.
+        let mut glossary = std::collections::BTreeMap::new();
+        for path in &conf.glossary_files {
+            match glossary::load(path) {
+                Ok(terms) => glossary.extend(terms),
+                Err(err) => log::warn!("Cannot read glossary {path:?}: {err}"),
+            }
+        }
+        glossary.extend(std::mem::take(&mut conf.glossary));
+        conf.rules.extend(glossary::rules(&glossary)?);
+        conf.rules
+            .extend(glossary::proper_nouns(&conf.spell.proper_nouns)?);
+        conf.rules.extend(glossary::forbidden(&conf.forbidden)?);
+        conf.rules.extend(glossary::inclusive(&conf.inclusive)?);
+        Ok(conf)
+    }
+}
+
+// Set the fields of `value` to those of `overrides`, merging objects field by field.
+fn merge(value: &mut serde_json::Value, overrides: serde_json::Value) {
+    match (value, overrides) {
+        (serde_json::Value::Object(value), serde_json::Value::Object(overrides)) => {
+            for (key, field) in overrides {
+                match value.get_mut(&key) {
+                    Some(existing) => merge(existing, field),
+                    None => {
+                        value.insert(key, field);
+                    }
+                }
+            }
+        }
+        (value, overrides) => *value = overrides,
+    }
 }
 
 // Handle a request, returning the response to send.
@@ -100,6 +324,13 @@ fn handle_document_symbols(
     )))
 }
 
+fn handle_code_lens(
+    workspace: &mut workspace::Workspace,
+    params: lsp_types::CodeLensParams,
+) -> Result<Option<Vec<lsp_types::CodeLens>>> {
+    Ok(Some(workspace.code_lenses(&params.text_document.uri)?))
+}
+
 fn handle_references(
     workspace: &mut workspace::Workspace,
     params: ReferenceParams,
@@ -113,26 +344,90 @@ fn handle_completion(
 ) -> Result<Option<CompletionResponse>> {
     let pos = params.text_document_position.position;
     let uri = params.text_document_position.text_document.uri;
-    workspace.complete(&uri, pos.line.try_into()?, pos.character.try_into()?)
+    workspace.complete(&uri, pos.line, pos.character)
+}
+
+fn handle_hover(
+    workspace: &mut workspace::Workspace,
+    params: lsp_types::HoverParams,
+) -> Result<Option<lsp_types::Hover>> {
+    let pos = params.text_document_position_params.position;
+    let uri = params.text_document_position_params.text_document.uri;
+    workspace.hover(&uri, pos.line, pos.character)
+}
+
+fn handle_status(workspace: &mut workspace::Workspace, _params: ()) -> Result<workspace::Status> {
+    workspace.status()
+}
+
+fn handle_statistics(
+    workspace: &mut workspace::Workspace,
+    _params: (),
+) -> Result<workspace::Statistics> {
+    Ok(workspace.statistics())
 }
 
 fn handle_code_action(
-    _workspace: &mut workspace::Workspace,
+    workspace: &mut workspace::Workspace,
     params: CodeActionParams,
 ) -> Result<Option<CodeActionResponse>> {
-    eprintln!("Got action {params:?}");
+    log::debug!("Got action {params:?}");
     let uri = params.text_document.uri;
     let mut res = vec![];
     for diag in params.context.diagnostics {
         log::trace!("Generating actions for {diag:?}");
-        // If data is None, there are no suggestions
-        let Some(data) = diag.data else {
-            continue;
+        if workspace::is_spelling(&diag) {
+            res.extend(
+                [
+                    (
+                        format!("Add {} to dictionary", diag.message),
+                        ADD_WORD_COMMAND,
+                    ),
+                    (
+                        format!("Ignore {} for this session", diag.message),
+                        IGNORE_WORD_COMMAND,
+                    ),
+                ]
+                .into_iter()
+                .map(|(title, command)| {
+                    lsp_types::CodeActionOrCommand::CodeAction(CodeAction {
+                        title: title.clone(),
+                        kind: Some(CodeActionKind::QUICKFIX),
+                        command: Some(lsp_types::Command {
+                            title,
+                            command: command.into(),
+                            arguments: Some(vec![diag.message.clone().into()]),
+                        }),
+                        ..Default::default()
+                    })
+                }),
+            );
+        }
+        // The fixes are found in the text the diagnostic was found in, where columns count
+        // characters, but the edits apply to the client's range.
+        let checked = lsp_types::Diagnostic {
+            range: workspace.char_range(&uri, diag.range),
+            ..diag.clone()
         };
-        let data: spell::DiagnosticData = serde_json::from_value(data)?;
-        res.extend(data.fixes.iter().map(|fix| {
+        let fixes = workspace.fixes(&uri, &checked)?;
+        // Splits the checker already suggested are only offered once.
+        let splits: Vec<String> = workspace
+            .splits(&diag)?
+            .into_iter()
+            .filter(|split| !fixes.contains(split))
+            .collect();
+        let original = workspace::original(&diag);
+        let titles = fixes
+            .iter()
+            .map(|fix| (format!("Change {original} to {fix}"), fix))
+            .chain(
+                splits
+                    .iter()
+                    .map(|split| (format!("Split into '{split}'"), split)),
+            );
+        res.extend(titles.map(|(title, fix)| {
             lsp_types::CodeActionOrCommand::CodeAction(CodeAction {
-                title: format!("Change {} to {}", data.original, fix),
+                title,
                 kind: Some(CodeActionKind::QUICKFIX),
                 diagnostics: None,
                 edit: Some(lsp_types::WorkspaceEdit {
@@ -140,7 +435,7 @@ fn handle_code_action(
                         [(
                             uri.clone(),
                             vec![TextEdit {
-                                range: data.range,
+                                range: diag.range,
                                 new_text: fix.to_owned(),
                             }],
                         )]
@@ -158,42 +453,72 @@ fn handle_code_action(
     Ok(Some(res))
 }
 
+fn handle_execute_command(
+    workspace: &mut workspace::Workspace,
+    params: lsp_types::ExecuteCommandParams,
+) -> Result<Option<serde_json::Value>> {
+    match params.command.as_str() {
+        ADD_WORD_COMMAND => {
+            for word in params.arguments {
+                let word: String = serde_json::from_value(word)?;
+                workspace.add_word(&word)?;
+            }
+            Ok(None)
+        }
+        IGNORE_WORD_COMMAND => {
+            for word in params.arguments {
+                let word: String = serde_json::from_value(word)?;
+                workspace.ignore_word(&word)?;
+            }
+            Ok(None)
+        }
+        INSTALL_DICTIONARY_COMMAND => {
+            for lang in params.arguments {
+                let lang: String = serde_json::from_value(lang)?;
+                workspace.install_dictionary(&lang)?;
+            }
+            Ok(None)
+        }
+        TYPOS_COMMAND => {
+            let limit = match params.arguments.first() {
+                Some(limit) => serde_json::from_value(limit.clone())?,
+                None => TYPOS_LIMIT,
+            };
+            Ok(Some(serde_json::to_value(workspace.typos(limit)?)?))
+        }
+        cmd => Err(format!("Unknown command {cmd}"))?,
+    }
+}
+
+fn notify_did_change_watched_files(
+    workspace: &mut workspace::Workspace,
+    params: lsp_types::DidChangeWatchedFilesParams,
+) -> Result<Option<lsp_server::Notification>> {
+    for change in params.changes {
+        let deleted = change.typ == lsp_types::FileChangeType::DELETED;
+        workspace.disk_changed(&change.uri, deleted)?;
+    }
+    Ok(None)
+}
+
 fn notify_did_open(
     workspace: &mut workspace::Workspace,
     params: DidOpenTextDocumentParams,
 ) -> Result<Option<lsp_server::Notification>> {
-    let uri = params.text_document.uri;
-    let diags = workspace.open(uri.clone(), params.text_document.text)?;
-
-    let params = lsp_types::PublishDiagnosticsParams {
-        uri,
-        diagnostics: diags,
-        version: None,
-    };
-
-    Ok(Some(lsp_server::Notification {
-        method: PublishDiagnostics::METHOD.into(),
-        params: serde_json::to_value(&params)?,
-    }))
+    workspace.open(
+        params.text_document.uri,
+        &params.text_document.language_id,
+        params.text_document.text,
+    )?;
+    Ok(None)
 }
 
 fn notify_did_save(
     workspace: &mut workspace::Workspace,
     params: DidSaveTextDocumentParams,
 ) -> Result<Option<lsp_server::Notification>> {
-    let uri = params.text_document.uri;
-    let diags = workspace.save(uri.clone())?;
-
-    let params = lsp_types::PublishDiagnosticsParams {
-        uri,
-        diagnostics: diags,
-        version: None,
-    };
-
-    Ok(Some(lsp_server::Notification {
-        method: PublishDiagnostics::METHOD.into(),
-        params: serde_json::to_value(&params)?,
-    }))
+    workspace.save(params.text_document.uri)?;
+    Ok(None)
 }
 
 fn notify_did_change(
@@ -201,14 +526,238 @@ fn notify_did_change(
     params: DidChangeTextDocumentParams,
 ) -> Result<Option<lsp_server::Notification>> {
     let uri = params.text_document.uri;
-    workspace.edit(&uri, params.content_changes)?;
+    workspace.edit(&uri, params.text_document.version, params.content_changes)?;
     Ok(None)
 }
 
-pub fn run(connection: Connection) -> Result<()> {
-    let server_capabilities = serde_json::to_value(&ServerCapabilities {
-        // BUG: technically we are supposed to support UTF-16.
-        // From what I've seen editors seem to be happy with UTF-8.
+// Download the dictionary for `lang`, e.g. "en-GB", from `source`, or the default source.
+pub fn install_dictionary(lang: &str, source: Option<&str>) -> Result<()> {
+    let mut opts = spell::Options::default();
+    if let Some(source) = source {
+        opts.dictionary_source = source.into();
+    }
+    spell::install_dictionary(lang, &opts)
+}
+
+// Print each spell checker spelgud can use, and the dictionaries each can use.
+pub fn list_dictionaries() -> Result<()> {
+    let mut programs = Config::default().programs;
+    if cfg!(feature = "hunspell") {
+        // Before the built-in checker, which is last.
+        programs.insert(programs.len() - 1, spell::Program::Libhunspell);
+    }
+    for available in spell::available(&programs) {
+        let Some(dictionaries) = available.dictionaries else {
+            println!("{}: not installed", available.name);
+            continue;
+        };
+        println!("{}:", available.name);
+        if dictionaries.is_empty() {
+            println!("  no dictionaries found");
+        }
+        for (name, path) in dictionaries {
+            match path {
+                Some(path) => println!("  {name} ({})", path.display()),
+                None if available.name == "spelgud" => println!("  {name} (bundled)"),
+                None => println!("  {name}"),
+            }
+        }
+    }
+    if let Some(dict) = spell::default_dictionary() {
+        println!("Default dictionary: {dict}");
+    }
+    Ok(())
+}
+
+// Check the files under `root` that the scan would, and print the `limit` most common
+// misspellings with suggested fixes.
+pub fn typos(root: &std::path::Path, limit: Option<usize>) -> Result<()> {
+    let mut conf = Config::load(Some(root), None)?;
+    conf.scan.get_or_insert_with(Default::default);
+    let mut workspace = workspace::Workspace::new(conf)?;
+    workspace.set_root(root);
+    workspace.scan()?;
+    workspace.check_all()?;
+    for typo in workspace.typos(limit.unwrap_or(TYPOS_LIMIT))? {
+        let files = match typo.files {
+            1 => "1 file".to_string(),
+            n => format!("{n} files"),
+        };
+        let fixes = match typo.fixes.is_empty() {
+            true => String::new(),
+            false => format!(" -> {}", typo.fixes.join(", ")),
+        };
+        println!("{:>6}  {} ({files}){fixes}", typo.count, typo.word);
+    }
+    Ok(())
+}
+
+// Check the given files, and the files the scan would check in the given directories,
+// and print every word in them with how often it is used and whether it is in the
+// dictionary. With `unknown`, only the words that aren't are printed.
+pub fn words(paths: &[std::path::PathBuf], unknown: bool) -> Result<()> {
+    let mut workspace = check::workspace()?;
+    check::check_with(&mut workspace, paths)?;
+    for word in workspace.words() {
+        match (word.known, unknown) {
+            (true, true) => {}
+            (true, false) => println!("{:>6}  known    {}", word.count, word.word),
+            (false, _) => println!("{:>6}  unknown  {}", word.count, word.word),
+        }
+    }
+    Ok(())
+}
+
+// Write a commented configuration file and an empty word list for the project in `root`,
+// with the languages found in it.
+pub fn init(root: &std::path::Path) -> Result<()> {
+    for path in init::init(root)? {
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+// Log to stderr as configured by RUST_LOG, and once a client connects, to the file and
+// client it configures.
+pub fn init_logging() -> Result<()> {
+    logging::init()
+}
+
+pub use check::{Format, Options as CheckOptions, FORMATS};
+
+// Check the given files, and the files the scan would check in the given directories,
+// printing each error found. Returns whether the check passed, i.e. there were no more
+// than `max_errors` errors.
+pub fn check(paths: &[std::path::PathBuf], opts: &CheckOptions) -> Result<bool> {
+    let problems = check::check(paths, opts)?;
+    if !opts.quiet {
+        check::write(&mut std::io::stdout().lock(), &problems, opts.format)?;
+    }
+    Ok(problems.len() <= opts.max_errors)
+}
+
+// Check the messages of the commits in the given git range, e.g. "main..HEAD", printing each
+// error found. Returns whether there were none.
+pub fn commits(range: &str) -> Result<bool> {
+    let problems = check::commits(range)?;
+    check::write(&mut std::io::stdout().lock(), &problems, Format::Text)?;
+    Ok(problems.is_empty())
+}
+
+// Go through the errors in the given files and directories, asking how to fix each.
+pub fn fix(paths: &[std::path::PathBuf]) -> Result<()> {
+    use std::io::IsTerminal;
+    let highlight = std::io::stdout().is_terminal();
+    fix::fix(
+        paths,
+        &mut std::io::stdin().lock(),
+        &mut std::io::stdout().lock(),
+        highlight,
+    )
+}
+
+// Handle a message from the client, other than a shutdown request, which is left to the
+// caller as it waits for the client to exit.
+fn handle_message(
+    sender: &crossbeam_channel::Sender<Message>,
+    workspace: &mut workspace::Workspace,
+    msg: Message,
+) -> Result<()> {
+    log::info!("Handling message {msg:?}");
+    match msg {
+        Message::Request(req) => {
+            let resp = match req.method.as_str() {
+                DocumentSymbolRequest::METHOD => Some(handle::<DocumentSymbolRequest>(
+                    workspace,
+                    req,
+                    handle_document_symbols,
+                )),
+                References::METHOD => Some(handle::<References>(workspace, req, handle_references)),
+                CodeLensRequest::METHOD => {
+                    Some(handle::<CodeLensRequest>(workspace, req, handle_code_lens))
+                }
+                Completion::METHOD => Some(handle::<Completion>(workspace, req, handle_completion)),
+                HoverRequest::METHOD => Some(handle::<HoverRequest>(workspace, req, handle_hover)),
+                ExecuteCommand::METHOD => Some(handle::<ExecuteCommand>(
+                    workspace,
+                    req,
+                    handle_execute_command,
+                )),
+                StatusRequest::METHOD => {
+                    Some(handle::<StatusRequest>(workspace, req, handle_status))
+                }
+                StatisticsRequest::METHOD => Some(handle::<StatisticsRequest>(
+                    workspace,
+                    req,
+                    handle_statistics,
+                )),
+                CodeActionRequest::METHOD => Some(handle::<CodeActionRequest>(
+                    workspace,
+                    req,
+                    handle_code_action,
+                )),
+                _ => None,
+            };
+            if let Some(resp) = resp {
+                sender.send(resp?)?;
+            }
+        }
+        Message::Response(_) => {}
+        Message::Notification(not) => {
+            let resp = match not.method.as_str() {
+                DidOpenTextDocument::METHOD => {
+                    notify::<DidOpenTextDocument>(workspace, not, notify_did_open)?
+                }
+                DidSaveTextDocument::METHOD => {
+                    notify::<DidSaveTextDocument>(workspace, not, notify_did_save)?
+                }
+                DidChangeTextDocument::METHOD => {
+                    notify::<DidChangeTextDocument>(workspace, not, notify_did_change)?
+                }
+                DidChangeWatchedFiles::METHOD => notify::<DidChangeWatchedFiles>(
+                    workspace,
+                    not,
+                    notify_did_change_watched_files,
+                )?,
+                _ => None,
+            };
+            if let Some(resp) = resp {
+                sender.send(resp)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Ask the editor to report changes to the files the scan checks.
+fn watch_request(scan: &scan::Options) -> Result<Message> {
+    let watchers = scan
+        .include
+        .iter()
+        .map(|glob| lsp_types::FileSystemWatcher {
+            glob_pattern: lsp_types::GlobPattern::String(glob.clone()),
+            kind: None,
+        })
+        .collect();
+    let registration = lsp_types::Registration {
+        id: "spelgud-watch".into(),
+        method: DidChangeWatchedFiles::METHOD.into(),
+        register_options: Some(serde_json::to_value(
+            lsp_types::DidChangeWatchedFilesRegistrationOptions { watchers },
+        )?),
+    };
+    Ok(Message::Request(lsp_server::Request {
+        id: "spelgud-watch".to_string().into(),
+        method: lsp_types::request::RegisterCapability::METHOD.into(),
+        params: serde_json::to_value(lsp_types::RegistrationParams {
+            registrations: vec![registration],
+        })?,
+    }))
+}
+
+// The capabilities the server reports to clients when initialized.
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
         position_encoding: Some(lsp_types::PositionEncodingKind::UTF8),
         document_symbol_provider: Some(OneOf::Left(true)),
         workspace_symbol_provider: Some(OneOf::Left(true)),
@@ -235,76 +784,254 @@ pub fn run(connection: Connection) -> Result<()> {
             },
         )),
         code_action_provider: Some(lsp_types::CodeActionProviderCapability::Simple(true)),
+        hover_provider: Some(lsp_types::HoverProviderCapability::Simple(true)),
+        code_lens_provider: Some(lsp_types::CodeLensOptions {
+            resolve_provider: Some(false),
+        }),
+        execute_command_provider: Some(lsp_types::ExecuteCommandOptions {
+            commands: vec![
+                ADD_WORD_COMMAND.into(),
+                IGNORE_WORD_COMMAND.into(),
+                INSTALL_DICTIONARY_COMMAND.into(),
+                TYPOS_COMMAND.into(),
+            ],
+            ..Default::default()
+        }),
         ..Default::default()
-    })
-    .unwrap();
+    }
+}
+
+pub fn run(connection: Connection) -> Result<()> {
+    serve(connection, None)
+}
+
+/// Run the language server, checking spelling with the given checker rather than one of
+/// the configured programs, e.g. a company's terminology service.
+pub fn run_with_checker(connection: Connection, checker: Box<dyn backend::Checker>) -> Result<()> {
+    serve(connection, Some(checker))
+}
+
+fn serve(connection: Connection, checker: Option<Box<dyn backend::Checker>>) -> Result<()> {
+    let server_capabilities = serde_json::to_value(capabilities())?;
 
     log::info!("Initializing");
     let init_params = connection.initialize(server_capabilities)?;
-    let params: InitializeParams = serde_json::from_value(init_params)?;
-    let conf: Config = match params.initialization_options {
-        Some(opts) => serde_json::from_value(opts)?,
-        None => Config {
-            programs: vec![
-                spell::Program::Aspell,
-                spell::Program::Ispell,
-                spell::Program::Hunspell,
-            ],
-        },
-    };
-
-    let mut workspace = workspace::Workspace::new(conf.programs.as_slice())?;
+    let mut server = Server::start(init_params, checker, &connection.sender, None)?;
+    let disk_changes = server.disk_changes();
+    let done = server.workspace.done().clone();
 
-    for msg in &connection.receiver {
-        log::info!("Handling message {msg:?}");
-        match msg {
-            Message::Request(req) => {
-                if connection.handle_shutdown(&req)? {
-                    log::info!("Shutting down");
-                    return Ok(());
-                }
-                let resp = match req.method.as_str() {
-                    DocumentSymbolRequest::METHOD => Some(handle::<DocumentSymbolRequest>(
-                        &mut workspace,
-                        req,
-                        handle_document_symbols,
-                    )),
-                    References::METHOD => {
-                        Some(handle::<References>(&mut workspace, req, handle_references))
-                    }
-                    Completion::METHOD => {
-                        Some(handle::<Completion>(&mut workspace, req, handle_completion))
+    loop {
+        // Wait for the next message, a check to finish, a changed file to be due for checking,
+        // or diagnostics that were held back to be due for publishing.
+        let due = match server.due() {
+            Some(due) => crossbeam_channel::at(due),
+            None => crossbeam_channel::never(),
+        };
+        crossbeam_channel::select! {
+            recv(connection.receiver) -> msg => {
+                let Ok(msg) = msg else { break };
+                if let Message::Request(req) = &msg {
+                    if connection.handle_shutdown(req)? {
+                        log::info!("Shutting down");
+                        return Ok(());
                     }
-                    CodeActionRequest::METHOD => Some(handle::<CodeActionRequest>(
-                        &mut workspace,
-                        req,
-                        handle_code_action,
-                    )),
-                    _ => None,
-                };
-                if let Some(resp) = resp {
-                    connection.sender.send(resp?)?;
                 }
+                handle_message(&connection.sender, &mut server.workspace, msg)?;
             }
-            Message::Response(_) => {}
-            Message::Notification(not) => {
-                let resp = match not.method.as_str() {
-                    DidOpenTextDocument::METHOD => {
-                        notify::<DidOpenTextDocument>(&mut workspace, not, notify_did_open)?
-                    }
-                    DidSaveTextDocument::METHOD => {
-                        notify::<DidSaveTextDocument>(&mut workspace, not, notify_did_save)?
-                    }
-                    DidChangeTextDocument::METHOD => {
-                        notify::<DidChangeTextDocument>(&mut workspace, not, notify_did_change)?
-                    }
-                    _ => None,
-                };
-                if let Some(resp) = resp {
-                    connection.sender.send(resp)?;
+            recv(done) -> done => server.workspace.finish(done?)?,
+            recv(disk_changes) -> changes => server.disk_changed(changes?)?,
+            recv(due) -> _ => {}
+        }
+        server.step(&connection.sender)?;
+    }
+    Ok(())
+}
+
+// A server for a client that has initialized, shared by `run` and `run_async`, which wait
+// for events in their own ways and then hand them to it.
+struct Server {
+    workspace: workspace::Workspace,
+    watcher: Option<watch::Watcher>,
+    // Whether the client can apply edits the server asks it to.
+    apply_edit: bool,
+    // Requests to the client need ids of their own. Their responses are ignored.
+    requests: usize,
+    _logging: logging::Configured,
+}
+
+impl Server {
+    // Start serving a client that initialized with `params`, sending it messages with `sender`.
+    // `wake` is called whenever a check finishes or files change on disk.
+    fn start(
+        params: serde_json::Value,
+        checker: Option<Box<dyn backend::Checker>>,
+        sender: &crossbeam_channel::Sender<Message>,
+        wake: Option<Wake>,
+    ) -> Result<Server> {
+        let params: InitializeParams = serde_json::from_value(params)?;
+        let root = match &params.workspace_folders {
+            Some(folders) if !folders.is_empty() => Some(&folders[0].uri),
+            _ => params.root_uri.as_ref(),
+        };
+        let root = root.and_then(|root| root.to_file_path().ok());
+        let apply_edit = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|w| w.apply_edit)
+            == Some(true);
+        let conf = Config::load(root.as_deref(), params.initialization_options)?;
+        let _logging = logging::configure(&conf.log, sender);
+
+        let scan = conf.scan.clone();
+        let mut workspace = match checker {
+            Some(checker) => workspace::Workspace::with_checker(conf, checker)?,
+            None => workspace::Workspace::new(conf)?,
+        };
+        if let Some(wake) = &wake {
+            workspace.wake_with(wake.clone());
+        }
+        let mut watcher = None;
+        if let Some(root) = &root {
+            workspace.set_root(root);
+        }
+        if let (Some(root), Some(scan)) = (root, scan) {
+            workspace.scan()?;
+            // Find out about scanned files changing outside the editor, from the editor if it
+            // can watch files for us, or by watching them ourselves if not.
+            let dynamic = params
+                .capabilities
+                .workspace
+                .and_then(|w| w.did_change_watched_files)
+                .and_then(|c| c.dynamic_registration);
+            if dynamic == Some(true) {
+                sender.send(watch_request(&scan)?)?;
+            } else {
+                match watch::start(root, scan, wake) {
+                    Ok(w) => watcher = Some(w),
+                    Err(err) => log::warn!("Cannot watch the workspace: {err}"),
                 }
             }
         }
+        Ok(Server {
+            workspace,
+            watcher,
+            apply_edit,
+            requests: 0,
+            _logging,
+        })
+    }
+
+    // Receives the files changed outside the editor.
+    fn disk_changes(&self) -> crossbeam_channel::Receiver<Vec<watch::Change>> {
+        match &self.watcher {
+            Some(watcher) => watcher.changes.clone(),
+            None => crossbeam_channel::never(),
+        }
+    }
+
+    fn disk_changed(&mut self, changes: Vec<watch::Change>) -> Result<()> {
+        for change in changes {
+            if let Ok(uri) = lsp_types::Url::from_file_path(&change.path) {
+                self.workspace.disk_changed(&uri, change.deleted)?;
+            }
+        }
+        Ok(())
+    }
+
+    // When a changed file is due for checking, or diagnostics that were held back are due for
+    // publishing, if ever.
+    fn due(&self) -> Option<std::time::Instant> {
+        let due = [self.workspace.next_check(), self.workspace.next_publish()];
+        due.into_iter().flatten().min()
+    }
+
+    // Start the checks that are due, and send the client whatever is ready for it.
+    fn step(&mut self, sender: &crossbeam_channel::Sender<Message>) -> Result<()> {
+        let workspace = &mut self.workspace;
+        workspace.check_pending()?;
+        for params in workspace.take_publishes() {
+            sender.send(Message::Notification(lsp_server::Notification {
+                method: PublishDiagnostics::METHOD.into(),
+                params: serde_json::to_value(&params)?,
+            }))?;
+        }
+        for edit in workspace.take_edits() {
+            if !self.apply_edit {
+                continue;
+            }
+            self.requests += 1;
+            sender.send(Message::Request(lsp_server::Request {
+                id: format!("spelgud-fix-{}", self.requests).into(),
+                method: lsp_types::request::ApplyWorkspaceEdit::METHOD.into(),
+                params: serde_json::to_value(edit)?,
+            }))?;
+        }
+        workspace.evict_idle();
+        for notice in workspace.take_messages() {
+            let (method, params) = if notice.show {
+                (
+                    lsp_types::notification::ShowMessage::METHOD,
+                    serde_json::to_value(lsp_types::ShowMessageParams {
+                        typ: lsp_types::MessageType::WARNING,
+                        message: notice.text,
+                    })?,
+                )
+            } else {
+                (
+                    lsp_types::notification::LogMessage::METHOD,
+                    serde_json::to_value(lsp_types::LogMessageParams {
+                        typ: lsp_types::MessageType::WARNING,
+                        message: notice.text,
+                    })?,
+                )
+            };
+            sender.send(Message::Notification(lsp_server::Notification {
+                method: method.into(),
+                params,
+            }))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_load() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            tmp.path().join(CONFIG_FILE),
+            r#"
+debounce_ms = 100 # overridden by the editor
+readability = true
+wordlists = ['docs\words.txt']
+forbidden."lorem ipsum" = """
+Placeholder \"text\"\tleft in"""
+inclusive = { enabled = true, disabled = ["sanity check"] }
+"#,
+        )
+        .unwrap();
+        let conf = Config::load(
+            Some(tmp.path()),
+            Some(serde_json::json!({"debounce_ms": 50})),
+        )
+        .unwrap();
+        assert_eq!(conf.debounce_ms, 50);
+        assert!(conf.readability);
+        assert_eq!(conf.spell.wordlists, [tmp.path().join("docs\\words.txt")]);
+        assert_eq!(
+            conf.forbidden["lorem ipsum"],
+            "Placeholder \"text\"\tleft in"
+        );
+        assert!(conf.inclusive.enabled);
+        assert_eq!(conf.inclusive.disabled, ["sanity check"]);
+
+        std::fs::write(tmp.path().join(CONFIG_FILE), "a = 1\na = 2\n").unwrap();
+        let err = Config::load(Some(tmp.path()), None).unwrap_err();
+        assert!(err.to_string().contains("duplicate key"), "{err}");
     }
-    Ok(())
 }
@@ -0,0 +1,312 @@
+// Logging, to stderr as configured by RUST_LOG, and to a file and the client as configured
+// by the client, as stderr is often hidden by editors.
+use crate::Result;
+use lsp_server::Message;
+use lsp_types::notification::Notification;
+use std::io::Write;
+use std::sync::Mutex;
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<Level> for log::LevelFilter {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Off => log::LevelFilter::Off,
+            Level::Error => log::LevelFilter::Error,
+            Level::Warn => log::LevelFilter::Warn,
+            Level::Info => log::LevelFilter::Info,
+            Level::Debug => log::LevelFilter::Debug,
+            Level::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Options {
+    // If set, append messages to this file.
+    pub file: Option<std::path::PathBuf>,
+    // The least severe messages written to the file.
+    pub level: Level,
+    // The least severe messages sent to the client as window/logMessage notifications.
+    pub client_level: Level,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            file: None,
+            level: Level::Info,
+            client_level: Level::Warn,
+        }
+    }
+}
+
+pub struct Logger {
+    stderr: env_logger::Logger,
+    outputs: Mutex<Outputs>,
+}
+
+#[derive(Default)]
+struct Outputs {
+    file: Option<(std::fs::File, log::LevelFilter)>,
+    client: Option<(crossbeam_channel::Sender<Message>, log::LevelFilter)>,
+}
+
+static LOGGER: std::sync::OnceLock<Logger> = std::sync::OnceLock::new();
+
+thread_local! {
+    // Whether this thread is logging, so messages logged while sending a message to the
+    // client aren't themselves sent.
+    static LOGGING: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+// Log to stderr as configured by RUST_LOG, until `configure` is called.
+pub fn init() -> Result<()> {
+    let logger = LOGGER.get_or_init(Logger::new);
+    log::set_logger(logger)?;
+    log::set_max_level(logger.max_level());
+    Ok(())
+}
+
+// Also log to the file and client given by `opts`, if logging was set up by `init`,
+// until the returned value is dropped, e.g. when the server exits.
+pub fn configure(opts: &Options, client: &crossbeam_channel::Sender<Message>) -> Configured {
+    if let Some(logger) = LOGGER.get() {
+        logger.configure(opts, client);
+        log::set_max_level(logger.max_level());
+    }
+    Configured
+}
+
+pub struct Configured;
+
+impl Drop for Configured {
+    fn drop(&mut self) {
+        if let Some(logger) = LOGGER.get() {
+            *logger.outputs() = Outputs::default();
+            log::set_max_level(logger.max_level());
+        }
+    }
+}
+
+impl Logger {
+    fn new() -> Logger {
+        Logger {
+            stderr: env_logger::Builder::from_default_env().build(),
+            outputs: Mutex::default(),
+        }
+    }
+
+    fn outputs(&self) -> std::sync::MutexGuard<'_, Outputs> {
+        self.outputs.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn configure(&self, opts: &Options, client: &crossbeam_channel::Sender<Message>) {
+        let file = opts.file.as_ref().map(|path| {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path);
+            (path, file)
+        });
+        *self.outputs() = Outputs {
+            file: None,
+            client: Some((client.clone(), opts.client_level.into())),
+        };
+        match file {
+            Some((_, Ok(file))) => self.outputs().file = Some((file, opts.level.into())),
+            // Logged rather than failing, so the server still starts, and the client is
+            // told why.
+            Some((path, Err(err))) => log::error!("Cannot open log file {path:?}: {err}"),
+            None => {}
+        }
+    }
+
+    fn max_level(&self) -> log::LevelFilter {
+        let outputs = self.outputs();
+        let file = outputs.file.as_ref().map(|(_, level)| *level);
+        let client = outputs.client.as_ref().map(|(_, level)| *level);
+        [Some(self.stderr.filter()), file, client]
+            .into_iter()
+            .flatten()
+            .max()
+            .unwrap_or(log::LevelFilter::Off)
+    }
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.stderr.matches(record) {
+            self.stderr.log(record);
+        }
+        if LOGGING.with(|logging| logging.replace(true)) {
+            return;
+        }
+        let client = {
+            let mut outputs = self.outputs();
+            if let Some((file, level)) = &mut outputs.file {
+                if record.level() <= *level {
+                    let _ = writeln!(
+                        file,
+                        "{} {:<5} {}: {}",
+                        timestamp(std::time::SystemTime::now()),
+                        record.level(),
+                        record.target(),
+                        record.args()
+                    );
+                }
+            }
+            outputs
+                .client
+                .as_ref()
+                // lsp-server logs every message it sends, which would never end.
+                .filter(|(_, level)| {
+                    record.level() <= *level && !record.target().starts_with("lsp_server")
+                })
+                .map(|(sender, _)| sender.clone())
+        };
+        // Sent outside the lock, as sending waits for the client's writer.
+        if let Some(sender) = client {
+            let _ = sender.send(log_message(record));
+        }
+        LOGGING.with(|logging| logging.set(false));
+    }
+
+    fn flush(&self) {
+        if let Some((file, _)) = &mut self.outputs().file {
+            let _ = file.flush();
+        }
+    }
+}
+
+fn log_message(record: &log::Record) -> Message {
+    let typ = match record.level() {
+        log::Level::Error => lsp_types::MessageType::ERROR,
+        log::Level::Warn => lsp_types::MessageType::WARNING,
+        log::Level::Info => lsp_types::MessageType::INFO,
+        log::Level::Debug | log::Level::Trace => lsp_types::MessageType::LOG,
+    };
+    let params = lsp_types::LogMessageParams {
+        typ,
+        message: record.args().to_string(),
+    };
+    Message::Notification(lsp_server::Notification {
+        method: lsp_types::notification::LogMessage::METHOD.into(),
+        params: serde_json::to_value(params).unwrap_or_default(),
+    })
+}
+
+// The time in UTC, e.g. 2024-01-31T12:34:56.789Z.
+fn timestamp(time: std::time::SystemTime) -> String {
+    let since = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = since.as_secs();
+    let (days, secs) = (secs / 86400, secs % 86400);
+    // Convert days since the epoch to a date, as in Howard Hinnant's civil_from_days.
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+        since.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Log;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_timestamp() {
+        let at = |secs| timestamp(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs));
+        assert_eq!(at(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(at(951782400 + 3723), "2000-02-29T01:02:03.000Z");
+        assert_eq!(at(1706704496), "2024-01-31T12:34:56.000Z");
+    }
+
+    #[test]
+    fn test_log() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("spelgud.log");
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let logger = Logger::new();
+        let opts: Options = serde_json::from_value(serde_json::json!({
+            "file": path,
+            "level": "debug",
+        }))
+        .unwrap();
+        logger.configure(&opts, &sender);
+
+        let log = |level, target, message| {
+            logger.log(
+                &log::Record::builder()
+                    .level(level)
+                    .target(target)
+                    .args(format_args!("{message}"))
+                    .build(),
+            )
+        };
+        log(log::Level::Trace, "spelgud", "Too detailed");
+        log(log::Level::Debug, "spelgud", "Checking");
+        log(log::Level::Warn, "spelgud", "Checker died");
+        log(log::Level::Warn, "lsp_server::msg", "Sending");
+        logger.flush();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().map(|line| &line[25..]).collect();
+        assert_eq!(
+            lines,
+            [
+                "DEBUG spelgud: Checking",
+                "WARN  spelgud: Checker died",
+                "WARN  lsp_server::msg: Sending"
+            ]
+        );
+        let sent: Vec<Message> = receiver.try_iter().collect();
+        assert_eq!(sent.len(), 1, "{sent:?}");
+        let Message::Notification(sent) = &sent[0] else {
+            panic!("{sent:?}");
+        };
+        assert_eq!(sent.method, "window/logMessage");
+        assert_eq!(
+            sent.params,
+            serde_json::json!({"type": 2, "message": "Checker died"})
+        );
+
+        assert!(serde_json::from_value::<Options>(serde_json::json!({"level": "loud"})).is_err());
+
+        // A file that can't be opened is reported to the client.
+        let opts = Options {
+            file: Some(tmp.path().join("missing/spelgud.log")),
+            ..Default::default()
+        };
+        logger.configure(&opts, &sender);
+        assert!(logger.outputs().file.is_none());
+    }
+}
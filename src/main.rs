@@ -1,7 +1,329 @@
-fn main() -> spelgud::Result<()> {
-    env_logger::init();
-    let (connection, io_threads) = lsp_server::Connection::stdio();
-    spelgud::run(connection)?;
-    io_threads.join()?;
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{CommandFactory, Parser, Subcommand};
+use lsp_server::{Connection, Message};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// A language server for spell checking. With no subcommand, it runs the language server on
+/// stdio.
+#[derive(Parser)]
+#[command(
+    version,
+    after_help = "check exits with 0 if it passed, 1 if there were too many \
+errors, and 2 if it failed to check. commits does the same, allowing no errors."
+)]
+struct Cli {
+    /// Run the language server for each client that connects to a TCP address, e.g.
+    /// 127.0.0.1:9257
+    #[arg(long, value_name = "ADDR", conflicts_with = "pipe")]
+    listen: Option<String>,
+    /// Run the language server for a client listening on a Unix socket or Windows named pipe
+    #[arg(long, value_name = "PATH")]
+    pipe: Option<String>,
+    /// Print the capabilities the language server reports
+    #[arg(long, conflicts_with_all = ["listen", "pipe"])]
+    capabilities: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print a shell completion script
+    Completions { shell: clap_complete::Shell },
+    /// Manage hunspell dictionaries
+    #[command(subcommand)]
+    Dict(Dict),
+    /// Write a configuration file and word list for a project
+    Init {
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+    },
+    /// List the spell checkers and dictionaries available
+    ListDicts,
+    /// Report the most common misspellings
+    Typos {
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+        /// How many misspellings to report
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+    },
+    /// List the words in files and directories by frequency
+    Words {
+        #[arg(default_value = ".")]
+        paths: Vec<PathBuf>,
+        /// Only list words the spell checker doesn't know
+        #[arg(long)]
+        unknown: bool,
+    },
+    /// Print the errors in files and directories, reading stdin for a path of -
+    Check {
+        #[arg(default_value = ".")]
+        paths: Vec<PathBuf>,
+        /// How to print errors
+        #[arg(
+            long,
+            default_value = "text",
+            value_parser = PossibleValuesParser::new(spelgud::FORMATS)
+                .try_map(|format| format.parse::<spelgud::Format>()),
+        )]
+        format: spelgud::Format,
+        /// Pass if there are at most this many errors
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        max_errors: usize,
+        /// Print nothing, only setting the exit code
+        #[arg(long)]
+        quiet: bool,
+        /// Check the files staged in git, as they are staged
+        #[arg(long)]
+        staged: bool,
+        /// Only report errors in lines changed since a git revision
+        #[arg(long, value_name = "REV")]
+        since: Option<String>,
+        /// Only report errors in lines changed since the last commit
+        #[arg(long, conflicts_with = "since")]
+        diff: bool,
+    },
+    /// Print the errors in the messages of commits in a git range, e.g. main..HEAD
+    Commits { range: String },
+    /// Fix the errors in files and directories, one by one
+    Fix {
+        #[arg(default_value = ".")]
+        paths: Vec<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum Dict {
+    /// Download a hunspell dictionary
+    Install {
+        lang: String,
+        /// Where to download it from, with {lang} replaced by the language and {ext} by aff
+        /// or dic
+        #[arg(long, value_name = "URL")]
+        source: Option<String>,
+    },
+}
+
+fn main() -> spelgud::Result<ExitCode> {
+    spelgud::init_logging()?;
+    let cli = Cli::parse();
+    let Some(command) = cli.command else {
+        if cli.capabilities {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&spelgud::capabilities())?
+            );
+            return Ok(ExitCode::SUCCESS);
+        }
+        let transport = match (&cli.listen, &cli.pipe) {
+            (Some(addr), _) => Transport::Listen(addr),
+            (_, Some(path)) => Transport::Pipe(path),
+            (None, None) => Transport::Stdio,
+        };
+        serve(transport)?;
+        return Ok(ExitCode::SUCCESS);
+    };
+    match command {
+        Command::Completions { shell } => {
+            clap_complete::generate(
+                shell,
+                &mut Cli::command(),
+                "spelgud",
+                &mut std::io::stdout(),
+            );
+        }
+        Command::Dict(Dict::Install { lang, source }) => {
+            spelgud::install_dictionary(&lang, source.as_deref())?;
+        }
+        Command::Init { dir } => spelgud::init(&dir)?,
+        Command::ListDicts => spelgud::list_dictionaries()?,
+        Command::Typos { dir, limit } => spelgud::typos(&std::fs::canonicalize(dir)?, limit)?,
+        Command::Words { paths, unknown } => spelgud::words(&paths, unknown)?,
+        Command::Check {
+            paths,
+            format,
+            max_errors,
+            quiet,
+            staged,
+            since,
+            diff,
+        } => {
+            let opts = spelgud::CheckOptions {
+                format,
+                max_errors,
+                quiet,
+                staged,
+                since: since.or(diff.then(|| "HEAD".into())),
+            };
+            return Ok(exit_code(spelgud::check(&paths, &opts)));
+        }
+        Command::Commits { range } => return Ok(exit_code(spelgud::commits(&range))),
+        Command::Fix { paths } => spelgud::fix(&paths)?,
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+// The exit code for whether a check passed: 0 if it did, 1 if there were too many errors,
+// and 2 if it failed to check.
+fn exit_code(passed: spelgud::Result<bool>) -> ExitCode {
+    match passed {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+// How the language server talks to its clients.
+enum Transport<'a> {
+    Stdio,
+    // Listen for clients connecting to a TCP address.
+    Listen(&'a str),
+    // Connect to a client listening on a Unix socket or Windows named pipe.
+    Pipe(&'a str),
+}
+
+fn serve(transport: Transport) -> spelgud::Result<()> {
+    match transport {
+        Transport::Stdio => {
+            let (connection, io_threads) = Connection::stdio();
+            spelgud::run(connection)?;
+            io_threads.join()?;
+            Ok(())
+        }
+        Transport::Listen(addr) => listen(addr),
+        Transport::Pipe(path) => {
+            let (reader, writer) = pipe(path)?;
+            serve_stream(std::io::BufReader::new(reader), writer)
+        }
+    }
+}
+
+// Serve each client that connects to `addr` from its own thread, with its own workspace,
+// so e.g. several editors can share one server. This runs until the process is killed.
+fn listen(addr: &str) -> spelgud::Result<()> {
+    log::info!("Listening on {addr}");
+    loop {
+        let (connection, io_threads) = Connection::listen(addr)?;
+        log::info!("Client connected to {addr}");
+        std::thread::spawn(move || {
+            let served = spelgud::run(connection).and_then(|()| Ok(io_threads.join()?));
+            match served {
+                Ok(()) => log::info!("Client disconnected"),
+                Err(err) => log::error!("Client failed: {err}"),
+            }
+        });
+    }
+}
+
+// Open the client's pipe, returning its ends to read from and write to.
+#[cfg(unix)]
+fn pipe(path: &str) -> spelgud::Result<(impl std::io::Read, impl Write)> {
+    let stream = std::os::unix::net::UnixStream::connect(path)?;
+    Ok((stream.try_clone()?, stream))
+}
+
+// Reads and writes of a pipe opened for synchronous I/O take turns, so while one thread
+// waits for the client's next message, the other couldn't send it a response. The pipe is
+// opened for overlapped I/O instead, which tokio does, on a runtime that is kept until the
+// process exits, as the server is.
+#[cfg(windows)]
+fn pipe(path: &str) -> spelgud::Result<(impl std::io::Read, impl Write)> {
+    use tokio_util::io::SyncIoBridge;
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_io()
+        .build()?;
+    let runtime: &'static tokio::runtime::Runtime = Box::leak(Box::new(runtime));
+    let pipe = {
+        let _context = runtime.enter();
+        tokio::net::windows::named_pipe::ClientOptions::new().open(path)?
+    };
+    let (reader, writer) = tokio::io::split(pipe);
+    Ok((
+        SyncIoBridge::new_with_handle(reader, runtime.handle().clone()),
+        SyncIoBridge::new_with_handle(writer, runtime.handle().clone()),
+    ))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn pipe(_path: &str) -> spelgud::Result<(std::io::Empty, std::io::Sink)> {
+    Err("Pipes aren't supported on this platform")?
+}
+
+// Run the language server for a client connected by the given reader and writer,
+// which are read from and written to on their own threads, as lsp-server does for stdio.
+fn serve_stream(
+    mut reader: impl BufRead + Send + 'static,
+    mut writer: impl Write + Send + 'static,
+) -> spelgud::Result<()> {
+    let (reader_sender, receiver) = crossbeam_channel::bounded(0);
+    let reader = std::thread::spawn(move || -> std::io::Result<()> {
+        while let Some(msg) = Message::read(&mut reader)? {
+            let exit = matches!(&msg, Message::Notification(n) if n.method == "exit");
+            if reader_sender.send(msg).is_err() || exit {
+                break;
+            }
+        }
+        Ok(())
+    });
+    let (sender, writer_receiver) = crossbeam_channel::bounded::<Message>(0);
+    let writer = std::thread::spawn(move || {
+        writer_receiver
+            .into_iter()
+            .try_for_each(|msg| msg.write(&mut writer))
+    });
+    spelgud::run(Connection { sender, receiver })?;
+    // The connection is dropped by now, so the writer stops once it has sent everything.
+    writer.join().map_err(|_| "Writer panicked")??;
+    reader.join().map_err(|_| "Reader panicked")??;
     Ok(())
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use lsp_server::{Notification, Request, Response};
+    use std::io::BufReader;
+    use std::os::unix::net::UnixStream;
+
+    // Read messages until the response to a request, skipping e.g. log messages.
+    fn response(reader: &mut impl BufRead) -> spelgud::Result<Response> {
+        loop {
+            match Message::read(reader)? {
+                Some(Message::Response(resp)) => return Ok(resp),
+                Some(_) => continue,
+                None => Err("Disconnected before responding")?,
+            }
+        }
+    }
+
+    #[test]
+    fn test_serve_stream() -> spelgud::Result<()> {
+        let (mut client, server) = UnixStream::pair()?;
+        let reader = BufReader::new(server.try_clone()?);
+        let served =
+            std::thread::spawn(move || serve_stream(reader, server).map_err(|e| e.to_string()));
+
+        let mut reader = BufReader::new(client.try_clone()?);
+        let params = serde_json::to_value(lsp_types::InitializeParams::default())?;
+        Message::Request(Request::new(1.into(), "initialize".into(), params)).write(&mut client)?;
+        let resp = response(&mut reader)?;
+        assert_eq!(resp.id, 1.into());
+        let result: lsp_types::InitializeResult = serde_json::from_value(resp.result.unwrap())?;
+        assert!(result.capabilities.hover_provider.is_some());
+
+        let initialized = Notification::new("initialized".into(), serde_json::json!({}));
+        Message::Notification(initialized).write(&mut client)?;
+        Message::Request(Request::new(2.into(), "shutdown".into(), ())).write(&mut client)?;
+        assert_eq!(response(&mut reader)?.id, 2.into());
+        Message::Notification(Notification::new("exit".into(), ())).write(&mut client)?;
+        served.join().unwrap()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,361 @@
+// The language server on a tokio runtime, for serving from async code, e.g. alongside other
+// services in a tokio application. Messages are read and written without blocking, and the
+// server waits on the runtime for messages, finished checks, changed files and timers rather
+// than on threads of its own. Spell checker programs started by the server are read on the
+// runtime too, so reads time out without a thread per process.
+// Handling an event still runs spelgud's synchronous code, e.g. asking a checker for
+// suggestions, so the runtime is told the thread blocks while it does.
+use crate::{capabilities, handle_message, Result, Server};
+use lsp_server::{ErrorCode, Message, Response};
+use lsp_types::notification::{Exit, Initialized, Notification};
+use lsp_types::request::{Initialize, Request, Shutdown};
+use std::sync::Arc;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt,
+};
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+// How long to wait for the client to exit after shutting down, as lsp-server does.
+const EXIT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Run the language server on the current tokio runtime, reading messages from `input` and
+/// writing them to `output`, until the client exits. The runtime must be multi-threaded,
+/// with I/O and time enabled, e.g. as started by `#[tokio::main]`.
+pub async fn run_async(
+    input: impl AsyncRead + Unpin,
+    output: impl AsyncWrite + Unpin,
+) -> Result<()> {
+    let flavor = Handle::try_current().map(|runtime| runtime.runtime_flavor());
+    if flavor.ok() != Some(RuntimeFlavor::MultiThread) {
+        return Err("run_async must be run on a multi-threaded tokio runtime".into());
+    }
+    let (sender, mut incoming) = tokio::sync::mpsc::unbounded_channel();
+    let read = async move {
+        let mut input = tokio::io::BufReader::new(input);
+        while let Some(msg) = read_message(&mut input).await? {
+            if sender.send(msg).is_err() {
+                break;
+            }
+        }
+        Ok::<_, Box<dyn std::error::Error>>(())
+    };
+    let serve = serve(&mut incoming, output);
+    tokio::pin!(read, serve);
+    tokio::select! {
+        read = &mut read => read?,
+        served = &mut serve => return served,
+    }
+    // Messages already read are still handled once the input ends.
+    serve.await
+}
+
+async fn serve(
+    incoming: &mut tokio::sync::mpsc::UnboundedReceiver<Message>,
+    mut output: impl AsyncWrite + Unpin,
+) -> Result<()> {
+    // The server sends messages to the client, and logs to it, from any thread. They are
+    // written out after each event, as logging from another thread is followed by a wake.
+    let (sender, outgoing) = crossbeam_channel::unbounded();
+
+    log::info!("Initializing");
+    let Some(params) = initialize(incoming, &mut output).await? else {
+        return Ok(());
+    };
+    let wake = Arc::new(tokio::sync::Notify::new());
+    let waker = wake.clone();
+    let mut server = tokio::task::block_in_place(|| {
+        Server::start(
+            params,
+            None,
+            &sender,
+            Some(Arc::new(move || waker.notify_one())),
+        )
+    })?;
+    let disk_changes = server.disk_changes();
+    let done = server.workspace.done().clone();
+
+    loop {
+        write_messages(&outgoing, &mut output).await?;
+        // Wait for the next message, a check to finish, a changed file to be due for checking,
+        // or diagnostics that were held back to be due for publishing.
+        let due = server.due().map(tokio::time::Instant::from_std);
+        let msg = tokio::select! {
+            msg = incoming.recv() => match msg {
+                Some(msg) => Some(msg),
+                None => break,
+            },
+            _ = wake.notified() => None,
+            _ = sleep_until(due) => None,
+        };
+        if let Some(Message::Request(req)) = &msg {
+            if req.method == Shutdown::METHOD {
+                log::info!("Shutting down");
+                sender.send(Response::new_ok(req.id.clone(), ()).into())?;
+                write_messages(&outgoing, &mut output).await?;
+                return exit(incoming).await;
+            }
+        }
+        tokio::task::block_in_place(|| {
+            if let Some(msg) = msg {
+                handle_message(&sender, &mut server.workspace, msg)?;
+            }
+            for done in done.try_iter() {
+                server.workspace.finish(done)?;
+            }
+            for changes in disk_changes.try_iter() {
+                server.disk_changed(changes)?;
+            }
+            server.step(&sender)
+        })?;
+    }
+    write_messages(&outgoing, &mut output).await
+}
+
+// Wait for the client to initialize, as lsp-server's Connection::initialize does, returning
+// the initialize params, or None if the input ends first.
+async fn initialize(
+    incoming: &mut tokio::sync::mpsc::UnboundedReceiver<Message>,
+    output: &mut (impl AsyncWrite + Unpin),
+) -> Result<Option<serde_json::Value>> {
+    let (id, params) = loop {
+        match incoming.recv().await {
+            Some(Message::Request(req)) if req.method == Initialize::METHOD => {
+                break (req.id, req.params)
+            }
+            Some(Message::Request(req)) => {
+                let resp = Response::new_err(
+                    req.id.clone(),
+                    ErrorCode::ServerNotInitialized as i32,
+                    format!("expected initialize request, got {req:?}"),
+                );
+                write_message(output, resp.into()).await?;
+            }
+            Some(Message::Notification(not)) if not.method != Exit::METHOD => {}
+            Some(msg) => Err(format!("expected initialize request, got {msg:?}"))?,
+            None => return Ok(None),
+        }
+    };
+    let result = serde_json::json!({ "capabilities": capabilities() });
+    write_message(output, Response::new_ok(id, result).into()).await?;
+    output.flush().await?;
+    match incoming.recv().await {
+        Some(Message::Notification(not)) if not.method == Initialized::METHOD => Ok(Some(params)),
+        Some(msg) => Err(format!("expected initialized notification, got: {msg:?}"))?,
+        None => Ok(None),
+    }
+}
+
+// Wait for the client to exit after shutting down.
+async fn exit(incoming: &mut tokio::sync::mpsc::UnboundedReceiver<Message>) -> Result<()> {
+    match tokio::time::timeout(EXIT_TIMEOUT, incoming.recv()).await {
+        Ok(Some(Message::Notification(not))) if not.method == Exit::METHOD => Ok(()),
+        Ok(Some(msg)) => Err(format!("unexpected message during shutdown: {msg:?}"))?,
+        Ok(None) => Ok(()),
+        Err(_) => Err("timed out waiting for exit notification")?,
+    }
+}
+
+// Sleep until `due`, or forever if it is None.
+async fn sleep_until(due: Option<tokio::time::Instant>) {
+    match due {
+        Some(due) => tokio::time::sleep_until(due).await,
+        None => std::future::pending().await,
+    }
+}
+
+// Read a message, framed with headers as in LSP, or None at the end of the input.
+async fn read_message(input: &mut (impl AsyncBufRead + Unpin)) -> Result<Option<Message>> {
+    // The whole frame is collected and handed to lsp-server to parse.
+    let mut frame = vec![];
+    let mut size = None;
+    loop {
+        let start = frame.len();
+        if input.read_until(b'\n', &mut frame).await? == 0 {
+            if frame.is_empty() {
+                return Ok(None);
+            }
+            Err("unexpected end of input in message headers")?;
+        }
+        let header = std::str::from_utf8(&frame[start..])?.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(": ") {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                size = Some(value.parse::<usize>()?);
+            }
+        }
+    }
+    let size = size.ok_or("message has no Content-Length header")?;
+    let start = frame.len();
+    frame.resize(start + size, 0);
+    input.read_exact(&mut frame[start..]).await?;
+    Ok(Message::read(&mut frame.as_slice())?)
+}
+
+async fn write_message(output: &mut (impl AsyncWrite + Unpin), msg: Message) -> Result<()> {
+    let mut frame = vec![];
+    msg.write(&mut frame)?;
+    output.write_all(&frame).await?;
+    Ok(())
+}
+
+// Write the messages sent so far.
+async fn write_messages(
+    outgoing: &crossbeam_channel::Receiver<Message>,
+    output: &mut (impl AsyncWrite + Unpin),
+) -> Result<()> {
+    for msg in outgoing.try_iter() {
+        write_message(output, msg).await?;
+    }
+    Ok(output.flush().await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    // A client of a server run by run_async.
+    struct Client {
+        input: tokio::io::BufReader<tokio::io::ReadHalf<tokio::io::DuplexStream>>,
+        output: tokio::io::WriteHalf<tokio::io::DuplexStream>,
+    }
+
+    impl Client {
+        async fn send(&mut self, msg: Message) {
+            write_message(&mut self.output, msg).await.unwrap();
+            self.output.flush().await.unwrap();
+        }
+
+        async fn request(&mut self, id: i32, method: &str, params: serde_json::Value) -> Response {
+            let req = lsp_server::Request::new(id.into(), method.into(), params);
+            self.send(req.into()).await;
+            loop {
+                if let Message::Response(resp) = self.recv().await {
+                    return resp;
+                }
+            }
+        }
+
+        async fn notify(&mut self, method: &str, params: serde_json::Value) {
+            let not = lsp_server::Notification::new(method.into(), params);
+            self.send(not.into()).await;
+        }
+
+        async fn recv(&mut self) -> Message {
+            let msg = read_message(&mut self.input);
+            let msg = tokio::time::timeout(std::time::Duration::from_secs(10), msg);
+            msg.await.unwrap().unwrap().unwrap()
+        }
+
+        // Wait for the next notification with the given method, returning its params.
+        async fn notified<N: Notification>(&mut self) -> N::Params {
+            loop {
+                if let Message::Notification(not) = self.recv().await {
+                    if not.method == N::METHOD {
+                        return serde_json::from_value(not.params).unwrap();
+                    }
+                }
+            }
+        }
+
+        // Wait for the next message logged to the client.
+        async fn log(&mut self) -> String {
+            let params = self.notified::<lsp_types::notification::LogMessage>().await;
+            params.message
+        }
+
+        // Wait for the next diagnostics, returning their messages.
+        async fn diagnostics(&mut self) -> Vec<String> {
+            let params = self
+                .notified::<lsp_types::notification::PublishDiagnostics>()
+                .await;
+            params
+                .diagnostics
+                .into_iter()
+                .map(|diag| diag.message)
+                .collect()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_run_async() {
+        let (client, server) = tokio::io::duplex(4096);
+        let (input, output) = tokio::io::split(server);
+        let server = tokio::spawn(async move {
+            run_async(input, output)
+                .await
+                .map_err(|err| err.to_string())
+        });
+        let (input, output) = tokio::io::split(client);
+        let mut client = Client {
+            input: tokio::io::BufReader::new(input),
+            output,
+        };
+
+        // A checker speaking the ispell protocol that rejects "kwick" and never answers "slow".
+        let script = r#"echo '@(#) fake'
+            while read -r l; do case "$l" in
+                ^slow) sleep 10;;
+                ^kwick) echo '& kwick 1 0: quick'; echo;;
+                ^*) echo;;
+            esac; done"#;
+        let init = client
+            .request(
+                1,
+                Initialize::METHOD,
+                serde_json::json!({
+                    "capabilities": {},
+                    "initializationOptions": {
+                        "programs": [{"custom": {
+                            "command": ["sh", "-c", script],
+                            "format": "ispell",
+                        }}],
+                        "timeout_ms": 200,
+                    },
+                }),
+            )
+            .await;
+        assert!(init.result.unwrap()["capabilities"].is_object());
+        client
+            .notify(Initialized::METHOD, serde_json::json!({}))
+            .await;
+
+        let open = |text: &str| {
+            serde_json::json!({"textDocument": {
+                "uri": "file:///tmp/test.txt",
+                "languageId": "plaintext",
+                "version": 1,
+                "text": text,
+            }})
+        };
+        client.notify("textDocument/didOpen", open("kwick\n")).await;
+        let diags = client.diagnostics().await;
+        assert_eq!(diags.len(), 1);
+        assert!(diags[0].contains("kwick"), "{diags:?}");
+
+        // A checker that stops responding times out, and the server carries on.
+        client.notify("textDocument/didOpen", open("slow\n")).await;
+        let resp = client
+            .request(2, "spelgud/status", serde_json::json!(null))
+            .await;
+        assert!(resp.error.is_none(), "{resp:?}");
+        assert!(client.log().await.contains("did not respond within 200ms"));
+        assert_eq!(client.diagnostics().await, Vec::<String>::new());
+
+        let resp = client
+            .request(3, Shutdown::METHOD, serde_json::json!(null))
+            .await;
+        assert!(resp.error.is_none(), "{resp:?}");
+        client.notify(Exit::METHOD, serde_json::json!(null)).await;
+        assert_eq!(server.await.unwrap(), Ok(()));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_run_async_current_thread() {
+        let (_client, server) = tokio::io::duplex(4096);
+        let (input, output) = tokio::io::split(server);
+        assert!(run_async(input, output).await.is_err());
+    }
+}
@@ -0,0 +1,149 @@
+// Python bindings for the checker, e.g. for Sphinx or mkdocs plugins, built with maturin
+// from pyproject.toml:
+//
+//     import spelgud
+//
+//     for m in spelgud.check("# Helo\n", "markdown"):
+//         print(m.line, m.word, m.suggestions)
+use crate::checker::Checker;
+use pyo3::prelude::*;
+use std::sync::Mutex;
+
+pyo3::create_exception!(spelgud, SpelgudError, pyo3::exceptions::PyException);
+
+// A misspelled word, with the byte range of the word in the UTF-8 encoded text.
+#[pyclass(frozen, get_all, module = "spelgud")]
+#[derive(Debug)]
+struct Misspelling {
+    word: String,
+    line: usize,
+    start: usize,
+    end: usize,
+    suggestions: Vec<String>,
+}
+
+// A word that would be checked.
+#[pyclass(frozen, get_all, module = "spelgud")]
+#[derive(Debug)]
+struct Word {
+    word: String,
+    line: usize,
+    start: usize,
+    end: usize,
+}
+
+#[pymethods]
+impl Misspelling {
+    fn __repr__(&self) -> String {
+        format!("{self:?}")
+    }
+}
+
+#[pymethods]
+impl Word {
+    fn __repr__(&self) -> String {
+        format!("{self:?}")
+    }
+}
+
+// The checker is started on first use and kept for later calls, as starting one may
+// mean starting a spell checker program.
+static CHECKER: Mutex<Option<Checker>> = Mutex::new(None);
+
+// Call `f` with the checker, without holding the GIL, as checking may wait on a spell
+// checker program.
+fn with_checker<T: Send>(
+    py: Python,
+    f: impl FnOnce(&mut Checker) -> crate::Result<T> + Send,
+) -> PyResult<T> {
+    let run = || -> crate::Result<T> {
+        let mut checker = CHECKER.lock().unwrap_or_else(|err| err.into_inner());
+        let checker = match checker.as_mut() {
+            Some(checker) => checker,
+            None => checker.insert(Checker::new()?),
+        };
+        f(checker)
+    };
+    // Errors aren't Send, so only their messages are passed back.
+    py.detach(|| run().map_err(|err| err.to_string()))
+        .map_err(SpelgudError::new_err)
+}
+
+// Check `text` in the language with the given id, e.g. "markdown", or as plain text.
+#[pyfunction]
+#[pyo3(signature = (text, lang=None))]
+fn check(py: Python, text: &str, lang: Option<&str>) -> PyResult<Vec<Misspelling>> {
+    let found = with_checker(py, |checker| match lang {
+        Some(lang) => checker.check_as(text, lang),
+        None => checker.check(text),
+    })?;
+    Ok(found
+        .into_iter()
+        .map(|found| Misspelling {
+            word: found.word,
+            line: found.line,
+            start: found.range.start,
+            end: found.range.end,
+            suggestions: found.suggestions,
+        })
+        .collect())
+}
+
+// The words in `text` that would be checked, as for `check`.
+#[pyfunction]
+#[pyo3(signature = (text, lang=None))]
+fn tokenize(py: Python, text: &str, lang: Option<&str>) -> PyResult<Vec<Word>> {
+    let lang = lang.unwrap_or("plaintext");
+    let words = with_checker(py, |checker| checker.tokenize(text, lang))?;
+    Ok(words
+        .into_iter()
+        .map(|word| Word {
+            word: word.word,
+            line: word.line,
+            start: word.range.start,
+            end: word.range.end,
+        })
+        .collect())
+}
+
+// Suggest corrections for `word`, best first.
+#[pyfunction]
+fn suggest(py: Python, word: &str) -> PyResult<Vec<String>> {
+    with_checker(py, |checker| checker.suggest(word))
+}
+
+#[pymodule]
+fn spelgud(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("SpelgudError", m.py().get_type::<SpelgudError>())?;
+    m.add_class::<Misspelling>()?;
+    m.add_class::<Word>()?;
+    m.add_function(wrap_pyfunction!(check, m)?)?;
+    m.add_function(wrap_pyfunction!(tokenize, m)?)?;
+    m.add_function(wrap_pyfunction!(suggest, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module() {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = pyo3::wrap_pymodule!(spelgud)(py);
+            let locals = pyo3::types::PyDict::new(py);
+            locals.set_item("spelgud", module).unwrap();
+            py.run(
+                c"
+words = spelgud.tokenize('Some *txt* and `code`', 'markdown')
+assert [(w.word, w.start, w.end) for w in words] == [('Some', 0, 4), ('txt', 6, 9), ('and', 11, 14)]
+assert repr(words[0]).startswith('Word {')
+",
+                None,
+                Some(&locals),
+            )
+            .unwrap();
+        });
+    }
+}
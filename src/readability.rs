@@ -0,0 +1,232 @@
+// Readability scores for each section of a document, shown as code lenses so writers can
+// see where their prose gets hard going. Scores are estimated with the Flesch reading ease
+// and Flesch-Kincaid grade formulas, from counts of words, sentences and syllables.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Score {
+    pub words: usize,
+    pub sentences: usize,
+    pub syllables: usize,
+}
+
+impl Score {
+    // The US school grade needed to follow the text.
+    pub fn grade(&self) -> f64 {
+        0.39 * self.words_per_sentence() + 11.8 * self.syllables_per_word() - 15.59
+    }
+
+    // From about 0 (very hard) to 100 (very easy), though it can fall outside that range.
+    pub fn ease(&self) -> f64 {
+        206.835 - 1.015 * self.words_per_sentence() - 84.6 * self.syllables_per_word()
+    }
+
+    fn words_per_sentence(&self) -> f64 {
+        self.words as f64 / self.sentences.max(1) as f64
+    }
+
+    fn syllables_per_word(&self) -> f64 {
+        self.syllables as f64 / self.words.max(1) as f64
+    }
+}
+
+impl std::fmt::Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Grade {:.1}, reading ease {:.0} ({} words)",
+            self.grade(),
+            self.ease(),
+            self.words
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    // The line of the section's heading, or of the start of the document.
+    pub line: u32,
+    pub score: Score,
+}
+
+// The sections of checked text, each starting at a markdown heading if `headings` is set.
+// Otherwise, or before the first heading, the text is scored as a whole. Sections without
+// any words, such as a heading directly followed by another, are left out.
+pub fn sections(checked: &str, headings: bool) -> Vec<Section> {
+    let mut sections = vec![];
+    let mut start = 0;
+    let mut lines = vec![];
+    for (i, line) in crate::file::lines(checked).enumerate() {
+        if headings && is_heading(line) {
+            sections.push((start, std::mem::take(&mut lines)));
+            start = i;
+        } else {
+            lines.push(line);
+        }
+    }
+    sections.push((start, lines));
+    sections
+        .into_iter()
+        .map(|(line, lines)| Section {
+            line: u32::try_from(line).unwrap_or(u32::MAX),
+            score: score(&lines),
+        })
+        .filter(|section| section.score.words > 0)
+        .collect()
+}
+
+// Whether a line is a markdown ATX heading, e.g. "## Usage".
+fn is_heading(line: &str) -> bool {
+    let trimmed = line.trim_start_matches(' ');
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    line.len() - trimmed.len() < 4
+        && (1..=6).contains(&level)
+        && trimmed[level..].starts_with(char::is_whitespace)
+}
+
+pub fn score(lines: &[&str]) -> Score {
+    let mut score = Score::default();
+    // Whether there are words since the end of the last sentence.
+    let mut open = false;
+    for line in lines {
+        let trimmed = line.trim();
+        // A blank line or list item ends any sentence left open, e.g. a list item without
+        // a full stop.
+        if open && (trimmed.is_empty() || is_list_item(trimmed)) {
+            score.sentences += 1;
+            open = false;
+        }
+        let words = crate::token::words(line);
+        let mut words = words
+            .into_iter()
+            .filter(|(_, word)| word.chars().any(char::is_alphabetic))
+            .peekable();
+        let chars: Vec<char> = line.chars().collect();
+        for (i, &c) in chars.iter().enumerate() {
+            if words.peek().is_some_and(|(start, _)| *start == i) {
+                let (_, word) = words.next().unwrap();
+                score.words += 1;
+                score.syllables += syllables(word);
+                open = true;
+            }
+            let ends = chars[i + 1..]
+                .iter()
+                .find(|c| !matches!(c, '"' | '\'' | ')' | '’' | '”'))
+                .is_none_or(|c| c.is_whitespace());
+            if open && matches!(c, '.' | '!' | '?') && ends {
+                score.sentences += 1;
+                open = false;
+            }
+        }
+    }
+    if open {
+        score.sentences += 1;
+    }
+    score
+}
+
+fn is_list_item(line: &str) -> bool {
+    let number = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    ["- ", "* ", "+ "]
+        .iter()
+        .any(|bullet| line.starts_with(bullet))
+        || (number.len() < line.len() && (number.starts_with(". ") || number.starts_with(") ")))
+}
+
+// An estimate of the number of syllables in a word: its groups of vowels, not counting a
+// silent "e" at the end, as in "make".
+fn syllables(word: &str) -> usize {
+    let word = word.to_lowercase();
+    let is_vowel = |c: char| "aeiouy".contains(c);
+    let mut count = 0;
+    let mut last = None;
+    for c in word.chars().filter(|c| c.is_alphabetic()) {
+        if is_vowel(c) && !last.is_some_and(is_vowel) {
+            count += 1;
+        }
+        last = Some(c);
+    }
+    if count > 1 && word.ends_with('e') && !word.ends_with("le") && !word.ends_with("ee") {
+        count -= 1;
+    }
+    count.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_syllables() {
+        let counts: Vec<usize> = [
+            "the",
+            "make",
+            "table",
+            "readability",
+            "rhythm",
+            "don't",
+            "free",
+            "Queue",
+        ]
+        .iter()
+        .map(|word| syllables(word))
+        .collect();
+        assert_eq!(counts, [1, 1, 2, 5, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_score() {
+        let score = |text: &str| score(&crate::file::lines(text).collect::<Vec<_>>());
+        assert_eq!(
+            score("The cat sat. It was happy!\nWas it (really)? Yes."),
+            Score {
+                words: 10,
+                sentences: 4,
+                syllables: 12,
+            }
+        );
+        // Numbers, abbreviations in words and unfinished sentences.
+        assert_eq!(score("Pi is 3.14 or so\n").sentences, 1);
+        assert_eq!(
+            score("Steps:\n\n- open it\n- close it\n\nDone").sentences,
+            4
+        );
+        assert_eq!(score("He said \"stop.\" Then left.").sentences, 2);
+        assert_eq!(score(""), Score::default());
+    }
+
+    #[test]
+    fn test_formulas() {
+        let easy = Score {
+            words: 10,
+            sentences: 2,
+            syllables: 12,
+        };
+        assert_eq!(format!("{easy}"), "Grade 0.5, reading ease 100 (10 words)");
+        let hard = Score {
+            words: 40,
+            sentences: 1,
+            syllables: 80,
+        };
+        assert_eq!(format!("{hard}"), "Grade 23.6, reading ease -3 (40 words)");
+    }
+
+    #[test]
+    fn test_sections() {
+        let text = "Intro text.\n# Usage\nRun it.\n\n## Options\n### None\nNothing here.\n";
+        let lines: Vec<(u32, usize)> = sections(text, true)
+            .iter()
+            .map(|section| (section.line, section.score.words))
+            .collect();
+        assert_eq!(lines, [(0, 2), (1, 2), (5, 2)]);
+        assert_eq!(
+            sections("#hashtag is not\n    # nor this\n", true)
+                .iter()
+                .map(|section| (section.line, section.score.words))
+                .collect::<Vec<_>>(),
+            [(0, 5)]
+        );
+        assert_eq!(sections(text, false).len(), 1);
+        assert_eq!(sections("\n\n", true), []);
+    }
+}
@@ -0,0 +1,155 @@
+// Finding the files in the workspace to check at startup, so errors in files that
+// haven't been opened are also shown.
+use crate::glob;
+use crate::ignore::Ignore;
+use std::path::{Path, PathBuf};
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Options {
+    // Files to check, as globs relative to the workspace root.
+    pub include: Vec<String>,
+    // Files and directories to skip, even if included.
+    pub exclude: Vec<String>,
+    // The most files to check, so a huge workspace doesn't stall the server.
+    pub max_files: usize,
+    // How long changes made outside the editor must settle before they are checked, if the
+    // editor can't report them.
+    pub debounce_ms: u64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            include: vec!["**/*.md".into(), "**/*.txt".into()],
+            exclude: vec![
+                "**/.*/**".into(),
+                "**/node_modules/**".into(),
+                "**/target/**".into(),
+            ],
+            max_files: 1000,
+            debounce_ms: 500,
+        }
+    }
+}
+
+// The files under `root` matching the options and not ignored, in a stable order.
+pub fn files(root: &Path, opts: &Options) -> Vec<PathBuf> {
+    let mut ignore = Ignore::new(root);
+    let mut found = vec![];
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries: Vec<PathBuf> = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries.flatten().map(|entry| entry.path()).collect(),
+            Err(err) => {
+                log::warn!("Cannot scan {dir:?}: {err}");
+                continue;
+            }
+        };
+        entries.sort();
+        // Visit directories in order, as the last one pushed is scanned first.
+        for path in entries.into_iter().rev() {
+            let Some(rel) = relative(root, &path) else {
+                continue;
+            };
+            if ignore.ignored(&path) {
+                continue;
+            }
+            if path.is_dir() {
+                if !excluded(opts, &format!("{rel}/")) {
+                    dirs.push(path);
+                }
+            } else if included(opts, &rel) {
+                found.push((rel, path));
+            }
+        }
+    }
+    found.sort();
+    if found.len() > opts.max_files {
+        log::warn!(
+            "Only checking {} of {} files in {root:?}",
+            opts.max_files,
+            found.len()
+        );
+        found.truncate(opts.max_files);
+    }
+    found.into_iter().map(|(_, path)| path).collect()
+}
+
+// Whether the file at `path` under `root` is one to check.
+pub fn matches(root: &Path, path: &Path, opts: &Options) -> bool {
+    relative(root, path).is_some_and(|rel| included(opts, &rel))
+}
+
+fn included(opts: &Options, rel: &str) -> bool {
+    opts.include.iter().any(|p| glob::matches(p, rel)) && !excluded(opts, rel)
+}
+
+fn excluded(opts: &Options, rel: &str) -> bool {
+    opts.exclude.iter().any(|p| glob::matches(p, rel))
+}
+
+// The path relative to the root, separated by "/" on every platform.
+fn relative(root: &Path, path: &Path) -> Option<String> {
+    let parts: Option<Vec<&str>> = path
+        .strip_prefix(root)
+        .ok()?
+        .components()
+        .map(|c| c.as_os_str().to_str())
+        .collect();
+    Some(parts?.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        for path in [
+            "README.md",
+            "notes.txt",
+            "main.rs",
+            "docs/guide.md",
+            "docs/api/index.md",
+            "node_modules/pkg/README.md",
+            ".git/description.txt",
+        ] {
+            let path = tmp.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "text").unwrap();
+        }
+        let files = |opts: &Options| -> Vec<String> {
+            files(tmp.path(), opts)
+                .iter()
+                .map(|path| relative(tmp.path(), path).unwrap())
+                .collect()
+        };
+        assert_eq!(
+            files(&Options::default()),
+            [
+                "README.md",
+                "docs/api/index.md",
+                "docs/guide.md",
+                "notes.txt"
+            ]
+        );
+        let opts = Options {
+            include: vec!["docs/**".into()],
+            max_files: 1,
+            ..Default::default()
+        };
+        assert_eq!(files(&opts), ["docs/api/index.md"]);
+
+        let opts = Options::default();
+        assert!(matches(tmp.path(), &tmp.path().join("new.md"), &opts));
+        assert!(!matches(
+            tmp.path(),
+            &tmp.path().join("target/new.md"),
+            &opts
+        ));
+        assert!(!matches(tmp.path(), Path::new("/elsewhere/new.md"), &opts));
+    }
+}
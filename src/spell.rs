@@ -1,8 +1,28 @@
-use std::{
-    io::{BufRead, Write},
-    process::{Command, Stdio},
-};
+#[cfg(feature = "builtin")]
+mod builtin;
+mod codespell;
+mod custom;
+mod dictionary;
+#[cfg(test)]
+pub mod fakes;
+#[cfg(feature = "hunspell")]
+mod hunspell;
+pub mod keyboard;
+mod known;
+pub mod languagetool;
+mod offensive;
+mod personal;
+#[cfg(not(target_family = "wasm"))]
+mod pool;
+#[cfg(not(target_family = "wasm"))]
+mod process;
+pub mod rank;
+pub mod variants;
+
+#[cfg(not(target_family = "wasm"))]
+use process::Process;
 
+use crate::token::Script;
 use lsp_types::Diagnostic;
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
@@ -15,159 +35,1293 @@ pub struct DiagnosticData {
     pub range: lsp_types::Range,
 }
 
-pub struct Process(std::process::Child);
+// User-configurable options that control how words are checked.
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Options {
+    // Only words in these scripts are sent to the checker.
+    pub scripts: Vec<Script>,
+    // Accept words made of several dictionary words run together, e.g. "Rechtschreibprüfung".
+    pub allow_compounds: bool,
+    // The dictionary to use, either a name like "en_US" or a path without extension.
+    pub dictionary: Option<String>,
+    // Report common typos using codespell's table of corrections.
+    pub codespell: codespell::Options,
+    // The most processes to run at once for an external checker.
+    pub max_processes: usize,
+    // How long to wait for an external checker to check a line before restarting it.
+    pub timeout_ms: u64,
+    // Where to download dictionaries from. "{lang}" is replaced with the language,
+    // and "{ext}" with "aff" or "dic".
+    pub dictionary_source: String,
+    // How many correctly spelled words to remember, so they aren't checked again.
+    pub known_words: usize,
+    // Files of words to accept, one per line, e.g. a project's word list.
+    pub wordlists: Vec<std::path::PathBuf>,
+    // Files of words to accept in any case and with any apostrophe, one per line, e.g. a long
+    // list of a field's jargon. Words in them are never sent to the checker.
+    pub jargon: Vec<std::path::PathBuf>,
+    // Hunspell dictionaries whose words are accepted along with the main dictionary, e.g. a
+    // project's "dicts/custom" for "dicts/custom.dic" and "dicts/custom.aff".
+    pub dictionaries: Vec<std::path::PathBuf>,
+    // A file of words, most common first, used to put common words first in suggestions.
+    // If unset, the bundled English list is used if built in.
+    pub frequencies: Option<std::path::PathBuf>,
+    // A file of pairs of words that are often found together, e.g. "piece of", used to put
+    // suggestions that fit with the words around a misspelling first.
+    pub pairs: Option<std::path::PathBuf>,
+    // The keyboard layout words are typed on, used to put corrections a single slip of the
+    // finger away first, e.g. "jumps" for "jumpd". Null to ignore the keyboard.
+    pub keyboard: Option<keyboard::Layout>,
+    // The most suggestions to offer for a misspelling.
+    pub max_suggestions: usize,
+    // Never suggest profanity or slurs.
+    pub hide_offensive: bool,
+    // Correct known typos with a single, certain fix when a file is saved.
+    pub fix_on_save: bool,
+    // Names that must be written with exactly this case, e.g. "GitHub" or "macOS".
+    pub proper_nouns: Vec<String>,
+    // If set, report words spelled the American way in a British document, or vice versa.
+    pub variant: Option<variants::Preferred>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            scripts: Script::defaults(),
+            allow_compounds: false,
+            dictionary: None,
+            codespell: Default::default(),
+            max_processes: 4,
+            timeout_ms: 10_000,
+            dictionary_source: DICTIONARY_SOURCE.into(),
+            known_words: 100_000,
+            wordlists: vec![],
+            jargon: vec![],
+            dictionaries: vec![],
+            frequencies: None,
+            pairs: None,
+            keyboard: Some(keyboard::Layout::Qwerty),
+            max_suggestions: 10,
+            hide_offensive: false,
+            fix_on_save: false,
+            variant: None,
+            proper_nouns: vec![],
+        }
+    }
+}
+
+// Hunspell dictionaries collected from various sources, named by language, e.g. "en-GB".
+const DICTIONARY_SOURCE: &str =
+    "https://raw.githubusercontent.com/wooorm/dictionaries/main/dictionaries/{lang}/index.{ext}";
 
-#[derive(serde::Deserialize, Debug)]
+// Download a dictionary so it can be used by the built-in checker and libhunspell.
+pub fn install_dictionary(lang: &str, opts: &Options) -> Result<()> {
+    let dict = dictionary::install(lang, &opts.dictionary_source)?;
+    log::info!("Installed dictionary {dict:?}");
+    Ok(())
+}
+
+// A spell checker that can be used, and the dictionaries it can use.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Available {
+    pub name: String,
+    // The dictionaries it can use, each with where it is if known,
+    // or None if it isn't installed.
+    pub dictionaries: Option<Vec<(String, Option<std::path::PathBuf>)>>,
+}
+
+// The given programs, and the dictionaries each can use, e.g. to find out why a
+// dictionary isn't being used.
+pub fn available(programs: &[Program]) -> Vec<Available> {
+    programs
+        .iter()
+        .map(|prog| Available {
+            name: match prog {
+                // Distinguished from the hunspell program here.
+                Program::Libhunspell => "libhunspell".into(),
+                prog => prog.name(),
+            },
+            dictionaries: prog.dictionaries(),
+        })
+        .collect()
+}
+
+// The dictionary used if none is configured, from the current locale.
+pub fn default_dictionary() -> Option<String> {
+    dictionary::locale_language()
+}
+
+/// A misspelled word found by a [`Checker`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct Misspelling {
+    /// The character offset of the word in the checked text.
+    pub offset: usize,
+    pub word: String,
+    /// Suggested corrections, or None if the checker only finds misspellings, in which case
+    /// suggestions are asked for when needed, e.g. for a code action.
+    pub fixes: Option<Vec<String>>,
+}
+
+/// A spell checker. Implement this to add a new backend, and pass it to
+/// [`run_with_checker`](crate::run_with_checker) to use it in the language server.
+pub trait Checker: Send {
+    /// Find the misspelled words in a single line of normalized text.
+    fn check(&mut self, text: &str) -> Result<Vec<Misspelling>>;
+    /// Suggest corrections for a word.
+    fn suggest(&mut self, word: &str) -> Result<Vec<String>>;
+    /// Check several lines at once, returning the misspellings in each.
+    fn check_lines(&mut self, texts: &[String]) -> Result<Vec<Vec<Misspelling>>> {
+        texts.iter().map(|text| self.check(text)).collect()
+    }
+    /// The name shown as the source of the diagnostics it finds.
+    fn name(&self) -> String {
+        "custom".into()
+    }
+    /// The checker's version, if known.
+    fn version(&self) -> Option<String> {
+        None
+    }
+    /// The dictionaries in use, if known.
+    fn dictionaries(&self) -> Vec<String> {
+        vec![]
+    }
+    /// Accept a word for the rest of the session.
+    fn accept(&mut self, _word: &str) -> Result<()> {
+        Ok(())
+    }
+    /// Add a word to the checker's own personal dictionary and save it.
+    /// Returns false if the checker has no personal dictionary.
+    fn add(&mut self, _word: &str) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+// The state of a checker, as reported by the status request.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct Status {
+    pub name: String,
+    pub version: Option<String>,
+    pub dictionaries: Vec<String>,
+}
+
+// The error returned when a checker takes too long to respond.
+#[derive(Debug)]
+pub struct Timeout(std::time::Duration);
+
+impl std::fmt::Display for Timeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Spell checker did not respond within {:?}", self.0)
+    }
+}
+
+impl std::error::Error for Timeout {}
+
+// Turns the misspellings found by checkers into diagnostics for a document.
+pub struct Backend {
+    // The primary checker followed by any secondary checkers, each with the name
+    // used as the source of its diagnostics.
+    checkers: Vec<(String, Box<dyn Checker>)>,
+    opts: Options,
+    // Problems to tell the user about that didn't prevent checking, e.g. a checker timing out.
+    messages: Vec<Notice>,
+    // The programs to choose the primary checker from.
+    programs: Vec<Program>,
+    // If none of the programs could be started, where the primary checker belongs in
+    // `checkers` and when starting one was last attempted.
+    missing_primary: Option<(usize, std::time::Instant)>,
+    // Words the user has added to their personal dictionary, which are never reported.
+    personal: personal::Personal,
+    // Orders suggestions, best first.
+    ranker: rank::Ranker,
+    // The typos corrected on save, if enabled.
+    autofix: Option<codespell::Codespell>,
+}
+
+// A typo to correct without asking, e.g. on save.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AutoFix {
+    pub range: lsp_types::Range,
+    pub typo: String,
+    pub fix: String,
+}
+
+// A message for the user.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Notice {
+    pub text: String,
+    // Whether to show the message to the user, rather than only logging it.
+    pub show: bool,
+}
+
+// How often to try again to start a checker if none are available.
+const RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+#[derive(serde::Deserialize, Debug, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum Program {
     Aspell,
     Ispell,
     Hunspell,
+    // The hunspell library, only available if built with the "hunspell" feature.
+    Libhunspell,
+    // The spell checker built into spelgud, which reads hunspell dictionaries, only available
+    // if built with the "builtin" feature.
+    Builtin,
+    // A user-supplied command.
+    Custom(custom::Custom),
 }
 
 impl Program {
-    fn command(&self) -> Option<std::path::PathBuf> {
+    // The name shown as the source of diagnostics from this program.
+    fn name(&self) -> String {
         match self {
-            Program::Aspell => which::which("aspell"),
-            Program::Ispell => which::which("ispell"),
-            Program::Hunspell => which::which("hunspell"),
+            Program::Aspell => "aspell".into(),
+            Program::Ispell => "ispell".into(),
+            Program::Hunspell | Program::Libhunspell => "hunspell".into(),
+            Program::Builtin => "spelgud".into(),
+            Program::Custom(custom) => custom.name(),
         }
-        .ok()
     }
 
-    fn pick(choices: &[Program]) -> Result<std::path::PathBuf> {
-        Ok(choices
-            .iter()
-            .find_map(|c| c.command())
-            .ok_or("No spell checker found")?)
+    // The dictionaries this program can use, or None if it is not installed.
+    fn dictionaries(&self) -> Option<Vec<(String, Option<std::path::PathBuf>)>> {
+        let hunspell = || {
+            dictionary::installed()
+                .into_iter()
+                .map(|(name, dict)| (name, Some(dict.dic)))
+                .collect::<Vec<_>>()
+        };
+        match self {
+            Program::Hunspell => {
+                self.command()?;
+                Some(hunspell())
+            }
+            Program::Libhunspell if cfg!(feature = "hunspell") => Some(hunspell()),
+            Program::Libhunspell => None,
+            Program::Builtin if cfg!(feature = "builtin") => {
+                let mut dicts = hunspell();
+                if cfg!(feature = "bundled-dict") && !dicts.iter().any(|(name, _)| name == "en_US")
+                {
+                    dicts.push(("en_US".into(), None));
+                }
+                Some(dicts)
+            }
+            Program::Builtin => None,
+            // What a custom command can use is up to the command.
+            Program::Custom(_) => Some(vec![]),
+            Program::Aspell | Program::Ispell => self.list_dictionaries(),
+        }
+    }
+
+    #[cfg(target_family = "wasm")]
+    fn list_dictionaries(&self) -> Option<Vec<(String, Option<std::path::PathBuf>)>> {
+        None
+    }
+
+    // Start this program, returning None if it is not installed.
+    fn start(&self, opts: &Options) -> Option<Result<Box<dyn Checker>>> {
+        let checker: Result<Box<dyn Checker>> = match self {
+            Program::Libhunspell => libhunspell(opts),
+            Program::Builtin => builtin(opts),
+            _ => self.spawn(opts)?,
+        };
+        Some(checker)
+    }
+
+    // Start a pool of processes for this program, returning None if it is not installed.
+    #[cfg(not(target_family = "wasm"))]
+    fn spawn(&self, opts: &Options) -> Option<Result<Box<dyn Checker>>> {
+        let start = self.starter(opts)?;
+        Some(start().map(|first| {
+            Box::new(pool::Pool::new(first, opts.max_processes, start)) as Box<dyn Checker>
+        }))
+    }
+
+    // WebAssembly hosts can't run other programs, so only in-process checkers are available.
+    #[cfg(target_family = "wasm")]
+    fn spawn(&self, _opts: &Options) -> Option<Result<Box<dyn Checker>>> {
+        None
+    }
+}
+
+#[cfg(feature = "builtin")]
+fn builtin(opts: &Options) -> Result<Box<dyn Checker>> {
+    let Some(dict) = dictionary::find(opts.dictionary.as_deref()) else {
+        return bundled(opts);
+    };
+    Ok(Box::new(builtin::Builtin::new(
+        &dict,
+        opts.allow_compounds,
+    )?))
+}
+
+// Fall back to the embedded English dictionary, unless another language was asked for.
+#[cfg(feature = "bundled-dict")]
+fn bundled(opts: &Options) -> Result<Box<dyn Checker>> {
+    match opts.dictionary.as_deref() {
+        None | Some("en" | "en_US" | "en-US") => {
+            Ok(Box::new(builtin::Builtin::bundled(opts.allow_compounds)?))
+        }
+        Some(_) => Err("No dictionary found")?,
     }
 }
 
-impl Process {
-    // Launches the first executable spell checker in the list.
-    pub fn new(programs: &[Program]) -> Result<Process> {
-        let cmd = Program::pick(programs)?;
+#[cfg(all(feature = "builtin", not(feature = "bundled-dict")))]
+fn bundled(_opts: &Options) -> Result<Box<dyn Checker>> {
+    Err("No dictionary found")?
+}
+
+#[cfg(not(feature = "builtin"))]
+fn builtin(_opts: &Options) -> Result<Box<dyn Checker>> {
+    Err("spelgud was built without the builtin feature")?
+}
+
+#[cfg(feature = "hunspell")]
+fn libhunspell(opts: &Options) -> Result<Box<dyn Checker>> {
+    let dict = dictionary::find(opts.dictionary.as_deref()).ok_or("No dictionary found")?;
+    Ok(Box::new(hunspell::Hunspell::new(&dict)?))
+}
 
-        let mut proc = Command::new(cmd.as_path())
-            .arg("-a")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()?;
+#[cfg(not(feature = "hunspell"))]
+fn libhunspell(_opts: &Options) -> Result<Box<dyn Checker>> {
+    Err("spelgud was built without the hunspell feature")?
+}
 
-        log::info!("Started {cmd:?} with pid {}", proc.id());
+impl Backend {
+    // Start the first available spell checker in `programs`, along with every available
+    // checker in `secondary`, whose results are merged with those of the first.
+    // If none of `programs` are available, checking continues without them and they are
+    // tried again later.
+    pub fn new(programs: &[Program], secondary: &[Program], opts: &Options) -> Result<Backend> {
+        Self::start(programs, None, secondary, opts)
+    }
 
-        let stdin = proc.stdin.as_mut().unwrap();
-        let mut stdout = std::io::BufReader::new(proc.stdout.as_mut().unwrap());
+    // Use the given checker rather than one of the programs, e.g. one implemented by
+    // another crate, along with every available checker in `secondary`.
+    pub fn with_primary(
+        checker: Box<dyn Checker>,
+        secondary: &[Program],
+        opts: &Options,
+    ) -> Result<Backend> {
+        Self::start(&[], Some(checker), secondary, opts)
+    }
 
-        // Read the initial version line.
-        let mut output = String::new();
-        stdout.read_line(&mut output)?;
-        log::trace!("Read line '{output}'");
+    fn start(
+        programs: &[Program],
+        primary: Option<Box<dyn Checker>>,
+        secondary: &[Program],
+        opts: &Options,
+    ) -> Result<Backend> {
+        let mut backend = Backend {
+            checkers: vec![],
+            opts: opts.clone(),
+            messages: vec![],
+            programs: programs.to_vec(),
+            missing_primary: None,
+            personal: Default::default(),
+            ranker: rank::Ranker::new(
+                opts.frequencies.as_deref(),
+                opts.pairs.as_deref(),
+                opts.keyboard,
+            ),
+            autofix: match opts.fix_on_save {
+                true => Some(codespell::Codespell::curated(&opts.codespell)?),
+                false => None,
+            },
+        };
+        if opts.codespell.enabled {
+            // Check for known typos first, so their single, confident fix is the one reported.
+            let codespell = codespell::Codespell::new(&opts.codespell)?;
+            let codespell = backend.remember(Box::new(codespell));
+            backend.checkers.push(("codespell".into(), codespell));
+        }
+        if let Some(checker) = primary {
+            let name = checker.name();
+            log::info!("Using {name}");
+            let checker = backend.remember(checker);
+            backend.checkers.push((name, checker));
+        } else if !backend.start_primary(backend.checkers.len()) {
+            backend.messages.push(Notice {
+                text: "No spell checker found. Install aspell or hunspell, or a hunspell \
+                       dictionary for the built-in checker."
+                    .into(),
+                show: true,
+            });
+        }
+        let aspell = backend.checkers.iter().any(|(name, _)| name == "aspell");
+        backend.personal = personal::Personal::load(opts.dictionary.as_deref(), aspell);
+        backend.personal.load_wordlists(&opts.wordlists);
+        backend.personal.load_jargon(&opts.jargon);
+        backend
+            .personal
+            .load_dictionaries(&opts.dictionaries, opts.allow_compounds);
+        backend.personal.load_names(&opts.proper_nouns);
+        for prog in secondary {
+            match prog.start(opts) {
+                Some(Ok(checker)) => {
+                    let checker = backend.remember(checker);
+                    backend.checkers.push((prog.name(), checker));
+                }
+                Some(Err(err)) => log::warn!("Cannot use {prog:?}: {err}"),
+                None => log::warn!("Cannot use {prog:?}: not installed"),
+            }
+        }
+        Ok(backend)
+    }
 
-        // Enable terse mode, so we don't need to read "*" for every ok word.
-        log::trace!("Read line '{output}'");
-        stdin.write_all("!\n".as_bytes())?;
-        Ok(Process(proc))
+    // Use the given checker, e.g. a test double.
+    #[cfg(test)]
+    pub fn with_checker(name: &str, checker: Box<dyn Checker>, opts: &Options) -> Backend {
+        Backend {
+            checkers: vec![(name.to_string(), checker)],
+            opts: opts.clone(),
+            messages: vec![],
+            programs: vec![],
+            missing_primary: None,
+            personal: Default::default(),
+            ranker: Default::default(),
+            autofix: None,
+        }
     }
 
+    // Wrap a checker so the words it approves aren't checked again.
+    fn remember(&self, checker: Box<dyn Checker>) -> Box<dyn Checker> {
+        Box::new(known::Known::new(checker, self.opts.known_words))
+    }
+
+    // Start the first available program, inserting it into `checkers` at `index`.
+    // Returns false if none could be started.
+    fn start_primary(&mut self, index: usize) -> bool {
+        let found = self
+            .programs
+            .iter()
+            .find_map(|prog| match prog.start(&self.opts)? {
+                Ok(checker) => Some((prog.name(), self.remember(checker))),
+                Err(err) => {
+                    log::warn!("Cannot use {prog:?}: {err}");
+                    None
+                }
+            });
+        match found {
+            Some((name, checker)) => {
+                log::info!("Using {name}");
+                self.checkers.insert(index, (name, checker));
+                self.missing_primary = None;
+            }
+            None => {
+                log::error!("No spell checker found");
+                self.missing_primary = Some((index, std::time::Instant::now()));
+            }
+        }
+        self.missing_primary.is_none()
+    }
+
+    #[cfg(test)]
     pub fn diags(&mut self, text: &str) -> Result<Vec<Diagnostic>> {
-        let stdin = self.0.stdin.as_mut().unwrap();
-        let mut stdout = std::io::BufReader::new(self.0.stdout.as_mut().unwrap());
+        let lines = crate::file::lines(text)
+            .map(|line| line.trim_end_matches(['\r', '\n']))
+            .enumerate()
+            .map(|(line, input)| Ok((line.try_into()?, 0, input)));
+        self.diags_at(lines.collect::<Result<Vec<_>>>()?)
+    }
+
+    // Check each of the given pieces of text, where each is a single line that starts
+    // at the given line and character of the document.
+    pub fn diags_at<'a>(
+        &mut self,
+        inputs: impl IntoIterator<Item = (u32, u32, &'a str)>,
+    ) -> Result<Vec<Diagnostic>> {
+        if let Some((index, tried)) = self.missing_primary {
+            if tried.elapsed() >= RETRY_INTERVAL && self.start_primary(index) {
+                self.messages.push(Notice {
+                    text: format!("Found spell checker {}", self.checkers[index].0),
+                    show: false,
+                });
+            }
+        }
+        let inputs: Vec<_> = inputs.into_iter().collect();
         let mut diags = vec![];
-        for (line, input) in text.lines().enumerate() {
-            let line = line.try_into()?;
-            if input.is_empty() {
-                continue;
+        let mut error = None;
+        for (name, checker) in &mut self.checkers {
+            let found = Self::check(
+                checker.as_mut(),
+                &self.opts,
+                &self.personal,
+                &self.ranker,
+                &inputs,
+            );
+            match found {
+                Ok(found) => merge(
+                    &mut diags,
+                    found.into_iter().map(|diag| Diagnostic {
+                        source: Some(name.clone()),
+                        ..diag
+                    }),
+                ),
+                // The checker has been restarted, so just skip this check.
+                Err(err) if err.is::<Timeout>() => {
+                    log::info!("{name}: {err}");
+                    self.messages.push(Notice {
+                        text: format!("{name}: {err}"),
+                        show: false,
+                    });
+                }
+                // Still report what the other checkers found if one fails.
+                Err(err) => {
+                    log::error!("{name} failed: {err}");
+                    error.get_or_insert(err);
+                }
             }
+        }
+        match error {
+            Some(err) if diags.is_empty() => Err(err),
+            _ => Ok(diags),
+        }
+    }
 
-            log::trace!("Writing '{input}'");
-            stdin.write_all(input.as_bytes())?;
-            stdin.write_all("\n".as_bytes())?;
-            stdin.flush()?;
-
-            loop {
-                let mut output = String::new();
-                stdout.read_line(&mut output)?;
-                log::trace!("Read line {line}: '{output}'");
-
-                // http://aspell.net/man-html/Through-A-Pipe.html#Through-A-Pipe
-                // OK: *
-                // Suggestions: & original count offset: miss, miss, …
-                // None: # original offset
-                // Offset is a character offset.
-                let parts: Vec<&str> = output.split(&[' ', ':', ',']).collect();
-                let diag = match parts.as_slice() {
-                    ["&", original, _count, offset, misses @ ..] => {
-                        let range = lsp_types::Range {
-                            start: lsp_types::Position {
-                                line,
-                                character: offset.parse::<u32>()?,
-                            },
-                            end: lsp_types::Position {
-                                line,
-                                character: offset.parse::<u32>()?
-                                    + u32::try_from(original.chars().count())?,
-                            },
-                        };
-                        lsp_types::Diagnostic {
-                            range,
-                            severity: Some(lsp_types::DiagnosticSeverity::ERROR),
-                            message: original.to_string(),
-                            data: Some(serde_json::to_value(DiagnosticData {
-                                range,
-                                original: original.to_string(),
-                                fixes: misses
-                                    .iter()
-                                    .filter(|s| !s.is_empty())
-                                    .map(|s| s.trim().to_string())
-                                    .collect(),
-                            })?),
-                            ..Default::default()
-                        }
-                    }
-                    ["#", original, offset] => lsp_types::Diagnostic {
-                        range: lsp_types::Range {
-                            start: lsp_types::Position {
-                                line,
-                                character: offset.parse::<u32>()?,
-                            },
-                            end: lsp_types::Position {
-                                line,
-                                character: offset.parse::<u32>()?
-                                    + u32::try_from(original.chars().count())?,
-                            },
-                        },
-                        severity: Some(lsp_types::DiagnosticSeverity::ERROR),
-                        message: original.to_string(),
-                        ..Default::default()
-                    },
-                    ["\n"] => {
-                        log::trace!("Done parsing diagnostics for line {line}");
-                        break; // done with results for this line
-                    }
-                    _ => Err(format!("Unexpected line: {output}: {parts:?}"))?,
+    fn check(
+        checker: &mut dyn Checker,
+        opts: &Options,
+        personal: &personal::Personal,
+        ranker: &rank::Ranker,
+        inputs: &[(u32, u32, &str)],
+    ) -> Result<Vec<Diagnostic>> {
+        // Feed the checker only the words we consider words, so its tokenization matches ours.
+        let sent: Vec<_> = inputs
+            .iter()
+            .map(|input| crate::token::normalize(input.2, &opts.scripts))
+            .collect();
+        // Documents repeat the same words many times, so each distinct word is sent to the
+        // checker once, on a line of its own, and its verdict applies to every occurrence.
+        // Jargon is accepted without asking the checker, as lists of it can be long.
+        let mut unique: Vec<String> = vec![];
+        let mut index = std::collections::HashMap::<&str, usize>::new();
+        for word in sent
+            .iter()
+            .flat_map(|s| crate::token::words(s))
+            .map(|(_, w)| w)
+            .filter(|w| !personal.is_jargon(w))
+        {
+            index.entry(word).or_insert_with(|| {
+                unique.push(word.to_string());
+                unique.len() - 1
+            });
+        }
+        let verdicts = checker.check_lines(&unique)?;
+        let document = rank::pairs(sent.iter().map(String::as_str));
+        let mut diags = vec![];
+        for (&(line, column, input), sent) in inputs.iter().zip(&sent) {
+            for (offset, word) in crate::token::words(sent) {
+                let Some(&i) = index.get(word) else {
+                    continue;
                 };
-                diags.push(diag);
+                let found = verdicts[i].iter();
+                for m in found.filter(|m| !personal.contains(&m.word)) {
+                    let offset = offset + m.offset;
+                    // The word may be longer in the input if it was composed during normalization.
+                    let len = crate::token::original_len(input, offset, &m.word);
+                    let start = column + u32::try_from(offset)?;
+                    let fixes = m.fixes.clone().map(|mut fixes| {
+                        add_new(&mut fixes, personal.suggest(&m.word));
+                        let context = rank::Context::at(sent, offset, Some(&document));
+                        order(ranker, opts, &m.word, fixes, &context)
+                    });
+                    diags.push(diagnostic(line, start, len.try_into()?, &m.word, fixes)?);
+                }
             }
         }
         Ok(diags)
     }
+
+    // If no checker could be started, try again now, e.g. after installing a dictionary.
+    pub fn retry(&mut self) {
+        if let Some((index, _)) = self.missing_primary {
+            self.start_primary(index);
+        }
+    }
+
+    // Add a word to the user's personal dictionary. Checkers that have their own personal
+    // dictionary, such as aspell, save it themselves.
+    pub fn add_word(&mut self, word: &str) -> Result<()> {
+        let mut saved = false;
+        for (_, checker) in &mut self.checkers {
+            saved |= checker.add(word)?;
+        }
+        self.personal.add(word, !saved)
+    }
+
+    // Accept a word until the server exits.
+    pub fn accept_word(&mut self, word: &str) -> Result<()> {
+        for (_, checker) in &mut self.checkers {
+            checker.accept(word)?;
+        }
+        self.personal.accept(word);
+        Ok(())
+    }
+
+    // The number of words accepted for this session.
+    pub fn session_words(&self) -> usize {
+        self.personal.session_words()
+    }
+
+    // The state of each checker in use.
+    pub fn status(&self) -> Vec<Status> {
+        self.checkers
+            .iter()
+            .map(|(name, checker)| Status {
+                name: name.clone(),
+                version: checker.version(),
+                dictionaries: checker.dictionaries(),
+            })
+            .collect()
+    }
+
+    // Take the messages for the user produced since this was last called.
+    pub fn take_messages(&mut self) -> Vec<Notice> {
+        std::mem::take(&mut self.messages)
+    }
+
+    // Suggest corrections for a word from the first checker that has any, best first.
+    pub fn suggest(&mut self, word: &str) -> Result<Vec<String>> {
+        self.suggest_in(word, &rank::Context::default())
+    }
+
+    // The known typos in each line of a document to correct on save, if enabled. Words the
+    // user has accepted are left alone.
+    pub fn autofixes<'a>(&self, lines: impl IntoIterator<Item = &'a str>) -> Result<Vec<AutoFix>> {
+        let Some(autofix) = &self.autofix else {
+            return Ok(vec![]);
+        };
+        let mut fixes = vec![];
+        for (line, text) in lines.into_iter().enumerate() {
+            for (offset, word) in crate::token::words(text) {
+                if self.personal.contains(word) {
+                    continue;
+                }
+                if let Some(fix) = autofix.correct(word) {
+                    let line = u32::try_from(line)?;
+                    let start = u32::try_from(offset)?;
+                    let end = start + u32::try_from(word.chars().count())?;
+                    fixes.push(AutoFix {
+                        range: lsp_types::Range::new(
+                            lsp_types::Position::new(line, start),
+                            lsp_types::Position::new(line, end),
+                        ),
+                        typo: word.to_string(),
+                        fix,
+                    });
+                }
+            }
+        }
+        Ok(fixes)
+    }
+
+    // The ways to split a word run together from two correctly spelled words, e.g. "a lot"
+    // for "alot", by where the split is, as a split near the start is more often right,
+    // e.g. "in fact" rather than "inf act" for "infact".
+    pub fn splits(&mut self, word: &str) -> Result<Vec<String>> {
+        let parts: Vec<String> = split_points(word)
+            .flat_map(|(first, second)| [first, second])
+            .map(str::to_string)
+            .collect();
+        let mut wrong = std::collections::HashSet::new();
+        for (_, checker) in &mut self.checkers {
+            let verdicts = checker.check_lines(&parts)?;
+            for (part, found) in parts.iter().zip(verdicts) {
+                if found.iter().any(|m| !self.personal.contains(&m.word)) {
+                    wrong.insert(part.as_str());
+                }
+            }
+        }
+        let mut splits: Vec<String> = split_points(word)
+            .filter(|(first, second)| !wrong.contains(first) && !wrong.contains(second))
+            .map(|(first, second)| format!("{first} {second}"))
+            .collect();
+        if self.opts.hide_offensive {
+            splits.retain(|split| !offensive::is_offensive(split));
+        }
+        splits.truncate(self.opts.max_suggestions);
+        Ok(splits)
+    }
+
+    // Suggest corrections for a word found in the given context, best first.
+    pub fn suggest_in(&mut self, word: &str, context: &rank::Context) -> Result<Vec<String>> {
+        for (_, checker) in &mut self.checkers {
+            let mut fixes = checker.suggest(word)?;
+            add_new(&mut fixes, self.personal.suggest(word));
+            if !fixes.is_empty() {
+                return Ok(order(&self.ranker, &self.opts, word, fixes, context));
+            }
+        }
+        Ok(vec![])
+    }
+}
+
+// Put the suggestions for a misspelled word in order: leave out any offensive ones if asked
+// to, rank the rest best first, put them in the word's case, and keep only as many as the
+// user wants. Ties in ranking are broken alphabetically, so menus are the same every time.
+fn order(
+    ranker: &rank::Ranker,
+    opts: &Options,
+    word: &str,
+    mut fixes: Vec<String>,
+    context: &rank::Context,
+) -> Vec<String> {
+    if opts.hide_offensive {
+        fixes.retain(|fix| !offensive::is_offensive(fix));
+    }
+    ranker.rank(word, &mut fixes, context);
+    let mut fixes = match_case(word, fixes);
+    fixes.truncate(opts.max_suggestions);
+    fixes
+}
+
+// Add the suggestions not already in `fixes`, e.g. those from a project's dictionaries.
+fn add_new(fixes: &mut Vec<String>, more: Vec<String>) {
+    for fix in more {
+        if !fixes.contains(&fix) {
+            fixes.push(fix);
+        }
+    }
+}
+
+// The ways to split a word in two at each character. Words of a single letter are common
+// at the start of run-together words, e.g. "alot", but at the end, e.g. "thes", they are
+// more likely to be a typo than a missing space.
+fn split_points(word: &str) -> impl Iterator<Item = (&str, &str)> {
+    word.char_indices()
+        .skip(1)
+        .map(|(i, _)| word.split_at(i))
+        .filter(|(_, second)| second.chars().nth(1).is_some())
 }
 
-impl Drop for Process {
-    fn drop(&mut self) {
-        log::info!("Closing process {}", self.0.id());
-        if let Err(err) = self.0.wait() {
-            log::error!("Failed to close process {}: {err}", self.0.id());
+// Add the diagnostics from `more` that don't cover the same range as one already in `diags`,
+// so an error found by several checkers is only reported once.
+pub fn merge(diags: &mut Vec<Diagnostic>, more: impl IntoIterator<Item = Diagnostic>) {
+    for diag in more {
+        if !diags.iter().any(|d| d.range == diag.range) {
+            diags.push(diag);
         }
     }
 }
 
+// Find the misspelled words in the text, without suggesting corrections, for checkers
+// that work a word at a time. Suggesting is much slower than checking, so it is left
+// until the suggestions for a particular word are needed.
+#[cfg(any(feature = "builtin", feature = "hunspell", test))]
+fn find_words(
+    text: &str,
+    mut correct: impl FnMut(&str) -> Result<bool>,
+) -> Result<Vec<Misspelling>> {
+    let mut misspellings = vec![];
+    for (offset, word) in crate::token::words(text) {
+        if !correct(word)? {
+            misspellings.push(Misspelling {
+                offset,
+                word: word.to_string(),
+                fixes: None,
+            });
+        }
+    }
+    Ok(misspellings)
+}
+
+// Check each word of the text individually, for checkers that work a word at a time.
+// `check` returns None if a word is spelled correctly, or suggested corrections if not.
+fn check_words(
+    text: &str,
+    mut check: impl FnMut(&str) -> Result<Option<Vec<String>>>,
+) -> Result<Vec<Misspelling>> {
+    let mut misspellings = vec![];
+    for (offset, word) in crate::token::words(text) {
+        if let Some(fixes) = check(word)? {
+            misspellings.push(Misspelling {
+                offset,
+                word: word.to_string(),
+                fixes: Some(fixes),
+            });
+        }
+    }
+    Ok(misspellings)
+}
+
+// Build the diagnostic for a misspelled word, spanning `len` characters from `start`.
+// If the checker provided suggestions, they are stored for use in code actions.
+fn diagnostic(
+    line: u32,
+    start: u32,
+    len: u32,
+    word: &str,
+    fixes: Option<Vec<String>>,
+) -> Result<Diagnostic> {
+    let range = lsp_types::Range {
+        start: lsp_types::Position {
+            line,
+            character: start,
+        },
+        end: lsp_types::Position {
+            line,
+            character: start + len,
+        },
+    };
+    let data = match fixes {
+        Some(fixes) => Some(serde_json::to_value(DiagnosticData {
+            range,
+            original: word.to_string(),
+            fixes,
+        })?),
+        None => None,
+    };
+    Ok(Diagnostic {
+        range,
+        severity: Some(lsp_types::DiagnosticSeverity::ERROR),
+        message: word.to_string(),
+        data,
+        ..Default::default()
+    })
+}
+
+// How a word is capitalized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    Lower,
+    // The first letter is uppercase, e.g. "Quick" or "McDonald".
+    Capitalized,
+    // Every letter is uppercase, e.g. "QUICK", though not a single capital, e.g. "I".
+    Upper,
+    // Any other mix, e.g. "iPhone".
+    Mixed,
+}
+
+impl Case {
+    pub fn of(word: &str) -> Case {
+        let letters: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).collect();
+        if letters.len() > 1 && letters.iter().all(|c| c.is_uppercase()) {
+            Case::Upper
+        } else if word.chars().next().is_some_and(char::is_uppercase) {
+            Case::Capitalized
+        } else if letters.iter().any(|c| c.is_uppercase()) {
+            Case::Mixed
+        } else {
+            Case::Lower
+        }
+    }
+}
+
+// Match the case of fixes to a capitalized or all-caps word, e.g. "Quick" or "QUICK" for
+// "Quik" or "QUIK", dropping any that are then the same as a better one. Fixes for a
+// lowercase word are left as they are, as they may rightly be capitalized, e.g. "Paris".
+pub fn match_case(word: &str, fixes: Vec<String>) -> Vec<String> {
+    let fix: fn(&str) -> String = match Case::of(word) {
+        Case::Upper => str::to_uppercase,
+        Case::Capitalized => |fix| {
+            let mut chars = fix.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        },
+        Case::Lower | Case::Mixed => return fixes,
+    };
+    let mut matched: Vec<String> = vec![];
+    for fix in fixes.iter().map(|f| fix(f)) {
+        if !matched.contains(&fix) {
+            matched.push(fix);
+        }
+    }
+    matched
+}
+
+// Parse a line of output from the checker, where `sent` is the line that was checked.
+// Returns None for the blank line ending the results for a line.
+// http://aspell.net/man-html/Through-A-Pipe.html#Through-A-Pipe
+// OK: *
+// Suggestions: & original count offset: miss, miss, …
+// None: # original offset
+// Offset is a character offset, though some versions report a byte offset.
+// Offsets include the ^ that prefixes each input line.
+#[cfg_attr(target_family = "wasm", allow(dead_code))]
+fn parse(output: &str, sent: &str) -> Result<Option<Misspelling>> {
+    let output = output.trim_end_matches(['\r', '\n']);
+    // Suggestions may themselves contain spaces, e.g. "in to", so split them off first.
+    let (header, misses) = output.split_once(": ").unwrap_or((output, ""));
+    let parts: Vec<&str> = header.split(' ').collect();
+    let (original, offset, fixes) = match parts.as_slice() {
+        [""] => return Ok(None),
+        ["&", original, _count, offset] => (original, offset, Some(misses)),
+        ["#", original, offset] => (original, offset, None),
+        _ => Err(format!("Unexpected line: {output}: {parts:?}"))?,
+    };
+    let offset = offset.parse::<usize>()?.saturating_sub(1);
+    let fixes = fixes.map(|misses| {
+        misses
+            .split(", ")
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect()
+    });
+    Ok(Some(Misspelling {
+        offset: char_offset(sent, original, offset),
+        word: original.to_string(),
+        fixes,
+    }))
+}
+
+// Convert an offset reported by the checker to a character offset into the input.
+// Checkers disagree on whether offsets count characters or bytes, which only matters
+// for non-ASCII text, so use whichever one actually points at the word.
+#[cfg_attr(target_family = "wasm", allow(dead_code))]
+fn char_offset(input: &str, word: &str, offset: usize) -> usize {
+    let by_char = input.char_indices().nth(offset).map(|(i, _)| i);
+    if by_char.is_some_and(|i| input[i..].starts_with(word)) {
+        return offset;
+    }
+    match input.get(offset..) {
+        Some(rest) if rest.starts_with(word) => input[..offset].chars().count(),
+        _ => offset,
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::fakes::{Fake, Recorder, Vocabulary};
     use super::*;
     use pretty_assertions::assert_eq;
 
+    #[test]
+    fn test_match_case() {
+        let fixes = || {
+            vec![
+                "quick".to_string(),
+                "Quick".into(),
+                "quack".into(),
+                "Paris".into(),
+            ]
+        };
+        assert_eq!(match_case("quik", fixes()), fixes());
+        assert_eq!(match_case("Quik", fixes()), ["Quick", "Quack", "Paris"]);
+        assert_eq!(match_case("QUIK", fixes()), ["QUICK", "QUACK", "PARIS"]);
+        assert_eq!(match_case("QuIK", fixes()), ["Quick", "Quack", "Paris"]);
+        assert_eq!(match_case("DON'T", vec!["don't".into()]), ["DON'T"]);
+        // A single capital letter is capitalized, not all-caps.
+        assert_eq!(match_case("I", vec!["in".into()]), ["In"]);
+        assert_eq!(match_case("Éte", vec!["été".into()]), ["Été"]);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(parse("\n", "").unwrap().is_none());
+        assert_eq!(
+            parse("& dont 2 5: don't, do nt\n", "Eat dont").unwrap(),
+            Some(Misspelling {
+                offset: 4,
+                word: "dont".into(),
+                fixes: Some(vec!["don't".into(), "do nt".into()]),
+            })
+        );
+        assert_eq!(
+            parse("# shouldn't've 1\n", "shouldn't've").unwrap(),
+            Some(Misspelling {
+                offset: 0,
+                word: "shouldn't've".into(),
+                fixes: None,
+            })
+        );
+        assert!(parse("garbage\n", "").is_err());
+    }
+
+    #[test]
+    fn test_options() {
+        let opts: Options = serde_json::from_value(serde_json::json!({
+            "allow_compounds": true,
+        }))
+        .unwrap();
+        assert!(opts.allow_compounds);
+        assert_eq!(opts.scripts, Script::defaults());
+        assert_eq!(Program::Aspell.compound_args(true), ["--run-together"]);
+        assert!(Program::Hunspell.compound_args(true).is_empty());
+    }
+
+    #[test]
+    fn test_backend() {
+        let mut backend =
+            Backend::with_checker("fake", Box::new(Fake(vec!["kwick"])), &Options::default());
+        let diags = backend
+            .diags_at([(1, 2, "The kwick fox"), (3, 0, ""), (4, 0, "kwick")])
+            .unwrap();
+        assert_eq!(diags.len(), 2);
+        assert_eq!(diags[0].message, "kwick");
+        assert_eq!(diags[0].range.start, lsp_types::Position::new(1, 6));
+        assert_eq!(diags[0].range.end, lsp_types::Position::new(1, 11));
+        let data: DiagnosticData = serde_json::from_value(diags[0].data.clone().unwrap()).unwrap();
+        assert_eq!(data.fixes, vec!["fix"]);
+        assert_eq!(diags[1].range.start, lsp_types::Position::new(4, 0));
+        assert_eq!(backend.suggest("kwick").unwrap(), vec!["fix"]);
+    }
+
+    #[test]
+    fn test_jargon() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("jargon.txt");
+        std::fs::write(&path, "kubectl\n").unwrap();
+        let sent = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let checker = Recorder(sent.clone());
+        let mut backend = Backend::with_checker("recorder", Box::new(checker), &Options::default());
+        backend.personal.load_jargon(&[path]);
+        let diags = backend
+            .diags_at([(0, 0, "The kwick Kubectl’s kubectl")])
+            .unwrap();
+        assert_eq!(diags.len(), 1);
+        assert_eq!(*sent.lock().unwrap(), ["The", "kwick"]);
+    }
+
+    // A checker that suggests the given words for any misspelling, and finds "kwick".
+    struct Suggester(Vec<&'static str>);
+
+    impl Checker for Suggester {
+        fn check(&mut self, text: &str) -> Result<Vec<Misspelling>> {
+            check_words(text, |word| {
+                Ok((word.to_lowercase() == "kwick")
+                    .then(|| self.0.iter().map(|s| s.to_string()).collect()))
+            })
+        }
+
+        fn suggest(&mut self, _word: &str) -> Result<Vec<String>> {
+            Ok(self.0.iter().map(|s| s.to_string()).collect())
+        }
+    }
+
+    #[test]
+    fn test_suggestion_order() {
+        let opts = Options {
+            max_suggestions: 3,
+            keyboard: None,
+            ..Default::default()
+        };
+        // The checker's order makes no difference.
+        let orders = [
+            vec!["quick", "kick", "quick", "Kwik", "wick", "kwik"],
+            vec!["kwik", "wick", "Kwik", "quick", "kick", "quick"],
+        ];
+        for order in orders {
+            let checker = Suggester(order);
+            let mut backend = Backend::with_checker("suggester", Box::new(checker), &opts);
+            assert_eq!(backend.suggest("kwick").unwrap(), ["kick", "kwik", "wick"]);
+            assert_eq!(backend.suggest("Kwick").unwrap(), ["Kwik", "Kick", "Wick"]);
+            let diags = backend.diags_at([(0, 0, "KWICK")]).unwrap();
+            let data: DiagnosticData =
+                serde_json::from_value(diags[0].data.clone().unwrap()).unwrap();
+            assert_eq!(data.fixes, ["KWIK", "KICK", "WICK"]);
+        }
+    }
+
+    #[test]
+    fn test_hide_offensive() {
+        let checker = || Box::new(Suggester(vec!["shit", "shot", "shut"]));
+        let mut backend = Backend::with_checker("suggester", checker(), &Options::default());
+        assert!(backend.suggest("shiy").unwrap().contains(&"shit".into()));
+        let opts = Options {
+            hide_offensive: true,
+            max_suggestions: 1,
+            ..Default::default()
+        };
+        let mut backend = Backend::with_checker("suggester", checker(), &opts);
+        assert_eq!(backend.suggest("shiy").unwrap(), ["shot"]);
+        assert_eq!(backend.suggest("SHIY").unwrap(), ["SHOT"]);
+    }
+
+    #[test]
+    fn test_splits() {
+        let known = ["a", "lot", "in", "inf", "fact", "act", "s", "the"];
+        let checker = Box::new(Vocabulary(known.to_vec()));
+        let mut backend = Backend::with_checker("known", checker, &Options::default());
+        assert_eq!(backend.splits("alot").unwrap(), ["a lot"]);
+        assert_eq!(backend.splits("infact").unwrap(), ["in fact", "inf act"]);
+        // A single letter at the end is more likely a typo.
+        assert!(backend.splits("thes").unwrap().is_empty());
+        assert!(backend.splits("qwerty").unwrap().is_empty());
+        backend.accept_word("qwer").unwrap();
+        backend.accept_word("ty").unwrap();
+        assert_eq!(backend.splits("qwerty").unwrap(), ["qwer ty"]);
+    }
+
+    #[test]
+    fn test_autofixes() {
+        let lines = ["Teh cat", "", "is 'seperate' from teh dog."];
+        let mut backend =
+            Backend::with_checker("fake", Box::new(Fake(vec![])), &Options::default());
+        assert_eq!(backend.autofixes(lines).unwrap(), []);
+        backend.autofix = Some(codespell::Codespell::curated(&Default::default()).unwrap());
+        let fixes = backend.autofixes(lines).unwrap();
+        let found: Vec<_> = fixes
+            .iter()
+            .map(|f| {
+                (
+                    f.range.start.line,
+                    f.range.start.character,
+                    f.range.end.character,
+                )
+            })
+            .collect();
+        assert_eq!(found, [(0, 0, 3), (2, 4, 12), (2, 19, 22)]);
+        assert_eq!(fixes[0].fix, "The");
+        assert_eq!(fixes[1].typo, "seperate");
+        assert_eq!(fixes[1].fix, "separate");
+        // Accepted words are left alone.
+        backend.accept_word("teh").unwrap();
+        let fixes = backend.autofixes(lines).unwrap();
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(fixes[0].typo, "seperate");
+    }
+
+    #[test]
+    fn test_unique_words() {
+        let sent = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let checker = Box::new(Recorder(sent.clone()));
+        let mut backend = Backend::with_checker("recorder", checker, &Options::default());
+        let diags = backend
+            .diags_at([(0, 0, "The kwick fox, the kwick dog"), (1, 4, "kwick fox")])
+            .unwrap();
+        assert_eq!(*sent.lock().unwrap(), ["The", "kwick", "fox", "the", "dog"]);
+        let found: Vec<_> = diags.iter().map(|d| d.range.start).collect();
+        assert_eq!(
+            found,
+            vec![
+                lsp_types::Position::new(0, 4),
+                lsp_types::Position::new(0, 19),
+                lsp_types::Position::new(1, 4),
+            ]
+        );
+    }
+
+    // A checker that always times out.
+    struct Hung;
+
+    impl Checker for Hung {
+        fn check(&mut self, _text: &str) -> Result<Vec<Misspelling>> {
+            Err(Timeout(std::time::Duration::from_millis(1)))?
+        }
+
+        fn suggest(&mut self, _word: &str) -> Result<Vec<String>> {
+            Ok(vec![])
+        }
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut backend = Backend::with_checker("hung", Box::new(Hung), &Options::default());
+        assert!(backend.diags("The kwick fox").unwrap().is_empty());
+        assert_eq!(
+            backend.take_messages(),
+            vec![Notice {
+                text: "hung: Spell checker did not respond within 1ms".into(),
+                show: false
+            }]
+        );
+        assert!(backend.take_messages().is_empty());
+    }
+
+    #[test]
+    fn test_no_checker() {
+        let program = |command: &[&str]| {
+            Program::Custom(custom::Custom {
+                command: command.iter().map(|arg| arg.to_string()).collect(),
+                format: custom::Format::Json,
+            })
+        };
+        let opts = Options {
+            codespell: codespell::Options {
+                enabled: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut backend = Backend::new(&[program(&["/nonexistent/checker"])], &[], &opts).unwrap();
+        assert!(backend.diags("The kwick fox").unwrap().is_empty());
+        let messages = backend.take_messages();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].show);
+
+        // Once a checker is available, it is used after the retry interval.
+        backend.programs = vec![program(&[
+            "sh",
+            "-c",
+            r#"while read -r l; do case "$l" in *kwick*) echo '[{"word": "kwick", "offset": 0}]';; *) echo '[]';; esac; done"#,
+        ])];
+        assert!(backend.diags("The kwick fox").unwrap().is_empty());
+        let (index, tried) = backend.missing_primary.unwrap();
+        backend.missing_primary = Some((index, tried - RETRY_INTERVAL));
+        assert_eq!(backend.diags("The kwick fox").unwrap().len(), 1);
+        assert!(backend.missing_primary.is_none());
+    }
+
+    #[test]
+    fn test_status() {
+        let mut backend =
+            Backend::with_checker("fake", Box::new(Fake(vec![])), &Options::default());
+        backend.checkers.push((
+            "codespell".into(),
+            Box::new(codespell::Codespell::new(&Default::default()).unwrap()),
+        ));
+        let status = backend.status();
+        assert_eq!(
+            status[0],
+            Status {
+                name: "fake".into(),
+                version: None,
+                dictionaries: vec![],
+            }
+        );
+        assert_eq!(status[1].name, "codespell");
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut backend =
+            Backend::with_checker("a", Box::new(Fake(vec!["kwick"])), &Options::default());
+        backend
+            .checkers
+            .push(("b".into(), Box::new(Fake(vec!["kwick", "brwn"]))));
+        let diags = backend.diags("The kwick brwn fox").unwrap();
+        let found: Vec<_> = diags
+            .iter()
+            .map(|d| (d.message.as_str(), d.source.as_deref()))
+            .collect();
+        assert_eq!(found, vec![("kwick", Some("a")), ("brwn", Some("b"))]);
+    }
+
+    #[test]
+    fn test_parse_decomposed() {
+        // The checker sees the composed word, which is longer in the input.
+        let mut backend = Backend::with_checker(
+            "fake",
+            Box::new(Fake(vec!["caf\u{e9}e"])),
+            &Options::default(),
+        );
+        let diags = backend.diags("A cafe\u{301}e").unwrap();
+        assert_eq!(diags[0].range.start.character, 2);
+        assert_eq!(diags[0].range.end.character, 8);
+        let sent = crate::token::normalize("A cafe\u{301}e", &Script::defaults());
+        assert_eq!(
+            parse("& caf\u{e9}e 1 3: caf\u{e9}\n", &sent)
+                .unwrap()
+                .unwrap()
+                .offset,
+            2
+        );
+    }
+
+    #[test]
+    fn test_char_offset() {
+        let input = "Ошибка в словее";
+        assert_eq!(char_offset(input, "словее", 9), 9);
+        assert_eq!(char_offset(input, "словее", 16), 9);
+        assert_eq!(char_offset("naïve wrd", "wrd", 6), 6);
+        assert_eq!(char_offset("naïve wrd", "wrd", 7), 6);
+    }
+
     #[test]
     fn test_diags() {
         test_diags_impl(Program::Aspell);
@@ -177,7 +1331,7 @@ mod tests {
 
     fn test_diags_impl(prog: Program) {
         eprintln!("test_diags_impl({:?})", prog.command());
-        let mut proc = Process::new(&[prog]).unwrap();
+        let mut proc = Backend::new(&[prog], &[], &Options::default()).unwrap();
         let actual = proc
             .diags(
                 [
@@ -0,0 +1,664 @@
+// A spell checker written in Rust that reads hunspell dictionaries directly,
+// so no external program or library needs to be installed.
+// It supports the commonly used subset of the affix file format: prefixes and suffixes
+// (and their combination), flag aliases, forbidden words, compounding via COMPOUNDFLAG,
+// and suggestions based on TRY and REP.
+use super::{dictionary::Dictionary, Result};
+use std::collections::{HashMap, HashSet};
+
+type Flag = u64;
+
+// How flags are written in the dictionary and affix file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlagType {
+    // A single character per flag, the default.
+    Char,
+    // Two characters per flag.
+    Long,
+    // Comma-separated decimal numbers.
+    Num,
+}
+
+// One alternative of a prefix or suffix rule, e.g. "SFX D y ied [^aeiou]y".
+#[derive(Debug)]
+struct Affix {
+    flag: Flag,
+    // Whether the affix may be combined with an affix of the other kind.
+    cross: bool,
+    // Removed from the stem before adding the affix.
+    strip: String,
+    add: String,
+    // What the stem must look like at the end the affix attaches to.
+    condition: Vec<Class>,
+}
+
+#[derive(Debug)]
+enum Class {
+    Any,
+    OneOf(Vec<char>),
+    NoneOf(Vec<char>),
+}
+
+impl Class {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Class::Any => true,
+            Class::OneOf(chars) => chars.contains(&c),
+            Class::NoneOf(chars) => !chars.contains(&c),
+        }
+    }
+}
+
+pub struct Builtin {
+    // The word list the dictionary was loaded from.
+    dictionary: Option<String>,
+    words: HashMap<String, HashSet<Flag>>,
+    prefixes: Vec<Affix>,
+    suffixes: Vec<Affix>,
+    // Characters to try when generating suggestions, most common first.
+    try_chars: Vec<char>,
+    // Common mistakes, e.g. ("f", "ph").
+    replacements: Vec<(String, String)>,
+    forbidden: Option<Flag>,
+    no_suggest: Option<Flag>,
+    need_affix: Option<Flag>,
+    only_in_compound: Option<Flag>,
+    compound: Option<Flag>,
+    compound_min: usize,
+    // Accept any run-together words, not just those the dictionary allows in compounds.
+    allow_compounds: bool,
+}
+
+// The most suggestions offered for a word.
+const MAX_SUGGESTIONS: usize = 10;
+
+impl Builtin {
+    pub fn new(dict: &Dictionary, allow_compounds: bool) -> Result<Builtin> {
+        let aff = decode(std::fs::read(&dict.aff)?)?;
+        let dic = decode(std::fs::read(&dict.dic)?)?;
+        let mut builtin = Builtin::parse(&aff, &dic, allow_compounds)?;
+        builtin.dictionary = Some(dict.dic.display().to_string());
+        log::info!(
+            "Loaded {} words from dictionary {dict:?}",
+            builtin.words.len()
+        );
+        Ok(builtin)
+    }
+
+    // Load the English dictionary embedded in the binary.
+    #[cfg(feature = "bundled-dict")]
+    pub fn bundled(allow_compounds: bool) -> Result<Builtin> {
+        let aff = include_str!("en_US.aff");
+        let dic = expand(include_str!("en_US.dic.fc"));
+        let mut builtin = Builtin::parse(aff, &dic, allow_compounds)?;
+        builtin.dictionary = Some("en_US (bundled)".into());
+        log::info!(
+            "Loaded {} words from bundled dictionary",
+            builtin.words.len()
+        );
+        Ok(builtin)
+    }
+
+    fn parse(aff: &str, dic: &str, allow_compounds: bool) -> Result<Builtin> {
+        let mut builtin = Builtin {
+            dictionary: None,
+            words: HashMap::new(),
+            prefixes: vec![],
+            suffixes: vec![],
+            try_chars: vec![],
+            replacements: vec![],
+            forbidden: None,
+            no_suggest: None,
+            need_affix: None,
+            only_in_compound: None,
+            compound: None,
+            compound_min: 3,
+            allow_compounds,
+        };
+        let mut flag_type = FlagType::Char;
+        let mut aliases: Vec<Vec<Flag>> = vec![];
+        // Whether each affix flag may be combined, from the header line of its rule.
+        let mut cross: HashMap<Flag, bool> = HashMap::new();
+
+        for line in aff.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let flag = |s: &str| parse_flags(s, flag_type).first().copied();
+            match fields.as_slice() {
+                ["FLAG", "long"] => flag_type = FlagType::Long,
+                ["FLAG", "num"] => flag_type = FlagType::Num,
+                // A count, preceding the aliases
+                ["AF", n] if n.parse::<usize>().is_ok() && aliases.is_empty() => {}
+                ["AF", flags, ..] => aliases.push(parse_flags(flags, flag_type)),
+                ["TRY", chars] => builtin.try_chars = chars.chars().collect(),
+                ["REP", from, to] => builtin
+                    .replacements
+                    .push((from.replace('_', " "), to.replace('_', " "))),
+                ["FORBIDDENWORD", f] => builtin.forbidden = flag(f),
+                ["NOSUGGEST", f] => builtin.no_suggest = flag(f),
+                ["NEEDAFFIX", f] => builtin.need_affix = flag(f),
+                ["ONLYINCOMPOUND", f] => builtin.only_in_compound = flag(f),
+                ["COMPOUNDFLAG", f] => builtin.compound = flag(f),
+                ["COMPOUNDMIN", n] => builtin.compound_min = n.parse()?,
+                [kind @ ("PFX" | "SFX"), f, combine @ ("Y" | "N"), n]
+                    if n.parse::<usize>().is_ok() =>
+                {
+                    let f = flag(f).ok_or(format!("Invalid {kind} line: {line}"))?;
+                    cross.insert(f, *combine == "Y");
+                }
+                [kind @ ("PFX" | "SFX"), f, strip, add, condition, ..] => {
+                    let f = flag(f).ok_or(format!("Invalid {kind} line: {line}"))?;
+                    // Continuation flags on the affix (add/flags) are not supported.
+                    let add = add.split('/').next().unwrap_or_default();
+                    let affix = Affix {
+                        flag: f,
+                        cross: cross.get(&f).copied().unwrap_or_default(),
+                        strip: if *strip == "0" { "" } else { strip }.to_string(),
+                        add: if add == "0" { "" } else { add }.to_string(),
+                        condition: parse_condition(condition),
+                    };
+                    match *kind {
+                        "PFX" => builtin.prefixes.push(affix),
+                        _ => builtin.suffixes.push(affix),
+                    }
+                }
+                _ => {}
+            }
+        }
+        if builtin.try_chars.is_empty() {
+            builtin.try_chars = "etaoinshrdlcumwfgypbvkjxqz".chars().collect();
+        }
+
+        // The first line holds the approximate number of words.
+        for line in dic.lines().skip(1) {
+            let Some(entry) = line.split(['\t', ' ']).next().filter(|e| !e.is_empty()) else {
+                continue;
+            };
+            let (word, flags) = match entry.split_once('/') {
+                Some((word, flags)) => {
+                    let flags = match flags.parse::<usize>() {
+                        Ok(n) if !aliases.is_empty() => {
+                            aliases.get(n.wrapping_sub(1)).cloned().unwrap_or_default()
+                        }
+                        _ => parse_flags(flags, flag_type),
+                    };
+                    (word, flags)
+                }
+                None => (entry, vec![]),
+            };
+            builtin
+                .words
+                .entry(word.to_string())
+                .or_default()
+                .extend(flags);
+        }
+        Ok(builtin)
+    }
+
+    // Return None if the word is spelled correctly, or suggested corrections if not.
+    #[cfg(test)]
+    pub fn check_word(&self, word: &str) -> Option<Vec<String>> {
+        (!self.correct(word)).then(|| self.suggest(word))
+    }
+
+    pub fn correct(&self, word: &str) -> bool {
+        cases(word)
+            .iter()
+            .any(|w| !self.is_forbidden(w) && self.correct_case(w))
+    }
+
+    // Whether the word is correct, exactly as cased.
+    fn correct_case(&self, word: &str) -> bool {
+        let standalone = |flags: &HashSet<Flag>| {
+            !self.need_affix.is_some_and(|f| flags.contains(&f))
+                && !self.only_in_compound.is_some_and(|f| flags.contains(&f))
+        };
+        self.words.get(word).is_some_and(standalone) || self.affixed(word) || self.compounded(word)
+    }
+
+    fn is_forbidden(&self, word: &str) -> bool {
+        self.forbidden
+            .is_some_and(|f| self.words.get(word).is_some_and(|flags| flags.contains(&f)))
+    }
+
+    fn has_flags(&self, stem: &str, flags: &[Flag]) -> bool {
+        self.words
+            .get(stem)
+            .is_some_and(|f| flags.iter().all(|flag| f.contains(flag)))
+    }
+
+    // Whether the word is a dictionary word with a valid prefix and/or suffix.
+    fn affixed(&self, word: &str) -> bool {
+        let by_suffix = self.suffixes.iter().any(|sfx| {
+            let Some(stem) = sfx.remove_suffix(word) else {
+                return false;
+            };
+            self.has_flags(&stem, &[sfx.flag])
+                || (sfx.cross
+                    && self.prefixes.iter().filter(|p| p.cross).any(|pfx| {
+                        pfx.remove_prefix(&stem)
+                            .is_some_and(|root| self.has_flags(&root, &[pfx.flag, sfx.flag]))
+                    }))
+        });
+        by_suffix
+            || self.prefixes.iter().any(|pfx| {
+                pfx.remove_prefix(word)
+                    .is_some_and(|stem| self.has_flags(&stem, &[pfx.flag]))
+            })
+    }
+
+    // Whether the word is made of several words that may be joined together.
+    fn compounded(&self, word: &str) -> bool {
+        if self.compound.is_none() && !self.allow_compounds {
+            return false;
+        }
+        let min = self.compound_min.max(1);
+        let chars: Vec<(usize, char)> = word.char_indices().collect();
+        (min..chars.len().saturating_sub(min - 1)).any(|i| {
+            let (head, tail) = word.split_at(chars[i].0);
+            self.compound_part(head) && (self.compound_part(tail) || self.compounded(tail))
+        })
+    }
+
+    fn compound_part(&self, part: &str) -> bool {
+        if self.allow_compounds {
+            return cases(part)
+                .iter()
+                .any(|p| self.words.contains_key(p) || self.affixed(p));
+        }
+        let Some(compound) = self.compound else {
+            return false;
+        };
+        // A compound part may also take a suffix, e.g. the "books" in "phonebooks".
+        cases(part).iter().any(|p| {
+            self.has_flags(p, &[compound])
+                || self.suffixes.iter().any(|sfx| {
+                    sfx.remove_suffix(p)
+                        .is_some_and(|stem| self.has_flags(&stem, &[compound, sfx.flag]))
+                })
+        })
+    }
+
+    // Return up to MAX_SUGGESTIONS correctly spelled words similar to the given one.
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        let chars: Vec<char> = word.chars().collect();
+        let mut candidates = vec![];
+        for (from, to) in &self.replacements {
+            for (i, _) in word.match_indices(from.as_str()) {
+                candidates.push(format!("{}{to}{}", &word[..i], &word[i + from.len()..]));
+            }
+        }
+        candidates.extend(cases(word).into_iter().skip(1));
+        let edit = |f: &dyn Fn(&mut Vec<char>)| {
+            let mut c = chars.clone();
+            f(&mut c);
+            c.into_iter().collect::<String>()
+        };
+        for i in 0..chars.len() {
+            if i + 1 < chars.len() {
+                candidates.push(edit(&|c| c.swap(i, i + 1)));
+            }
+            candidates.push(edit(&|c| {
+                c.remove(i);
+            }));
+            for &t in &self.try_chars {
+                candidates.push(edit(&|c| c[i] = t));
+            }
+        }
+        for i in 0..=chars.len() {
+            for &t in &self.try_chars {
+                candidates.push(edit(&|c| c.insert(i, t)));
+            }
+        }
+        for (i, _) in word.char_indices().skip(1) {
+            candidates.push(format!("{} {}", &word[..i], &word[i..]));
+        }
+
+        let mut seen = HashSet::new();
+        let suggestions = candidates
+            .into_iter()
+            .filter(|c| c != word && !c.is_empty())
+            .filter(|c| c.split(' ').all(|w| self.correct(w) && !self.no_suggest(w)))
+            .filter(|c| seen.insert(c.clone()))
+            .take(MAX_SUGGESTIONS);
+        match word.chars().next() {
+            Some(first) if first.is_uppercase() => suggestions.map(|s| capitalize(&s)).collect(),
+            _ => suggestions.collect(),
+        }
+    }
+
+    fn no_suggest(&self, word: &str) -> bool {
+        self.no_suggest
+            .is_some_and(|f| self.words.get(word).is_some_and(|flags| flags.contains(&f)))
+    }
+}
+
+impl super::Checker for Builtin {
+    fn check(&mut self, text: &str) -> Result<Vec<super::Misspelling>> {
+        super::find_words(text, |word| Ok(self.correct(word)))
+    }
+
+    fn suggest(&mut self, word: &str) -> Result<Vec<String>> {
+        Ok(Builtin::suggest(self, word))
+    }
+
+    fn version(&self) -> Option<String> {
+        Some(env!("CARGO_PKG_VERSION").into())
+    }
+
+    fn dictionaries(&self) -> Vec<String> {
+        self.dictionary.iter().cloned().collect()
+    }
+}
+
+impl Affix {
+    // If the word ends with this suffix, return the stem it was added to.
+    fn remove_suffix(&self, word: &str) -> Option<String> {
+        let base = word.strip_suffix(self.add.as_str())?;
+        if base.is_empty() && self.strip.is_empty() {
+            return None;
+        }
+        let stem = format!("{base}{}", self.strip);
+        let tail = stem.chars().rev().take(self.condition.len());
+        (tail.clone().count() == self.condition.len()
+            && tail
+                .zip(self.condition.iter().rev())
+                .all(|(c, cls)| cls.matches(c)))
+        .then_some(stem)
+    }
+
+    // If the word starts with this prefix, return the stem it was added to.
+    fn remove_prefix(&self, word: &str) -> Option<String> {
+        let base = word.strip_prefix(self.add.as_str())?;
+        if base.is_empty() && self.strip.is_empty() {
+            return None;
+        }
+        let stem = format!("{}{base}", self.strip);
+        let head = stem.chars().take(self.condition.len());
+        (head.clone().count() == self.condition.len()
+            && head
+                .zip(self.condition.iter())
+                .all(|(c, cls)| cls.matches(c)))
+        .then_some(stem)
+    }
+}
+
+// The word as written, followed by the other casings it may appear as in the dictionary,
+// e.g. "Hello" may be "hello" at the start of a sentence, and "HELLO" may be "Hello" or "hello".
+fn cases(word: &str) -> Vec<String> {
+    let mut cases = vec![word.to_string()];
+    let mut chars = word.chars();
+    let Some(first) = chars.next() else {
+        return cases;
+    };
+    let rest = chars.as_str();
+    if first.is_uppercase() {
+        if rest.chars().all(|c| !c.is_uppercase()) {
+            cases.push(first.to_lowercase().chain(rest.chars()).collect());
+        } else if rest.chars().all(|c| !c.is_lowercase()) {
+            cases.push(first.to_string() + &rest.to_lowercase());
+            cases.push(word.to_lowercase());
+        }
+    }
+    cases
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn parse_flags(flags: &str, flag_type: FlagType) -> Vec<Flag> {
+    match flag_type {
+        FlagType::Char => flags.chars().map(Flag::from).collect(),
+        FlagType::Long => flags
+            .chars()
+            .collect::<Vec<_>>()
+            .chunks(2)
+            .map(|pair| pair.iter().fold(0, |acc, &c| (acc << 21) | Flag::from(c)))
+            .collect(),
+        FlagType::Num => flags.split(',').filter_map(|n| n.parse().ok()).collect(),
+    }
+}
+
+// Parse a condition such as "[^aeiou]y" into the classes of characters it matches.
+fn parse_condition(condition: &str) -> Vec<Class> {
+    if condition == "." {
+        return vec![];
+    }
+    let mut classes = vec![];
+    let mut chars = condition.chars();
+    while let Some(c) = chars.next() {
+        classes.push(match c {
+            '.' => Class::Any,
+            '[' => {
+                let set: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                match set.strip_prefix('^') {
+                    Some(set) => Class::NoneOf(set.chars().collect()),
+                    None => Class::OneOf(set.chars().collect()),
+                }
+            }
+            c => Class::OneOf(vec![c]),
+        });
+    }
+    classes
+}
+
+// Expand a front-coded word list, made by scripts/compress-dictionary.py, in which each word
+// after the count is written as the number of leading characters it shares with the previous
+// word followed by the rest of the word, e.g. "abandon", "2out" for "about".
+#[cfg(feature = "bundled-dict")]
+fn expand(coded: &str) -> String {
+    let mut lines = coded.lines();
+    let mut out = lines.next().unwrap_or_default().to_string();
+    let mut prev = String::new();
+    for line in lines {
+        let shared = line.bytes().take_while(u8::is_ascii_digit).count();
+        let n: usize = line[..shared].parse().unwrap_or_default();
+        let mut word: String = prev.chars().take(n).collect();
+        word.push_str(&line[shared..]);
+        out.push('\n');
+        out.push_str(&word);
+        prev = word;
+    }
+    out
+}
+
+// Decode a dictionary file using the encoding named by its SET line, if any.
+fn decode(bytes: Vec<u8>) -> Result<String> {
+    let set = bytes
+        .split(|&b| b == b'\n')
+        .find_map(|line| line.strip_prefix(b"SET "))
+        .map(|enc| String::from_utf8_lossy(enc).trim().to_ascii_uppercase());
+    match set.as_deref() {
+        None | Some("UTF-8") => Ok(String::from_utf8(bytes)?),
+        // Latin-1 maps each byte directly to the code point of the same value.
+        Some("ISO8859-1") => Ok(bytes.into_iter().map(char::from).collect()),
+        Some(enc) => Err(format!("Unsupported dictionary encoding {enc}"))?,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const AFF: &str = "SET UTF-8
+TRY esianrtolcdugmphbyfvkwz
+REP 1
+REP f ph
+FORBIDDENWORD !
+COMPOUNDFLAG C
+COMPOUNDMIN 3
+
+PFX U Y 1
+PFX U 0 un .
+
+SFX S Y 3
+SFX S y ies [^aeiou]y
+SFX S 0 s [aeiou]y
+SFX S 0 s [^y]
+
+SFX D N 1
+SFX D 0 ed [^e]
+";
+
+    const DIC: &str = "8
+happy/U
+day/S
+fly/S
+walk/DU
+do/U
+Paris
+phone/C
+book/SC
+irregardless/!
+";
+
+    fn builtin() -> Builtin {
+        Builtin::parse(AFF, DIC, false).unwrap()
+    }
+
+    #[test]
+    fn test_check() {
+        let b = builtin();
+        for word in [
+            "happy",
+            "unhappy",
+            "days",
+            "flies",
+            "walked",
+            "unwalk",
+            "Paris",
+            "Walk",
+            "WALK",
+            "phonebook",
+            "phonebooks",
+        ] {
+            assert!(b.correct(word), "{word} should be correct");
+        }
+        for word in [
+            "flys",
+            "dayies",
+            "unday",
+            "undone",
+            "paris",
+            "irregardless",
+            "Irregardless",
+            "hapy",
+            "unwalked",
+            "phonebok",
+        ] {
+            assert!(!b.correct(word), "{word} should be incorrect");
+        }
+    }
+
+    #[test]
+    fn test_find() {
+        // Misspellings are found without suggesting corrections, which are asked for later.
+        let mut b = builtin();
+        let found = super::super::Checker::check(&mut b, "happy days").unwrap();
+        assert!(found.is_empty());
+        let found = super::super::Checker::check(&mut b, "hapy days").unwrap();
+        assert_eq!(
+            found,
+            vec![super::super::Misspelling {
+                offset: 0,
+                word: "hapy".into(),
+                fixes: None,
+            }]
+        );
+        assert_eq!(
+            super::super::Checker::suggest(&mut b, "hapy")
+                .unwrap()
+                .first(),
+            Some(&"happy".to_string())
+        );
+    }
+
+    #[test]
+    fn test_cross_product() {
+        // U can combine with S, but D does not allow combining
+        let b = Builtin::parse(AFF, "2\nlock/US\nlatch/UD\n", false).unwrap();
+        assert!(b.correct("unlocks"));
+        assert!(b.correct("unlatch"));
+        assert!(b.correct("latched"));
+        assert!(!b.correct("unlatched"));
+    }
+
+    #[test]
+    fn test_suggest() {
+        let b = builtin();
+        assert_eq!(b.check_word("happy"), None);
+        assert_eq!(
+            b.check_word("hapy").unwrap().first().map(String::as_str),
+            Some("happy")
+        );
+        assert!(b.check_word("fone").unwrap().contains(&"phone".to_string()));
+        assert!(b
+            .check_word("Walkde")
+            .unwrap()
+            .contains(&"Walked".to_string()));
+        assert!(b
+            .check_word("daywalk")
+            .unwrap()
+            .contains(&"day walk".to_string()));
+        assert_eq!(b.suggest("irregardles"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_allow_compounds() {
+        assert!(!builtin().correct("daywalk"));
+        let b = Builtin::parse(AFF, DIC, true).unwrap();
+        assert!(b.correct("daywalk"));
+        assert!(b.correct("flieswalked"));
+        assert!(!b.correct("daywalkz"));
+    }
+
+    #[test]
+    fn test_flag_types() {
+        let aff = "FLAG long\nSFX Aa Y 1\nSFX Aa 0 s .\n";
+        let b = Builtin::parse(aff, "1\ncat/AaBb\n", false).unwrap();
+        assert!(b.correct("cats"));
+
+        let aff = "FLAG num\nAF 1\nAF 101,7\nSFX 101 Y 1\nSFX 101 0 s .\n";
+        let b = Builtin::parse(aff, "1\ncat/1\n", false).unwrap();
+        assert!(b.correct("cats"));
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            decode(b"SET ISO8859-1\nTRY \xe9\n".to_vec()).unwrap(),
+            "SET ISO8859-1\nTRY é\n"
+        );
+        assert!(decode(b"SET KOI8-R\n".to_vec()).is_err());
+    }
+
+    #[cfg(feature = "bundled-dict")]
+    #[test]
+    fn test_bundled() {
+        assert_eq!(
+            expand("3\nabandon\n2out\n5/S\n"),
+            "3\nabandon\nabout\nabout/S"
+        );
+        let b = Builtin::bundled(false).unwrap();
+        for word in [
+            "the",
+            "Spelling",
+            "checked",
+            "dictionaries",
+            "isn't",
+            "Monday",
+        ] {
+            assert_eq!(b.check_word(word), None, "{word}");
+        }
+        assert!(b
+            .check_word("recieve")
+            .unwrap()
+            .contains(&"receive".to_string()));
+        assert!(b.check_word("teh").is_some());
+    }
+}
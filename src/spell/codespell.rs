@@ -0,0 +1,193 @@
+// A table of common typos and their corrections, in the format of codespell's dictionary
+// (https://github.com/codespell-project/codespell). Known typos are reported with a single
+// correction, so they can be found quickly even if the main checker is unavailable.
+use super::{Misspelling, Result};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Options {
+    pub enabled: bool,
+    // Additional dictionaries in codespell's format, used along with codespell's own
+    // dictionary if it is installed.
+    pub dictionaries: Vec<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            enabled: true,
+            dictionaries: vec![],
+        }
+    }
+}
+
+// A few of the most common typos, used even if codespell is not installed.
+const BUNDLED: &str = include_str!("typos.txt");
+
+// Where codespell's dictionary lives within a python package directory.
+const CODESPELL_DICTIONARY: &str = "codespell_lib/data/dictionary.txt";
+
+pub struct Codespell {
+    // Lowercase typos, mapped to their correction.
+    typos: HashMap<String, String>,
+    // The dictionaries loaded in addition to the bundled list.
+    sources: Vec<String>,
+}
+
+impl Codespell {
+    pub fn new(opts: &Options) -> Result<Codespell> {
+        let mut codespell = Codespell {
+            typos: HashMap::new(),
+            sources: vec![],
+        };
+        codespell.load(BUNDLED);
+        if let Some(path) = find_installed() {
+            log::info!("Loading codespell dictionary {path:?}");
+            codespell.load(&std::fs::read_to_string(&path)?);
+            codespell.sources.push(path.display().to_string());
+        }
+        for path in &opts.dictionaries {
+            let text = std::fs::read_to_string(path)
+                .map_err(|err| format!("Failed to read {path}: {err}"))?;
+            codespell.load(&text);
+            codespell.sources.push(path.clone());
+        }
+        Ok(codespell)
+    }
+
+    // Only the bundled list and the user's own dictionaries, whose corrections are sure
+    // enough to make without asking.
+    pub fn curated(opts: &Options) -> Result<Codespell> {
+        let mut codespell = Codespell {
+            typos: HashMap::new(),
+            sources: vec![],
+        };
+        codespell.load(BUNDLED);
+        for path in &opts.dictionaries {
+            let text = std::fs::read_to_string(path)
+                .map_err(|err| format!("Failed to read {path}: {err}"))?;
+            codespell.load(&text);
+        }
+        Ok(codespell)
+    }
+
+    // Load lines of the form "typo->correction".
+    // Typos with several possible corrections ("typo->a, b,"), or whose correction is disabled
+    // with a reason ("typo->a, reason"), are skipped, as they can't be fixed with confidence.
+    fn load(&mut self, text: &str) {
+        for line in text.lines() {
+            let Some((typo, fix)) = line.split_once("->") else {
+                continue;
+            };
+            let fix = fix.trim();
+            if fix.is_empty() || fix.contains(',') {
+                continue;
+            }
+            self.typos.insert(typo.trim().to_lowercase(), fix.into());
+        }
+    }
+
+    // The correction for a typo, matching its case.
+    pub fn correct(&self, word: &str) -> Option<String> {
+        let fix = self.typos.get(&word.to_lowercase())?;
+        let mut chars = word.chars();
+        let first_upper = chars.next().is_some_and(char::is_uppercase);
+        Some(
+            if first_upper && chars.clone().count() > 0 && chars.all(char::is_uppercase) {
+                fix.to_uppercase()
+            } else if first_upper {
+                let mut fix_chars = fix.chars();
+                fix_chars
+                    .next()
+                    .map(|c| c.to_uppercase().chain(fix_chars).collect())
+                    .unwrap_or_default()
+            } else {
+                fix.clone()
+            },
+        )
+    }
+}
+
+impl super::Checker for Codespell {
+    fn check(&mut self, text: &str) -> Result<Vec<Misspelling>> {
+        super::check_words(text, |word| Ok(self.correct(word).map(|fix| vec![fix])))
+    }
+
+    fn suggest(&mut self, word: &str) -> Result<Vec<String>> {
+        Ok(self.correct(word).into_iter().collect())
+    }
+
+    fn dictionaries(&self) -> Vec<String> {
+        self.sources.clone()
+    }
+}
+
+// Find codespell's dictionary in the python package directories.
+fn find_installed() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let lib_dirs = ["/usr/lib", "/usr/local/lib"]
+        .into_iter()
+        .map(PathBuf::from)
+        .chain(home.map(|h| h.join(".local/lib")));
+    lib_dirs
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("python3"))
+        .flat_map(|entry| {
+            ["dist-packages", "site-packages"]
+                .map(|packages| entry.path().join(packages).join(CODESPELL_DICTIONARY))
+        })
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spell::Checker;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_load() {
+        let mut codespell = Codespell {
+            typos: HashMap::new(),
+            sources: vec![],
+        };
+        codespell.load("abandonned->abandoned\nclas->class, disabled because of name clash in c++\nalloed->allowed, aloud,\n");
+        assert_eq!(
+            codespell.correct("abandonned").as_deref(),
+            Some("abandoned")
+        );
+        assert_eq!(codespell.correct("clas"), None);
+        assert_eq!(codespell.correct("alloed"), None);
+    }
+
+    #[test]
+    fn test_check() {
+        let mut codespell = Codespell::new(&Options::default()).unwrap();
+        assert_eq!(
+            codespell.check("Teh cat will RECIEVE teh fish").unwrap(),
+            vec![
+                Misspelling {
+                    offset: 0,
+                    word: "Teh".into(),
+                    fixes: Some(vec!["The".into()]),
+                },
+                Misspelling {
+                    offset: 13,
+                    word: "RECIEVE".into(),
+                    fixes: Some(vec!["RECEIVE".into()]),
+                },
+                Misspelling {
+                    offset: 21,
+                    word: "teh".into(),
+                    fixes: Some(vec!["the".into()]),
+                },
+            ]
+        );
+        assert_eq!(codespell.suggest("seperate").unwrap(), vec!["separate"]);
+        assert!(codespell.suggest("separate").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,161 @@
+// A user-supplied spell checker, run as an external command that speaks either the
+// ispell pipe protocol or a simple JSON-lines protocol.
+// On WebAssembly, custom checkers can be configured but never started.
+#![cfg_attr(target_family = "wasm", allow(dead_code))]
+use super::{Misspelling, Options, Result};
+
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Custom {
+    // The program and its arguments. "{dictionary}" in an argument is replaced with the
+    // configured dictionary, and arguments containing it are dropped if none is set.
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub format: Format,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    // The pipe mode of ispell, aspell and hunspell (`-a`).
+    #[default]
+    Ispell,
+    // One JSON object per line in each direction. For each line of text the checker is sent
+    // {"text": "..."} and must reply with a single line holding a list of misspellings:
+    // [{"word": "kwick", "offset": 4, "suggestions": ["quick"]}]
+    // where offset is the character offset of the word in the text.
+    Json,
+}
+
+impl Custom {
+    // The name of the program, without its directory.
+    pub fn name(&self) -> String {
+        let program = self.command.first().map(String::as_str).unwrap_or("custom");
+        let path = std::path::Path::new(program);
+        path.file_name()
+            .map_or(program.into(), |name| name.to_string_lossy().into())
+    }
+
+    pub fn command(&self, opts: &Options) -> Result<std::process::Command> {
+        let args = self.command.iter().filter_map(|arg| {
+            if !arg.contains("{dictionary}") {
+                return Some(arg.clone());
+            }
+            let dict = opts.dictionary.as_ref()?;
+            Some(arg.replace("{dictionary}", dict))
+        });
+        let mut args = args.collect::<Vec<_>>().into_iter();
+        let program = args.next().ok_or("Custom spell checker has no command")?;
+        let mut cmd = std::process::Command::new(program);
+        cmd.args(args);
+        Ok(cmd)
+    }
+}
+
+// A misspelled word in a JSON checker's reply.
+#[derive(serde::Deserialize, Debug)]
+struct Reply {
+    word: String,
+    offset: usize,
+    #[serde(default)]
+    suggestions: Option<Vec<String>>,
+}
+
+// The request sent to a JSON checker to check `sent`.
+pub fn request(sent: &str) -> Result<String> {
+    Ok(serde_json::json!({ "text": sent }).to_string())
+}
+
+// Parse a JSON checker's reply to a request to check `sent`.
+pub fn parse(output: &str, sent: &str) -> Result<Vec<Misspelling>> {
+    let misspellings: Vec<Reply> = serde_json::from_str(output)
+        .map_err(|err| format!("Unexpected line: {}: {err}", output.trim_end()))?;
+    Ok(misspellings
+        .into_iter()
+        .map(|m| Misspelling {
+            offset: super::char_offset(sent, &m.word, m.offset),
+            word: m.word,
+            fixes: m.suggestions,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_command() {
+        let custom: Custom = serde_json::from_value(serde_json::json!({
+            "command": ["checker", "--pipe", "--dict={dictionary}"],
+        }))
+        .unwrap();
+        assert_eq!(custom.format, Format::Ispell);
+        let args = |opts: &Options| {
+            let cmd = custom.command(opts).unwrap();
+            let mut args = vec![cmd.get_program().to_string_lossy().to_string()];
+            args.extend(cmd.get_args().map(|a| a.to_string_lossy().to_string()));
+            args
+        };
+        assert_eq!(args(&Options::default()), vec!["checker", "--pipe"]);
+        let opts = Options {
+            dictionary: Some("en_GB".into()),
+            ..Default::default()
+        };
+        assert_eq!(args(&opts), vec!["checker", "--pipe", "--dict=en_GB"]);
+        let empty = Custom {
+            command: vec![],
+            format: Format::Json,
+        };
+        assert!(empty.command(&opts).is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(request("a \"b\"").unwrap(), r#"{"text":"a \"b\""}"#);
+        assert!(parse("[]\n", "ok").unwrap().is_empty());
+        let misspellings = parse(
+            r#"[{"word": "kwick", "offset": 4, "suggestions": ["quick"]}, {"word": "fx", "offset": 16}]"#,
+            "The kwick brown fx",
+        )
+        .unwrap();
+        assert_eq!(
+            misspellings,
+            vec![
+                Misspelling {
+                    offset: 4,
+                    word: "kwick".into(),
+                    fixes: Some(vec!["quick".into()]),
+                },
+                Misspelling {
+                    offset: 16,
+                    word: "fx".into(),
+                    fixes: None,
+                },
+            ]
+        );
+        assert!(parse("garbage\n", "").is_err());
+    }
+
+    #[test]
+    fn test_json_process() {
+        let custom = Custom {
+            command: vec![
+                "sh".into(),
+                "-c".into(),
+                r#"while read -r l; do case "$l" in *kwick*) echo '[{"word": "kwick", "offset": 0}]';; *) echo '[]';; esac; done"#.into(),
+            ],
+            format: Format::Json,
+        };
+        let mut backend = super::super::Backend::new(
+            &[super::super::Program::Custom(custom)],
+            &[],
+            &Options::default(),
+        )
+        .unwrap();
+        let diags = backend.diags("The kwick fox\n\nThe kwick dog").unwrap();
+        assert_eq!(diags.len(), 2);
+        assert_eq!(diags[0].source.as_deref(), Some("sh"));
+        assert_eq!(diags[1].range.start, lsp_types::Position::new(2, 4));
+    }
+}
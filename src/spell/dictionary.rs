@@ -0,0 +1,225 @@
+// Locating hunspell-format dictionaries, which are a pair of files: an affix file (.aff)
+// describing how words may be inflected, and a word list (.dic).
+use super::Result;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dictionary {
+    pub aff: PathBuf,
+    pub dic: PathBuf,
+}
+
+// Directories searched for dictionaries, after any listed in $DICPATH.
+const SEARCH_PATH: &[&str] = &[
+    "/usr/share/hunspell",
+    "/usr/share/myspell",
+    "/usr/share/myspell/dicts",
+    "/usr/local/share/hunspell",
+    "/Library/Spelling",
+];
+
+// Find a dictionary by name, e.g. "en_US", or by path without extension, e.g. "dicts/custom".
+// If no name is given, the language of the current locale is used.
+#[cfg(any(feature = "builtin", feature = "hunspell", test))]
+pub fn find(name: Option<&str>) -> Option<Dictionary> {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => locale_language()?,
+    };
+    if name.contains(std::path::MAIN_SEPARATOR) {
+        return at(Path::new(&name));
+    }
+    search_path()
+        .into_iter()
+        .find_map(|dir| at(&dir.join(&name)))
+}
+
+// The directories searched for dictionaries by name, in order.
+fn search_path() -> Vec<PathBuf> {
+    let dicpath = std::env::var_os("DICPATH").unwrap_or_default();
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    std::env::split_paths(&dicpath)
+        .chain(home.map(|h| h.join(".local/share/hunspell")))
+        .chain(cache_dir())
+        .chain(SEARCH_PATH.iter().map(PathBuf::from))
+        .collect()
+}
+
+// Every dictionary that can be found by name, sorted by name.
+pub fn installed() -> Vec<(String, Dictionary)> {
+    installed_in(&search_path())
+}
+
+// The dictionaries in the given directories. If several have the same name, the one in
+// the earliest directory is returned, as it is the one `find` would use.
+fn installed_in(dirs: &[PathBuf]) -> Vec<(String, Dictionary)> {
+    let mut found = std::collections::BTreeMap::new();
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+            if path.extension().is_none_or(|ext| ext != "dic") {
+                continue;
+            }
+            let (Some(name), Some(dict)) = (path.file_stem(), at(&path)) else {
+                continue;
+            };
+            found
+                .entry(name.to_string_lossy().into_owned())
+                .or_insert(dict);
+        }
+    }
+    found.into_iter().collect()
+}
+
+// Where downloaded dictionaries are kept.
+pub fn cache_dir() -> Option<PathBuf> {
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))?;
+    Some(cache.join("spelgud/dictionaries"))
+}
+
+// Download the dictionary for `lang`, e.g. "en-GB", into the cache directory.
+// `source` is a URL in which "{lang}" is replaced with the language and "{ext}" with
+// the extension of each file, "aff" or "dic".
+pub fn install(lang: &str, source: &str) -> Result<Dictionary> {
+    install_to(
+        &cache_dir().ok_or("No cache directory ($HOME is not set)")?,
+        lang,
+        source,
+    )
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn install_to(dir: &Path, lang: &str, source: &str) -> Result<Dictionary> {
+    if lang.is_empty() || lang.contains(['/', '\\', '.']) {
+        Err(format!("Invalid dictionary name {lang}"))?;
+    }
+    let curl = which::which("curl").map_err(|_| "curl is required to download dictionaries")?;
+    std::fs::create_dir_all(dir)?;
+    let base = dir.join(lang);
+    for ext in ["aff", "dic"] {
+        let url = source.replace("{lang}", lang).replace("{ext}", ext);
+        let path = base.with_extension(ext);
+        // Download to a temporary file, so a failed download doesn't leave a partial dictionary.
+        let part = base.with_extension(format!("{ext}.part"));
+        log::info!("Downloading {url} to {path:?}");
+        let status = std::process::Command::new(&curl)
+            .args([
+                "--fail",
+                "--silent",
+                "--show-error",
+                "--location",
+                "--output",
+            ])
+            .arg(&part)
+            .arg(&url)
+            .status()?;
+        if !status.success() {
+            std::fs::remove_file(&part).ok();
+            Err(format!(
+                "Failed to download {url}: curl exited with {status}"
+            ))?;
+        }
+        std::fs::rename(&part, &path)?;
+    }
+    at(&base).ok_or_else(|| format!("Failed to install dictionary {lang}").into())
+}
+
+#[cfg(target_family = "wasm")]
+fn install_to(_dir: &Path, _lang: &str, _source: &str) -> Result<Dictionary> {
+    Err("Downloading dictionaries is not supported on WebAssembly")?
+}
+
+// The dictionary at the given path, with or without extension, if both files exist.
+pub fn at(path: &Path) -> Option<Dictionary> {
+    let dict = Dictionary {
+        aff: path.with_extension("aff"),
+        dic: path.with_extension("dic"),
+    };
+    (dict.aff.is_file() && dict.dic.is_file()).then_some(dict)
+}
+
+// The language of the current locale, e.g. "en_US" for LANG=en_US.UTF-8.
+pub fn locale_language() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(std::env::var_os)
+        .filter_map(|v| v.into_string().ok())
+        .map(|v| v.split(['.', '@']).next().unwrap_or_default().to_string())
+        .find(|v| !v.is_empty() && v != "C" && v != "POSIX")
+        .or_else(|| Some("en_US".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_find_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("custom");
+        assert_eq!(find(base.to_str()), None);
+
+        std::fs::write(base.with_extension("aff"), "SET UTF-8\n").unwrap();
+        std::fs::write(base.with_extension("dic"), "1\nspelgud\n").unwrap();
+        assert_eq!(
+            find(base.to_str()),
+            Some(Dictionary {
+                aff: base.with_extension("aff"),
+                dic: base.with_extension("dic"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_installed_in() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        for (dir, name) in [(&first, "en_US"), (&second, "en_US"), (&second, "de_DE")] {
+            let base = dir.path().join(name);
+            std::fs::write(base.with_extension("aff"), "SET UTF-8\n").unwrap();
+            std::fs::write(base.with_extension("dic"), "1\nspelgud\n").unwrap();
+        }
+        // A word list without an affix file isn't a dictionary.
+        std::fs::write(second.path().join("words.dic"), "1\nspelgud\n").unwrap();
+
+        let dirs = [
+            first.path().into(),
+            second.path().into(),
+            "/nonexistent".into(),
+        ];
+        let found: Vec<(String, PathBuf)> = installed_in(&dirs)
+            .into_iter()
+            .map(|(name, dict)| (name, dict.dic))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("de_DE".into(), second.path().join("de_DE.dic")),
+                ("en_US".into(), first.path().join("en_US.dic")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_install() {
+        let source = tempfile::tempdir().unwrap();
+        std::fs::write(source.path().join("xx.aff"), "SET UTF-8\n").unwrap();
+        std::fs::write(source.path().join("xx.dic"), "1\nspelgud\n").unwrap();
+        let url = format!("file://{}/{{lang}}.{{ext}}", source.path().display());
+
+        let cache = tempfile::tempdir().unwrap();
+        let dir = cache.path().join("dictionaries");
+        let dict = install_to(&dir, "xx", &url).unwrap();
+        assert_eq!(dict.dic, dir.join("xx.dic"));
+        assert_eq!(std::fs::read_to_string(dict.dic).unwrap(), "1\nspelgud\n");
+
+        assert!(install_to(&dir, "yy", &url).is_err());
+        assert!(!dir.join("yy.aff.part").exists());
+        assert!(install_to(&dir, "../xx", &url).is_err());
+    }
+}
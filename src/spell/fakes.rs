@@ -0,0 +1,45 @@
+// Checkers for tests, which answer from fixed lists of words rather than a dictionary.
+use super::{check_words, find_words, Checker, Misspelling, Result};
+use std::sync::{Arc, Mutex};
+
+// A checker that considers the given words misspelled, suggesting "fix" for each.
+pub struct Fake(pub Vec<&'static str>);
+
+impl Checker for Fake {
+    fn check(&mut self, text: &str) -> Result<Vec<Misspelling>> {
+        check_words(text, |word| {
+            Ok(self.0.contains(&word).then(|| vec!["fix".to_string()]))
+        })
+    }
+
+    fn suggest(&mut self, _word: &str) -> Result<Vec<String>> {
+        Ok(vec!["fix".into()])
+    }
+}
+
+// A checker that considers only the given words correct.
+pub struct Vocabulary(pub Vec<&'static str>);
+
+impl Checker for Vocabulary {
+    fn check(&mut self, text: &str) -> Result<Vec<Misspelling>> {
+        find_words(text, |word| Ok(self.0.contains(&word)))
+    }
+
+    fn suggest(&mut self, _word: &str) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+}
+
+// A checker that reports "kwick", as `Fake` does, and records the texts it is asked to check.
+pub struct Recorder(pub Arc<Mutex<Vec<String>>>);
+
+impl Checker for Recorder {
+    fn check(&mut self, text: &str) -> Result<Vec<Misspelling>> {
+        self.0.lock().unwrap().push(text.to_string());
+        Fake(vec!["kwick"]).check(text)
+    }
+
+    fn suggest(&mut self, _word: &str) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+}
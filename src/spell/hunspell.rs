@@ -0,0 +1,99 @@
+// An in-process backend that links against libhunspell, enabled by the "hunspell" feature.
+// Words are checked one at a time through the C API, so there is no pipe protocol to parse.
+use super::{dictionary::Dictionary, Result};
+use std::ffi::{c_char, c_int, CStr, CString};
+
+#[repr(C)]
+struct Hunhandle {
+    _private: [u8; 0],
+}
+
+#[link(name = "hunspell-1.7")]
+extern "C" {
+    fn Hunspell_create(affpath: *const c_char, dpath: *const c_char) -> *mut Hunhandle;
+    fn Hunspell_destroy(handle: *mut Hunhandle);
+    fn Hunspell_get_dic_encoding(handle: *mut Hunhandle) -> *const c_char;
+    fn Hunspell_spell(handle: *mut Hunhandle, word: *const c_char) -> c_int;
+    fn Hunspell_suggest(
+        handle: *mut Hunhandle,
+        slst: *mut *mut *mut c_char,
+        word: *const c_char,
+    ) -> c_int;
+    fn Hunspell_free_list(handle: *mut Hunhandle, slst: *mut *mut *mut c_char, n: c_int);
+}
+
+pub struct Hunspell {
+    handle: *mut Hunhandle,
+    dictionary: Dictionary,
+}
+
+// The handle is only used by one thread at a time, through &mut self.
+unsafe impl Send for Hunspell {}
+
+impl Hunspell {
+    pub fn new(dict: &Dictionary) -> Result<Hunspell> {
+        let aff = CString::new(dict.aff.to_str().ok_or("Invalid dictionary path")?)?;
+        let dic = CString::new(dict.dic.to_str().ok_or("Invalid dictionary path")?)?;
+        let handle = unsafe { Hunspell_create(aff.as_ptr(), dic.as_ptr()) };
+        if handle.is_null() {
+            Err(format!("Failed to load dictionary {dict:?}"))?;
+        }
+        let hunspell = Hunspell {
+            handle,
+            dictionary: dict.clone(),
+        };
+
+        // Words are passed as UTF-8, so the dictionary must use the same encoding.
+        let encoding = unsafe { CStr::from_ptr(Hunspell_get_dic_encoding(handle)) };
+        let encoding = encoding.to_string_lossy().to_ascii_uppercase();
+        if encoding != "UTF-8" && encoding != "UTF8" {
+            Err(format!(
+                "Dictionary {dict:?} uses {encoding}, only UTF-8 is supported"
+            ))?;
+        }
+
+        log::info!("Loaded hunspell dictionary {dict:?}");
+        Ok(hunspell)
+    }
+
+    pub fn correct(&mut self, word: &str) -> Result<bool> {
+        let word = CString::new(word)?;
+        Ok(unsafe { Hunspell_spell(self.handle, word.as_ptr()) } != 0)
+    }
+
+    // Return None if the word is spelled correctly, or suggested corrections if not.
+    pub fn check_word(&mut self, word: &str) -> Result<Option<Vec<String>>> {
+        if self.correct(word)? {
+            return Ok(None);
+        }
+        let word = CString::new(word)?;
+        let mut list: *mut *mut c_char = std::ptr::null_mut();
+        let n = unsafe { Hunspell_suggest(self.handle, &mut list, word.as_ptr()) };
+        let fixes = (0..usize::try_from(n)?)
+            .map(|i| unsafe { CStr::from_ptr(*list.add(i)) })
+            .map(|s| s.to_string_lossy().into_owned())
+            .collect();
+        unsafe { Hunspell_free_list(self.handle, &mut list, n) };
+        Ok(Some(fixes))
+    }
+}
+
+impl super::Checker for Hunspell {
+    fn check(&mut self, text: &str) -> Result<Vec<super::Misspelling>> {
+        super::find_words(text, |word| self.correct(word))
+    }
+
+    fn suggest(&mut self, word: &str) -> Result<Vec<String>> {
+        Ok(self.check_word(word)?.unwrap_or_default())
+    }
+
+    fn dictionaries(&self) -> Vec<String> {
+        vec![self.dictionary.dic.display().to_string()]
+    }
+}
+
+impl Drop for Hunspell {
+    fn drop(&mut self) {
+        unsafe { Hunspell_destroy(self.handle) };
+    }
+}
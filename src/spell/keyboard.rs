@@ -0,0 +1,102 @@
+// Keyboard layouts, used to tell which corrections are a single slip of the finger away
+// from a misspelling, e.g. "jumps" for "jumpd", as "d" is next to "s" on a QWERTY keyboard.
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    Qwerty,
+    Azerty,
+    Dvorak,
+    Colemak,
+}
+
+impl Layout {
+    // The letter keys, top row first. Each row is offset to the right of the one above it
+    // by less than a key, so a key touches the two above it and the two below it.
+    fn rows(self) -> [&'static str; 3] {
+        match self {
+            Layout::Qwerty => ["qwertyuiop", "asdfghjkl", "zxcvbnm"],
+            Layout::Azerty => ["azertyuiop", "qsdfghjklm", "wxcvbn"],
+            Layout::Dvorak => ["',.pyfgcrl", "aoeuidhtns", ";qjkxbmwvz"],
+            Layout::Colemak => ["qwfpgjluy;", "arstdhneio", "zxcvbkm"],
+        }
+    }
+
+    // The row and column of a key.
+    fn position(self, key: char) -> Option<(usize, usize)> {
+        let key = key.to_lowercase().next()?;
+        self.rows()
+            .iter()
+            .enumerate()
+            .find_map(|(row, keys)| Some((row, keys.chars().position(|k| k == key)?)))
+    }
+
+    // Whether two keys are next to each other.
+    pub fn adjacent(self, a: char, b: char) -> bool {
+        let (Some((row_a, col_a)), Some((row_b, col_b))) = (self.position(a), self.position(b))
+        else {
+            return false;
+        };
+        match row_b as isize - row_a as isize {
+            0 => col_a.abs_diff(col_b) == 1,
+            // The row below is offset to the right, so its keys touch this row's key and the
+            // one to its left.
+            1 => col_b == col_a || col_b + 1 == col_a,
+            -1 => col_a == col_b || col_a + 1 == col_b,
+            _ => false,
+        }
+    }
+
+    // Whether `typed` could be `intended` with one slip: a key next to the right one
+    // pressed instead of it, or two keys pressed in the wrong order.
+    pub fn slip(self, typed: &str, intended: &str) -> bool {
+        let typed: Vec<char> = typed.chars().flat_map(char::to_lowercase).collect();
+        let intended: Vec<char> = intended.chars().flat_map(char::to_lowercase).collect();
+        if typed.len() != intended.len() {
+            return false;
+        }
+        let differ: Vec<usize> = (0..typed.len())
+            .filter(|&i| typed[i] != intended[i])
+            .collect();
+        match differ.as_slice() {
+            [i] => self.adjacent(typed[*i], intended[*i]),
+            [i, j] => *j == i + 1 && typed[*i] == intended[*j] && typed[*j] == intended[*i],
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adjacent() {
+        let qwerty = Layout::Qwerty;
+        assert!(qwerty.adjacent('d', 's'));
+        assert!(qwerty.adjacent('g', 't'));
+        assert!(qwerty.adjacent('g', 'y'));
+        assert!(qwerty.adjacent('h', 'n'));
+        assert!(qwerty.adjacent('N', 'h'));
+        assert!(!qwerty.adjacent('g', 'r'));
+        assert!(!qwerty.adjacent('q', 'p'));
+        assert!(!qwerty.adjacent('q', 'z'));
+        assert!(!qwerty.adjacent('d', '1'));
+        assert!(Layout::Azerty.adjacent('q', 'a'));
+        assert!(Layout::Dvorak.adjacent('e', 'u'));
+        assert!(Layout::Colemak.adjacent('n', 'e'));
+        assert!(!Layout::Colemak.adjacent('d', 's'));
+    }
+
+    #[test]
+    fn test_slip() {
+        let qwerty = Layout::Qwerty;
+        assert!(qwerty.slip("jumpd", "jumps"));
+        assert!(qwerty.slip("teh", "the"));
+        assert!(qwerty.slip("Teh", "The"));
+        assert!(!qwerty.slip("jumpd", "jumped"));
+        assert!(!qwerty.slip("jumpd", "jumpy"));
+        assert!(!qwerty.slip("tech", "that"));
+        assert!(!qwerty.slip("the", "the"));
+    }
+}
@@ -0,0 +1,137 @@
+// Remembers the words a checker has approved, so they aren't sent to it again.
+// Most words in a document are spelled correctly and used over and over, so this
+// leaves the checker with little more than the misspellings and new words.
+use super::{Checker, Misspelling, Result};
+use std::collections::{BTreeMap, HashMap};
+
+pub struct Known {
+    checker: Box<dyn Checker>,
+    // The most words to remember. The least recently used are forgotten first.
+    cap: usize,
+    // Each word, with when it was last used.
+    words: HashMap<String, u64>,
+    // The words by when they were last used.
+    used: BTreeMap<u64, String>,
+    clock: u64,
+}
+
+impl Known {
+    pub fn new(checker: Box<dyn Checker>, cap: usize) -> Known {
+        Known {
+            checker,
+            cap,
+            words: HashMap::new(),
+            used: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    // Whether the text is known to be spelled correctly, marking it as used if so.
+    fn contains(&mut self, text: &str) -> bool {
+        let Some(last) = self.words.get_mut(text) else {
+            return false;
+        };
+        self.clock += 1;
+        let word = self.used.remove(last).unwrap_or_default();
+        *last = self.clock;
+        self.used.insert(self.clock, word);
+        true
+    }
+
+    fn insert(&mut self, text: &str) {
+        if self.cap == 0 || self.contains(text) {
+            return;
+        }
+        if self.words.len() >= self.cap {
+            if let Some((_, oldest)) = self.used.pop_first() {
+                self.words.remove(&oldest);
+            }
+        }
+        self.clock += 1;
+        self.words.insert(text.to_string(), self.clock);
+        self.used.insert(self.clock, text.to_string());
+    }
+}
+
+impl Checker for Known {
+    fn check(&mut self, text: &str) -> Result<Vec<Misspelling>> {
+        Ok(self
+            .check_lines(&[text.to_string()])?
+            .pop()
+            .unwrap_or_default())
+    }
+
+    fn check_lines(&mut self, texts: &[String]) -> Result<Vec<Vec<Misspelling>>> {
+        let known: Vec<bool> = texts.iter().map(|text| self.contains(text)).collect();
+        let unknown: Vec<String> = texts
+            .iter()
+            .zip(&known)
+            .filter(|(_, &known)| !known)
+            .map(|(text, _)| text.clone())
+            .collect();
+        let mut found = self.checker.check_lines(&unknown)?.into_iter();
+        let mut results = vec![];
+        for (text, known) in texts.iter().zip(known) {
+            let misspellings = match known {
+                true => vec![],
+                false => found.next().unwrap_or_default(),
+            };
+            if !known && misspellings.is_empty() {
+                self.insert(text);
+            }
+            results.push(misspellings);
+        }
+        Ok(results)
+    }
+
+    fn suggest(&mut self, word: &str) -> Result<Vec<String>> {
+        self.checker.suggest(word)
+    }
+
+    fn version(&self) -> Option<String> {
+        self.checker.version()
+    }
+
+    fn dictionaries(&self) -> Vec<String> {
+        self.checker.dictionaries()
+    }
+
+    fn accept(&mut self, word: &str) -> Result<()> {
+        self.checker.accept(word)
+    }
+
+    fn add(&mut self, word: &str) -> Result<bool> {
+        self.checker.add(word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fakes::Recorder;
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::sync::{Arc, Mutex};
+
+    fn texts(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|text| text.to_string()).collect()
+    }
+
+    #[test]
+    fn test_known() {
+        let sent = Arc::new(Mutex::new(vec![]));
+        let mut known = Known::new(Box::new(Recorder(sent.clone())), 2);
+        let found = known.check_lines(&texts(&["the", "kwick", "fox"])).unwrap();
+        assert_eq!(found.iter().map(Vec::len).collect::<Vec<_>>(), [0, 1, 0]);
+
+        // Misspelled words are always checked again.
+        let found = known.check_lines(&texts(&["kwick", "dog"])).unwrap();
+        assert_eq!(found.iter().map(Vec::len).collect::<Vec<_>>(), [1, 0]);
+
+        // "the" was forgotten to make room for "dog", as it was used least recently.
+        known.check_lines(&texts(&["the", "fox"])).unwrap();
+        assert_eq!(
+            *sent.lock().unwrap(),
+            ["the", "kwick", "fox", "kwick", "dog", "the"]
+        );
+    }
+}
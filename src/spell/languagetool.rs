@@ -0,0 +1,253 @@
+// A client for a LanguageTool server (https://languagetool.org/http-api/), which reports
+// grammar and style issues alongside spelling errors.
+// Only plain HTTP is supported, as the server is expected to run locally, e.g.
+// `java -cp languagetool-server.jar org.languagetool.server.HTTPServer --port 8081`.
+use super::{DiagnosticData, Result};
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Url};
+use std::io::{Read, Write};
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Options {
+    // The server's base URL.
+    pub url: String,
+    // A language code such as "en-US", or "auto" to detect it from the text.
+    pub language: String,
+    // Rule IDs that should not be reported.
+    pub disabled_rules: Vec<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            url: "http://localhost:8081".into(),
+            language: "auto".into(),
+            // Skipped regions leave runs of spaces behind, which this rule would flag.
+            disabled_rules: vec!["WHITESPACE_RULE".into()],
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct Response {
+    matches: Vec<Match>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct Match {
+    message: String,
+    // Offsets are in UTF-16 code units.
+    offset: usize,
+    length: usize,
+    replacements: Vec<Replacement>,
+    rule: Rule,
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct Replacement {
+    value: String,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+    id: String,
+    issue_type: String,
+}
+
+// The most replacements offered for a single match.
+const MAX_REPLACEMENTS: usize = 5;
+
+// Check the text, where `checked` is the text with non-prose regions blanked out by a filter.
+// Blanked regions are sent to the server as markup, so offsets refer to the original text.
+pub fn check(opts: &Options, text: &str, checked: &str) -> Result<Vec<Diagnostic>> {
+    let mut form = form_urlencoded::Serializer::new(String::new());
+    form.append_pair("data", &annotate(text, checked).to_string())
+        .append_pair("language", &opts.language);
+    if !opts.disabled_rules.is_empty() {
+        form.append_pair("disabledRules", &opts.disabled_rules.join(","));
+    }
+    let url = Url::parse(&opts.url)?.join("v2/check")?;
+    let response: Response = serde_json::from_str(&post(&url, &form.finish())?)?;
+
+    response
+        .matches
+        .into_iter()
+        .map(|m| {
+            let range = lsp_types::Range {
+                start: position(text, m.offset),
+                end: position(text, m.offset + m.length),
+            };
+            let data = DiagnosticData {
+                original: utf16_slice(text, m.offset, m.length),
+                fixes: m
+                    .replacements
+                    .into_iter()
+                    .take(MAX_REPLACEMENTS)
+                    .map(|r| r.value)
+                    .collect(),
+                range,
+            };
+            Ok(Diagnostic {
+                range,
+                severity: Some(match m.rule.issue_type.as_str() {
+                    "misspelling" | "grammar" => DiagnosticSeverity::WARNING,
+                    _ => DiagnosticSeverity::INFORMATION,
+                }),
+                code: Some(NumberOrString::String(m.rule.id)),
+                source: Some("LanguageTool".into()),
+                message: m.message,
+                data: Some(serde_json::to_value(data)?),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+// Build the annotated text for the request's "data" parameter,
+// where characters blanked out in `checked` are marked as markup.
+fn annotate(text: &str, checked: &str) -> serde_json::Value {
+    let mut annotation: Vec<serde_json::Value> = vec![];
+    let mut run = String::new();
+    let mut markup = false;
+    for (t, c) in text.chars().zip(checked.chars()) {
+        let is_markup = t != c;
+        if is_markup != markup && !run.is_empty() {
+            let key = if markup { "markup" } else { "text" };
+            annotation.push(serde_json::json!({ key: std::mem::take(&mut run) }));
+        }
+        markup = is_markup;
+        run.push(t);
+    }
+    if !run.is_empty() {
+        let key = if markup { "markup" } else { "text" };
+        annotation.push(serde_json::json!({ key: run }));
+    }
+    serde_json::json!({ "annotation": annotation })
+}
+
+// Convert an offset in UTF-16 code units to a line and character.
+fn position(text: &str, offset: usize) -> Position {
+    let mut units = 0;
+    let mut pos = Position::default();
+    for c in text.chars() {
+        if units >= offset {
+            break;
+        }
+        units += c.len_utf16();
+        if c == '\n' {
+            pos.line += 1;
+            pos.character = 0;
+        } else {
+            pos.character += 1;
+        }
+    }
+    pos
+}
+
+fn utf16_slice(text: &str, offset: usize, length: usize) -> String {
+    let units: Vec<u16> = text.encode_utf16().skip(offset).take(length).collect();
+    String::from_utf16_lossy(&units)
+}
+
+// Send a form-encoded POST request and return the response body.
+fn post(url: &Url, body: &str) -> Result<String> {
+    if url.scheme() != "http" {
+        Err(format!(
+            "Unsupported LanguageTool URL {url}, only http is supported"
+        ))?;
+    }
+    let host = url.host_str().ok_or(format!("No host in {url}"))?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    let mut stream = std::net::TcpStream::connect((host, port))?;
+    stream.set_read_timeout(Some(std::time::Duration::from_secs(30)))?;
+    log::debug!("Sending {} bytes to {url}", body.len());
+    write!(
+        stream,
+        "POST {} HTTP/1.0\r\n\
+         Host: {host}\r\n\
+         Content-Type: application/x-www-form-urlencoded\r\n\
+         Content-Length: {}\r\n\r\n{body}",
+        url.path(),
+        body.len()
+    )?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or("Malformed response from LanguageTool")?;
+    let status = head.lines().next().unwrap_or_default();
+    if status.split(' ').nth(1) != Some("200") {
+        Err(format!("LanguageTool request failed: {status}: {body}"))?;
+    }
+    Ok(body.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::io::BufRead;
+
+    #[test]
+    fn test_annotate() {
+        assert_eq!(
+            annotate("Use `x` here.\nOk", "Use     here.\nOk"),
+            serde_json::json!({"annotation": [
+                {"text": "Use "},
+                {"markup": "`x`"},
+                {"text": " here.\nOk"},
+            ]})
+        );
+    }
+
+    #[test]
+    fn test_position() {
+        let text = "a😀b\nc";
+        assert_eq!(position(text, 3), Position::new(0, 2));
+        assert_eq!(position(text, 5), Position::new(1, 0));
+        assert_eq!(utf16_slice(text, 1, 3), "😀b");
+    }
+
+    #[test]
+    fn test_check() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let opts = Options {
+            url: format!("http://{}", listener.local_addr().unwrap()),
+            ..Default::default()
+        };
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = std::io::BufReader::new(stream);
+            let mut request = String::new();
+            while reader.read_line(&mut request).unwrap() > 2 {}
+            let body = r#"{"matches": [{
+                "message": "Possible agreement error",
+                "offset": 9,
+                "length": 2,
+                "replacements": [{"value": "are"}],
+                "rule": {"id": "AGREEMENT", "issueType": "grammar"}
+            }]}"#;
+            write!(reader.get_mut(), "HTTP/1.0 200 OK\r\n\r\n{body}").unwrap();
+            request
+        });
+
+        let diags = check(&opts, "`x`\nThey is", "   \nThey is").unwrap();
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /v2/check HTTP/1.0"));
+        assert_eq!(diags.len(), 1);
+        assert_eq!(
+            diags[0].range,
+            lsp_types::Range::new(Position::new(1, 5), Position::new(1, 7))
+        );
+        assert_eq!(
+            diags[0].code,
+            Some(NumberOrString::String("AGREEMENT".into()))
+        );
+        assert_eq!(diags[0].severity, Some(DiagnosticSeverity::WARNING));
+        let data: DiagnosticData = serde_json::from_value(diags[0].data.clone().unwrap()).unwrap();
+        assert_eq!(data.original, "is");
+        assert_eq!(data.fixes, vec!["are"]);
+    }
+}
@@ -0,0 +1,43 @@
+// A small list of profanity and slurs, so they can be kept out of suggestions, e.g. when
+// spell-checking in front of others. A misspelling may be a single edit away from one,
+// e.g. "shirt" without its "r", and a checker will happily suggest it.
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+const BUNDLED: &str = include_str!("offensive.txt");
+
+fn words() -> &'static HashSet<&'static str> {
+    static WORDS: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    WORDS.get_or_init(|| {
+        BUNDLED
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect()
+    })
+}
+
+// Whether a suggestion is, or contains as one of its words, an offensive word.
+pub fn is_offensive(suggestion: &str) -> bool {
+    suggestion
+        .split(|c: char| !c.is_alphabetic() && c != '\'')
+        .any(|word| words().contains(word.to_lowercase().as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_offensive() {
+        assert!(is_offensive("shit"));
+        assert!(is_offensive("Shit"));
+        assert!(is_offensive("SHIT"));
+        assert!(is_offensive("holy shit"));
+        assert!(is_offensive("bull-shit"));
+        assert!(!is_offensive("shirt"));
+        assert!(!is_offensive("assess"));
+        assert!(!is_offensive("Scunthorpe"));
+        assert!(!is_offensive(""));
+    }
+}
@@ -0,0 +1,464 @@
+// The user's personal word lists, shared with aspell and hunspell, so words added with
+// other tools are accepted and words added here are accepted by other tools.
+use super::Result;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Default)]
+pub struct Personal {
+    words: HashSet<String>,
+    // Words from the project's word lists, with ASCII apostrophes. They are also accepted
+    // with a common ending, e.g. "spelguds" and "spelgud's" for "spelgud".
+    listed: HashSet<String>,
+    // Words accepted until the server exits.
+    session: HashSet<String>,
+    // The words in proper nouns, in lowercase. They are accepted in any case, as a wrong
+    // case is reported by a style rule with the right one as a fix.
+    names: HashSet<String>,
+    // Words from plain lists, e.g. of a field's jargon, as given by `key`. They are accepted
+    // in any case, and with any apostrophe.
+    jargon: HashSet<String>,
+    // A project's own hunspell dictionaries, e.g. of medical terms.
+    #[cfg(feature = "builtin")]
+    dictionaries: Vec<super::builtin::Builtin>,
+    // The file new words are added to.
+    path: Option<PathBuf>,
+}
+
+impl Personal {
+    // Load the personal dictionaries for the given dictionary name, e.g. "en_US".
+    // `aspell` is whether aspell is the primary checker, in which case new words are added to
+    // its personal dictionary rather than hunspell's.
+    pub fn load(dictionary: Option<&str>, aspell: bool) -> Personal {
+        let Some(home) = std::env::var_os("HOME").map(PathBuf::from) else {
+            return Personal::default();
+        };
+        Self::load_from(&home, dictionary, aspell)
+    }
+
+    fn load_from(home: &Path, dictionary: Option<&str>, aspell: bool) -> Personal {
+        let name = dictionary
+            .map(String::from)
+            .or_else(super::dictionary::locale_language)
+            .unwrap_or_default();
+        // aspell keeps a list per language, e.g. "en" for "en_US".
+        let lang = name.split(['_', '-']).next().unwrap_or_default();
+        let aspell_path = home.join(format!(".aspell.{lang}.pws"));
+        let hunspell_paths = [
+            home.join(format!(".hunspell_{name}")),
+            home.join(".hunspell_default"),
+        ];
+
+        let mut words = HashSet::new();
+        if let Ok(text) = std::fs::read_to_string(&aspell_path) {
+            // The first line is a header, e.g. "personal_ws-1.1 en 2 utf-8".
+            words.extend(text.lines().skip(1).map(str::trim).map(String::from));
+        }
+        for path in &hunspell_paths {
+            if let Ok(text) = std::fs::read_to_string(path) {
+                // Words may be followed by flags, e.g. "word/S", and forbidden words start with "*".
+                words.extend(
+                    text.lines()
+                        .filter(|line| !line.starts_with('*'))
+                        .filter_map(|line| line.split('/').next())
+                        .map(str::trim)
+                        .map(String::from),
+                );
+            }
+        }
+        words.remove("");
+        log::info!("Loaded {} words from personal dictionaries", words.len());
+
+        let path = if aspell {
+            aspell_path
+        } else {
+            hunspell_paths
+                .iter()
+                .find(|path| path.is_file())
+                .unwrap_or(&hunspell_paths[0])
+                .clone()
+        };
+        Personal {
+            words,
+            session: HashSet::new(),
+            listed: HashSet::new(),
+            names: HashSet::new(),
+            jargon: HashSet::new(),
+            #[cfg(feature = "builtin")]
+            dictionaries: vec![],
+            path: Some(path),
+        }
+    }
+
+    // Also accept the words in the given files, one per line, along with their plurals and
+    // possessives. Lines starting with "#" are comments.
+    pub fn load_wordlists(&mut self, paths: &[PathBuf]) {
+        for path in paths {
+            match std::fs::read_to_string(path) {
+                Ok(text) => self.listed.extend(
+                    text.lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                        .map(plain_apostrophes),
+                ),
+                Err(err) => log::warn!("Cannot read word list {path:?}: {err}"),
+            }
+        }
+    }
+
+    // Also accept the words in the given files, one per line, whatever their case, e.g. "Kubectl"
+    // for "kubectl". Lines starting with "#" are comments.
+    pub fn load_jargon(&mut self, paths: &[PathBuf]) {
+        for path in paths {
+            match std::fs::read_to_string(path) {
+                Ok(text) => self.jargon.extend(
+                    text.lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                        .map(key),
+                ),
+                Err(err) => log::warn!("Cannot read word list {path:?}: {err}"),
+            }
+        }
+        log::info!("Loaded {} words of jargon", self.jargon.len());
+    }
+
+    // Whether the word is in a list of jargon, in any case and with any apostrophe, e.g.
+    // "don’t" for "don't". A possessive is accepted if the word is, e.g. "Kubernetes's".
+    pub fn is_jargon(&self, word: &str) -> bool {
+        if self.jargon.is_empty() {
+            return false;
+        }
+        let word = key(word);
+        let stem = word.strip_suffix("'s").or_else(|| word.strip_suffix('\''));
+        self.jargon.contains(&word) || stem.is_some_and(|stem| self.jargon.contains(stem))
+    }
+
+    // Also accept the words in the given names, e.g. "Visual" and "Studio".
+    pub fn load_names(&mut self, names: &[String]) {
+        let words = names.iter().flat_map(|name| name.split_whitespace());
+        self.names.extend(words.map(str::to_lowercase));
+    }
+
+    // Also accept the words in the given hunspell dictionaries, each a path with or without
+    // the ".dic" extension, e.g. "dicts/custom" for "dicts/custom.dic" and "dicts/custom.aff".
+    #[cfg(feature = "builtin")]
+    pub fn load_dictionaries(&mut self, paths: &[PathBuf], allow_compounds: bool) {
+        for path in paths {
+            let Some(dict) = super::dictionary::at(path) else {
+                log::warn!("Cannot find dictionary {path:?}: expected a .dic and a .aff file");
+                continue;
+            };
+            match super::builtin::Builtin::new(&dict, allow_compounds) {
+                Ok(builtin) => self.dictionaries.push(builtin),
+                Err(err) => log::warn!("Cannot load dictionary {path:?}: {err}"),
+            }
+        }
+    }
+
+    // Project dictionaries are read by the built-in checker, so can't be used without it.
+    #[cfg(not(feature = "builtin"))]
+    pub fn load_dictionaries(&mut self, paths: &[PathBuf], _allow_compounds: bool) {
+        if !paths.is_empty() {
+            log::warn!("Cannot load dictionaries {paths:?}: built without the builtin feature");
+        }
+    }
+
+    // Corrections for a word from the project's dictionaries, if any.
+    #[cfg(feature = "builtin")]
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        self.dictionaries
+            .iter()
+            .flat_map(|dict| dict.suggest(word))
+            .collect()
+    }
+
+    #[cfg(not(feature = "builtin"))]
+    pub fn suggest(&self, _word: &str) -> Vec<String> {
+        vec![]
+    }
+
+    // Whether the word is in one of the project's dictionaries.
+    #[cfg(feature = "builtin")]
+    fn in_dictionaries(&self, word: &str) -> bool {
+        self.dictionaries.iter().any(|dict| dict.correct(word))
+    }
+
+    #[cfg(not(feature = "builtin"))]
+    fn in_dictionaries(&self, _word: &str) -> bool {
+        false
+    }
+
+    // Whether the word is in the personal dictionary. As with aspell, a lowercase entry also
+    // accepts the word capitalized, e.g. at the start of a sentence.
+    pub fn contains(&self, word: &str) -> bool {
+        let has = |word: &str| self.words.contains(word) || self.session.contains(word);
+        if has(word)
+            || self.names.contains(&word.to_lowercase())
+            || self.is_jargon(word)
+            || self.in_dictionaries(word)
+        {
+            return true;
+        }
+        if capitalized(word) && has(&word.to_lowercase()) {
+            return true;
+        }
+        stems(&plain_apostrophes(word)).iter().any(|stem| {
+            self.listed.contains(stem)
+                || capitalized(stem) && self.listed.contains(&stem.to_lowercase())
+        })
+    }
+
+    // Accept a word until the server exits.
+    pub fn accept(&mut self, word: &str) {
+        self.session.insert(word.to_string());
+    }
+
+    pub fn session_words(&self) -> usize {
+        self.session.len()
+    }
+
+    // Add a word, saving it to the personal dictionary file if `save` is set.
+    pub fn add(&mut self, word: &str, save: bool) -> Result<()> {
+        if !save {
+            self.words.insert(word.to_string());
+            return Ok(());
+        }
+        let path = self
+            .path
+            .as_ref()
+            .ok_or("No personal dictionary ($HOME is not set)")?;
+        if path.extension().is_some_and(|ext| ext == "pws") {
+            add_aspell(path, word)?;
+        } else {
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            writeln!(file, "{word}")?;
+        }
+        log::info!("Added {word} to {path:?}");
+        self.words.insert(word.to_string());
+        Ok(())
+    }
+}
+
+// Whether only the first letter of the word is uppercase, e.g. at the start of a sentence.
+fn capitalized(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars.next().is_some_and(char::is_uppercase) && chars.all(|c| !c.is_uppercase())
+}
+
+// The word with each apostrophe, e.g. "’", written as "'".
+fn plain_apostrophes(word: &str) -> String {
+    word.chars()
+        .map(|c| match crate::token::is_apostrophe(c) {
+            true => '\'',
+            false => c,
+        })
+        .collect()
+}
+
+// A word as stored in a list of jargon, in lowercase with ASCII apostrophes.
+fn key(word: &str) -> String {
+    plain_apostrophes(word).to_lowercase()
+}
+
+// The word itself, followed by the words it may be formed from with a common ending, e.g.
+// "spelgud" for "spelgud's", "spelguds" and "spelgudes", and "proxy" for "proxies".
+fn stems(word: &str) -> Vec<String> {
+    let mut stems = vec![word.to_string()];
+    for (ending, replacement) in [("'s", ""), ("s", ""), ("es", ""), ("ies", "y")] {
+        match word.strip_suffix(ending) {
+            Some(stem) if !stem.is_empty() => stems.push(format!("{stem}{replacement}")),
+            _ => {}
+        }
+    }
+    stems
+}
+
+// Add a word to an aspell personal dictionary, keeping the count in its header up to date.
+fn add_aspell(path: &Path, word: &str) -> Result<()> {
+    let text = std::fs::read_to_string(path).unwrap_or_default();
+    let mut lines = text.lines();
+    let header = lines.next().unwrap_or_default();
+    let mut fields: Vec<String> = header.split(' ').map(String::from).collect();
+    if fields.len() < 4 {
+        let lang = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.split('.').nth(2))
+            .unwrap_or("en");
+        fields = vec![
+            "personal_ws-1.1".into(),
+            lang.into(),
+            "0".into(),
+            "utf-8".into(),
+        ];
+    }
+    let mut words: Vec<&str> = lines.filter(|line| !line.trim().is_empty()).collect();
+    words.push(word);
+    fields[2] = words.len().to_string();
+    let mut out = fields.join(" ");
+    for word in words {
+        out.push('\n');
+        out.push_str(word);
+    }
+    out.push('\n');
+    std::fs::write(path, out)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_load() {
+        let home = tempfile::tempdir().unwrap();
+        std::fs::write(
+            home.path().join(".aspell.en.pws"),
+            "personal_ws-1.1 en 2 utf-8\nspelgud\nrustacean\n",
+        )
+        .unwrap();
+        std::fs::write(home.path().join(".hunspell_en_US"), "ferris/S\n*badword\n").unwrap();
+        let personal = Personal::load_from(home.path(), Some("en_US"), true);
+        assert!(personal.contains("spelgud"));
+        assert!(personal.contains("Spelgud"));
+        assert!(!personal.contains("SPELGUD"));
+        assert!(personal.contains("ferris"));
+        assert!(!personal.contains("badword"));
+        assert!(!personal.contains("personal_ws-1.1"));
+    }
+
+    #[test]
+    fn test_add() {
+        let home = tempfile::tempdir().unwrap();
+        let mut personal = Personal::load_from(home.path(), Some("en_US"), true);
+        personal.add("spelgud", true).unwrap();
+        personal.add("ferris", true).unwrap();
+        personal.add("unsaved", false).unwrap();
+        assert!(personal.contains("unsaved"));
+        assert!(personal.contains("ferris"));
+        assert_eq!(
+            std::fs::read_to_string(home.path().join(".aspell.en.pws")).unwrap(),
+            "personal_ws-1.1 en 2 utf-8\nspelgud\nferris\n"
+        );
+
+        let mut personal = Personal::load_from(home.path(), Some("en_US"), false);
+        assert!(personal.contains("spelgud"));
+        assert!(!personal.contains("unsaved"));
+        personal.add("rustacean", true).unwrap();
+        personal.accept("session");
+        assert!(personal.contains("Session"));
+        assert_eq!(personal.session_words(), 1);
+        assert_eq!(
+            std::fs::read_to_string(home.path().join(".hunspell_en_US")).unwrap(),
+            "rustacean\n"
+        );
+    }
+
+    #[test]
+    fn test_load_names() {
+        let mut personal = Personal::default();
+        personal.load_names(&["GitHub".into(), "Visual Studio Code".into()]);
+        assert!(personal.contains("GitHub"));
+        assert!(personal.contains("studio"));
+        assert!(personal.contains("Github"));
+        assert!(!personal.contains("Git"));
+        assert_eq!(personal.session_words(), 0);
+    }
+
+    #[test]
+    fn test_load_wordlists() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("words.txt");
+        std::fs::write(
+            &path,
+            "# Words\nspelgud\nproxy\nmatch\nO’Brien\nrustacean's\n",
+        )
+        .unwrap();
+        let mut personal = Personal::default();
+        personal.load_wordlists(&[path]);
+        for word in [
+            "spelgud",
+            "Spelgud",
+            "spelguds",
+            "spelgud's",
+            "Spelgud’s",
+            "proxies",
+            "Proxies",
+            "matches",
+            "O'Brien",
+            "O’Brien's",
+            "rustacean’s",
+        ] {
+            assert!(personal.contains(word), "{word}");
+        }
+        for word in [
+            "SPELGUD",
+            "spelgudx",
+            "sspelgud",
+            "o'brien",
+            "rustacean",
+            "Words",
+        ] {
+            assert!(!personal.contains(word), "{word}");
+        }
+    }
+
+    #[test]
+    fn test_stems() {
+        assert_eq!(stems("cat"), ["cat"]);
+        assert_eq!(stems("proxies"), ["proxies", "proxie", "proxi", "proxy"]);
+        assert_eq!(stems("dog's"), ["dog's", "dog", "dog'"]);
+        assert_eq!(stems("s"), ["s"]);
+    }
+
+    #[test]
+    fn test_load_jargon() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("jargon.txt");
+        std::fs::write(&path, "# Terms\nkubectl\nKubernetes\nO’Reilly\n").unwrap();
+        let mut personal = Personal::default();
+        personal.load_jargon(&[path, tmp.path().join("missing.txt")]);
+        assert!(personal.contains("Kubectl"));
+        assert!(personal.contains("KUBERNETES"));
+        assert!(personal.contains("Kubernetes's"));
+        assert!(personal.contains("Kubernetes’"));
+        assert!(personal.contains("O'Reilly"));
+        assert!(personal.contains("o'reilly’s"));
+        assert!(!personal.contains("kube"));
+        assert!(!personal.contains("Terms"));
+    }
+
+    #[test]
+    #[cfg(feature = "builtin")]
+    fn test_load_dictionaries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let base = tmp.path().join("custom");
+        std::fs::write(
+            base.with_extension("aff"),
+            "SET UTF-8\nSFX S Y 1\nSFX S 0 s .\n",
+        )
+        .unwrap();
+        std::fs::write(
+            base.with_extension("dic"),
+            "2\nacetaminophen/S\nibuprofen\n",
+        )
+        .unwrap();
+        let mut personal = Personal::default();
+        personal.load_dictionaries(
+            &[base.with_extension("dic"), tmp.path().join("missing")],
+            false,
+        );
+        assert!(personal.contains("acetaminophens"));
+        assert!(personal.contains("Ibuprofen"));
+        assert!(!personal.contains("ibuprofens"));
+        assert!(personal
+            .suggest("ibuprofin")
+            .contains(&"ibuprofen".to_string()));
+    }
+}
@@ -0,0 +1,345 @@
+// A pool of checker processes, so that a large amount of text can be split between
+// several processes and checked in parallel.
+use super::{Checker, Misspelling, Process, Result};
+use std::sync::{Condvar, Mutex};
+
+// The fewest lines worth handing to a process of their own.
+const MIN_SHARD: usize = 64;
+
+// Starts a new process for the pool.
+type Start = Box<dyn Fn() -> Result<Process> + Send + Sync>;
+
+pub struct Pool {
+    state: Mutex<State>,
+    returned: Condvar,
+    start: Start,
+    size: usize,
+    version: Option<String>,
+    dictionaries: Vec<String>,
+}
+
+struct State {
+    idle: Vec<Process>,
+    // Words accepted for the session, which new processes must also be told about.
+    session: Vec<String>,
+    // The number of processes started, whether idle or checked out.
+    started: usize,
+}
+
+// A process checked out of the pool, which is returned when dropped.
+struct Checkout<'a> {
+    pool: &'a Pool,
+    process: Option<Process>,
+}
+
+impl Pool {
+    // Create a pool of up to `size` processes, starting with `first`.
+    // More processes are started with `start` as they are needed.
+    pub fn new(first: Process, size: usize, start: Start) -> Pool {
+        Pool {
+            version: first.version(),
+            dictionaries: first.dictionaries(),
+            state: Mutex::new(State {
+                idle: vec![first],
+                session: vec![],
+                started: 1,
+            }),
+            returned: Condvar::new(),
+            start,
+            size: size.max(1),
+        }
+    }
+
+    // Take an idle process, starting a new one if none are idle and the pool is not full,
+    // or waiting for one to be returned if it is.
+    fn checkout(&self) -> Result<Checkout<'_>> {
+        let mut state = self.state.lock().map_err(|err| err.to_string())?;
+        loop {
+            if let Some(process) = state.idle.pop() {
+                return Ok(Checkout {
+                    pool: self,
+                    process: Some(process),
+                });
+            }
+            if state.started < self.size {
+                state.started += 1;
+                drop(state);
+                log::debug!("Starting another checker process");
+                return match self.start_process() {
+                    Ok(process) => Ok(Checkout {
+                        pool: self,
+                        process: Some(process),
+                    }),
+                    Err(err) => {
+                        self.state.lock().map_err(|err| err.to_string())?.started -= 1;
+                        Err(err)
+                    }
+                };
+            }
+            state = self.returned.wait(state).map_err(|err| err.to_string())?;
+        }
+    }
+
+    // Start a new process, telling it about the words accepted for this session.
+    fn start_process(&self) -> Result<Process> {
+        let session = self
+            .state
+            .lock()
+            .map_err(|err| err.to_string())?
+            .session
+            .clone();
+        let mut process = (self.start)()?;
+        for word in &session {
+            process.accept(word)?;
+        }
+        Ok(process)
+    }
+
+    // Check each of the texts with a single process.
+    fn check_all(&self, texts: &[String]) -> Result<Vec<Vec<Misspelling>>> {
+        let mut checkout = self.checkout()?;
+        match self.retry(&mut checkout, |process| process.check_lines(texts)) {
+            // If the process keeps dying partway through, e.g. it crashes on certain input,
+            // check a line at a time so the other lines are still checked.
+            Err(err) if !err.is::<super::Timeout>() && texts.len() > 1 => {
+                log::warn!("Checking lines one at a time: {err}");
+                texts
+                    .iter()
+                    .map(|text| self.retry(&mut checkout, |process| process.check(text)))
+                    .collect()
+            }
+            result => result,
+        }
+    }
+
+    // Run `f` with a checked out process. If the process has died, e.g. it crashed or was
+    // killed, replace it with a new one and try again once. If it timed out, it is replaced
+    // but not retried, as the same input would likely hang again.
+    fn retry<T>(
+        &self,
+        checkout: &mut Checkout,
+        f: impl Fn(&mut Process) -> Result<T>,
+    ) -> Result<T> {
+        let process = checkout.process.as_mut().unwrap();
+        match f(process) {
+            Err(err) if err.is::<super::Timeout>() => {
+                match self.start_process() {
+                    Ok(new) => *process = new,
+                    Err(err) => log::error!("Failed to restart checker: {err}"),
+                }
+                Err(err)
+            }
+            Err(err) if process.broken => {
+                log::warn!("Checker process died ({err}), restarting it");
+                *process = self.start_process()?;
+                f(process)
+            }
+            result => result,
+        }
+    }
+}
+
+impl Drop for Checkout<'_> {
+    fn drop(&mut self) {
+        if let (Some(process), Ok(mut state)) = (self.process.take(), self.pool.state.lock()) {
+            state.idle.push(process);
+            self.pool.returned.notify_one();
+        }
+    }
+}
+
+impl Checker for Pool {
+    fn check(&mut self, text: &str) -> Result<Vec<Misspelling>> {
+        self.retry(&mut self.checkout()?, |process| process.check(text))
+    }
+
+    fn suggest(&mut self, word: &str) -> Result<Vec<String>> {
+        self.retry(&mut self.checkout()?, |process| process.suggest(word))
+    }
+
+    fn version(&self) -> Option<String> {
+        self.version.clone()
+    }
+
+    fn dictionaries(&self) -> Vec<String> {
+        self.dictionaries.clone()
+    }
+
+    fn accept(&mut self, word: &str) -> Result<()> {
+        let state = self.state.get_mut().map_err(|err| err.to_string())?;
+        state.session.push(word.to_string());
+        for process in &mut state.idle {
+            process.accept(word)?;
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, word: &str) -> Result<bool> {
+        // Add the word to every process, as each saves its own copy of the personal dictionary.
+        let mut saved = false;
+        let state = self.state.get_mut().map_err(|err| err.to_string())?;
+        state.session.push(word.to_string());
+        for process in &mut state.idle {
+            saved |= process.add(word)?;
+        }
+        Ok(saved)
+    }
+
+    fn check_lines(&mut self, texts: &[String]) -> Result<Vec<Vec<Misspelling>>> {
+        let shard = MIN_SHARD.max(texts.len().div_ceil(self.size));
+        if texts.len() <= shard {
+            return self.check_all(texts);
+        }
+        let pool = &*self;
+        let shards: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = texts
+                .chunks(shard)
+                .map(|texts| {
+                    // Errors can't be sent between threads, so pass back their message.
+                    scope.spawn(move || pool.check_all(texts).map_err(|err| err.to_string()))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or(Err("Checker thread panicked".into()))
+                })
+                .collect()
+        });
+        let mut misspellings = vec![];
+        for shard in shards {
+            misspellings.extend(shard?);
+        }
+        Ok(misspellings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spell::custom::{Custom, Format};
+    use pretty_assertions::assert_eq;
+
+    // A checker that reports the word "kwick" at offset 0 of every line.
+    fn start() -> Result<Process> {
+        start_script(r#"while read -r l; do echo '[{"word": "kwick", "offset": 0}]'; done"#)
+    }
+
+    fn start_script(script: &str) -> Result<Process> {
+        let custom = Custom {
+            command: vec!["sh".into(), "-c".into(), script.into()],
+            format: Format::Json,
+        };
+        let opts = crate::spell::Options {
+            timeout_ms: 200,
+            ..Default::default()
+        };
+        Process::start(custom.command(&opts)?, Format::Json, &opts)
+    }
+
+    #[test]
+    fn test_accept() {
+        // A checker speaking the ispell protocol that rejects "kwick" until it is accepted.
+        let start = || {
+            let custom = Custom {
+                command: vec![
+                    "sh".into(),
+                    "-c".into(),
+                    r#"echo '@(#) fake'; ok=no
+                    while read -r l; do case "$l" in
+                        @kwick) ok=yes;;
+                        ^kwick) [ $ok = no ] && echo '# kwick 1'; echo;;
+                        ^*) echo;;
+                    esac; done"#
+                        .into(),
+                ],
+                format: Format::Ispell,
+            };
+            Process::start(
+                custom.command(&Default::default())?,
+                Format::Ispell,
+                &Default::default(),
+            )
+        };
+        let mut pool = Pool::new(start().unwrap(), 2, Box::new(start));
+        assert_eq!(pool.check("kwick").unwrap().len(), 1);
+        pool.accept("kwick").unwrap();
+        assert!(pool.check("kwick").unwrap().is_empty());
+        // Processes started later also accept the word.
+        let results = pool
+            .check_lines(&vec!["kwick".into(); MIN_SHARD * 2])
+            .unwrap();
+        assert_eq!(pool.state.lock().unwrap().started, 2);
+        assert!(results.iter().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn test_timeout() {
+        // A checker that hangs when asked to check "hang".
+        let start = || {
+            start_script(
+                r#"while read -r l; do case "$l" in *hang*) sleep 5;; esac; echo '[]'; done"#,
+            )
+        };
+        let mut pool = Pool::new(start().unwrap(), 1, Box::new(start));
+        assert!(pool.check("ok").unwrap().is_empty());
+        let err = pool.check("hang").unwrap_err();
+        assert!(err.is::<crate::spell::Timeout>());
+        assert!(pool.check("ok").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_respawn() {
+        // A checker that exits after checking a single line.
+        let once = || start_script(r#"read -r l; echo '[{"word": "kwick", "offset": 0}]'"#);
+        let mut pool = Pool::new(once().unwrap(), 1, Box::new(once));
+        for _ in 0..3 {
+            assert_eq!(pool.check("kwick").unwrap().len(), 1);
+        }
+        assert_eq!(
+            pool.check_lines(&["kwick".into(), "kwick".into()])
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // A checker that always fails is only retried once.
+        let exit = || start_script("exit 1");
+        let mut pool = Pool::new(exit().unwrap(), 1, Box::new(exit));
+        assert!(pool.check("kwick").is_err());
+    }
+
+    #[test]
+    fn test_batch() {
+        // Results are matched to lines in order, though all lines are sent before any are read.
+        let start = || {
+            start_script(
+                r#"while read -r l; do case "$l" in
+                    *kwick*) echo '[{"word": "kwick", "offset": 0}]';;
+                    *) echo '[]';;
+                esac; done"#,
+            )
+        };
+        let mut process = start().unwrap();
+        let texts = ["kwick", "fox", "dog", "kwick"].map(String::from);
+        let found = process.check_lines(&texts).unwrap();
+        assert_eq!(found.iter().map(Vec::len).collect::<Vec<_>>(), [1, 0, 0, 1]);
+        assert!(process.check_lines(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pool() {
+        let mut pool = Pool::new(start().unwrap(), 3, Box::new(start));
+        let texts = vec!["kwick".to_string(); MIN_SHARD * 4];
+        let results = pool.check_lines(&texts).unwrap();
+        assert_eq!(results.len(), texts.len());
+        assert!(results.iter().all(|r| r.len() == 1));
+        assert_eq!(pool.state.lock().unwrap().started, 3);
+        assert_eq!(pool.state.lock().unwrap().idle.len(), 3);
+
+        assert_eq!(pool.check("kwick").unwrap().len(), 1);
+        assert_eq!(pool.state.lock().unwrap().idle.len(), 3);
+    }
+}
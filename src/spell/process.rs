@@ -0,0 +1,529 @@
+// Spell checkers run as separate programs, which check text sent to them through a pipe.
+// Not available on WebAssembly, which has no way to start other programs.
+use super::{custom, parse, Checker, Misspelling, Options, Program, Result, Timeout};
+use std::{
+    io::{BufRead, Write},
+    process::{Command, Stdio},
+    sync::mpsc::RecvTimeoutError,
+    time::Duration,
+};
+
+// An external program, communicating through its pipe mode.
+pub struct Process {
+    child: std::process::Child,
+    output: Output,
+    timeout: std::time::Duration,
+    format: custom::Format,
+    // Set once the process can no longer be communicated with.
+    pub(super) broken: bool,
+    // The version reported when the process started.
+    version: Option<String>,
+    dictionary: Option<String>,
+    // For a process that only finds misspellings, starts one that also suggests corrections.
+    with_suggestions: Option<Start>,
+    // The process started by `with_suggestions`, once suggestions are asked for.
+    suggester: Option<Box<Process>>,
+    // Words accepted or added this session, which the suggester must also be told about.
+    session: Vec<String>,
+}
+
+type Start = Box<dyn Fn() -> Result<Process> + Send + Sync>;
+
+// Lines of output from a process.
+enum Output {
+    // Read by a separate thread, so reads can time out.
+    Thread(std::sync::mpsc::Receiver<std::io::Result<String>>),
+    // Read on the tokio runtime spelgud runs on, which times reads out without a thread.
+    #[cfg(feature = "async")]
+    Runtime(
+        tokio::runtime::Handle,
+        tokio::io::Lines<tokio::io::BufReader<tokio::process::ChildStdout>>,
+    ),
+}
+
+impl Output {
+    // Read the next line of output, if there is one within the timeout.
+    fn next(
+        &mut self,
+        timeout: Duration,
+    ) -> std::result::Result<std::io::Result<String>, RecvTimeoutError> {
+        match self {
+            Output::Thread(output) => output.recv_timeout(timeout),
+            #[cfg(feature = "async")]
+            Output::Runtime(runtime, lines) => {
+                let line = async { tokio::time::timeout(timeout, lines.next_line()).await };
+                // The runtime's own threads must be told they are blocking.
+                match tokio::task::block_in_place(|| runtime.block_on(line)) {
+                    Ok(Ok(Some(line))) => Ok(Ok(line)),
+                    Ok(Ok(None)) => Err(RecvTimeoutError::Disconnected),
+                    Ok(Err(err)) => Ok(Err(err)),
+                    Err(_) => Err(RecvTimeoutError::Timeout),
+                }
+            }
+        }
+    }
+}
+
+// The tokio runtime spelgud is running on, if any. Only a multi-threaded runtime can be
+// blocked on while reading.
+#[cfg(feature = "async")]
+fn runtime() -> Option<tokio::runtime::Handle> {
+    use tokio::runtime::{Handle, RuntimeFlavor};
+    Handle::try_current()
+        .ok()
+        .filter(|runtime| runtime.runtime_flavor() == RuntimeFlavor::MultiThread)
+}
+
+// Read the output of processes started by `start` on the runtime spelgud is running on, if
+// any. The runtime is found when the starter is made, as processes may be restarted from a
+// worker thread.
+#[cfg(feature = "async")]
+fn on_runtime<T>(start: impl Fn() -> T) -> impl Fn() -> T {
+    let runtime = runtime();
+    move || {
+        let _entered = runtime.as_ref().map(tokio::runtime::Handle::enter);
+        start()
+    }
+}
+
+#[cfg(not(feature = "async"))]
+fn on_runtime<T>(start: impl Fn() -> T) -> impl Fn() -> T {
+    start
+}
+
+impl Program {
+    pub(super) fn command(&self) -> Option<std::path::PathBuf> {
+        match self {
+            Program::Aspell => which::which("aspell"),
+            Program::Ispell => which::which("ispell"),
+            Program::Hunspell => which::which("hunspell"),
+            Program::Libhunspell | Program::Builtin | Program::Custom(_) => return None,
+        }
+        .ok()
+    }
+
+    // The dictionaries aspell or ispell can use, or None if it is not installed.
+    pub(super) fn list_dictionaries(&self) -> Option<Vec<(String, Option<std::path::PathBuf>)>> {
+        let cmd = self.command()?;
+        let output = |args: &[&str]| {
+            let output = Command::new(&cmd).args(args).output().ok()?;
+            String::from_utf8(output.stdout).ok()
+        };
+        let dicts = match self {
+            Program::Aspell => output(&["dump", "dicts"])
+                .unwrap_or_default()
+                .lines()
+                .map(|name| (name.to_string(), None))
+                .collect(),
+            // ispell's hashed dictionaries are in the LIBDIR it was built with,
+            // which it prints as e.g. `LIBDIR = "/usr/lib/ispell"`.
+            _ => {
+                let info = output(&["-vv"]).unwrap_or_default();
+                let libdir = info.lines().find_map(|line| {
+                    let (_, dir) = line.trim().split_once("LIBDIR = ")?;
+                    Some(std::path::PathBuf::from(dir.trim_matches('"')))
+                });
+                let mut dicts: Vec<_> = libdir
+                    .and_then(|dir| std::fs::read_dir(dir).ok())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|entry| {
+                        let path = entry.ok()?.path();
+                        let name = path.file_stem()?.to_string_lossy().into_owned();
+                        (path.extension()? == "hash").then_some((name, Some(path)))
+                    })
+                    .collect();
+                dicts.sort();
+                dicts
+            }
+        };
+        Some(dicts)
+    }
+
+    // Arguments that make the program read and write UTF-8, regardless of the dictionary's encoding.
+    pub(super) fn encoding_args(&self) -> &'static [&'static str] {
+        match self {
+            Program::Aspell => &["--encoding=utf-8"],
+            Program::Hunspell => &["-i", "utf-8"],
+            // ispell has no notion of encodings beyond its dictionary's
+            Program::Ispell | Program::Libhunspell | Program::Builtin => &[],
+            // a custom command's arguments are entirely up to the user
+            Program::Custom(_) => &[],
+        }
+    }
+
+    // Arguments that stop the program suggesting corrections for each misspelling,
+    // which takes much longer than finding them.
+    fn detect_only_args(&self) -> &'static [&'static str] {
+        match self {
+            Program::Aspell => &["--dont-suggest"],
+            // ispell and hunspell always suggest corrections in pipe mode
+            _ => &[],
+        }
+    }
+
+    // Arguments that control whether run-together words are accepted.
+    pub(super) fn compound_args(&self, allow: bool) -> &'static [&'static str] {
+        match (self, allow) {
+            (Program::Aspell, true) => &["--run-together"],
+            (Program::Aspell, false) => &["--dont-run-together"],
+            (Program::Ispell, true) => &["-C"],
+            (Program::Ispell, false) => &["-B"],
+            // hunspell dictionaries define their own compounding rules in the affix file
+            (Program::Hunspell | Program::Libhunspell | Program::Builtin, _) => &[],
+            (Program::Custom(_), _) => &[],
+        }
+    }
+
+    // A function that starts a process for this program, or None if it is not installed.
+    pub(super) fn starter(&self, opts: &Options) -> Option<Start> {
+        let opts = opts.clone();
+        Some(match self {
+            Program::Custom(custom) => {
+                let custom = custom.clone();
+                Box::new(on_runtime(move || {
+                    Process::start(custom.command(&opts)?, custom.format, &opts)
+                }))
+            }
+            _ => {
+                let prog = self.clone();
+                let cmd = self.command()?;
+                Box::new(on_runtime(move || Process::new(&prog, &cmd, &opts)))
+            }
+        })
+    }
+}
+
+impl Process {
+    fn new(prog: &Program, cmd: &std::path::Path, opts: &Options) -> Result<Process> {
+        let command = {
+            let (prog, cmd, opts) = (prog.clone(), cmd.to_path_buf(), opts.clone());
+            move || {
+                let mut cmd = Command::new(&cmd);
+                cmd.arg("-a")
+                    .args(prog.encoding_args())
+                    .args(prog.compound_args(opts.allow_compounds))
+                    .args(opts.dictionary.iter().flat_map(|d| ["-d", d]));
+                cmd
+            }
+        };
+        let detect_only = prog.detect_only_args();
+        if detect_only.is_empty() {
+            return Self::start(command(), custom::Format::Ispell, opts);
+        }
+        let mut cmd = command();
+        cmd.args(detect_only);
+        match Self::start(cmd, custom::Format::Ispell, opts) {
+            Ok(mut proc) => {
+                let opts = opts.clone();
+                proc.with_suggestions = Some(Box::new(on_runtime(move || {
+                    Self::start(command(), custom::Format::Ispell, &opts)
+                })));
+                Ok(proc)
+            }
+            // Older versions may not support finding misspellings alone.
+            Err(err) => {
+                log::warn!("Cannot start {prog:?} with {detect_only:?}: {err}");
+                Self::start(command(), custom::Format::Ispell, opts)
+            }
+        }
+    }
+
+    // Start a checker that communicates in the given format.
+    pub(super) fn start(
+        mut cmd: Command,
+        format: custom::Format,
+        opts: &Options,
+    ) -> Result<Process> {
+        let mut proc = cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+
+        log::info!("Started {:?} with pid {}", cmd.get_program(), proc.id());
+        let stdout = proc.stdout.take().unwrap();
+        let output = Self::output(stdout)?;
+        let mut proc = Process {
+            child: proc,
+            output,
+            timeout: Duration::from_millis(opts.timeout_ms),
+            format,
+            broken: false,
+            version: None,
+            dictionary: opts.dictionary.clone(),
+            with_suggestions: None,
+            suggester: None,
+            session: vec![],
+        };
+        if format == custom::Format::Ispell {
+            // Read the initial version line, e.g.
+            // "@(#) International Ispell Version 3.1.20 (but really Aspell 0.60.8)".
+            let version = proc.read()?;
+            proc.version = Some(version.trim_start_matches("@(#) ").trim_end().into());
+            // Enable terse mode, so we don't need to read "*" for every ok word.
+            proc.write("!")?;
+        }
+        Ok(proc)
+    }
+
+    // Read the process's output on the runtime started on, if any, or else on a thread.
+    #[cfg(feature = "async")]
+    fn output(stdout: std::process::ChildStdout) -> Result<Output> {
+        use tokio::io::AsyncBufReadExt;
+        let Some(runtime) = runtime() else {
+            return Ok(Self::read_on_thread(stdout));
+        };
+        let stdout = tokio::process::ChildStdout::from_std(stdout)?;
+        let lines = tokio::io::BufReader::new(stdout).lines();
+        Ok(Output::Runtime(runtime, lines))
+    }
+
+    #[cfg(not(feature = "async"))]
+    fn output(stdout: std::process::ChildStdout) -> Result<Output> {
+        Ok(Self::read_on_thread(stdout))
+    }
+
+    fn read_on_thread(stdout: std::process::ChildStdout) -> Output {
+        let stdout = std::io::BufReader::new(stdout);
+        let (sender, output) = std::sync::mpsc::channel();
+        // The thread ends when the process closes its output.
+        std::thread::spawn(move || {
+            for line in stdout.lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Output::Thread(output)
+    }
+
+    // Write a line of input to the checker, or several separated by newlines.
+    fn write(&mut self, input: &str) -> Result<()> {
+        log::trace!("Writing '{input}'");
+        let stdin = self.child.stdin.as_mut().unwrap();
+        let written = stdin
+            .write_all(input.as_bytes())
+            .and_then(|_| stdin.write_all("\n".as_bytes()))
+            .and_then(|_| stdin.flush());
+        // Failing to write means the process has died, e.g. a broken pipe.
+        self.broken |= written.is_err();
+        Ok(written?)
+    }
+
+    // The line to send to check `text`.
+    fn request(&self, text: &str) -> Result<String> {
+        match self.format {
+            custom::Format::Json => custom::request(text),
+            // Prefix each line with ^ so it is never interpreted as a command,
+            // e.g. if it starts with * or #.
+            custom::Format::Ispell => Ok(format!("^{text}")),
+        }
+    }
+
+    // Read the results of checking `text`.
+    fn response(&mut self, text: &str) -> Result<Vec<Misspelling>> {
+        if self.format == custom::Format::Json {
+            return custom::parse(&self.read()?, text);
+        }
+        let mut misspellings = vec![];
+        // A blank line marks the end of the results for this line.
+        while let Some(m) = parse(&self.read()?, text)? {
+            misspellings.push(m);
+        }
+        Ok(misspellings)
+    }
+
+    // Read a line of output from the checker.
+    // If it doesn't respond in time, it is killed and a Timeout is returned.
+    fn read(&mut self) -> Result<String> {
+        let output = match self.output.next(self.timeout) {
+            Ok(Ok(output)) => output,
+            Ok(Err(err)) => {
+                self.broken = true;
+                Err(err)?
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                self.broken = true;
+                Err("Spell checker exited unexpectedly")?
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                self.broken = true;
+                log::warn!("Killing unresponsive process {}", self.child.id());
+                self.child.kill()?;
+                Err(Timeout(self.timeout))?
+            }
+        };
+        log::trace!("Read line '{output}'");
+        Ok(output)
+    }
+
+    // Note a word accepted or added this session, so the suggester, if there is one, doesn't
+    // suggest corrections for it. The suggester only accepts it, as this process saves the
+    // personal dictionary.
+    fn remember(&mut self, word: &str) -> Result<()> {
+        if self.with_suggestions.is_none() {
+            return Ok(());
+        }
+        self.session.push(word.to_string());
+        match &mut self.suggester {
+            Some(suggester) => suggester.accept(word),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Checker for Process {
+    fn check(&mut self, text: &str) -> Result<Vec<Misspelling>> {
+        self.write(&self.request(text)?)?;
+        self.response(text)
+    }
+
+    // Send every line in a single write, then read the results, rather than waiting for
+    // the results of each line before sending the next.
+    fn check_lines(&mut self, texts: &[String]) -> Result<Vec<Vec<Misspelling>>> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+        let requests: Vec<String> = texts
+            .iter()
+            .map(|text| self.request(text))
+            .collect::<Result<_>>()?;
+        self.write(&requests.join("\n"))?;
+        texts.iter().map(|text| self.response(text)).collect()
+    }
+
+    fn version(&self) -> Option<String> {
+        self.version.clone()
+    }
+
+    fn dictionaries(&self) -> Vec<String> {
+        self.dictionary.iter().cloned().collect()
+    }
+
+    fn accept(&mut self, word: &str) -> Result<()> {
+        if self.format == custom::Format::Ispell {
+            self.write(&format!("@{word}"))?;
+        }
+        self.remember(word)
+    }
+
+    fn add(&mut self, word: &str) -> Result<bool> {
+        if self.format != custom::Format::Ispell {
+            return Ok(false);
+        }
+        // Add the word to the personal dictionary, then save it.
+        self.write(&format!("*{word}"))?;
+        self.write("#")?;
+        self.remember(word)?;
+        Ok(true)
+    }
+
+    fn suggest(&mut self, word: &str) -> Result<Vec<String>> {
+        if let Some(start) = &self.with_suggestions {
+            if self.suggester.as_ref().is_none_or(|p| p.broken) {
+                let mut suggester = start()?;
+                for word in &self.session {
+                    suggester.accept(word)?;
+                }
+                self.suggester = Some(Box::new(suggester));
+            }
+            return self.suggester.as_mut().unwrap().suggest(word);
+        }
+        Ok(self
+            .check(word)?
+            .into_iter()
+            .find(|m| m.word == word)
+            .and_then(|m| m.fixes)
+            .unwrap_or_default())
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        log::info!("Closing process {}", self.child.id());
+        if let Err(err) = self.child.wait() {
+            log::error!("Failed to close process {}: {err}", self.child.id());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spell::custom::Custom;
+    use pretty_assertions::assert_eq;
+
+    // A checker speaking the ispell protocol that suggests "quick" for "kwick" until it is
+    // accepted.
+    fn start() -> Result<Process> {
+        let custom = Custom {
+            command: vec![
+                "sh".into(),
+                "-c".into(),
+                r#"echo '@(#) fake'; ok=no
+                while read -r l; do case "$l" in
+                    @kwick) ok=yes;;
+                    ^kwick) [ $ok = no ] && echo '& kwick 1 0: quick'; echo;;
+                    ^*) echo;;
+                esac; done"#
+                    .into(),
+            ],
+            format: custom::Format::Ispell,
+        };
+        Process::start(
+            custom.command(&Default::default())?,
+            custom::Format::Ispell,
+            &Default::default(),
+        )
+    }
+
+    #[test]
+    fn test_suggester_session() {
+        // Words accepted before the suggester starts are replayed to it.
+        let mut proc = start().unwrap();
+        proc.with_suggestions = Some(Box::new(start));
+        proc.accept("kwick").unwrap();
+        assert!(proc.suggest("kwick").unwrap().is_empty());
+
+        // Words accepted after it starts are forwarded to it.
+        let mut proc = start().unwrap();
+        proc.with_suggestions = Some(Box::new(start));
+        assert_eq!(proc.suggest("kwick").unwrap(), ["quick"]);
+        proc.accept("kwick").unwrap();
+        assert!(proc.suggest("kwick").unwrap().is_empty());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_read_on_runtime() {
+        // A checker that never answers "slow".
+        let custom = Custom {
+            command: vec![
+                "sh".into(),
+                "-c".into(),
+                r#"echo '@(#) fake'
+                while read -r l; do case "$l" in
+                    ^slow) sleep 10;;
+                    ^kwick) echo '& kwick 1 0: quick'; echo;;
+                    ^*) echo;;
+                esac; done"#
+                    .into(),
+            ],
+            format: custom::Format::Ispell,
+        };
+        let opts = Options {
+            timeout_ms: 200,
+            ..Default::default()
+        };
+        let start = Program::Custom(custom).starter(&opts).unwrap();
+        let mut proc = start().unwrap();
+        assert!(matches!(proc.output, Output::Runtime(..)));
+        assert_eq!(proc.version.as_deref(), Some("fake"));
+        assert_eq!(proc.suggest("kwick").unwrap(), ["quick"]);
+
+        // Processes restarted from another thread are still read on the runtime.
+        let started = std::thread::spawn(move || start().map_err(|err| err.to_string()));
+        let mut proc = started.join().unwrap().unwrap();
+        assert!(matches!(proc.output, Output::Runtime(..)));
+        let err = proc.check("slow").unwrap_err();
+        assert!(err.is::<Timeout>(), "{err}");
+        assert!(proc.broken);
+    }
+}
@@ -0,0 +1,372 @@
+// Ordering suggestions so the likeliest correction comes first. Checkers order suggestions
+// by how close they are to the misspelling, so a rare word may come before a common one
+// that is just as close, e.g. "quirk" before "quick" for "quikc", and they don't look at
+// the words around it, e.g. "price" before "piece" for "peice of cake". Each suggestion
+// is scored by how many edits it is from the misspelling, how common it is, how often it
+// is found next to the words either side of the misspelling, in general English and in
+// the document being checked, and whether it is a single slip of the finger away on the
+// user's keyboard.
+use super::keyboard::Layout;
+use super::Case;
+use crate::token::words;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// How much each edit between the misspelling and a suggestion counts against it, relative
+// to a tenfold difference in how common it is. The checker's own order isn't used, as it
+// differs between checkers and versions, and menus should be the same every time.
+const EDIT_WEIGHT: f64 = 0.5;
+
+// How much being found next to a neighbouring word counts in a suggestion's favour,
+// relative to a tenfold difference in how common it is.
+const PAIR_WEIGHT: f64 = 2.0;
+
+// How much being a single slip of the finger away counts in a suggestion's favour,
+// relative to a tenfold difference in how common it is.
+const SLIP_WEIGHT: f64 = 1.0;
+
+// Pairs of adjacent words, in lowercase.
+pub type Pairs = HashSet<(String, String)>;
+
+#[derive(Default)]
+pub struct Ranker {
+    // The rank of each known word by frequency, from 1 for the most common.
+    frequencies: HashMap<String, usize>,
+    // Pairs of words that are often found together, e.g. "piece of".
+    pairs: Pairs,
+    // The keyboard words are typed on, if known.
+    keyboard: Option<Layout>,
+}
+
+// What surrounds a misspelled word.
+#[derive(Default)]
+pub struct Context<'a> {
+    // The words either side of it on its line, in lowercase.
+    pub before: Option<String>,
+    pub after: Option<String>,
+    // The pairs of words in the rest of the document.
+    pub document: Option<&'a Pairs>,
+}
+
+impl<'a> Context<'a> {
+    // The context of the word at the character `offset` of `line`.
+    pub fn at(line: &str, offset: usize, document: Option<&'a Pairs>) -> Context<'a> {
+        let words = words(line);
+        let before = words.iter().rev().find(|(start, _)| *start < offset);
+        let after = words.iter().find(|(start, _)| *start > offset);
+        Context {
+            before: before.map(|(_, word)| word.to_lowercase()),
+            after: after.map(|(_, word)| word.to_lowercase()),
+            document,
+        }
+    }
+}
+
+// The pairs of adjacent words on each line of a document.
+pub fn pairs<'a>(lines: impl IntoIterator<Item = &'a str>) -> Pairs {
+    let mut pairs = Pairs::new();
+    for line in lines {
+        let words = words(line);
+        for pair in words.windows(2) {
+            pairs.insert((pair[0].1.to_lowercase(), pair[1].1.to_lowercase()));
+        }
+    }
+    pairs
+}
+
+impl Ranker {
+    // Rank by the words and pairs of words in the given files, or the bundled English
+    // lists if there are none and they were built in, and typing slips on `keyboard`.
+    pub fn new(
+        frequencies: Option<&Path>,
+        pairs: Option<&Path>,
+        keyboard: Option<Layout>,
+    ) -> Ranker {
+        let read = |path: Option<&Path>, bundled: &str| match path {
+            Some(path) => std::fs::read_to_string(path).unwrap_or_else(|err| {
+                log::warn!("Cannot read word frequencies {path:?}: {err}");
+                String::new()
+            }),
+            None => bundled.to_string(),
+        };
+        Ranker {
+            keyboard,
+            ..Ranker::parse(
+                &read(frequencies, bundled::FREQUENCIES),
+                &read(pairs, bundled::PAIRS),
+            )
+        }
+    }
+
+    // Read a list of words, most common first, and a list of common pairs of words, one
+    // per line. Anything after them, e.g. their counts, is ignored. Lines starting with "#"
+    // are comments.
+    fn parse(frequencies: &str, pairs: &str) -> Ranker {
+        let mut ranker = Ranker::default();
+        for words in entries(frequencies) {
+            let rank = ranker.frequencies.len() + 1;
+            ranker
+                .frequencies
+                .entry(words[0].to_lowercase())
+                .or_insert(rank);
+        }
+        for words in entries(pairs).filter(|words| words.len() >= 2) {
+            ranker
+                .pairs
+                .insert((words[0].to_lowercase(), words[1].to_lowercase()));
+        }
+        ranker
+    }
+
+    // Reorder the suggestions for a misspelled word, best first, dropping duplicates.
+    // Suggestions in the same case as the word come first, e.g. "Quick" before "quick" for
+    // "Quik", and ties are broken alphabetically, so the order is always the same.
+    pub fn rank(&self, word: &str, suggestions: &mut Vec<String>, context: &Context) {
+        let case = Case::of(word);
+        let mut scored: Vec<(bool, f64, String)> = vec![];
+        for fix in suggestions.iter() {
+            if !scored.iter().any(|(_, _, seen)| seen == fix) {
+                let score = self.score(word, fix, context);
+                scored.push((Case::of(fix) != case, score, fix.clone()));
+            }
+        }
+        scored.sort_by(|a, b| {
+            a.0.cmp(&b.0)
+                .then(b.1.total_cmp(&a.1))
+                .then_with(|| a.2.cmp(&b.2))
+        });
+        *suggestions = scored.into_iter().map(|(_, _, fix)| fix).collect();
+    }
+
+    // Higher is better. By Zipf's law, how common a word is falls with its rank, so the
+    // log of the rank is used, with unknown words ranked beyond the end of the list.
+    fn score(&self, word: &str, suggestion: &str, context: &Context) -> f64 {
+        let suggestion = suggestion.to_lowercase();
+        let mut score = -(edits(&word.to_lowercase(), &suggestion) as f64) * EDIT_WEIGHT;
+        if self
+            .keyboard
+            .is_some_and(|keyboard| keyboard.slip(word, &suggestion))
+        {
+            score += SLIP_WEIGHT;
+        }
+        if !self.frequencies.is_empty() {
+            let rank = self.frequencies.get(&suggestion).copied();
+            score -= (rank.unwrap_or(self.frequencies.len() * 10) as f64).log10();
+        }
+        let neighbours = [
+            context
+                .before
+                .clone()
+                .map(|before| (before, suggestion.clone())),
+            context
+                .after
+                .clone()
+                .map(|after| (suggestion.clone(), after)),
+        ];
+        for pair in neighbours.into_iter().flatten() {
+            if self.pairs.contains(&pair) {
+                score += PAIR_WEIGHT;
+            }
+            if context
+                .document
+                .is_some_and(|document| document.contains(&pair))
+            {
+                score += PAIR_WEIGHT;
+            }
+        }
+        score
+    }
+}
+
+// The number of characters to insert, delete, replace or swap with the next to turn `a` into
+// `b`, e.g. 1 for "teh" and "the".
+fn edits(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // The edits between the first i characters of `a` and the first j of `b`, for the
+    // current row i and the two before it.
+    let mut rows = [
+        vec![0; b.len() + 1],
+        vec![0; b.len() + 1],
+        (0..=b.len()).collect(),
+    ];
+    for i in 1..=a.len() {
+        rows.rotate_left(1);
+        rows[2][0] = i;
+        for j in 1..=b.len() {
+            let replace = rows[1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut best = replace.min(rows[1][j] + 1).min(rows[2][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[0][j - 2] + 1);
+            }
+            rows[2][j] = best;
+        }
+    }
+    rows[2][b.len()]
+}
+
+// The words on each line of a list that aren't comments.
+fn entries(text: &str) -> impl Iterator<Item = Vec<&str>> {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .filter(|words| !words.is_empty())
+}
+
+#[cfg(feature = "bundled-frequencies")]
+mod bundled {
+    pub const FREQUENCIES: &str = include_str!("frequencies.txt");
+    pub const PAIRS: &str = include_str!("pairs.txt");
+}
+
+#[cfg(not(feature = "bundled-frequencies"))]
+mod bundled {
+    pub const FREQUENCIES: &str = "";
+    pub const PAIRS: &str = "";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn ranked(ranker: &Ranker, suggestions: &[&str], context: &Context) -> Vec<String> {
+        ranked_for(ranker, "", suggestions, context)
+    }
+
+    fn ranked_for(
+        ranker: &Ranker,
+        word: &str,
+        suggestions: &[&str],
+        context: &Context,
+    ) -> Vec<String> {
+        let mut suggestions: Vec<String> = suggestions.iter().map(|s| s.to_string()).collect();
+        ranker.rank(word, &mut suggestions, context);
+        suggestions
+    }
+
+    #[test]
+    fn test_rank() {
+        let ranker = Ranker::parse("# words\nthe 100\nof 90\nquick 10\nQuiet\n", "");
+        let none = Context::default();
+        assert_eq!(
+            ranked(&ranker, &["quirk", "quick"], &none),
+            ["quick", "quirk"]
+        );
+        // Capitalized suggestions are as common as their lowercase forms.
+        assert_eq!(
+            ranked(&ranker, &["Quirk", "Quiet"], &none),
+            ["Quiet", "Quirk"]
+        );
+        // Words as close and as common are in alphabetical order, whatever the checker's order.
+        assert_eq!(
+            ranked(&ranker, &["quirk", "quack", "quark"], &none),
+            ["quack", "quark", "quirk"]
+        );
+        assert_eq!(
+            ranked(&ranker, &["quark", "quirk", "quack"], &none),
+            ["quack", "quark", "quirk"]
+        );
+        // Suggestions in the same case come first.
+        assert_eq!(
+            ranked_for(&ranker, "Quik", &["quick", "Quirk", "Quick"], &none),
+            ["Quick", "Quirk", "quick"]
+        );
+        assert_eq!(
+            ranked_for(&ranker, "quik", &["Quick", "quick", "quick"], &none),
+            ["quick", "Quick"]
+        );
+        // Without frequencies, the closest come first.
+        assert_eq!(
+            ranked_for(&Ranker::default(), "quikc", &["quirk", "quick"], &none),
+            ["quick", "quirk"]
+        );
+    }
+
+    #[test]
+    fn test_edits() {
+        assert_eq!(edits("teh", "the"), 1);
+        assert_eq!(edits("kwick", "quick"), 2);
+        assert_eq!(edits("jumpd", "jumped"), 1);
+        assert_eq!(edits("", "abc"), 3);
+        assert_eq!(edits("café", "cafe"), 1);
+    }
+
+    #[test]
+    fn test_context() {
+        let ranker = Ranker::parse("the\nprice\npiece\n", "# pairs\npiece of 10\nthe price\n");
+        let fixes = ["price", "piece"];
+        let line = "A peice of cake.";
+        let context = Context::at(line, 2, None);
+        assert_eq!(context.before.as_deref(), Some("a"));
+        assert_eq!(context.after.as_deref(), Some("of"));
+        assert_eq!(ranked(&ranker, &fixes, &context), ["piece", "price"]);
+        // Either neighbour counts.
+        let context = Context::at("What is the prise?", 12, None);
+        assert_eq!(
+            ranked(&ranker, &["prize", "price"], &context),
+            ["price", "prize"]
+        );
+        // Pairs are also taken from the document.
+        let document = pairs(["A slice of bread.", "Some slice of cake."]);
+        assert!(document.contains(&("slice".into(), "of".into())));
+        let context = Context::at("A sliec of pie.", 2, Some(&document));
+        assert_eq!(
+            ranked(&Ranker::default(), &["slick", "slice"], &context),
+            ["slice", "slick"]
+        );
+    }
+
+    #[test]
+    fn test_keyboard() {
+        let qwerty = Ranker {
+            keyboard: Some(Layout::Qwerty),
+            ..Default::default()
+        };
+        let none = Context::default();
+        assert_eq!(
+            ranked_for(&qwerty, "jumpd", &["jumped", "jumps"], &none),
+            ["jumps", "jumped"]
+        );
+        assert_eq!(
+            ranked_for(&qwerty, "teh", &["ten", "tea", "the"], &none),
+            ["ten", "the", "tea"]
+        );
+        // "d" and "s" aren't next to each other on a Colemak keyboard.
+        let colemak = Ranker {
+            keyboard: Some(Layout::Colemak),
+            ..Default::default()
+        };
+        assert_eq!(
+            ranked_for(&colemak, "jumpd", &["jumped", "jumps"], &none),
+            ["jumped", "jumps"]
+        );
+        assert_eq!(
+            ranked_for(&Ranker::default(), "jumpd", &["jumped", "jumps"], &none),
+            ["jumped", "jumps"]
+        );
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let ranker = Ranker::new(Some(Path::new("/no/such/frequencies.txt")), None, None);
+        assert!(ranker.frequencies.is_empty());
+    }
+
+    #[cfg(feature = "bundled-frequencies")]
+    #[test]
+    fn test_bundled() {
+        let ranker = Ranker::new(None, None, Some(Layout::Qwerty));
+        assert_eq!(ranker.frequencies["the"], 1);
+        let none = Context::default();
+        assert_eq!(
+            ranked(&ranker, &["quirk", "quick"], &none),
+            ["quick", "quirk"]
+        );
+        let context = Context::at("It's a peice of cake.", 7, None);
+        assert_eq!(
+            ranked(&ranker, &["price", "piece"], &context),
+            ["piece", "price"]
+        );
+    }
+}
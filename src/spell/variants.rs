@@ -0,0 +1,187 @@
+// American and British spellings of the same word, e.g. "color" and "colour", so a
+// document that mixes them can be made consistent. Both are usually accepted, e.g. by a
+// personal dictionary or an en_GB dictionary that allows "-ize", so this looks at the
+// words that passed the spell check.
+use super::DiagnosticData;
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const BUNDLED: &str = include_str!("variants.txt");
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Preferred {
+    // Whichever spelling most of the document uses.
+    Consistent,
+    Us,
+    Gb,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variant {
+    Us,
+    Gb,
+}
+
+impl Variant {
+    fn name(self) -> &'static str {
+        match self {
+            Variant::Us => "American",
+            Variant::Gb => "British",
+        }
+    }
+}
+
+// Each word with a variant, in lowercase, mapped to its variant and the other spelling.
+fn words() -> &'static HashMap<String, (Variant, String)> {
+    static WORDS: OnceLock<HashMap<String, (Variant, String)>> = OnceLock::new();
+    WORDS.get_or_init(|| {
+        let mut words = HashMap::new();
+        let lines = BUNDLED.lines().map(str::trim);
+        for line in lines.filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let (us, gb, endings) = match parts.as_slice() {
+                [us, gb] => (us, gb, ""),
+                [us, gb, endings] => (us, gb, *endings),
+                _ => continue,
+            };
+            for ending in endings.split(',') {
+                let (us, gb) = (format!("{us}{ending}"), format!("{gb}{ending}"));
+                words.insert(us.clone(), (Variant::Us, gb.clone()));
+                words.insert(gb, (Variant::Gb, us));
+            }
+        }
+        words
+    })
+}
+
+// Diagnostics for the words, given with their line and character, that don't use the
+// preferred spelling, each with the other spelling as a fix. When the document should be
+// consistent, the spelling used by more of its words is preferred, and nothing is
+// reported if both are used equally.
+pub fn check(preferred: Preferred, words: &[(u32, u32, &str)]) -> Vec<Diagnostic> {
+    let found: Vec<(u32, u32, &str, Variant, &String)> = words
+        .iter()
+        .filter_map(|&(line, character, word)| {
+            let (variant, other) = self::words().get(&word.to_lowercase())?;
+            Some((line, character, word, *variant, other))
+        })
+        .collect();
+    let preferred = match preferred {
+        Preferred::Us => Variant::Us,
+        Preferred::Gb => Variant::Gb,
+        Preferred::Consistent => {
+            let us = found.iter().filter(|f| f.3 == Variant::Us).count();
+            match us.cmp(&(found.len() - us)) {
+                std::cmp::Ordering::Greater => Variant::Us,
+                std::cmp::Ordering::Less => Variant::Gb,
+                std::cmp::Ordering::Equal => return vec![],
+            }
+        }
+    };
+    found
+        .into_iter()
+        .filter(|f| f.3 != preferred)
+        .map(|(line, character, word, variant, other)| {
+            let end = character + word.chars().count() as u32;
+            let range = Range::new(Position::new(line, character), Position::new(line, end));
+            let data = DiagnosticData {
+                original: word.to_string(),
+                fixes: super::match_case(word, vec![other.clone()]),
+                range,
+            };
+            Diagnostic {
+                range,
+                severity: Some(DiagnosticSeverity::INFORMATION),
+                code: Some(NumberOrString::String("variant".into())),
+                source: Some("spelgud".into()),
+                message: format!(
+                    "{word} is {} spelling, but this document uses {} spelling",
+                    variant.name(),
+                    preferred.name()
+                ),
+                data: serde_json::to_value(data).ok(),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    // The text and fix of each diagnostic.
+    fn found(preferred: Preferred, text: &str) -> Vec<(String, String)> {
+        let words: Vec<(u32, u32, &str)> = crate::token::words(text)
+            .into_iter()
+            .map(|(start, word)| (0, start as u32, word))
+            .collect();
+        check(preferred, &words)
+            .into_iter()
+            .map(|diag| {
+                let data: DiagnosticData = serde_json::from_value(diag.data.unwrap()).unwrap();
+                (data.original, data.fixes[0].clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_words() {
+        let words = words();
+        assert_eq!(words["colour"], (Variant::Gb, "color".into()));
+        assert_eq!(words["colors"], (Variant::Us, "colours".into()));
+        assert_eq!(
+            words["organisations"],
+            (Variant::Gb, "organizations".into())
+        );
+        assert_eq!(words["centred"], (Variant::Gb, "centered".into()));
+        assert!(!words.contains_key("centreed"));
+        assert!(!words.contains_key("humorous"));
+    }
+
+    #[test]
+    fn test_consistent() {
+        let text = "The colour of the centre, organised by colour. Color it gray.";
+        assert_eq!(
+            found(Preferred::Consistent, text),
+            [
+                ("Color".into(), "Colour".into()),
+                ("gray".into(), "grey".into())
+            ]
+        );
+        assert_eq!(found(Preferred::Consistent, "color and colour"), []);
+        assert_eq!(found(Preferred::Consistent, "colour and centre"), []);
+    }
+
+    #[test]
+    fn test_preferred() {
+        let text = "The colour of the centre. COLOR it.";
+        assert_eq!(
+            found(Preferred::Us, text),
+            [
+                ("colour".into(), "color".into()),
+                ("centre".into(), "center".into())
+            ]
+        );
+        assert_eq!(
+            found(Preferred::Gb, text),
+            [("COLOR".into(), "COLOUR".into())]
+        );
+    }
+
+    #[test]
+    fn test_message() {
+        let diags = check(Preferred::Us, &[(2, 4, "Colour")]);
+        assert_eq!(
+            diags[0].message,
+            "Colour is British spelling, but this document uses American spelling"
+        );
+        assert_eq!(
+            diags[0].range,
+            Range::new(Position::new(2, 4), Position::new(2, 10))
+        );
+    }
+}
@@ -0,0 +1,327 @@
+// Style rules configured by the user, such as preferring "use" to "utilize", in the manner
+// of Vale (https://vale.sh). Each rule finds words or a regular expression in the checked
+// text, and is reported alongside spelling errors with its message and replacements.
+use crate::spell::DiagnosticData;
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position};
+use regex::{Captures, Regex, RegexBuilder};
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    #[default]
+    Warning,
+    Information,
+    Hint,
+}
+
+impl From<Severity> for DiagnosticSeverity {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+            Severity::Information => DiagnosticSeverity::INFORMATION,
+            Severity::Hint => DiagnosticSeverity::HINT,
+        }
+    }
+}
+
+// A rule as configured.
+#[derive(serde::Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Options {
+    // Shown as the code of the rule's diagnostics. Defaults to `text` or `regex`.
+    pub name: Option<String>,
+    // Words to find, matched as whole words whatever their case, e.g. "utilize".
+    pub text: Option<String>,
+    // A regular expression to find instead, e.g. "\\butiliz(e|es|ed|ing)\\b".
+    pub regex: Option<String>,
+    // Whether the case of the text matters. By default it matters for `regex` only.
+    pub ignore_case: Option<bool>,
+    // Shown for each match, with "$0" replaced by the text matched and "$1" to "$9" by
+    // that of the regex's groups.
+    pub message: String,
+    // What to offer to replace each match with, with groups replaced as in `message`.
+    pub replace: Vec<String>,
+    // Whether to give replacements the case of the text they replace, e.g. "Use" for
+    // "Utilize". By default, they do if the case of the text doesn't matter.
+    pub match_case: Option<bool>,
+    pub severity: Severity,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(try_from = "Options")]
+pub struct Rule {
+    name: String,
+    regex: Regex,
+    // Whether to give replacements the case of the text they replace.
+    match_case: bool,
+    message: String,
+    replace: Vec<String>,
+    severity: Severity,
+}
+
+impl TryFrom<Options> for Rule {
+    type Error = String;
+
+    fn try_from(opts: Options) -> Result<Rule, String> {
+        let (pattern, ignore_case) = match (opts.text, opts.regex) {
+            (Some(text), None) => (
+                format!(r"\b{}\b", regex::escape(&text)),
+                opts.ignore_case.unwrap_or(true),
+            ),
+            (None, Some(regex)) => (regex, opts.ignore_case.unwrap_or(false)),
+            _ => Err("A style rule needs either text or regex")?,
+        };
+        let name = opts.name.unwrap_or_else(|| pattern.clone());
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map_err(|err| format!("Invalid regex in style rule {name}: {err}"))?;
+        if opts.message.is_empty() {
+            Err(format!("Style rule {name} has no message"))?;
+        }
+        Ok(Rule {
+            name,
+            regex,
+            match_case: opts.match_case.unwrap_or(ignore_case),
+            message: opts.message,
+            replace: opts.replace,
+            severity: opts.severity,
+        })
+    }
+}
+
+// The matches of each rule in a line of checked text that starts at the given line and
+// character of the document.
+pub fn check(rules: &[Rule], line: u32, character: u32, text: &str) -> Vec<Diagnostic> {
+    if rules.is_empty() {
+        return vec![];
+    }
+    // Matches are found at byte offsets, but positions are in characters.
+    let position = |offset: usize| {
+        let offset = text[..offset].chars().count();
+        Position::new(line, character + offset as u32)
+    };
+    let mut diags = vec![];
+    for rule in rules {
+        for captures in rule.regex.captures_iter(text) {
+            let found = captures.get(0).expect("a match has a whole group");
+            if found.is_empty() {
+                continue;
+            }
+            let original = found.as_str().to_string();
+            let mut fixes = rule
+                .replace
+                .iter()
+                .map(|replace| expand(replace, &captures))
+                .collect();
+            if rule.match_case {
+                fixes = crate::spell::match_case(&original, fixes);
+            }
+            // The text may already be what the rule wants, e.g. "GitHub" for a rule
+            // replacing "github" in any case.
+            if fixes.contains(&original) {
+                continue;
+            }
+            let range = lsp_types::Range::new(position(found.start()), position(found.end()));
+            let data = DiagnosticData {
+                original,
+                fixes,
+                range,
+            };
+            diags.push(Diagnostic {
+                range,
+                severity: Some(rule.severity.into()),
+                code: Some(NumberOrString::String(rule.name.clone())),
+                source: Some("style".into()),
+                message: expand(&rule.message, &captures),
+                data: serde_json::to_value(data).ok(),
+                ..Default::default()
+            });
+        }
+    }
+    diags
+}
+
+// Replace "$0" to "$9" with the text matched by the whole pattern and its groups.
+fn expand(template: &str, captures: &Captures) -> String {
+    let mut expanded = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        let group = chars.peek().and_then(|d| d.to_digit(10));
+        match (c, group) {
+            ('$', Some(group)) => {
+                chars.next();
+                if let Some(group) = captures.get(group as usize) {
+                    expanded.push_str(group.as_str());
+                }
+            }
+            (c, _) => expanded.push(c),
+        }
+    }
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn rule(opts: serde_json::Value) -> Rule {
+        serde_json::from_value(opts).unwrap()
+    }
+
+    // The text, message and fixes of each diagnostic.
+    fn found(diags: &[Diagnostic]) -> Vec<(u32, u32, String, Vec<String>)> {
+        diags
+            .iter()
+            .map(|diag| {
+                let data: DiagnosticData =
+                    serde_json::from_value(diag.data.clone().unwrap()).unwrap();
+                (
+                    diag.range.start.character,
+                    diag.range.end.character,
+                    diag.message.clone(),
+                    data.fixes,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_text() {
+        let rules = [rule(serde_json::json!({
+            "text": "utilize",
+            "message": "Prefer 'use' to '$0'",
+            "replace": ["use"],
+        }))];
+        let diags = check(&rules, 2, 4, "Utilize it; don't reutilize or utilize.");
+        assert_eq!(
+            found(&diags),
+            [
+                (
+                    4,
+                    11,
+                    "Prefer 'use' to 'Utilize'".into(),
+                    vec!["Use".into()]
+                ),
+                (
+                    35,
+                    42,
+                    "Prefer 'use' to 'utilize'".into(),
+                    vec!["use".into()]
+                ),
+            ]
+        );
+        assert_eq!(diags[0].range.start.line, 2);
+        assert_eq!(
+            diags[0].code,
+            Some(NumberOrString::String(r"\butilize\b".into()))
+        );
+        assert_eq!(diags[0].severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(diags[0].source.as_deref(), Some("style"));
+    }
+
+    #[test]
+    fn test_regex() {
+        let rules = [
+            rule(serde_json::json!({
+                "name": "Utilize",
+                "regex": r"\butiliz(e|es|ed|ing)\b",
+                "message": "Use 'us$1'",
+                "replace": ["us$1"],
+                "severity": "hint",
+            })),
+            // Regexes match case by default, so this misses "Utilizing".
+            rule(serde_json::json!({
+                "regex": "utilizing",
+                "message": "Use 'using'",
+            })),
+        ];
+        let diags = check(&rules, 0, 0, "We utilized it. Utilizing it.");
+        assert_eq!(
+            found(&diags),
+            [(3, 11, "Use 'used'".into(), vec!["used".into()])]
+        );
+        assert_eq!(
+            diags[0].code,
+            Some(NumberOrString::String("Utilize".into()))
+        );
+        assert_eq!(diags[0].severity, Some(DiagnosticSeverity::HINT));
+    }
+
+    #[test]
+    fn test_nested_repetition() {
+        // Matching takes linear time, so patterns that would backtrack on every position of
+        // a long line don't hang the server.
+        let rules = [rule(serde_json::json!({
+            "regex": "(a+)+$",
+            "message": "Too many a's",
+        }))];
+        let text = format!("{}b", "a".repeat(10_000));
+        assert!(check(&rules, 0, 0, &text).is_empty());
+    }
+
+    #[test]
+    fn test_multibyte() {
+        // Positions are in characters.
+        let rules = [rule(serde_json::json!({
+            "text": "utilize",
+            "message": "Use 'use'",
+        }))];
+        let diags = check(&rules, 0, 0, "Café—utilize");
+        assert_eq!(found(&diags)[0].0, 5);
+    }
+
+    #[test]
+    fn test_case() {
+        let rules = [rule(serde_json::json!({
+            "text": "github",
+            "message": "Write '$0' as GitHub",
+            "replace": ["GitHub"],
+            "match_case": false,
+        }))];
+        let diags = check(&rules, 0, 0, "On Github, GITHUB or GitHub");
+        assert_eq!(
+            found(&diags),
+            [
+                (
+                    3,
+                    9,
+                    "Write 'Github' as GitHub".into(),
+                    vec!["GitHub".into()]
+                ),
+                (
+                    11,
+                    17,
+                    "Write 'GITHUB' as GitHub".into(),
+                    vec!["GitHub".into()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        let parse = |opts| {
+            serde_json::from_value::<Rule>(opts)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            parse(serde_json::json!({"message": "No pattern"})),
+            "A style rule needs either text or regex"
+        );
+        let err = parse(serde_json::json!({"name": "Bad", "regex": "(a", "message": "Oops"}));
+        assert!(
+            err.starts_with("Invalid regex in style rule Bad: "),
+            "{err}"
+        );
+        assert!(err.contains("unclosed group"), "{err}");
+        assert_eq!(
+            parse(serde_json::json!({"text": "a"})),
+            "Style rule \\ba\\b has no message"
+        );
+    }
+}
@@ -0,0 +1,603 @@
+// Finds the comments and string literals in source code, so only those are spell checked.
+// Languages with a tree-sitter grammar are parsed, so e.g. a Rust lifetime or a "#" in a
+// Python string isn't mistaken for a delimiter. Other languages, including those configured
+// by the user, go through a small lexer that knows only how comments and strings are delimited.
+// The grammars are C, so WebAssembly builds leave them out and use the lexer for every language.
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Comment,
+    String,
+}
+
+// Which regions of source code are spell checked.
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    // Check both comments and strings.
+    #[default]
+    All,
+    Comments,
+    // Check only strings, e.g. for UI code where user-facing text matters most.
+    Strings,
+}
+
+impl Mode {
+    pub fn includes(&self, kind: Kind) -> bool {
+        match self {
+            Mode::All => true,
+            Mode::Comments => kind == Kind::Comment,
+            Mode::Strings => kind == Kind::String,
+        }
+    }
+}
+
+// A comment or string, where `range` covers the content between the delimiters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub kind: Kind,
+    pub range: Range<usize>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Syntax {
+    // Prefixes that start a comment running to the end of the line, e.g. "//".
+    pub line_comments: Vec<String>,
+    // Start and end delimiters of block comments, e.g. ("/*", "*/").
+    pub block_comments: Vec<(String, String)>,
+    // Start and end delimiters of strings in which a backslash escapes the next character.
+    pub strings: Vec<(String, String)>,
+    // Start and end delimiters of strings without escapes.
+    pub raw_strings: Vec<(String, String)>,
+    // The grammar to parse the language with, if it has one. The delimiters are still used to
+    // strip comment markers from the comments it finds.
+    #[serde(skip)]
+    pub grammar: Option<Grammar>,
+}
+
+// The languages that are parsed with tree-sitter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grammar {
+    Rust,
+    Python,
+    Go,
+    // Also used for JSX.
+    JavaScript,
+    TypeScript,
+    Tsx,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl Grammar {
+    fn language(self) -> tree_sitter::Language {
+        match self {
+            Grammar::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Grammar::Python => tree_sitter_python::LANGUAGE.into(),
+            Grammar::Go => tree_sitter_go::LANGUAGE.into(),
+            Grammar::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Grammar::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Grammar::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+        }
+    }
+}
+
+// Whether a syntax node within a string is code rather than text, e.g. the `{name}` in a Python
+// f-string or the `${name}` in a JavaScript template string.
+#[cfg(not(target_family = "wasm"))]
+fn is_code(node: &str) -> bool {
+    matches!(node, "interpolation" | "template_substitution")
+}
+
+// The kind of region a syntax node is, if any, across all grammars.
+#[cfg(not(target_family = "wasm"))]
+fn node_kind(node: &str) -> Option<Kind> {
+    match node {
+        "comment" | "line_comment" | "block_comment" => Some(Kind::Comment),
+        "string"
+        | "string_literal"
+        | "raw_string_literal"
+        | "interpreted_string_literal"
+        | "template_string"
+        | "jsx_text" => Some(Kind::String),
+        _ => None,
+    }
+}
+
+// A language configured by the user. A non-empty syntax is used in place of the built-in syntax
+// with the same id, or adds support for a language that has no built-in syntax.
+#[derive(serde::Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Language {
+    // File extensions that identify the language if the client does not provide a language id.
+    pub extensions: Vec<String>,
+    pub check: Mode,
+    // Also check the words that make up identifiers, reporting misspellings as hints.
+    pub identifiers: bool,
+    #[serde(flatten)]
+    pub syntax: Syntax,
+}
+
+fn owned(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(start, end)| (start.to_string(), end.to_string()))
+        .collect()
+}
+
+impl Syntax {
+    fn new(
+        line_comments: &[&str],
+        block_comments: &[(&str, &str)],
+        strings: &[(&str, &str)],
+        raw_strings: &[(&str, &str)],
+    ) -> Syntax {
+        Syntax {
+            line_comments: line_comments.iter().map(|s| s.to_string()).collect(),
+            block_comments: owned(block_comments),
+            strings: owned(strings),
+            raw_strings: owned(raw_strings),
+            grammar: None,
+        }
+    }
+
+    fn with_grammar(self, grammar: Grammar) -> Syntax {
+        Syntax {
+            grammar: Some(grammar),
+            ..self
+        }
+    }
+
+    // The built-in syntax for a language, identified by its LSP language id.
+    pub fn builtin(language_id: &str) -> Option<Syntax> {
+        let c_comments: (&[&str], &[(&str, &str)]) = (&["//"], &[("/*", "*/")]);
+        Some(match language_id {
+            "rust" => Syntax::new(
+                c_comments.0,
+                c_comments.1,
+                &[("\"", "\"")],
+                &[("r#\"", "\"#"), ("r##\"", "\"##")],
+            )
+            .with_grammar(Grammar::Rust),
+            "go" => Syntax::new(
+                c_comments.0,
+                c_comments.1,
+                &[("\"", "\""), ("'", "'")],
+                &[("`", "`")],
+            )
+            .with_grammar(Grammar::Go),
+            "javascript" | "javascriptreact" | "typescript" | "typescriptreact" => Syntax::new(
+                c_comments.0,
+                c_comments.1,
+                &[("\"", "\""), ("'", "'"), ("`", "`")],
+                &[],
+            )
+            .with_grammar(match language_id {
+                "typescript" => Grammar::TypeScript,
+                "typescriptreact" => Grammar::Tsx,
+                _ => Grammar::JavaScript,
+            }),
+            "c" | "cpp" | "csharp" | "java" => {
+                Syntax::new(c_comments.0, c_comments.1, &[("\"", "\"")], &[])
+            }
+            "python" => Syntax::new(
+                &["#"],
+                &[],
+                &[
+                    ("\"\"\"", "\"\"\""),
+                    ("'''", "'''"),
+                    ("\"", "\""),
+                    ("'", "'"),
+                ],
+                &[],
+            )
+            .with_grammar(Grammar::Python),
+            "shellscript" => Syntax::new(&["#"], &[], &[("\"", "\"")], &[("'", "'")]),
+            _ => return None,
+        })
+    }
+
+    // Map a file extension to the language id used by `builtin`.
+    pub fn language_id(extension: &str) -> Option<&'static str> {
+        Some(match extension {
+            "rs" => "rust",
+            "go" => "go",
+            "js" | "mjs" | "cjs" => "javascript",
+            "jsx" => "javascriptreact",
+            "ts" | "mts" | "cts" => "typescript",
+            "tsx" => "typescriptreact",
+            "c" | "h" => "c",
+            "cc" | "cpp" | "cxx" | "hh" | "hpp" | "hxx" => "cpp",
+            "cs" => "csharp",
+            "java" => "java",
+            "py" | "pyi" => "python",
+            "sh" | "bash" => "shellscript",
+            _ => return None,
+        })
+    }
+
+    // Find all comments and strings in the text.
+    pub fn regions(&self, text: &str) -> Vec<Region> {
+        match self.grammar.and_then(|grammar| self.parse(grammar, text)) {
+            Some(regions) => regions,
+            None => self.lex(text),
+        }
+    }
+
+    // Find the comments and strings in the syntax tree of the text, in order.
+    // Nodes within them, e.g. the expressions in a template string, are not searched, and
+    // a string is split into a region for each run of text between them.
+    #[cfg(not(target_family = "wasm"))]
+    fn parse(&self, grammar: Grammar, text: &str) -> Option<Vec<Region>> {
+        let mut parser = tree_sitter::Parser::new();
+        if let Err(err) = parser.set_language(&grammar.language()) {
+            log::warn!("Cannot load the {grammar:?} grammar: {err}");
+            return None;
+        }
+        let tree = parser.parse(text, None)?;
+        let mut regions = vec![];
+        let mut cursor = tree.walk();
+        loop {
+            let node = cursor.node();
+            // Keywords are unnamed nodes, e.g. TypeScript's `string` type.
+            let kind = node.is_named().then(|| node_kind(node.kind())).flatten();
+            if let Some(kind) = kind {
+                let ranges = self.content(kind, node, text).unwrap_or_default();
+                regions.extend(ranges.into_iter().map(|range| Region { kind, range }));
+            }
+            if kind.is_none() && cursor.goto_first_child() {
+                continue;
+            }
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return Some(regions);
+                }
+            }
+        }
+    }
+
+    #[cfg(target_family = "wasm")]
+    fn parse(&self, _grammar: Grammar, _text: &str) -> Option<Vec<Region>> {
+        None
+    }
+
+    // The non-empty ranges of a comment or string node between its delimiters, leaving out any
+    // code within a string.
+    #[cfg(not(target_family = "wasm"))]
+    fn content(
+        &self,
+        kind: Kind,
+        node: tree_sitter::Node,
+        text: &str,
+    ) -> Option<Vec<Range<usize>>> {
+        let range = match kind {
+            Kind::String => match node.child_count() {
+                // Text without delimiters, e.g. in JSX.
+                0 => node.byte_range(),
+                // Only the content, e.g. of a Rust raw string, whose delimiters aren't nodes.
+                1 => node.child(0)?.byte_range(),
+                // The content between the opening and closing delimiters, e.g. `"` or `'''`.
+                n => node.child(0)?.end_byte()..node.child(n - 1)?.start_byte(),
+            },
+            // Comments are a single token, so the delimiters are found in their text.
+            Kind::Comment => {
+                let range = node.byte_range();
+                let comment = text[range.clone()].trim_end_matches(['\r', '\n']);
+                let (start, end) = match self.opener(comment) {
+                    Some((_, open, None, _)) => (open.len(), comment.len()),
+                    Some((_, open, Some(close), _))
+                        if comment.len() >= open.len() + close.len() =>
+                    {
+                        let end =
+                            comment.len() - close.len() * usize::from(comment.ends_with(close));
+                        (open.len(), end)
+                    }
+                    _ => (0, comment.len()),
+                };
+                range.start + start..range.start + end
+            }
+        };
+        let mut ranges = vec![];
+        let mut start = range.start;
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            if is_code(child.kind()) {
+                ranges.push(start..child.start_byte());
+                start = child.end_byte();
+            }
+        }
+        ranges.push(start..range.end);
+        ranges.retain(|range| range.end > range.start);
+        Some(ranges)
+    }
+
+    // Find the comments and strings by their delimiters.
+    fn lex(&self, text: &str) -> Vec<Region> {
+        let mut regions = vec![];
+        let mut i = 0;
+        while i < text.len() {
+            let Some((kind, open, close, escapes)) = self.opener(&text[i..]) else {
+                i += text[i..].chars().next().map_or(1, char::len_utf8);
+                continue;
+            };
+            let start = i + open.len();
+            let (end, next) = match close {
+                None => {
+                    let end = text[start..].find('\n').map_or(text.len(), |n| start + n);
+                    (end, end)
+                }
+                Some(close) => {
+                    // A single quote is often an apostrophe or a lifetime rather than the start
+                    // of a string, so don't let single-quoted strings run past the end of a line.
+                    let single_line = close == "'";
+                    match find_close(&text[start..], close, escapes, single_line) {
+                        Some(n) => (start + n, start + n + close.len()),
+                        None if single_line => (start, start),
+                        None => (text.len(), text.len()),
+                    }
+                }
+            };
+            if end > start {
+                regions.push(Region {
+                    kind,
+                    range: start..end,
+                });
+            }
+            i = next;
+        }
+        regions
+    }
+
+    // Find the words that make up identifiers outside of comments and strings.
+    // Identifiers are split on underscores, digits and camelCase boundaries.
+    // Parts shorter than four letters are skipped, as these are usually
+    // keywords or abbreviations (fn, len, ctx) that would only produce noise.
+    pub fn identifiers(&self, text: &str) -> Vec<Range<usize>> {
+        let mut code = vec![];
+        let mut start = 0;
+        for region in self.regions(text) {
+            code.push(start..region.range.start);
+            start = region.range.end;
+        }
+        code.push(start..text.len());
+
+        let mut words = vec![];
+        for range in code {
+            let code = &text[range.clone()];
+            let mut chars = code.char_indices().peekable();
+            while let Some((i, c)) = chars.next() {
+                if !(c.is_alphabetic() || c == '_') {
+                    continue;
+                }
+                let mut end = i + c.len_utf8();
+                while let Some((j, c)) = chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_') {
+                    end = j + c.len_utf8();
+                }
+                let ident = &code[i..end];
+                words.extend(
+                    split_identifier(ident)
+                        .into_iter()
+                        .filter(|r| ident[r.clone()].chars().count() >= 4)
+                        .map(|r| range.start + i + r.start..range.start + i + r.end),
+                );
+            }
+        }
+        words
+    }
+
+    // Return the longest comment or string delimiter that starts the text.
+    fn opener(&self, text: &str) -> Option<(Kind, &str, Option<&str>, bool)> {
+        let line = self
+            .line_comments
+            .iter()
+            .map(|s| (Kind::Comment, s.as_str(), None, false));
+        line.chain(delimited(&self.block_comments, Kind::Comment, false))
+            .chain(delimited(&self.strings, Kind::String, true))
+            .chain(delimited(&self.raw_strings, Kind::String, false))
+            .filter(|(_, open, _, _)| !open.is_empty() && text.starts_with(open))
+            .max_by_key(|(_, open, _, _)| open.len())
+    }
+}
+
+fn delimited(
+    pairs: &[(String, String)],
+    kind: Kind,
+    escapes: bool,
+) -> impl Iterator<Item = (Kind, &str, Option<&str>, bool)> {
+    pairs
+        .iter()
+        .map(move |(s, e)| (kind, s.as_str(), Some(e.as_str()), escapes))
+}
+
+// Split an identifier like `parseHTTPResponse_v2` into words: parse, HTTP, Response, v.
+pub fn split_identifier(ident: &str) -> Vec<Range<usize>> {
+    let mut words = vec![];
+    let mut start = None;
+    let chars: Vec<(usize, char)> = ident.char_indices().collect();
+    for (n, &(i, c)) in chars.iter().enumerate() {
+        if !c.is_alphabetic() {
+            if let Some(s) = start.take() {
+                words.push(s..i);
+            }
+            continue;
+        }
+        let Some(s) = start else {
+            start = Some(i);
+            continue;
+        };
+        let prev = chars[n - 1].1;
+        let next = chars.get(n + 1).map(|&(_, c)| c);
+        // A boundary is either lower followed by upper (camelCase),
+        // or the last upper in a run of uppers followed by lower (HTTPResponse).
+        let boundary = c.is_uppercase()
+            && (prev.is_lowercase()
+                || (prev.is_uppercase() && next.is_some_and(char::is_lowercase)));
+        if boundary {
+            words.push(s..i);
+            start = Some(i);
+        }
+    }
+    if let Some(s) = start {
+        words.push(s..ident.len());
+    }
+    words
+}
+
+// Find the offset of the closing delimiter.
+fn find_close(text: &str, close: &str, escapes: bool, single_line: bool) -> Option<usize> {
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if text[i..].starts_with(close) {
+            return Some(i);
+        }
+        match c {
+            '\\' if escapes => {
+                chars.next();
+            }
+            '\n' if single_line => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn regions(language_id: &str, text: &str) -> Vec<(Kind, String)> {
+        Syntax::builtin(language_id)
+            .unwrap()
+            .regions(text)
+            .into_iter()
+            .map(|r| (r.kind, text[r.range].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_rust() {
+        assert_eq!(
+            regions(
+                "rust",
+                "// A coment\nfn f<'a>(x: &'a str) -> &str { /* blok */ \"a \\\"strng\\\"\" }\nconst R: &str = r#\"raw \"qoute\"\"#;"
+            ),
+            vec![
+                (Kind::Comment, " A coment".into()),
+                (Kind::Comment, " blok ".into()),
+                (Kind::String, "a \\\"strng\\\"".into()),
+                (Kind::String, "raw \"qoute\"".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_python() {
+        assert_eq!(
+            regions(
+                "python",
+                "x = 'it\\'s'  # a \"coment\"\n\"\"\"Doc\nstrnig\"\"\"\ny = \"#notcomment\""
+            ),
+            vec![
+                (Kind::String, "it\\'s".into()),
+                (Kind::Comment, " a \"coment\"".into()),
+                (Kind::String, "Doc\nstrnig".into()),
+                (Kind::String, "#notcomment".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_go_and_javascript() {
+        assert_eq!(
+            regions("go", "s := `raw \\ strng` // done"),
+            vec![
+                (Kind::String, "raw \\ strng".into()),
+                (Kind::Comment, " done".into()),
+            ]
+        );
+        // The expressions in a template string are left out.
+        assert_eq!(
+            regions(
+                "javascript",
+                "let s = `templte\n${x} texxt ${y}`; /* multi\nline */"
+            ),
+            vec![
+                (Kind::String, "templte\n".into()),
+                (Kind::String, " texxt ".into()),
+                (Kind::Comment, " multi\nline ".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_identifier() {
+        fn split(s: &str) -> Vec<&str> {
+            split_identifier(s).into_iter().map(|r| &s[r]).collect()
+        }
+        assert_eq!(split("recieve_handler"), vec!["recieve", "handler"]);
+        assert_eq!(
+            split("parseHTTPResponse2x"),
+            vec!["parse", "HTTP", "Response", "x"]
+        );
+        assert_eq!(split("__init__"), vec!["init"]);
+        assert_eq!(split("ÜberFlag"), vec!["Über", "Flag"]);
+        assert_eq!(split("ALL_CAPS"), vec!["ALL", "CAPS"]);
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let text =
+            "fn recieve_handler(msg: &str) { // not_checked\n let parseResult = \"skip_me\"; }";
+        let words: Vec<&str> = Syntax::builtin("rust")
+            .unwrap()
+            .identifiers(text)
+            .into_iter()
+            .map(|r| &text[r])
+            .collect();
+        assert_eq!(words, vec!["recieve", "handler", "parse", "Result"]);
+    }
+
+    #[test]
+    fn test_grammar() {
+        // Rust block comments nest, and a character literal isn't a string.
+        assert_eq!(
+            regions("rust", "fn f() { /* a /* b */ c */ let c = '\"'; }"),
+            vec![(Kind::Comment, " a /* b */ c ".into())]
+        );
+        assert_eq!(
+            regions("python", "x = f'hi {name!r} ther' 'mor'"),
+            vec![
+                (Kind::String, "hi ".into()),
+                (Kind::String, " ther".into()),
+                (Kind::String, "mor".into()),
+            ]
+        );
+        // A type named string is not a string.
+        assert_eq!(
+            regions("typescript", "let s: string = 'x'; /** doc */"),
+            vec![(Kind::String, "x".into()), (Kind::Comment, "* doc ".into())]
+        );
+        assert_eq!(regions("typescript", "`${a}${b}`"), vec![]);
+        assert_eq!(
+            regions("javascriptreact", "<p title=\"titel\">Helo</p>"),
+            vec![
+                (Kind::String, "titel".into()),
+                (Kind::String, "Helo".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unterminated() {
+        // Without a grammar, an unterminated comment runs to the end of the file. With one, it
+        // isn't checked until it is closed.
+        assert_eq!(
+            regions("c", "/* open"),
+            vec![(Kind::Comment, " open".into())]
+        );
+        assert_eq!(regions("rust", "/* open"), vec![]);
+        assert_eq!(regions("python", "don't\nstop"), vec![]);
+    }
+}
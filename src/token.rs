@@ -0,0 +1,245 @@
+// Splitting text into words before it is handed to the spell checker.
+// Word boundaries follow the default rules of UAX #29 (Unicode text segmentation), as
+// implemented by the unicode-segmentation crate, so words next to smart quotes, dashes or
+// non-breaking spaces are split off cleanly.
+// Apostrophes, whether ASCII (') or typographic (’), are kept when they join letters,
+// so "don't", "it’s" and "O’Brien" are each a single word.
+// Leading and trailing apostrophes, such as in 'quoted' text, are not part of the word.
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+// Whether the character is used as an apostrophe.
+pub fn is_apostrophe(c: char) -> bool {
+    matches!(c, '\'' | '\u{2019}' | '\u{02BC}')
+}
+
+// Return each word in the line, along with its character offset.
+// A word is a segment between UAX #29 word boundaries that contains a letter or digit,
+// which includes punctuation joining letters or digits on both sides, such as "don't" or
+// "3.14", and combining marks.
+pub fn words(line: &str) -> Vec<(usize, &str)> {
+    let mut words = vec![];
+    let mut offset = 0;
+    for segment in line.split_word_bounds() {
+        if segment.chars().any(char::is_alphanumeric) {
+            words.push((offset, segment));
+        }
+        offset += segment.chars().count();
+    }
+    words
+}
+
+// The writing system of a word, used to skip scripts the dictionary cannot check.
+// Scripts are identified by the Unicode blocks their letters belong to.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Devanagari,
+    Thai,
+    // Chinese, Japanese and Korean, which are not separated by spaces and cannot be checked
+    // by any of the supported programs.
+    Cjk,
+    Other,
+}
+
+impl Script {
+    // Scripts that are checked unless configured otherwise.
+    pub fn defaults() -> Vec<Script> {
+        vec![
+            Script::Latin,
+            Script::Greek,
+            Script::Cyrillic,
+            Script::Armenian,
+            Script::Hebrew,
+            Script::Arabic,
+            Script::Devanagari,
+            Script::Thai,
+            Script::Other,
+        ]
+    }
+
+    // The script of a word, taken from its first letter.
+    pub fn of(word: &str) -> Script {
+        let Some(c) = word.chars().find(|c| c.is_alphabetic()) else {
+            return Script::Other;
+        };
+        match c {
+            'A'..='Z' | 'a'..='z' => Script::Latin,
+            '\u{00C0}'..='\u{024F}'
+            | '\u{1E00}'..='\u{1EFF}'
+            | '\u{2C60}'..='\u{2C7F}'
+            | '\u{A720}'..='\u{A7FF}' => Script::Latin,
+            '\u{0370}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}' => Script::Greek,
+            '\u{0400}'..='\u{052F}'
+            | '\u{1C80}'..='\u{1C8F}'
+            | '\u{2DE0}'..='\u{2DFF}'
+            | '\u{A640}'..='\u{A69F}' => Script::Cyrillic,
+            '\u{0530}'..='\u{058F}' => Script::Armenian,
+            '\u{0590}'..='\u{05FF}' => Script::Hebrew,
+            '\u{0600}'..='\u{06FF}'
+            | '\u{0750}'..='\u{077F}'
+            | '\u{08A0}'..='\u{08FF}'
+            | '\u{FB50}'..='\u{FDFF}'
+            | '\u{FE70}'..='\u{FEFF}' => Script::Arabic,
+            '\u{0900}'..='\u{097F}' => Script::Devanagari,
+            '\u{0E00}'..='\u{0E7F}' => Script::Thai,
+            '\u{1100}'..='\u{11FF}'
+            | '\u{2E80}'..='\u{2FDF}'
+            | '\u{3040}'..='\u{31FF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{AC00}'..='\u{D7AF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{20000}'..='\u{2FFFF}' => Script::Cjk,
+            _ => Script::Other,
+        }
+    }
+}
+
+// Return a copy of the line containing only its words, with everything else replaced by spaces.
+// Apostrophes within words are replaced with ASCII apostrophes, which spell checkers understand.
+// Words are converted to Unicode Normalization Form C, as dictionaries contain composed characters.
+// A word that becomes shorter is padded with spaces, so the start of each word is unchanged.
+// URLs, email addresses, words containing digits and words in scripts other than
+// those given are blanked out entirely.
+// Every character maps to exactly one character, so offsets are unchanged.
+pub fn normalize(line: &str, scripts: &[Script]) -> String {
+    let mut out: Vec<char> = line.chars().map(|_| ' ').collect();
+    let mut offset = 0;
+    for chunk in line.split(char::is_whitespace) {
+        let len = chunk.chars().count();
+        if !is_address(chunk) {
+            let words = words(chunk).into_iter();
+            let words = words.filter(|(_, w)| scripts.contains(&Script::of(w)));
+            for (n, word) in words.filter(|(_, w)| !w.chars().any(char::is_numeric)) {
+                let composed: String = word.nfc().collect();
+                let word = if composed.chars().count() <= word.chars().count() {
+                    &composed
+                } else {
+                    word
+                };
+                for (i, c) in word.chars().enumerate() {
+                    out[offset + n + i] = if is_apostrophe(c) { '\'' } else { c };
+                }
+            }
+        }
+        offset += len + 1;
+    }
+    out.into_iter().collect()
+}
+
+// Return the length in characters of the word at the given character offset of the line,
+// which the checker reported in normalized form. If there is no such word, this is the
+// length of the reported word itself.
+pub fn original_len(line: &str, offset: usize, reported: &str) -> usize {
+    words(line)
+        .into_iter()
+        .find(|&(n, word)| n == offset && word.nfc().eq(reported.chars()))
+        .map_or(reported, |(_, word)| word)
+        .chars()
+        .count()
+}
+
+// Whether a whitespace-delimited chunk of text is a URL or email address.
+fn is_address(chunk: &str) -> bool {
+    chunk.contains("://")
+        || chunk.starts_with("www.")
+        || chunk
+            .split_once('@')
+            .is_some_and(|(user, host)| !user.is_empty() && host.contains('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_words() {
+        assert_eq!(
+            words("Don't say 'hi', it’s O’Brien's—ok"),
+            vec![
+                (0, "Don't"),
+                (6, "say"),
+                (11, "hi"),
+                (16, "it’s"),
+                (21, "O’Brien's"),
+                (31, "ok"),
+            ]
+        );
+        assert_eq!(words("students' work"), vec![(0, "students"), (10, "work")]);
+        assert_eq!(words("naïve café"), vec![(0, "naïve"), (6, "café")]);
+        assert_eq!(words(" 42 "), vec![(1, "42")]);
+    }
+
+    #[test]
+    fn test_scripts() {
+        assert_eq!(Script::of("Ошибка"), Script::Cyrillic);
+        assert_eq!(Script::of("λάθος"), Script::Greek);
+        assert_eq!(Script::of("拼写"), Script::Cjk);
+        assert_eq!(Script::of("42nd"), Script::Latin);
+        assert_eq!(
+            normalize("Ошибкаа in 中文文本 and λάθοςς", &Script::defaults()),
+            "Ошибкаа in      and λάθοςς"
+        );
+        assert_eq!(
+            normalize("Ошибкаа in λάθοςς", &[Script::Latin]),
+            "        in       "
+        );
+    }
+
+    #[test]
+    fn test_nfc() {
+        let nfd = "Cafe\u{301} ne\u{301}e";
+        assert_eq!(normalize(nfd, &Script::defaults()), "Caf\u{e9}  n\u{e9}e ");
+        assert_eq!(original_len(nfd, 6, "n\u{e9}e"), 4);
+        assert_eq!(original_len(nfd, 6, "n"), 1);
+        assert_eq!(original_len(nfd, 2, "fe"), 2);
+    }
+
+    #[test]
+    fn test_segmentation() {
+        // Smart quotes, dashes and non-breaking spaces separate words
+        assert_eq!(
+            words("“Helo”—said\u{a0}he‘s…"),
+            vec![(1, "Helo"), (7, "said"), (12, "he‘s")]
+        );
+        // Combining marks stay with their letter
+        assert_eq!(words("cafe\u{301} ok"), vec![(0, "cafe\u{301}"), (6, "ok")]);
+        // Numbers keep their separators, but trailing punctuation is dropped
+        assert_eq!(
+            words("v1.2, 3,000 e.g. end."),
+            vec![(0, "v1.2"), (6, "3,000"), (12, "e.g"), (17, "end")]
+        );
+        // Emoji joined by a zero-width joiner are not words, and Hebrew abbreviations keep
+        // their gershayim
+        assert_eq!(words("👩\u{200d}💻 dev"), vec![(4, "dev")]);
+        assert_eq!(words("צה״ל 1,000.5"), vec![(0, "צה״ל"), (5, "1,000.5")]);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize("it’s \"done\".", &Script::defaults()),
+            "it's  done  "
+        );
+        assert_eq!(
+            normalize(
+                "See https://exmple.com or me@exmple.com, ok?",
+                &Script::defaults()
+            ),
+            "See                    or                ok "
+        );
+        assert_eq!(normalize("café—ok", &Script::defaults()), "café ok");
+        assert_eq!(
+            normalize("h2o\u{a0}is 2nd wtr", &Script::defaults()),
+            "    is     wtr"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+// Punctuation that doesn't match the project's typographic style, e.g. straight quotes
+// where curly ones are wanted, or an em dash where only ASCII is allowed.
+use crate::spell::DiagnosticData;
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position};
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Style {
+    // Curly quotes, em dashes and ellipses, rather than "'", "--" and "...".
+    Smart,
+    // ASCII punctuation only.
+    Ascii,
+}
+
+// The punctuation to change in a line of checked text that starts at the given line and
+// character of the document.
+pub fn check(style: Style, line: u32, character: u32, text: &str) -> Vec<Diagnostic> {
+    let chars: Vec<char> = text.chars().collect();
+    let found = match style {
+        Style::Smart => smart(&chars),
+        Style::Ascii => ascii(&chars),
+    };
+    found
+        .into_iter()
+        .map(|(start, len, fix)| {
+            let original: String = chars[start..start + len].iter().collect();
+            let range = lsp_types::Range::new(
+                Position::new(line, character + start as u32),
+                Position::new(line, character + (start + len) as u32),
+            );
+            let data = DiagnosticData {
+                original: original.clone(),
+                fixes: vec![fix.into()],
+                range,
+            };
+            Diagnostic {
+                range,
+                severity: Some(DiagnosticSeverity::INFORMATION),
+                code: Some(NumberOrString::String("typography".into())),
+                source: Some("typography".into()),
+                message: format!("Use {fix} instead of {original}"),
+                data: serde_json::to_value(data).ok(),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+// The offset, length and replacement of each piece of ASCII punctuation.
+fn smart(chars: &[char]) -> Vec<(usize, usize, &'static str)> {
+    let mut found = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let prev = i.checked_sub(1).map(|p| chars[p]);
+        // A quote opens after a space or bracket, and otherwise closes or is an apostrophe.
+        let opens = prev.is_none_or(|p| p.is_whitespace() || "([{—–-/".contains(p));
+        let run = chars[i..].iter().take_while(|&&c| c == chars[i]).count();
+        match chars[i] {
+            '"' => found.push((i, 1, if opens { "“" } else { "”" })),
+            '\'' => found.push((i, 1, if opens { "‘" } else { "’" })),
+            // "--" starting a word is more likely a command line option than a dash.
+            '-' if run == 2 => {
+                let next = chars.get(i + 2);
+                let option = opens && next.is_some_and(|n| !n.is_whitespace());
+                if !option {
+                    found.push((i, 2, "—"));
+                }
+            }
+            '.' if run == 3 => found.push((i, 3, "…")),
+            _ => {}
+        }
+        i += if matches!(chars[i], '-' | '.') {
+            run
+        } else {
+            1
+        };
+    }
+    found
+}
+
+// The offset, length and replacement of each piece of non-ASCII punctuation.
+fn ascii(chars: &[char]) -> Vec<(usize, usize, &'static str)> {
+    chars
+        .iter()
+        .enumerate()
+        .filter_map(|(i, c)| {
+            let fix = match c {
+                '“' | '”' => "\"",
+                '‘' | '’' => "'",
+                '—' => "--",
+                '–' => "-",
+                '…' => "...",
+                _ => return None,
+            };
+            Some((i, 1, fix))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    // The text each diagnostic covers and its fix.
+    fn found(style: Style, text: &str) -> Vec<(u32, String, String)> {
+        check(style, 0, 0, text)
+            .into_iter()
+            .map(|diag| {
+                let data: DiagnosticData = serde_json::from_value(diag.data.unwrap()).unwrap();
+                (
+                    diag.range.start.character,
+                    data.original,
+                    data.fixes[0].clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_smart() {
+        assert_eq!(
+            found(
+                Style::Smart,
+                r#"She said "don't" -- or 'not'... Run --help."#
+            ),
+            [
+                (9, "\"".into(), "“".into()),
+                (13, "'".into(), "’".into()),
+                (15, "\"".into(), "”".into()),
+                (17, "--".into(), "—".into()),
+                (23, "'".into(), "‘".into()),
+                (27, "'".into(), "’".into()),
+                (28, "...".into(), "…".into()),
+            ]
+        );
+        assert_eq!(
+            found(Style::Smart, "a--b, a---b, a-b, ....")
+                .iter()
+                .map(|(start, _, fix)| (*start, fix.as_str()))
+                .collect::<Vec<_>>(),
+            [(1, "—")]
+        );
+        assert_eq!(found(Style::Smart, "“Curly” — it’s fine…"), []);
+    }
+
+    #[test]
+    fn test_ascii() {
+        assert_eq!(
+            found(Style::Ascii, "“It’s” — a – b…"),
+            [
+                (0, "“".into(), "\"".into()),
+                (3, "’".into(), "'".into()),
+                (5, "”".into(), "\"".into()),
+                (7, "—".into(), "--".into()),
+                (11, "–".into(), "-".into()),
+                (14, "…".into(), "...".into()),
+            ]
+        );
+        assert_eq!(found(Style::Ascii, r#"Plain "ASCII" -- isn't it..."#), []);
+    }
+
+    #[test]
+    fn test_diagnostic() {
+        let diags = check(Style::Smart, 3, 2, "Hi...");
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].message, "Use … instead of ...");
+        assert_eq!(
+            diags[0].range,
+            lsp_types::Range::new(Position::new(3, 4), Position::new(3, 7))
+        );
+        assert_eq!(diags[0].severity, Some(DiagnosticSeverity::INFORMATION));
+    }
+}
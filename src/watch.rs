@@ -0,0 +1,152 @@
+// Watches the files found by a workspace scan for changes made outside the editor, e.g. by
+// a git checkout, for editors that can't report them with didChangeWatchedFiles.
+// The operating system reports the changes through the notify crate, and bursts of changes
+// are collected by its debouncer, so nothing is done while the workspace is idle.
+// WebAssembly hosts can't watch files, so there only the editor can report changes.
+use crate::ignore::Ignore;
+use crate::scan;
+#[cfg(not(target_family = "wasm"))]
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+#[cfg(not(target_family = "wasm"))]
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use std::path::{Path, PathBuf};
+#[cfg(not(target_family = "wasm"))]
+use std::time::Duration;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+// A file that was created, modified or deleted.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Change {
+    pub path: PathBuf,
+    pub deleted: bool,
+}
+
+// Watches a directory until dropped.
+#[cfg_attr(target_family = "wasm", allow(dead_code))]
+pub struct Watcher {
+    #[cfg(not(target_family = "wasm"))]
+    _debouncer: Debouncer<RecommendedWatcher>,
+    pub changes: crossbeam_channel::Receiver<Vec<Change>>,
+}
+
+// Start watching `root`, sending the changes to matching files once no more have been made
+// for `debounce_ms`, then calling `wake` if given.
+#[cfg(not(target_family = "wasm"))]
+pub fn start(root: PathBuf, opts: scan::Options, wake: Option<crate::Wake>) -> Result<Watcher> {
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    let timeout = Duration::from_millis(opts.debounce_ms);
+    let dir = root.clone();
+    let mut debouncer = new_debouncer(timeout, move |events: DebounceEventResult| {
+        match events {
+            Ok(events) => {
+                let changes = changes(&root, &opts, events.into_iter().map(|e| e.path));
+                if !changes.is_empty() {
+                    // The receiver is only dropped when the server stops.
+                    let _ = sender.send(changes);
+                    if let Some(wake) = &wake {
+                        wake();
+                    }
+                }
+            }
+            Err(err) => log::warn!("Cannot watch {root:?}: {err}"),
+        }
+    })?;
+    debouncer.watcher().watch(&dir, RecursiveMode::Recursive)?;
+    Ok(Watcher {
+        _debouncer: debouncer,
+        changes: receiver,
+    })
+}
+
+#[cfg(target_family = "wasm")]
+pub fn start(_root: PathBuf, _opts: scan::Options, _wake: Option<crate::Wake>) -> Result<Watcher> {
+    Err("cannot watch files from WebAssembly".into())
+}
+
+// The changes to the given paths that a scan of `root` would find, in order.
+// A path that no longer exists was deleted.
+#[cfg_attr(target_family = "wasm", allow(dead_code))]
+fn changes(root: &Path, opts: &scan::Options, paths: impl Iterator<Item = PathBuf>) -> Vec<Change> {
+    let mut ignore = Ignore::new(root);
+    let mut changes: Vec<Change> = paths
+        .filter(|path| scan::matches(root, path, opts) && !ignore.ignored(path))
+        .filter(|path| !path.is_dir())
+        .map(|path| Change {
+            deleted: !path.exists(),
+            path,
+        })
+        .collect();
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    changes.dedup();
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_changes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        std::fs::write(root.join("a.md"), "text").unwrap();
+        std::fs::write(root.join("c.md"), "text").unwrap();
+        std::fs::write(root.join("main.rs"), "text").unwrap();
+        std::fs::write(root.join(".spelgudignore"), "ignored.md\n").unwrap();
+        let paths = ["c.md", "a.md", "b.md", "a.md", "main.rs", "ignored.md"];
+        let change = |path: &str, deleted| Change {
+            path: root.join(path),
+            deleted,
+        };
+        assert_eq!(
+            changes(
+                root,
+                &scan::Options::default(),
+                paths.iter().map(|p| root.join(p))
+            ),
+            [
+                change("a.md", false),
+                change("b.md", true),
+                change("c.md", false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_watch() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        std::fs::write(root.join("old.md"), "text").unwrap();
+        let opts = scan::Options {
+            debounce_ms: 50,
+            ..Default::default()
+        };
+        let watcher = start(root.clone(), opts, None).unwrap();
+        std::fs::write(root.join("new.md"), "text").unwrap();
+        std::fs::write(root.join("main.rs"), "text").unwrap();
+        std::fs::remove_file(root.join("old.md")).unwrap();
+
+        let mut seen = vec![];
+        while seen.len() < 2 {
+            let changes = watcher.changes.recv_timeout(Duration::from_secs(5));
+            seen.extend(changes.unwrap());
+        }
+        seen.sort_by(|a, b| a.path.cmp(&b.path));
+        seen.dedup();
+        assert_eq!(
+            seen,
+            [
+                Change {
+                    path: root.join("new.md"),
+                    deleted: false
+                },
+                Change {
+                    path: root.join("old.md"),
+                    deleted: true
+                },
+            ]
+        );
+    }
+}
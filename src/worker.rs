@@ -0,0 +1,261 @@
+// Checks documents on a separate thread, so the server keeps responding to other requests,
+// such as completions and code actions, while a large document is being checked.
+use crate::{spell, style, typography};
+use lsp_types::{Diagnostic, Url};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+// How many lines to check between looking for cancellation.
+const BATCH: usize = 256;
+
+// The text of a document to check.
+pub struct Job {
+    pub uri: Url,
+    // The document version to publish the diagnostics for.
+    pub version: Option<i32>,
+    // The file's generation when the job was made, to detect if it changed during the check.
+    pub generation: u64,
+    // The lines to check, with the line and character they start at.
+    pub lines: Vec<(u32, u32, String)>,
+    // Words within identifiers to check, reported as hints.
+    pub identifiers: Vec<(u32, u32, String)>,
+    // Whether the document is prose, rather than source code, so its punctuation matters.
+    pub prose: bool,
+    // The document's full text and its checked text, if its grammar should also be checked.
+    pub grammar: Option<(String, String)>,
+    // Whether more jobs follow for the rest of the lines, when a large check is split up.
+    pub partial: bool,
+    // Set when the document changes, so a check of the old text stops early.
+    pub cancelled: Arc<AtomicBool>,
+}
+
+// The result of a Job.
+pub struct Done {
+    pub job: Job,
+    // Errors are passed as strings, as boxed errors can't be sent between threads.
+    pub diags: std::result::Result<Vec<Diagnostic>, String>,
+    pub elapsed: std::time::Duration,
+}
+
+pub struct Worker {
+    jobs: crossbeam_channel::Sender<Job>,
+    done: crossbeam_channel::Receiver<Done>,
+    // Called after each job is done, once set.
+    wake: Arc<OnceLock<crate::Wake>>,
+}
+
+impl Worker {
+    // Start a thread that checks jobs with the given checker, until the Worker is dropped.
+    pub fn start(
+        checker: Arc<Mutex<spell::Backend>>,
+        languagetool: Option<spell::languagetool::Options>,
+        rules: Vec<style::Rule>,
+        typography: Option<typography::Style>,
+    ) -> Worker {
+        let (jobs, job_receiver) = crossbeam_channel::unbounded::<Job>();
+        let (done_sender, done) = crossbeam_channel::unbounded();
+        let wake = Arc::new(OnceLock::<crate::Wake>::new());
+        let waker = wake.clone();
+        std::thread::spawn(move || {
+            for job in job_receiver {
+                let start = std::time::Instant::now();
+                let diags = check(&checker, languagetool.as_ref(), &rules, typography, &job)
+                    .map_err(|e| e.to_string());
+                let done = Done {
+                    job,
+                    diags,
+                    elapsed: start.elapsed(),
+                };
+                if done_sender.send(done).is_err() {
+                    break;
+                }
+                if let Some(wake) = waker.get() {
+                    wake();
+                }
+            }
+        });
+        Worker { jobs, done, wake }
+    }
+
+    // Call `wake` after each job is done, for a server that waits on something other than
+    // the `done` channel, e.g. an async runtime.
+    pub fn wake_with(&self, wake: crate::Wake) {
+        let _ = self.wake.set(wake);
+    }
+
+    pub fn send(&self, job: Job) -> spell::Result<()> {
+        Ok(self.jobs.send(job)?)
+    }
+
+    // Receives the results of jobs, in the order they were sent.
+    pub fn done(&self) -> &crossbeam_channel::Receiver<Done> {
+        &self.done
+    }
+}
+
+fn check(
+    checker: &Mutex<spell::Backend>,
+    languagetool: Option<&spell::languagetool::Options>,
+    rules: &[style::Rule],
+    typography: Option<typography::Style>,
+    job: &Job,
+) -> spell::Result<Vec<Diagnostic>> {
+    let cancelled = || match job.cancelled.load(Ordering::Relaxed) {
+        true => Err("Check cancelled"),
+        false => Ok(()),
+    };
+    // The checker is locked for a batch at a time, so completions and code actions can ask it
+    // for suggestions between batches rather than waiting for the whole document. Yielding
+    // first gives a thread waiting for the lock the chance to take it.
+    let lock = || {
+        std::thread::yield_now();
+        checker.lock().map_err(|_| "Spell checker panicked")
+    };
+    let mut diags = vec![];
+    for lines in job.lines.chunks(BATCH) {
+        cancelled()?;
+        diags.extend(lock()?.diags_at(inputs(lines))?);
+    }
+    // Misspelled identifiers are usually deliberate or out of the author's control,
+    // so report them as hints rather than errors.
+    for identifiers in job.identifiers.chunks(BATCH) {
+        cancelled()?;
+        diags.extend(
+            lock()?
+                .diags_at(inputs(identifiers))?
+                .into_iter()
+                .map(|diag| Diagnostic {
+                    severity: Some(lsp_types::DiagnosticSeverity::HINT),
+                    ..diag
+                }),
+        );
+    }
+    for (line, character, text) in &job.lines {
+        diags.extend(style::check(rules, *line, *character, text));
+        if let (Some(style), true) = (typography, job.prose) {
+            diags.extend(typography::check(style, *line, *character, text));
+        }
+    }
+    if let (Some(opts), Some((text, checked))) = (languagetool, &job.grammar) {
+        cancelled()?;
+        // Grammar checking is an extra, so don't let it prevent reporting spelling errors.
+        match spell::languagetool::check(opts, text, checked) {
+            Ok(lt) => spell::merge(&mut diags, lt),
+            Err(err) => log::error!("LanguageTool check failed: {err}"),
+        }
+    }
+    Ok(diags)
+}
+
+fn inputs(lines: &[(u32, u32, String)]) -> Vec<(u32, u32, &str)> {
+    lines
+        .iter()
+        .map(|(line, character, text)| (*line, *character, text.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::sync::atomic::AtomicUsize;
+
+    // A checker that must not be used, as the job is cancelled before it starts.
+    struct Never;
+
+    impl spell::Checker for Never {
+        fn check(&mut self, text: &str) -> spell::Result<Vec<spell::Misspelling>> {
+            panic!("Checked {text} after cancelling")
+        }
+
+        fn suggest(&mut self, _word: &str) -> spell::Result<Vec<String>> {
+            Ok(vec![])
+        }
+    }
+
+    // A checker that counts the lines it checks, and waits at the end of the first batch
+    // until it is let go.
+    struct Gate {
+        checked: Arc<AtomicUsize>,
+        reached: crossbeam_channel::Sender<()>,
+        release: crossbeam_channel::Receiver<()>,
+    }
+
+    impl spell::Checker for Gate {
+        fn check(&mut self, _text: &str) -> spell::Result<Vec<spell::Misspelling>> {
+            if self.checked.fetch_add(1, Ordering::SeqCst) + 1 == BATCH {
+                self.reached.send(())?;
+                self.release.recv()?;
+            }
+            Ok(vec![])
+        }
+
+        fn suggest(&mut self, _word: &str) -> spell::Result<Vec<String>> {
+            Ok(vec![])
+        }
+    }
+
+    fn job(lines: Vec<(u32, u32, String)>, cancelled: Arc<AtomicBool>) -> Job {
+        Job {
+            uri: Url::parse("file:///example.txt").unwrap(),
+            version: Some(1),
+            generation: 0,
+            lines,
+            identifiers: vec![],
+            prose: true,
+            grammar: None,
+            partial: false,
+            cancelled,
+        }
+    }
+
+    #[test]
+    fn test_unlocked_between_batches() {
+        let checked = Arc::new(AtomicUsize::new(0));
+        let (reached, reached_receiver) = crossbeam_channel::bounded(0);
+        let (release_sender, release) = crossbeam_channel::bounded(0);
+        let gate = Gate {
+            checked: checked.clone(),
+            reached,
+            release,
+        };
+        let backend = spell::Backend::with_checker("gate", Box::new(gate), &Default::default());
+        let checker = Arc::new(Mutex::new(backend));
+        let worker = Worker::start(checker.clone(), None, vec![], None);
+        // Distinct words, so each is sent to the checker.
+        let word = |mut n: usize| {
+            let mut word = String::from("kwick");
+            loop {
+                word.push(char::from(b'a' + (n % 26) as u8));
+                n /= 26;
+                if n == 0 {
+                    break word;
+                }
+            }
+        };
+        let lines = (0..BATCH * 2).map(|n| (n as u32, 0, word(n))).collect();
+        worker
+            .send(job(lines, Arc::new(AtomicBool::new(false))))
+            .unwrap();
+        reached_receiver.recv().unwrap();
+        release_sender.send(()).unwrap();
+        // The checker can be used once the first batch is checked, before the second is.
+        let lock = checker.lock().unwrap();
+        assert_eq!(checked.load(Ordering::SeqCst), BATCH);
+        drop(lock);
+        assert!(worker.done().recv().unwrap().diags.is_ok());
+        assert_eq!(checked.load(Ordering::SeqCst), BATCH * 2);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let backend = spell::Backend::with_checker("never", Box::new(Never), &Default::default());
+        let worker = Worker::start(Arc::new(Mutex::new(backend)), None, vec![], None);
+        let cancelled = Arc::new(AtomicBool::new(true));
+        worker
+            .send(job(vec![(0, 0, "kwick".into())], cancelled))
+            .unwrap();
+        let done = worker.done().recv().unwrap();
+        assert_eq!(done.diags, Err("Check cancelled".to_string()));
+    }
+}
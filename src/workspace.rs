@@ -1,63 +1,975 @@
 use std::collections::hash_map;
 
+use crate::dict;
 use crate::file;
+use crate::filter::Filter;
+use crate::ignore::{self, Ignore};
+use crate::Config;
 
 use super::spell;
+use crate::worker;
 use lsp_types::Url;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 pub struct Workspace {
     files: std::collections::HashMap<Url, file::File>,
-    proc: spell::Process,
+    // Shared with the worker, which holds the lock while checking.
+    checker: Arc<Mutex<spell::Backend>>,
+    worker: worker::Worker,
+    config: Config,
+    // How long the most recent check of a file took.
+    last_check: Option<std::time::Duration>,
+    // Files to check once they are due.
+    pending: std::collections::HashMap<Url, Pending>,
+    // Files being checked by the worker, which aren't sent again until it is done,
+    // with the flag that cancels the check.
+    checking: std::collections::HashMap<Url, Arc<AtomicBool>>,
+    // When each loaded file was last used.
+    used: std::collections::HashMap<Url, std::time::Instant>,
+    // Files unloaded after going unused for a while, with the filter to reload them with.
+    evicted: std::collections::HashMap<Url, Filter>,
+    // The latest diagnostics for each file that have yet to be published.
+    unpublished: std::collections::HashMap<Url, lsp_types::PublishDiagnosticsParams>,
+    // When diagnostics were last published for each file.
+    published: std::collections::HashMap<Url, std::time::Instant>,
+    // The workspace root, if the editor gave one.
+    root: Option<std::path::PathBuf>,
+    // Files and directories under the root to leave alone.
+    ignore: Option<Ignore>,
+    // Files loaded by the scan that the editor hasn't opened, which are checked again
+    // when they change on disk.
+    scanned: std::collections::HashSet<Url>,
+    // Edits correcting typos in saved files, for the editor to apply.
+    edits: Vec<lsp_types::ApplyWorkspaceEditParams>,
+}
+
+// A file waiting to be checked.
+struct Pending {
+    // The version of the latest change.
+    version: Option<i32>,
+    // When to check it.
+    due: std::time::Instant,
+    // Whether to check every line, and grammar, rather than only lines changed since the last check.
+    full: bool,
+}
+
+// The server's state, as returned by the spelgud/status request.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct Status {
+    pub version: String,
+    pub checkers: Vec<spell::Status>,
+    pub open_files: usize,
+    // Open files unloaded from memory after going unused.
+    pub unloaded_files: usize,
+    pub session_words: usize,
+    pub last_check_ms: Option<u128>,
+}
+
+// Counts of words and spelling errors, as returned by the spelgud/statistics request.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub words: usize,
+    pub misspellings: usize,
+    // The number of different misspelled words.
+    pub unique_misspellings: usize,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct FileStatistics {
+    pub uri: Url,
+    #[serde(flatten)]
+    pub counts: Counts,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct Statistics {
+    // The totals for every loaded file.
+    #[serde(flatten)]
+    pub total: Counts,
+    // Each loaded file, those with the most misspellings first.
+    pub files: Vec<FileStatistics>,
+}
+
+// A word misspelled throughout the workspace, as reported by the spelgud.typos command.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct Typo {
+    pub word: String,
+    // How many times it is misspelled.
+    pub count: usize,
+    // How many files it is misspelled in.
+    pub files: usize,
+    pub fixes: Vec<String>,
+}
+
+// A word used in the workspace, as reported by `spelgud words`.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+pub struct Word {
+    pub word: String,
+    // How many times it is used.
+    pub count: usize,
+    // Whether it is in the dictionary, i.e. it was never reported as misspelled.
+    pub known: bool,
 }
 
 impl Workspace {
-    pub fn new(programs: &[spell::Program]) -> Result<Workspace> {
-        Ok(Workspace {
+    pub fn new(config: Config) -> Result<Workspace> {
+        let checker = spell::Backend::new(&config.programs, &config.secondary, &config.spell)?;
+        Ok(Self::with_backend(config, checker))
+    }
+
+    // Check with the given checker, rather than one of the configured programs.
+    pub fn with_checker(config: Config, checker: Box<dyn spell::Checker>) -> Result<Workspace> {
+        let checker = spell::Backend::with_primary(checker, &config.secondary, &config.spell)?;
+        Ok(Self::with_backend(config, checker))
+    }
+
+    fn with_backend(config: Config, checker: spell::Backend) -> Workspace {
+        let checker = Arc::new(Mutex::new(checker));
+        Workspace {
             files: hash_map::HashMap::new(),
-            proc: spell::Process::new(programs)?,
-        })
+            worker: worker::Worker::start(
+                checker.clone(),
+                config.languagetool.clone(),
+                config.rules.clone(),
+                config.typography,
+            ),
+            checker,
+            config,
+            last_check: None,
+            pending: hash_map::HashMap::new(),
+            checking: hash_map::HashMap::new(),
+            used: hash_map::HashMap::new(),
+            evicted: hash_map::HashMap::new(),
+            unpublished: hash_map::HashMap::new(),
+            published: hash_map::HashMap::new(),
+            root: None,
+            ignore: None,
+            scanned: std::collections::HashSet::new(),
+            edits: vec![],
+        }
+    }
+
+    // Open a file, and check it as soon as possible.
+    pub fn open(&mut self, uri: Url, language_id: &str, text: String) -> Result<()> {
+        if self.ignored(&uri) {
+            log::info!(
+                "Not checking {uri}, as it is ignored by a {}",
+                ignore::FILE_NAME
+            );
+            return Ok(());
+        }
+        let filter = Filter::detect(language_id, &uri, &self.config.filter);
+        let file = file::File::new(text, filter)?;
+        self.files.insert(uri.clone(), file);
+        self.evicted.remove(&uri);
+        self.scanned.remove(&uri);
+        self.used.insert(uri.clone(), std::time::Instant::now());
+        self.schedule(uri, None, std::time::Duration::ZERO, true);
+        Ok(())
+    }
+
+    // Set the workspace root, under which files can be scanned or ignored.
+    pub fn set_root(&mut self, root: &std::path::Path) {
+        self.root = Some(root.to_path_buf());
+        self.ignore = Some(Ignore::new(root));
+    }
+
+    // Whether the file is ignored by a `.spelgudignore`, so it is never checked.
+    fn ignored(&mut self, uri: &Url) -> bool {
+        let (Some(ignore), Some(path)) = (&mut self.ignore, file_path(uri)) else {
+            return false;
+        };
+        ignore.ignored(&path)
+    }
+
+    // Check the files under the root that match the scan options, as if they were opened,
+    // so their errors are shown before they are.
+    pub fn scan(&mut self) -> Result<()> {
+        let (Some(root), Some(opts)) = (&self.root, &self.config.scan) else {
+            return Ok(());
+        };
+        let paths = crate::scan::files(root, opts);
+        log::info!("Checking {} files in {root:?}", paths.len());
+        for path in paths {
+            let Ok(uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            self.reload(&uri)?;
+        }
+        Ok(())
+    }
+
+    // Check a file again after it changed on disk, if it is one the scan would check.
+    // Files open in the editor are left alone, as it is the editor's copy that is checked.
+    pub fn disk_changed(&mut self, uri: &Url, deleted: bool) -> Result<()> {
+        let path = file_path(uri).unwrap_or_default();
+        if path.file_name() == Some(std::ffi::OsStr::new(ignore::FILE_NAME)) {
+            if let Some(ignore) = &mut self.ignore {
+                ignore.forget();
+            }
+            return Ok(());
+        }
+        if self.ignored(uri) {
+            return Ok(());
+        }
+        let loaded = self.files.contains_key(uri) || self.evicted.contains_key(uri);
+        let scanned = self.scanned.contains(uri);
+        if loaded && !scanned {
+            return Ok(());
+        }
+        if !loaded && !self.should_scan(uri) {
+            return Ok(());
+        }
+        if !deleted {
+            log::info!("Checking {uri} after it changed on disk");
+            return self.reload(uri);
+        }
+        log::info!("Forgetting {uri} after it was deleted");
+        self.files.remove(uri);
+        self.evicted.remove(uri);
+        self.scanned.remove(uri);
+        self.pending.remove(uri);
+        self.used.remove(uri);
+        // Clear the deleted file's diagnostics.
+        self.unpublished.insert(
+            uri.clone(),
+            lsp_types::PublishDiagnosticsParams {
+                uri: uri.clone(),
+                diagnostics: vec![],
+                version: None,
+            },
+        );
+        Ok(())
+    }
+
+    // Whether the file is under the scanned directory and matches the scan's globs.
+    fn should_scan(&self, uri: &Url) -> bool {
+        let (Some(root), Some(opts)) = (&self.root, &self.config.scan) else {
+            return false;
+        };
+        file_path(uri).is_some_and(|path| crate::scan::matches(root, &path, opts))
+    }
+
+    // Load a file from disk and check it, as one found by the scan.
+    fn reload(&mut self, uri: &Url) -> Result<()> {
+        let path = file_path(uri).ok_or(format!("Not a file: {uri}"))?;
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                self.open(uri.clone(), "", text)?;
+                self.scanned.insert(uri.clone());
+            }
+            Err(err) => log::warn!("Cannot read {path:?}: {err}"),
+        }
+        Ok(())
+    }
+
+    // Check all of a file as soon as possible.
+    pub fn save(&mut self, uri: Url) -> Result<()> {
+        if self.ignored(&uri) {
+            return Ok(());
+        }
+        self.load(&uri)?;
+        if self.config.spell.fix_on_save {
+            self.fix_typos(&uri)?;
+        }
+        let version = self.pending.get(&uri).and_then(|p| p.version);
+        self.schedule(uri, version, std::time::Duration::ZERO, true);
+        Ok(())
     }
 
-    pub fn open(&mut self, uri: Url, text: String) -> Result<Vec<lsp_types::Diagnostic>> {
-        let diags = self.proc.diags(&text);
-        self.files.insert(uri, file::File::new(text)?);
-        diags
+    // Queue an edit correcting the known typos in a file, labelled with what it changes.
+    fn fix_typos(&mut self, uri: &Url) -> Result<()> {
+        let file = self
+            .files
+            .get(uri)
+            .ok_or(format!("File not loaded: {uri}"))?;
+        let text = file.checked_text(&self.config.filter);
+        let fixes = self.checker()?.autofixes(crate::file::lines(&text))?;
+        if fixes.is_empty() {
+            return Ok(());
+        }
+        let changed: Vec<String> = fixes
+            .iter()
+            .map(|fix| format!("{} -> {}", fix.typo, fix.fix))
+            .collect();
+        let label = format!("Fix typos: {}", changed.join(", "));
+        log::info!("{uri}: {label}");
+        let edits = fixes
+            .into_iter()
+            .map(|fix| lsp_types::TextEdit {
+                range: file.utf8_range(fix.range),
+                new_text: fix.fix,
+            })
+            .collect();
+        self.edits.push(lsp_types::ApplyWorkspaceEditParams {
+            label: Some(label),
+            edit: lsp_types::WorkspaceEdit {
+                changes: Some([(uri.clone(), edits)].into_iter().collect()),
+                ..Default::default()
+            },
+        });
+        Ok(())
     }
 
-    pub fn save(&mut self, uri: Url) -> Result<Vec<lsp_types::Diagnostic>> {
-        let file = self.files.get(&uri).ok_or("File not loaded: {uri}")?;
-        let diags = self.proc.diags(&file.text());
-        diags
+    // Take the edits to send to the editor made since this was last called.
+    pub fn take_edits(&mut self) -> Vec<lsp_types::ApplyWorkspaceEditParams> {
+        std::mem::take(&mut self.edits)
     }
 
+    // Apply changes to a file. The lines they touched are checked once the file has not
+    // changed for `debounce_ms`.
     pub fn edit(
         &mut self,
         uri: &Url,
+        version: i32,
         changes: Vec<lsp_types::TextDocumentContentChangeEvent>,
     ) -> Result<()> {
         log::trace!("edit");
+        if self.ignored(uri) {
+            return Ok(());
+        }
+        self.load(uri)?;
         self.files
             .get_mut(uri)
             .ok_or(format!("File not loaded: {uri}"))?
-            .edit(changes)
-            .into()
+            .edit(changes)?;
+        // Stop checking the old text, so the new text is checked sooner.
+        if let Some(cancelled) = self.checking.get(uri) {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+        let delay = std::time::Duration::from_millis(self.config.debounce_ms);
+        self.schedule(uri.clone(), Some(version), delay, false);
+        Ok(())
+    }
+
+    // Check the file after `delay`, replacing any check already scheduled for it.
+    fn schedule(&mut self, uri: Url, version: Option<i32>, delay: std::time::Duration, full: bool) {
+        // A pending full check must not be downgraded by a later change.
+        let full = full || self.pending.get(&uri).is_some_and(|p| p.full);
+        let due = std::time::Instant::now() + delay;
+        self.pending.insert(uri, Pending { version, due, full });
+    }
+
+    // When the next file should be checked, if any.
+    pub fn next_check(&self) -> Option<std::time::Instant> {
+        self.pending
+            .iter()
+            .filter(|(uri, _)| !self.checking.contains_key(*uri))
+            .map(|(_, p)| p.due)
+            .min()
+    }
+
+    // Reload the file from disk if it was evicted, and note that it was used.
+    fn load(&mut self, uri: &Url) -> Result<()> {
+        if let Some(filter) = self.evicted.remove(uri) {
+            log::info!("Reloading {uri}");
+            let path = file_path(uri).ok_or(format!("Not a file: {uri}"))?;
+            let text = std::fs::read_to_string(path)?;
+            self.files
+                .insert(uri.clone(), file::File::new(text, filter)?);
+        }
+        self.used.insert(uri.clone(), std::time::Instant::now());
+        Ok(())
+    }
+
+    // Unload files that haven't been used for `evict_after_secs`, to bound the memory used
+    // when many files are opened. Only files matching their copy on disk are unloaded,
+    // so they can be reloaded unchanged if they are used again. Buffers that aren't
+    // files, e.g. with an "untitled:" URI, are kept.
+    pub fn evict_idle(&mut self) {
+        if self.config.evict_after_secs == 0 {
+            return;
+        }
+        let now = std::time::Instant::now();
+        let after = std::time::Duration::from_secs(self.config.evict_after_secs);
+        let idle: Vec<Url> = self
+            .used
+            .iter()
+            .filter(|(uri, used)| {
+                file_path(uri).is_some()
+                    && now.duration_since(**used) >= after
+                    && !self.pending.contains_key(*uri)
+                    && !self.checking.contains_key(*uri)
+                    && !self.unpublished.contains_key(*uri)
+            })
+            .map(|(uri, _)| uri.clone())
+            .collect();
+        for uri in idle {
+            let Some(file) = self.files.get(&uri) else {
+                self.used.remove(&uri);
+                continue;
+            };
+            let on_disk = file_path(&uri).and_then(|path| std::fs::read_to_string(path).ok());
+            if on_disk != Some(file.text()) {
+                // Try again once it has been idle for another while, e.g. after it is saved.
+                self.used.insert(uri, now);
+                continue;
+            }
+            log::info!("Unloading idle file {uri}");
+            if let Some(file) = self.files.remove(&uri) {
+                self.evicted.insert(uri.clone(), file.filter().clone());
+            }
+            self.used.remove(&uri);
+        }
+    }
+
+    // Send the files that are due to be checked to the worker.
+    pub fn check_pending(&mut self) -> Result<()> {
+        let now = std::time::Instant::now();
+        let due: Vec<Url> = self
+            .pending
+            .iter()
+            .filter(|(uri, p)| p.due <= now && !self.checking.contains_key(*uri))
+            .map(|(uri, _)| uri.clone())
+            .collect();
+        for uri in due {
+            let Some(pending) = self.pending.remove(&uri) else {
+                continue;
+            };
+            let Some(file) = self.files.get_mut(&uri) else {
+                continue;
+            };
+            if pending.full {
+                file.invalidate();
+            }
+            let lines = file.changed_lines(&self.config.filter)?;
+            let identifiers = file.identifiers()?;
+            let mut grammar = (pending.full && self.config.languagetool.is_some())
+                .then(|| (file.text(), file.checked_text(&self.config.filter)));
+            // Split large checks up, so the diagnostics for the first lines are published
+            // without waiting for the rest of the file.
+            let chunks: Vec<_> = lines.chunks(self.config.stream_lines.max(1)).collect();
+            let cancelled = Arc::new(AtomicBool::new(false));
+            for (i, lines) in chunks.iter().enumerate() {
+                let partial = i + 1 < chunks.len();
+                let changed: std::collections::HashSet<u32> =
+                    lines.iter().map(|(line, _, _)| *line).collect();
+                let identifiers = identifiers
+                    .iter()
+                    .filter(|(line, _, _)| changed.contains(line))
+                    .cloned()
+                    .collect();
+                self.worker.send(worker::Job {
+                    uri: uri.clone(),
+                    version: pending.version,
+                    generation: file.generation(),
+                    lines: lines.to_vec(),
+                    identifiers,
+                    prose: !matches!(file.filter(), Filter::Code(_)),
+                    // Grammar is checked last, once the spelling of every line is known.
+                    grammar: if partial { None } else { grammar.take() },
+                    partial,
+                    cancelled: cancelled.clone(),
+                })?;
+            }
+            self.checking.insert(uri, cancelled);
+        }
+        Ok(())
+    }
+
+    // Receives the results of checks started by `check_pending`, to pass to `finish`.
+    pub fn done(&self) -> &crossbeam_channel::Receiver<worker::Done> {
+        self.worker.done()
+    }
+
+    // Call `wake` whenever a check is done, as well as sending it to `done`.
+    pub fn wake_with(&self, wake: crate::Wake) {
+        self.worker.wake_with(wake);
     }
 
+    // Store the results of a check, and the diagnostics to publish, unless the file
+    // changed while it was being checked, in which case it is checked again.
+    pub fn finish(&mut self, done: worker::Done) -> Result<()> {
+        let job = done.job;
+        if !job.partial {
+            self.checking.remove(&job.uri);
+        }
+        self.last_check = Some(done.elapsed);
+        let Some(file) = self.files.get_mut(&job.uri) else {
+            return Ok(());
+        };
+        if file.generation() != job.generation {
+            // The rest of a split check is also out of date, so wait for its last part.
+            if !job.partial && !self.pending.contains_key(&job.uri) {
+                let full = job.grammar.is_some();
+                self.schedule(job.uri, job.version, std::time::Duration::ZERO, full);
+            }
+            return Ok(());
+        }
+        let diags = match done.diags {
+            Ok(diags) => diags,
+            Err(err) => {
+                log::error!("Failed to check {}: {err}", job.uri);
+                return Ok(());
+            }
+        };
+        let mut diags = file.update(&job.lines, diags);
+        diags.extend(variants(&self.config, file));
+        let mut diags = truncate(diags, self.config.max_diagnostics_per_file);
+        for diag in &mut diags {
+            diag.range = file.utf8_range(diag.range);
+        }
+        let params = lsp_types::PublishDiagnosticsParams {
+            diagnostics: diags,
+            uri: job.uri.clone(),
+            version: job.version,
+        };
+        // Replace any diagnostics not yet published, as these are more recent.
+        self.unpublished.insert(job.uri, params);
+        Ok(())
+    }
+
+    // When the next held back diagnostics are due to be published, if any.
+    pub fn next_publish(&self) -> Option<std::time::Instant> {
+        let now = std::time::Instant::now();
+        self.unpublished
+            .keys()
+            .map(|uri| self.publish_due(uri, now))
+            .min()
+    }
+
+    // Take the diagnostics that are due to be published. Each file's diagnostics are
+    // published at most once per `publish_interval_ms`, so a burst of checks of the same
+    // file only publishes its first and latest results.
+    pub fn take_publishes(&mut self) -> Vec<lsp_types::PublishDiagnosticsParams> {
+        let now = std::time::Instant::now();
+        let due: Vec<Url> = self
+            .unpublished
+            .keys()
+            .filter(|uri| self.publish_due(uri, now) <= now)
+            .cloned()
+            .collect();
+        due.into_iter()
+            .filter_map(|uri| {
+                self.published.insert(uri.clone(), now);
+                self.unpublished.remove(&uri)
+            })
+            .collect()
+    }
+
+    // When the file's diagnostics can next be published, which is `now` if they can be
+    // published straight away.
+    fn publish_due(&self, uri: &Url, now: std::time::Instant) -> std::time::Instant {
+        let interval = std::time::Duration::from_millis(self.config.publish_interval_ms);
+        match self.published.get(uri) {
+            Some(last) => now.max(*last + interval),
+            None => now,
+        }
+    }
+
+    // Offer the corrections for the misspelled word at the given position, best first.
     pub fn complete(
-        &self,
+        &mut self,
         uri: &Url,
-        line: usize,
-        character: usize,
+        line: u32,
+        character: u32,
     ) -> Result<Option<lsp_types::CompletionResponse>> {
-        Ok(None)
+        if self.ignored(uri) {
+            return Ok(None);
+        }
+        self.load(uri)?;
+        let file = self
+            .files
+            .get(uri)
+            .ok_or(format!("File not loaded: {uri}"))?;
+        let pos = file.char_position(lsp_types::Position::new(line, character));
+        let Some(diag) = file.misspelling_at(pos.line, pos.character).cloned() else {
+            return Ok(None);
+        };
+        let range = file.utf8_range(diag.range);
+        let items = self
+            .fixes(uri, &diag)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, fix)| lsp_types::CompletionItem {
+                label: fix.clone(),
+                kind: Some(lsp_types::CompletionItemKind::TEXT),
+                // Clients sort and filter by these, so the order is kept and none are hidden
+                // for not starting with the misspelling.
+                sort_text: Some(format!("{i:04}")),
+                filter_text: Some(diag.message.clone()),
+                text_edit: Some(lsp_types::CompletionTextEdit::Edit(lsp_types::TextEdit {
+                    range,
+                    new_text: fix,
+                })),
+                ..Default::default()
+            })
+            .collect();
+        Ok(Some(lsp_types::CompletionResponse::Array(items)))
+    }
+
+    // Suggest corrections for the misspelled word at the given position, or show the
+    // definition of the word there if a DICT server is configured.
+    pub fn hover(
+        &mut self,
+        uri: &Url,
+        line: u32,
+        character: u32,
+    ) -> Result<Option<lsp_types::Hover>> {
+        if self.ignored(uri) {
+            return Ok(None);
+        }
+        self.load(uri)?;
+        let file = self
+            .files
+            .get(uri)
+            .ok_or(format!("File not loaded: {uri}"))?;
+        let pos = file.char_position(lsp_types::Position::new(line, character));
+        if let Some(diag) = file.misspelling_at(pos.line, pos.character) {
+            let fixes = self.fixes(uri, diag)?;
+            if !fixes.is_empty() {
+                let fixes: Vec<String> = fixes.iter().map(|fix| format!("`{fix}`")).collect();
+                return Ok(Some(lsp_types::Hover {
+                    contents: lsp_types::HoverContents::Markup(lsp_types::MarkupContent {
+                        kind: lsp_types::MarkupKind::Markdown,
+                        value: format!("Did you mean {}?", fixes.join(", ")),
+                    }),
+                    range: Some(file.utf8_range(diag.range)),
+                }));
+            }
+        }
+        let Some(opts) = &self.config.dict else {
+            return Ok(None);
+        };
+        let Some((range, word)) = file.word_at(pos.line, pos.character) else {
+            return Ok(None);
+        };
+        let definitions = dict::define(opts, &word)?;
+        if definitions.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(lsp_types::Hover {
+            contents: lsp_types::HoverContents::Markup(lsp_types::MarkupContent {
+                kind: lsp_types::MarkupKind::Markdown,
+                value: dict::markdown(&word, &definitions),
+            }),
+            range: Some(file.utf8_range(range)),
+        }))
+    }
+
+    // Suggest corrections for a misspelled word.
+    pub fn suggest(&self, word: &str) -> Result<Vec<String>> {
+        self.checker()?.suggest(word)
+    }
+
+    // The corrections for a diagnostic in a file, either those found with it or, for
+    // spelling errors, suggestions from the checker, best first.
+    pub fn fixes(&self, uri: &Url, diag: &lsp_types::Diagnostic) -> Result<Vec<String>> {
+        let fixes = match &diag.data {
+            Some(data) => serde_json::from_value::<spell::DiagnosticData>(data.clone())?.fixes,
+            None => vec![],
+        };
+        // Checkers may only find misspellings, leaving suggestions until they are needed.
+        if !fixes.is_empty() || !is_spelling(diag) {
+            return Ok(fixes);
+        }
+        let Some(file) = self.files.get(uri) else {
+            return self.suggest(&diag.message);
+        };
+        // Prefer suggestions that fit with the words around the misspelling.
+        let text = file.checked_text(&self.config.filter);
+        let lines: Vec<&str> = crate::file::lines(&text).collect();
+        let document = spell::rank::pairs(lines.iter().copied());
+        let line = lines.get(diag.range.start.line as usize).copied();
+        let context = spell::rank::Context::at(
+            line.unwrap_or_default(),
+            diag.range.start.character as usize,
+            Some(&document),
+        );
+        self.checker()?.suggest_in(&diag.message, &context)
+    }
+
+    // The ways to split a misspelled word run together from two words, e.g. "a lot" for
+    // "alot". Other errors are never split.
+    pub fn splits(&self, diag: &lsp_types::Diagnostic) -> Result<Vec<String>> {
+        match is_spelling(diag) {
+            true => self.checker()?.splits(&diag.message),
+            false => Ok(vec![]),
+        }
+    }
+
+    // A range in UTF-8 bytes from the client, e.g. of a diagnostic it was sent, in characters.
+    pub fn char_range(&self, uri: &Url, range: lsp_types::Range) -> lsp_types::Range {
+        match self.files.get(uri) {
+            Some(file) => file.char_range(range),
+            None => range,
+        }
     }
 
-    pub fn symbols(&self, uri: &Url) -> Result<Vec<lsp_types::SymbolInformation>> {
+    // The diagnostics found by the last check of a loaded file.
+    pub fn diagnostics(&self, uri: &Url) -> Vec<lsp_types::Diagnostic> {
+        self.files
+            .get(uri)
+            .map(|file| {
+                let diags = file.diagnostics().cloned();
+                diags.chain(variants(&self.config, file)).collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // Add a word to the user's personal dictionary, so it is no longer reported.
+    pub fn add_word(&mut self, word: &str) -> Result<()> {
+        self.invalidate();
+        self.checker()?.add_word(word)
+    }
+
+    // Download a dictionary, and start using it if no checker was available.
+    pub fn install_dictionary(&mut self, lang: &str) -> Result<()> {
+        spell::install_dictionary(lang, &self.config.spell)?;
+        self.invalidate();
+        self.checker()?.retry();
+        Ok(())
+    }
+
+    // Accept a word until the server exits.
+    pub fn ignore_word(&mut self, word: &str) -> Result<()> {
+        self.invalidate();
+        self.checker()?.accept_word(word)
+    }
+
+    // Check every line of every file again on the next change, as the results of the
+    // previous checks may be out of date.
+    fn invalidate(&mut self) {
+        for file in self.files.values_mut() {
+            file.invalidate();
+        }
+    }
+
+    pub fn status(&self) -> Result<Status> {
+        let checker = self.checker()?;
+        Ok(Status {
+            version: env!("CARGO_PKG_VERSION").into(),
+            checkers: checker.status(),
+            open_files: self.files.len() + self.evicted.len(),
+            unloaded_files: self.evicted.len(),
+            session_words: checker.session_words(),
+            last_check_ms: self.last_check.map(|d| d.as_millis()),
+        })
+    }
+
+    // Count the words and misspellings in each loaded file. Files unloaded after going
+    // unused aren't counted.
+    pub fn statistics(&self) -> Statistics {
+        let mut all = std::collections::HashSet::new();
+        let mut total = Counts::default();
+        let mut files: Vec<FileStatistics> = self
+            .files
+            .iter()
+            .map(|(uri, file)| {
+                let misspelled = file.misspellings();
+                let unique: std::collections::HashSet<&str> = misspelled.iter().copied().collect();
+                let counts = Counts {
+                    words: file.word_count(&self.config.filter),
+                    misspellings: misspelled.len(),
+                    unique_misspellings: unique.len(),
+                };
+                total.words += counts.words;
+                total.misspellings += counts.misspellings;
+                all.extend(unique);
+                FileStatistics {
+                    uri: uri.clone(),
+                    counts,
+                }
+            })
+            .collect();
+        total.unique_misspellings = all.len();
+        files.sort_by(|a, b| {
+            (b.counts.misspellings.cmp(&a.counts.misspellings)).then_with(|| a.uri.cmp(&b.uri))
+        });
+        Statistics { total, files }
+    }
+
+    // The `limit` most common misspellings in the loaded files, with suggested fixes,
+    // to decide which to add to the dictionary and which to fix everywhere.
+    pub fn typos(&self, limit: usize) -> Result<Vec<Typo>> {
+        let mut found: std::collections::HashMap<&str, (usize, usize)> = Default::default();
+        for file in self.files.values() {
+            let misspelled = file.misspellings();
+            let unique: std::collections::HashSet<&str> = misspelled.iter().copied().collect();
+            for word in &misspelled {
+                found.entry(word).or_default().0 += 1;
+            }
+            for word in unique {
+                found.entry(word).or_default().1 += 1;
+            }
+        }
+        let mut found: Vec<_> = found.into_iter().collect();
+        found.sort_by(|(a, (a_count, _)), (b, (b_count, _))| {
+            b_count.cmp(a_count).then_with(|| a.cmp(b))
+        });
+        found
+            .into_iter()
+            .take(limit)
+            .map(|(word, (count, files))| {
+                let mut seen = std::collections::HashSet::new();
+                let mut fixes = self.suggest(word)?;
+                fixes.retain(|fix| seen.insert(fix.clone()));
+                fixes.truncate(3);
+                Ok(Typo {
+                    word: word.to_string(),
+                    count,
+                    files,
+                    fixes,
+                })
+            })
+            .collect()
+    }
+
+    // Every word checked in the loaded files, most used first, e.g. to bootstrap a
+    // project's dictionary from its existing docs.
+    pub fn words(&self) -> Vec<Word> {
+        let mut counts: std::collections::HashMap<String, usize> = Default::default();
+        let mut misspelled = std::collections::HashSet::new();
+        for file in self.files.values() {
+            for word in file.words(&self.config.filter) {
+                *counts.entry(word).or_default() += 1;
+            }
+            misspelled.extend(file.misspellings());
+        }
+        let mut words: Vec<Word> = counts
+            .into_iter()
+            .map(|(word, count)| Word {
+                known: !misspelled.contains(word.as_str()),
+                word,
+                count,
+            })
+            .collect();
+        words.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.word.cmp(&b.word)));
+        words
+    }
+
+    // Check every pending file, waiting for the results, e.g. to report on the files
+    // found by `scan` without an editor.
+    pub fn check_all(&mut self) -> Result<()> {
+        loop {
+            self.check_pending()?;
+            if self.checking.is_empty() {
+                let Some(due) = self.next_check() else {
+                    return Ok(());
+                };
+                std::thread::sleep(due.saturating_duration_since(std::time::Instant::now()));
+                continue;
+            }
+            let done = self.worker.done().recv()?;
+            self.finish(done)?;
+        }
+    }
+
+    // Take the messages for the user produced since this was last called.
+    // If a check is in progress, they are left to be taken after it.
+    pub fn take_messages(&mut self) -> Vec<spell::Notice> {
+        match self.checker.try_lock() {
+            Ok(mut checker) => checker.take_messages(),
+            Err(_) => vec![],
+        }
+    }
+
+    // The checker, waiting for any check in progress to finish.
+    fn checker(&self) -> Result<std::sync::MutexGuard<'_, spell::Backend>> {
+        Ok(self.checker.lock().map_err(|_| "Spell checker panicked")?)
+    }
+
+    // The readability of each section of a prose file, e.g. under each markdown heading.
+    // Source code isn't scored, as its comments are rarely written to be read through.
+    pub fn code_lenses(&mut self, uri: &Url) -> Result<Vec<lsp_types::CodeLens>> {
+        if !self.config.readability || self.ignored(uri) {
+            return Ok(vec![]);
+        }
+        self.load(uri)?;
+        let file = self
+            .files
+            .get(uri)
+            .ok_or(format!("File not loaded: {uri}"))?;
+        let headings = match file.filter() {
+            Filter::Code(_) => return Ok(vec![]),
+            filter => *filter == Filter::Markdown,
+        };
+        let checked = file.checked_text(&self.config.filter);
+        Ok(crate::readability::sections(&checked, headings)
+            .into_iter()
+            .map(|section| {
+                let start = lsp_types::Position::new(section.line, 0);
+                lsp_types::CodeLens {
+                    range: lsp_types::Range::new(start, start),
+                    command: Some(lsp_types::Command {
+                        title: section.score.to_string(),
+                        command: String::new(),
+                        arguments: None,
+                    }),
+                    data: None,
+                }
+            })
+            .collect())
+    }
+
+    pub fn symbols(&mut self, uri: &Url) -> Result<Vec<lsp_types::SymbolInformation>> {
+        if self.ignored(uri) {
+            return Ok(vec![]);
+        }
+        self.load(uri)?;
         self.files
             .get(&uri)
             .ok_or("File not loaded: {uri}")?
             .symbols(uri)
     }
 }
+
+// Keep at most `max` diagnostics, so a file full of errors, e.g. one in another language,
+// doesn't overwhelm the editor. If any are dropped, a diagnostic in place of the first one
+// dropped says how many.
+fn truncate(mut diags: Vec<lsp_types::Diagnostic>, max: usize) -> Vec<lsp_types::Diagnostic> {
+    if diags.len() <= max {
+        return diags;
+    }
+    let dropped = diags.split_off(max);
+    diags.push(lsp_types::Diagnostic {
+        range: dropped[0].range,
+        severity: Some(lsp_types::DiagnosticSeverity::INFORMATION),
+        source: Some("spelgud".into()),
+        message: format!(
+            "{} more errors not shown (max_diagnostics_per_file is {max})",
+            dropped.len()
+        ),
+        ..Default::default()
+    });
+    diags
+}
+
+// Words spelled the American way in a British document, or vice versa. This needs the
+// whole document, so is found afresh from the results of each line's last check.
+fn variants(config: &Config, file: &file::File) -> Vec<lsp_types::Diagnostic> {
+    match config.spell.variant {
+        Some(preferred) => spell::variants::check(preferred, &file.accepted_words()),
+        None => vec![],
+    }
+}
+
+// Spelling errors have the misspelled word as their message, unlike e.g. grammar errors,
+// which have a description and a rule code.
+pub fn is_spelling(diag: &lsp_types::Diagnostic) -> bool {
+    diag.code.is_none() && !diag.message.contains(char::is_whitespace)
+}
+
+// The text a diagnostic is about. Spelling diagnostics are titled with the misspelling,
+// while others have a message and carry the text they cover in their data.
+pub fn original(diag: &lsp_types::Diagnostic) -> String {
+    let data = match is_spelling(diag) {
+        true => None,
+        false => diag.data.clone(),
+    };
+    data.and_then(|data| serde_json::from_value::<spell::DiagnosticData>(data).ok())
+        .map_or_else(|| diag.message.clone(), |data| data.original)
+}
+
+// The path of the file on disk, if the URI is for one. Others, e.g. "untitled:" buffers,
+// notebook cells and files on remote hosts, only exist in the editor. Their paths are
+// never used, even if they look like those of local files.
+fn file_path(uri: &Url) -> Option<std::path::PathBuf> {
+    match uri.scheme() {
+        "file" => uri.to_file_path().ok(),
+        _ => None,
+    }
+}
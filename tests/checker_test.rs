@@ -0,0 +1,85 @@
+use pretty_assertions::assert_eq;
+use spelgud::checker::{Checker, Misspelling};
+
+// The misspellings found, without their suggestions, which depend on the dictionary.
+fn found(misspellings: Vec<Misspelling>) -> Vec<(usize, std::ops::Range<usize>, String)> {
+    misspellings
+        .into_iter()
+        .map(|m| (m.line, m.range, m.word))
+        .collect()
+}
+
+#[test]
+fn test_check() -> spelgud::Result<()> {
+    let mut checker = Checker::new()?;
+    let text = "Tea is\n— closd tomorow.\n";
+    let found = checker.check(text)?;
+    assert!(!found[0].suggestions.is_empty(), "{found:?}");
+    assert_eq!(
+        self::found(found),
+        [(1, 11..16, "closd".into()), (1, 17..24, "tomorow".into())]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_check_as() -> spelgud::Result<()> {
+    let mut checker = Checker::new()?;
+    let text = "# Helo\n\nSome `mispelled` code.\n";
+    assert_eq!(
+        found(checker.check_as(text, "markdown")?),
+        [(0, 2..6, "Helo".into())]
+    );
+    assert_eq!(found(checker.check(text)?).len(), 2);
+
+    let tmp = tempfile::tempdir()?;
+    let path = tmp.path().join("main.rs");
+    std::fs::write(&path, "// A coment\nfn mian() {}\n")?;
+    assert_eq!(
+        found(checker.check_file(&path)?),
+        [(0, 5..11, "coment".into())]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_suggestions_in_context() -> spelgud::Result<()> {
+    let mut checker = Checker::new()?;
+    // "piece" is found before "of" elsewhere in the text.
+    let found = checker.check("A piece of bread, and a peice of cake.")?;
+    assert_eq!(found[0].suggestions[0], "piece", "{found:?}");
+    Ok(())
+}
+
+#[test]
+fn test_suggestions_match_case() -> spelgud::Result<()> {
+    let mut checker = Checker::new()?;
+    let found = checker.check("Quik, QUIK!")?;
+    assert!(found[0].suggestions.contains(&"Quick".into()), "{found:?}");
+    assert!(found[1].suggestions.contains(&"QUICK".into()), "{found:?}");
+    assert!(found[1].suggestions.iter().all(|s| s.to_uppercase() == *s));
+    assert!(checker.suggest("QUIK")?.contains(&"QUICK".into()));
+    Ok(())
+}
+
+#[test]
+fn test_tokenize() -> spelgud::Result<()> {
+    let checker = Checker::new()?;
+    let words = checker.tokenize("# Helo\n\n— `mispelled` don't\n", "markdown")?;
+    assert_eq!(
+        words
+            .into_iter()
+            .map(|w| (w.line, w.range, w.word))
+            .collect::<Vec<_>>(),
+        [(0, 2..6, "Helo".into()), (2, 24..29, "don't".into())]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_options() -> spelgud::Result<()> {
+    let mut checker = Checker::with_options(serde_json::json!({"scripts": ["greek"]}))?;
+    assert_eq!(checker.check("Nothing latin is chekced")?, []);
+    assert!(Checker::with_options(serde_json::json!({"scripts": 1})).is_err());
+    Ok(())
+}
@@ -0,0 +1,506 @@
+use pretty_assertions::assert_eq;
+
+// Run spelgud in `dir` with the given arguments, returning its exit code and what it printed.
+fn spelgud(dir: &std::path::Path, args: &[&str]) -> spelgud::Result<(i32, String)> {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_spelgud"))
+        .args(args)
+        .current_dir(dir)
+        .output()?;
+    let code = output.status.code().ok_or("spelgud was killed")?;
+    Ok((code, String::from_utf8(output.stdout)?))
+}
+
+// Run spelgud in `dir` with the given arguments and input, returning its exit code and
+// what it printed.
+fn spelgud_with_input(
+    dir: &std::path::Path,
+    args: &[&str],
+    input: &str,
+) -> spelgud::Result<(i32, String)> {
+    use std::io::Write;
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_spelgud"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(input.as_bytes())?;
+    let output = child.wait_with_output()?;
+    let code = output.status.code().ok_or("spelgud was killed")?;
+    Ok((code, String::from_utf8(output.stdout)?))
+}
+
+#[test]
+fn test_check() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    std::fs::create_dir(tmp.path().join("docs"))?;
+    std::fs::write(
+        tmp.path().join("docs/guide.md"),
+        "This duz not work.\n`duz` is code.\n",
+    )?;
+    std::fs::write(
+        tmp.path().join("notes.txt"),
+        "Fine.\nStill fine, but brokn.\n",
+    )?;
+    std::fs::write(tmp.path().join("main.rs"), "// This duz not work.")?;
+
+    // Directories are searched for the files the scan would check.
+    let (code, out) = spelgud(tmp.path(), &["check"])?;
+    assert_eq!(code, 1);
+    let found: Vec<&str> = out
+        .lines()
+        .map(|line| line.split_once(" (").map_or(line, |(found, _)| found))
+        .collect();
+    assert_eq!(found, ["docs/guide.md:1:6: duz", "notes.txt:2:17: brokn"]);
+    assert!(out.contains("brokn (") && out.contains("broken"), "{out}");
+
+    // Files are checked whether the scan would check them or not.
+    let (_, out) = spelgud(tmp.path(), &["check", "main.rs"])?;
+    assert!(out.starts_with("main.rs:1:9: duz"), "{out}");
+    Ok(())
+}
+
+#[test]
+fn test_exit_codes() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    std::fs::write(tmp.path().join("good.txt"), "This is fine.")?;
+    std::fs::write(tmp.path().join("bad.txt"), "This duz not work. It duz not.")?;
+
+    assert_eq!(spelgud(tmp.path(), &["check", "good.txt"])?, (0, "".into()));
+    assert_eq!(spelgud(tmp.path(), &["check", "bad.txt"])?.0, 1);
+    assert_eq!(
+        spelgud(tmp.path(), &["check", "bad.txt", "--quiet"])?,
+        (1, "".into())
+    );
+
+    // Up to --max-errors errors are allowed.
+    let args = ["check", "bad.txt", "--max-errors"];
+    assert_eq!(spelgud(tmp.path(), &[&args[..], &["2"]].concat())?.0, 0);
+    assert_eq!(spelgud(tmp.path(), &[&args[..], &["1"]].concat())?.0, 1);
+
+    // Failing to check is distinguished from finding errors.
+    assert_eq!(spelgud(tmp.path(), &["check", "missing.txt"])?.0, 2);
+    assert_eq!(spelgud(tmp.path(), &["check", "--format", "xml"])?.0, 2);
+    Ok(())
+}
+
+#[test]
+fn test_fix() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    std::fs::write(
+        tmp.path().join("a.txt"),
+        "Thiss duz not work.\nIt duz not.\n",
+    )?;
+    std::fs::write(tmp.path().join("b.txt"), "Nor duz this.\n")?;
+
+    // Replace "Thiss", ignore the first "duz" after an unexpected answer, pick the first
+    // suggestion for the second, then quit before getting to b.txt.
+    let (code, out) = spelgud_with_input(tmp.path(), &["fix"], "r\nThis\nx\ni\n1\nq\n")?;
+    assert_eq!(code, 0);
+    assert!(
+        out.contains("a.txt:1:1: Thiss\n    [Thiss] duz not work.\n"),
+        "{out}"
+    );
+    assert!(out.contains("Unexpected answer \"x\""), "{out}");
+    assert!(out.contains("Saved 2 fixes to a.txt"), "{out}");
+
+    let fixed = std::fs::read_to_string(tmp.path().join("a.txt"))?;
+    let first = fixed
+        .lines()
+        .nth(1)
+        .unwrap()
+        .split(' ')
+        .nth(1)
+        .unwrap()
+        .to_string();
+    assert_ne!(first, "duz");
+    assert_eq!(fixed, format!("This duz not work.\nIt {first} not.\n"));
+    assert_eq!(
+        std::fs::read_to_string(tmp.path().join("b.txt"))?,
+        "Nor duz this.\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_staged() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(args)
+            .current_dir(tmp.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    git(&["init", "-q"]);
+    std::fs::write(tmp.path().join("staged.md"), "This duz not work.\n")?;
+    std::fs::write(tmp.path().join("unstaged.md"), "Neither duz this.\n")?;
+    std::fs::write(tmp.path().join("main.rs"), "// Nor duz this.\n")?;
+    git(&["add", "staged.md", "main.rs"]);
+    // Fixing the file without staging the fix doesn't fix what is committed.
+    std::fs::write(tmp.path().join("staged.md"), "This does not work.\n")?;
+
+    let (code, out) = spelgud(tmp.path(), &["check", "--staged"])?;
+    assert_eq!(code, 1);
+    assert_eq!(out.lines().count(), 1, "{out}");
+    assert!(out.starts_with("staged.md:1:6: duz"), "{out}");
+
+    git(&["add", "staged.md"]);
+    assert_eq!(spelgud(tmp.path(), &["check", "--staged"])?, (0, "".into()));
+    Ok(())
+}
+
+#[test]
+fn test_since() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(tmp.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    git(&["init", "-q"]);
+    std::fs::write(tmp.path().join("old.md"), "This duz not work.\nFine.\n")?;
+    git(&["add", "old.md"]);
+    git(&["commit", "-q", "-m", "Add old.md"]);
+
+    // Existing errors aren't reported, only those in changed lines and new files.
+    std::fs::write(
+        tmp.path().join("old.md"),
+        "This duz not work.\nStill brokn.\n",
+    )?;
+    std::fs::write(tmp.path().join("new.md"), "Neither duz this.\n")?;
+    let (code, out) = spelgud(tmp.path(), &["check", "--diff"])?;
+    let found: Vec<&str> = out
+        .lines()
+        .map(|line| line.split_once(" (").map_or(line, |(found, _)| found))
+        .collect();
+    assert_eq!(code, 1);
+    assert_eq!(found, ["new.md:1:9: duz", "old.md:2:7: brokn"]);
+
+    let (code, out) = spelgud(tmp.path(), &["check", "--since", "HEAD", "old.md"])?;
+    assert_eq!(code, 1);
+    assert!(out.starts_with("old.md:2:7: brokn"), "{out}");
+
+    std::fs::write(
+        tmp.path().join("old.md"),
+        "This duz not work.\nFine again.\n",
+    )?;
+    std::fs::remove_file(tmp.path().join("new.md"))?;
+    assert_eq!(spelgud(tmp.path(), &["check", "--diff"])?, (0, "".into()));
+    Ok(())
+}
+
+#[test]
+fn test_commits() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let git = |args: &[&str]| {
+        let output = std::process::Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(tmp.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    git(&["init", "-q"]);
+    git(&["commit", "-q", "--allow-empty", "-m", "Strat"]);
+    git(&["commit", "-q", "--allow-empty", "-m", "Add a README"]);
+    let message =
+        "Fix the parser\n\nIt brok on empty input.\n\nSigned-off-by: Smoe One <s@exmple.com>";
+    git(&["commit", "-q", "--allow-empty", "-m", message]);
+    let hash = git(&["rev-parse", "--short", "HEAD"]);
+
+    // Only the commits in the range are checked, and trailers are skipped.
+    let (code, out) = spelgud(tmp.path(), &["commits", "HEAD~2..HEAD"])?;
+    assert_eq!(code, 1);
+    assert_eq!(out.lines().count(), 1, "{out}");
+    assert!(
+        out.starts_with(&format!("{}:3:4: brok", hash.trim())),
+        "{out}"
+    );
+
+    assert_eq!(
+        spelgud(tmp.path(), &["commits", "HEAD~2..HEAD~1"])?,
+        (0, "".into())
+    );
+    Ok(())
+}
+
+#[test]
+fn test_stdin() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    std::fs::write(tmp.path().join("notes.md"), "Neither duz this.\n")?;
+
+    let (code, out) =
+        spelgud_with_input(tmp.path(), &["check", "-"], "Fine.\nThis duz not work.\n")?;
+    assert_eq!(code, 1);
+    assert!(out.starts_with("<stdin>:2:6: duz"), "{out}");
+    assert_eq!(out.lines().count(), 1, "{out}");
+
+    // Text from stdin can be checked along with files.
+    let (_, out) = spelgud_with_input(tmp.path(), &["check", "notes.md", "-"], "Fine.\n")?;
+    assert!(out.starts_with("notes.md:1:9: duz"), "{out}");
+
+    assert_eq!(
+        spelgud_with_input(tmp.path(), &["check", "-"], "Fine.\n")?,
+        (0, "".into())
+    );
+    Ok(())
+}
+
+// Connect to a server started by the test, waiting for it to start listening.
+#[test]
+fn test_words() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    std::fs::write(
+        tmp.path().join("guide.md"),
+        "The spelgud guide.\n`ignored` is code.\nThe end of the guide.\n",
+    )?;
+
+    let (code, out) = spelgud(tmp.path(), &["words"])?;
+    assert_eq!(code, 0);
+    let words: Vec<Vec<&str>> = out
+        .lines()
+        .map(|line| line.split_whitespace().collect())
+        .collect();
+    assert_eq!(
+        words,
+        [
+            vec!["2", "known", "The"],
+            vec!["2", "known", "guide"],
+            vec!["1", "known", "code"],
+            vec!["1", "known", "end"],
+            vec!["1", "known", "is"],
+            vec!["1", "known", "of"],
+            vec!["1", "unknown", "spelgud"],
+            vec!["1", "known", "the"],
+        ]
+    );
+
+    let (_, out) = spelgud(tmp.path(), &["words", "guide.md", "--unknown"])?;
+    assert_eq!(out, "     1  unknown  spelgud\n");
+    Ok(())
+}
+
+#[test]
+fn test_init() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    std::fs::write(tmp.path().join("README.md"), "Check with spelgud.\n")?;
+    assert_eq!(spelgud(tmp.path(), &["check"])?.0, 1);
+
+    let (code, out) = spelgud(tmp.path(), &["init"])?;
+    assert_eq!(code, 0);
+    assert_eq!(out, "Wrote ./.spelgud.toml\nWrote ./.spelgud-words.txt\n");
+    assert_eq!(spelgud(tmp.path(), &["init"])?.0, 1);
+
+    // Words in the project's word list are accepted.
+    std::fs::write(tmp.path().join(".spelgud-words.txt"), "spelgud\n")?;
+    assert_eq!(spelgud(tmp.path(), &["check"])?, (0, "".into()));
+    Ok(())
+}
+
+#[test]
+fn test_completions() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    for shell in ["bash", "zsh", "fish"] {
+        let (code, out) = spelgud(tmp.path(), &["completions", shell])?;
+        assert_eq!(code, 0);
+        assert!(out.contains("list-dicts"), "{shell} lacks subcommands");
+        assert!(out.contains("sarif"), "{shell} lacks formats");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_usage() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let (code, out) = spelgud(tmp.path(), &["--help"])?;
+    assert_eq!(code, 0);
+    assert!(out.contains("check"), "{out}");
+    // Invalid arguments fail like a failed check.
+    assert_eq!(spelgud(tmp.path(), &["check", "--format=xml"])?.0, 2);
+    assert_eq!(spelgud(tmp.path(), &["check", "--bogus"])?.0, 2);
+    assert_eq!(spelgud(tmp.path(), &["completions", "tcsh"])?.0, 2);
+    Ok(())
+}
+
+#[test]
+fn test_capabilities() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let (code, out) = spelgud(tmp.path(), &["--capabilities"])?;
+    assert_eq!(code, 0);
+    let capabilities: lsp_types::ServerCapabilities = serde_json::from_str(&out)?;
+    assert!(capabilities.hover_provider.is_some());
+    Ok(())
+}
+
+#[test]
+fn test_list_dicts() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    std::fs::write(tmp.path().join("xx_XX.aff"), "SET UTF-8\n")?;
+    std::fs::write(tmp.path().join("xx_XX.dic"), "1\nspelgud\n")?;
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_spelgud"))
+        .arg("list-dicts")
+        .env("DICPATH", tmp.path())
+        .env("LANG", "xx_XX.UTF-8")
+        .env_remove("LC_ALL")
+        .env_remove("LC_MESSAGES")
+        .output()?;
+    assert!(output.status.success());
+    let out = String::from_utf8(output.stdout)?;
+    let dict = format!("  xx_XX ({})", tmp.path().join("xx_XX.dic").display());
+    let builtin: Vec<&str> = out.lines().skip_while(|line| *line != "spelgud:").collect();
+    assert!(builtin.contains(&dict.as_str()), "{out}");
+    assert!(out.ends_with("Default dictionary: xx_XX\n"), "{out}");
+    Ok(())
+}
+
+fn connect(addr: &str) -> spelgud::Result<(lsp_server::Connection, lsp_server::IoThreads)> {
+    let start = std::time::Instant::now();
+    loop {
+        match lsp_server::Connection::connect(addr) {
+            Ok(connected) => return Ok(connected),
+            Err(err) if start.elapsed() > std::time::Duration::from_secs(5) => Err(err)?,
+            Err(_) => std::thread::sleep(std::time::Duration::from_millis(20)),
+        }
+    }
+}
+
+// Initialize the server, checking that it responds.
+fn initialize(connection: &lsp_server::Connection) -> spelgud::Result<()> {
+    connection
+        .sender
+        .send(lsp_server::Message::Request(lsp_server::Request {
+            id: 1.into(),
+            method: "initialize".into(),
+            params: serde_json::to_value(lsp_types::InitializeParams::default())?,
+        }))?;
+    let lsp_server::Message::Response(resp) = connection
+        .receiver
+        .recv_timeout(std::time::Duration::from_secs(5))?
+    else {
+        Err("Expected a response")?
+    };
+    let result: lsp_types::InitializeResult = serde_json::from_value(resp.result.unwrap())?;
+    assert!(result.capabilities.hover_provider.is_some());
+    Ok(())
+}
+
+#[test]
+fn test_listen() -> spelgud::Result<()> {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port();
+    let addr = format!("127.0.0.1:{port}");
+    let mut server = std::process::Command::new(env!("CARGO_BIN_EXE_spelgud"))
+        .args(["--listen", &addr])
+        .spawn()?;
+
+    // Each client is served at once, even while others are connected.
+    let (first, _) = connect(&addr)?;
+    initialize(&first)?;
+    let (second, _) = connect(&addr)?;
+    initialize(&second)?;
+
+    server.kill()?;
+    server.wait()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pipe() -> spelgud::Result<()> {
+    use lsp_server::{Message, Notification, Request};
+    use std::io::BufReader;
+
+    let tmp = tempfile::tempdir()?;
+    let path = tmp.path().join("spelgud.sock");
+    let listener = std::os::unix::net::UnixListener::bind(&path)?;
+    let mut server = std::process::Command::new(env!("CARGO_BIN_EXE_spelgud"))
+        .arg(format!("--pipe={}", path.display()))
+        .spawn()?;
+
+    let (mut stream, _) = listener.accept()?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    Message::Request(Request {
+        id: 1.into(),
+        method: "initialize".into(),
+        params: serde_json::to_value(lsp_types::InitializeParams::default())?,
+    })
+    .write(&mut stream)?;
+    let Some(Message::Response(resp)) = Message::read(&mut reader)? else {
+        Err("Expected a response")?
+    };
+    let result: lsp_types::InitializeResult = serde_json::from_value(resp.result.unwrap())?;
+    assert!(result.capabilities.hover_provider.is_some());
+
+    // The server exits when the client asks it to.
+    Message::Notification(Notification::new(
+        "initialized".into(),
+        serde_json::json!({}),
+    ))
+    .write(&mut stream)?;
+    Message::Request(Request::new(2.into(), "shutdown".into(), ())).write(&mut stream)?;
+    // Skip any messages logged first, e.g. that no spell checker was found.
+    loop {
+        match Message::read(&mut reader)? {
+            Some(Message::Response(_)) => break,
+            Some(_) => continue,
+            None => Err("Expected a response")?,
+        }
+    }
+    Message::Notification(Notification::new("exit".into(), ())).write(&mut stream)?;
+    assert!(server.wait()?.success());
+    Ok(())
+}
+
+#[test]
+fn test_log_file() -> spelgud::Result<()> {
+    use lsp_server::{Message, Notification, Request};
+    use std::io::BufReader;
+
+    let tmp = tempfile::tempdir()?;
+    let mut server = std::process::Command::new(env!("CARGO_BIN_EXE_spelgud"))
+        .current_dir(tmp.path())
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()?;
+    let mut stdin = server.stdin.take().unwrap();
+    let mut reader = BufReader::new(server.stdout.take().unwrap());
+    // The response to a request, skipping any messages logged to the client.
+    let mut response = || loop {
+        match Message::read(&mut reader)? {
+            Some(Message::Response(resp)) => return spelgud::Result::Ok(resp),
+            Some(_) => {}
+            None => Err("Expected a response")?,
+        }
+    };
+
+    let params = lsp_types::InitializeParams {
+        initialization_options: Some(serde_json::json!({
+            "log": {"file": tmp.path().join("spelgud.log"), "level": "debug"},
+        })),
+        ..Default::default()
+    };
+    Message::Request(Request::new(1.into(), "initialize".into(), params)).write(&mut stdin)?;
+    response()?;
+    Message::Notification(Notification::new(
+        "initialized".into(),
+        serde_json::json!({}),
+    ))
+    .write(&mut stdin)?;
+    Message::Request(Request::new(2.into(), "shutdown".into(), ())).write(&mut stdin)?;
+    response()?;
+    Message::Notification(Notification::new("exit".into(), ())).write(&mut stdin)?;
+    assert!(server.wait()?.success());
+
+    let log = std::fs::read_to_string(tmp.path().join("spelgud.log"))?;
+    assert!(log.contains(" INFO  spelgud: Shutting down\n"), "{log}");
+    Ok(())
+}
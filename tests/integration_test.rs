@@ -1,7 +1,8 @@
 use core::panic;
 use lsp_server::{Connection, Message};
 use lsp_types::notification::{
-    DidChangeTextDocument, DidOpenTextDocument, DidSaveTextDocument, PublishDiagnostics,
+    DidChangeTextDocument, DidChangeWatchedFiles, DidOpenTextDocument, DidSaveTextDocument,
+    PublishDiagnostics,
 };
 use lsp_types::request::{CodeActionRequest, Completion, DocumentSymbolRequest, Shutdown};
 use lsp_types::{notification::Initialized, request::Initialize, InitializedParams};
@@ -163,9 +164,42 @@ impl TestClient {
     }
 
     fn new_with_root(path: impl AsRef<std::path::Path>) -> Result<TestClient> {
+        Self::new_with_options(path, None)
+    }
+
+    fn new_with_options(
+        path: impl AsRef<std::path::Path>,
+        options: Option<serde_json::Value>,
+    ) -> Result<TestClient> {
+        Self::start(path, options, Default::default(), spelgud::run)
+    }
+
+    fn new_with_capabilities(
+        path: impl AsRef<std::path::Path>,
+        options: Option<serde_json::Value>,
+        capabilities: lsp_types::ClientCapabilities,
+    ) -> Result<TestClient> {
+        Self::start(path, options, capabilities, spelgud::run)
+    }
+
+    fn new_with_checker(
+        path: impl AsRef<std::path::Path>,
+        checker: Box<dyn spelgud::backend::Checker>,
+    ) -> Result<TestClient> {
+        Self::start(path, None, Default::default(), move |server| {
+            spelgud::run_with_checker(server, checker)
+        })
+    }
+
+    fn start(
+        path: impl AsRef<std::path::Path>,
+        options: Option<serde_json::Value>,
+        capabilities: lsp_types::ClientCapabilities,
+        run: impl FnOnce(Connection) -> Result<()> + Send + 'static,
+    ) -> Result<TestClient> {
         let (client, server) = Connection::memory();
         let thread = std::thread::spawn(|| {
-            spelgud::run(server).unwrap();
+            run(server).unwrap();
         });
         let mut client = TestClient {
             conn: client,
@@ -175,6 +209,8 @@ impl TestClient {
 
         client.request::<Initialize>(InitializeParams {
             root_uri: Some(Url::from_file_path(std::fs::canonicalize(path).unwrap()).unwrap()),
+            initialization_options: options,
+            capabilities,
             ..Default::default()
         })?;
         client.notify::<Initialized>(InitializedParams {})?;
@@ -339,6 +375,241 @@ fn test_diagnostics_on_save() -> spelgud::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_diagnostics_on_change() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let path = tmp.path().join("example.txt");
+    let uri = Url::from_file_path(&path).unwrap();
+    let client = TestClient::new_with_root(&tmp)?;
+
+    std::fs::write(&path, "This has no errors.\nNeither does this.")?;
+    let diags = client.open(uri.clone())?;
+    assert!(diags.diagnostics.is_empty());
+
+    // Several quick changes are checked together once typing stops.
+    for (version, character, text) in [(1, 17, " duz"), (2, 21, "z")] {
+        let end = lsp_types::Position::new(1, character);
+        client.notify::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+            text_document: lsp_types::VersionedTextDocumentIdentifier {
+                uri: uri.clone(),
+                version,
+            },
+            content_changes: vec![TextDocumentContentChangeEvent {
+                text: text.into(),
+                range: Some(lsp_types::Range::new(end, end)),
+                range_length: None,
+            }],
+        })?;
+    }
+
+    let diags = client.recv::<PublishDiagnostics>()?;
+    assert_eq!(diags.version, Some(2));
+    assert_eq!(diags.diagnostics.len(), 1);
+    assert_eq!(
+        diags.diagnostics[0].range.start,
+        lsp_types::Position::new(1, 18)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_streaming() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let path = tmp.path().join("example.txt");
+    let uri = Url::from_file_path(&path).unwrap();
+    let options = serde_json::json!({"stream_lines": 2, "publish_interval_ms": 0});
+    let client = TestClient::new_with_options(&tmp, Some(options))?;
+
+    // Diagnostics are published for every two lines, as they are checked.
+    std::fs::write(&path, "This duz\nnot\nhave\nmany\nerrors duz")?;
+    assert_eq!(lines(client.open(uri)?), [0]);
+    assert_eq!(lines(client.recv::<PublishDiagnostics>()?), [0]);
+    assert_eq!(lines(client.recv::<PublishDiagnostics>()?), [0, 4]);
+
+    Ok(())
+}
+
+#[test]
+fn test_throttle() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let path = tmp.path().join("example.txt");
+    let uri = Url::from_file_path(&path).unwrap();
+    let options = serde_json::json!({"stream_lines": 2, "publish_interval_ms": 200});
+    let client = TestClient::new_with_options(&tmp, Some(options))?;
+
+    // The first results are published straight away, and those that follow soon after are
+    // held back, so only the latest are published.
+    std::fs::write(&path, "This duz\nnot\nhave\nmany\nerrors duz")?;
+    assert_eq!(lines(client.open(uri)?), [0]);
+    assert_eq!(lines(client.recv::<PublishDiagnostics>()?), [0, 4]);
+
+    Ok(())
+}
+
+#[test]
+fn test_max_diagnostics() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let path = tmp.path().join("example.txt");
+    let uri = Url::from_file_path(&path).unwrap();
+    let options = serde_json::json!({"max_diagnostics_per_file": 2});
+    let client = TestClient::new_with_options(&tmp, Some(options))?;
+
+    std::fs::write(&path, "Ceci n'est pas une pipe.")?;
+    let diags = client.open(uri)?.diagnostics;
+    let found: Vec<_> = diags.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(
+        found,
+        [
+            "Ceci",
+            "n'est",
+            "2 more errors not shown (max_diagnostics_per_file is 2)"
+        ]
+    );
+    assert_eq!(diags[2].range.start, lsp_types::Position::new(0, 11));
+    assert_eq!(
+        diags[2].severity,
+        Some(lsp_types::DiagnosticSeverity::INFORMATION)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_scan() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    std::fs::create_dir(tmp.path().join("docs"))?;
+    std::fs::write(tmp.path().join("docs/guide.md"), "This duz not work.")?;
+    std::fs::write(tmp.path().join("main.rs"), "// This duz not work.")?;
+    let options = serde_json::json!({"scan": {}});
+    let client = TestClient::new_with_options(&tmp, Some(options))?;
+
+    // Files are checked without being opened, if they match the globs.
+    let diags = client.recv::<PublishDiagnostics>()?;
+    let guide = std::fs::canonicalize(tmp.path())?.join("docs/guide.md");
+    assert_eq!(diags.uri, Url::from_file_path(guide).unwrap());
+    assert_eq!(lines(diags), [0]);
+
+    Ok(())
+}
+
+#[test]
+fn test_ignore() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let root = std::fs::canonicalize(tmp.path())?;
+    std::fs::create_dir_all(root.join("docs"))?;
+    std::fs::create_dir_all(root.join("drafts"))?;
+    std::fs::write(root.join(".spelgudignore"), "drafts/\n")?;
+    std::fs::write(root.join("docs/.spelgudignore"), "old.md\n")?;
+    std::fs::write(root.join("docs/guide.md"), "This duz not work.")?;
+    std::fs::write(root.join("docs/old.md"), "This duz not work.")?;
+    std::fs::write(root.join("drafts/idea.md"), "This duz not work.")?;
+    let options = serde_json::json!({"scan": {}});
+    let mut client = TestClient::new_with_options(&tmp, Some(options))?;
+
+    // Only the file that isn't ignored is scanned.
+    let diags = client.recv::<PublishDiagnostics>()?;
+    let guide = Url::from_file_path(root.join("docs/guide.md")).unwrap();
+    assert_eq!(diags.uri, guide);
+
+    // Ignored files aren't checked when opened either.
+    let idea = root.join("drafts/idea.md");
+    client.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+        text_document: TextDocumentItem {
+            uri: Url::from_file_path(&idea).unwrap(),
+            language_id: "".into(),
+            version: 0,
+            text: std::fs::read_to_string(&idea)?,
+        },
+    })?;
+    let status = client.request::<Status>(())?;
+    assert_eq!(status["open_files"], 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_watch() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let path = std::fs::canonicalize(tmp.path())?.join("guide.md");
+    let uri = Url::from_file_path(&path).unwrap();
+    std::fs::write(&path, "This works.")?;
+    let options = serde_json::json!({"scan": {"debounce_ms": 50}});
+    let client = TestClient::new_with_options(&tmp, Some(options))?;
+    assert!(client.recv::<PublishDiagnostics>()?.diagnostics.is_empty());
+
+    // Without the editor watching files, the server watches them itself.
+    std::fs::write(&path, "This duz not work.")?;
+    let diags = client.recv::<PublishDiagnostics>()?;
+    assert_eq!(diags.uri, uri);
+    assert_eq!(lines(diags), [0]);
+
+    std::fs::remove_file(&path)?;
+    assert!(client.recv::<PublishDiagnostics>()?.diagnostics.is_empty());
+
+    // Changes reported by the editor are checked too.
+    let other = path.with_file_name("other.md");
+    std::fs::write(&other, "Neither duz this.")?;
+    client.notify::<DidChangeWatchedFiles>(lsp_types::DidChangeWatchedFilesParams {
+        changes: vec![lsp_types::FileEvent {
+            uri: Url::from_file_path(&other).unwrap(),
+            typ: lsp_types::FileChangeType::CREATED,
+        }],
+    })?;
+    let diags = client.recv::<PublishDiagnostics>()?;
+    assert_eq!(diags.uri, Url::from_file_path(&other).unwrap());
+    assert_eq!(lines(diags), [0]);
+
+    Ok(())
+}
+
+// The lines of the published diagnostics.
+fn lines(diags: PublishDiagnosticsParams) -> Vec<u32> {
+    diags
+        .diagnostics
+        .iter()
+        .map(|d| d.range.start.line)
+        .collect()
+}
+
+#[test]
+fn test_evict() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let path = tmp.path().join("example.txt");
+    let uri = Url::from_file_path(&path).unwrap();
+    let options = serde_json::json!({"evict_after_secs": 1});
+    let mut client = TestClient::new_with_options(&tmp, Some(options))?;
+
+    std::fs::write(&path, "This has no errors.")?;
+    assert!(client.open(uri.clone())?.diagnostics.is_empty());
+
+    // The file is unloaded once idle, and reloaded when it is next used.
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    client.request::<Status>(())?;
+    let status = client.request::<Status>(())?;
+    assert_eq!(status["open_files"], 1);
+    assert_eq!(status["unloaded_files"], 1);
+
+    let end = lsp_types::Position::new(0, 18);
+    client.notify::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+        text_document: lsp_types::VersionedTextDocumentIdentifier {
+            uri: uri.clone(),
+            version: 1,
+        },
+        content_changes: vec![TextDocumentContentChangeEvent {
+            text: " duz".into(),
+            range: Some(lsp_types::Range::new(end, end)),
+            range_length: None,
+        }],
+    })?;
+    let diags = client.recv::<PublishDiagnostics>()?;
+    assert_eq!(diags.diagnostics.len(), 1);
+    let status = client.request::<Status>(())?;
+    assert_eq!(status["unloaded_files"], 0);
+
+    Ok(())
+}
+
 #[test]
 fn test_actions() -> spelgud::Result<()> {
     let mut client = TestClient::new()?;
@@ -394,6 +665,427 @@ fn test_actions() -> spelgud::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_split_action() -> spelgud::Result<()> {
+    let mut client = TestClient::new()?;
+    let uri = Url::parse("untitled:Untitled-1")?;
+    client.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+        text_document: TextDocumentItem {
+            uri: uri.clone(),
+            language_id: "plaintext".into(),
+            version: 0,
+            text: "Thanks alot.".into(),
+        },
+    })?;
+    let diags = client.recv::<PublishDiagnostics>()?;
+    let diag = diags.diagnostics.first().unwrap().clone();
+    assert_eq!(diag.message, "alot");
+
+    let actions = client
+        .request::<CodeActionRequest>(CodeActionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            range: diag.range,
+            context: CodeActionContext {
+                diagnostics: vec![diag.clone()],
+                only: None,
+                trigger_kind: None,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        })?
+        .expect("no actions");
+    // Offered once, whether or not the checker suggests it too.
+    let splits: Vec<&CodeAction> = actions
+        .iter()
+        .filter_map(|action| match action {
+            CodeActionOrCommand::CodeAction(a) => Some(a),
+            CodeActionOrCommand::Command(_) => None,
+        })
+        .filter(|a| {
+            let edits = a.edit.as_ref().and_then(|edit| edit.changes.as_ref());
+            edits.is_some_and(|changes| changes[&uri][0].new_text == "a lot")
+        })
+        .collect();
+    assert_eq!(splits.len(), 1, "{actions:?}");
+    assert!(
+        ["Change alot to a lot", "Split into 'a lot'"].contains(&splits[0].title.as_str()),
+        "{splits:?}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_multibyte_columns() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let mut client = TestClient::new_with_root(&tmp)?;
+    let path = tmp.path().join("notes.txt");
+    std::fs::write(&path, "Smart “quotes” 😀 and teh end.\n")?;
+    let uri = Url::from_file_path(&path).unwrap();
+    let diags = client.open(uri.clone())?;
+    let teh = diags
+        .diagnostics
+        .iter()
+        .find(|diag| diag.message == "teh")
+        .ok_or(format!("No teh in {diags:?}"))?;
+    // Columns count UTF-8 bytes, as advertised: "teh" is the 22nd character, but the
+    // quotes and emoji before it take more than one byte each.
+    let expected = Range::new(Position::new(0, 28), Position::new(0, 31));
+    assert_eq!(teh.range, expected);
+
+    let resp = client.request::<Completion>(completion_params(uri, Position::new(0, 29)))?;
+    let Some(lsp_types::CompletionResponse::Array(items)) = resp else {
+        panic!("Unexpected completion response {resp:?}");
+    };
+    let Some(lsp_types::CompletionTextEdit::Edit(edit)) = &items[0].text_edit else {
+        panic!("Unexpected completion {:?}", items[0]);
+    };
+    assert_eq!(edit.range, expected);
+    Ok(())
+}
+
+#[test]
+fn test_style_rules() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let options = serde_json::json!({"rules": [{
+        "name": "Utilize",
+        "text": "utilize",
+        "message": "Prefer 'use' to '$0'",
+        "replace": ["use"],
+    }]});
+    let mut client = TestClient::new_with_options(&tmp, Some(options))?;
+    let uri = Url::parse("untitled:Untitled-1")?;
+    client.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+        text_document: TextDocumentItem {
+            uri: uri.clone(),
+            language_id: "plaintext".into(),
+            version: 0,
+            text: "We utilize teh tools.".into(),
+        },
+    })?;
+    let diags = client.recv::<PublishDiagnostics>()?;
+    let style = diags
+        .diagnostics
+        .iter()
+        .find(|diag| diag.source.as_deref() == Some("style"))
+        .expect("no style diagnostic")
+        .clone();
+    assert_eq!(style.message, "Prefer 'use' to 'utilize'");
+    assert_eq!(
+        style.code,
+        Some(lsp_types::NumberOrString::String("Utilize".into()))
+    );
+    assert_eq!(
+        style.range,
+        lsp_types::Range::new(
+            lsp_types::Position::new(0, 3),
+            lsp_types::Position::new(0, 10)
+        )
+    );
+    // Spelling is still checked alongside the rules.
+    assert!(diags.diagnostics.iter().any(|diag| diag.message == "teh"));
+
+    let actions = client
+        .request::<CodeActionRequest>(CodeActionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            range: style.range,
+            context: CodeActionContext {
+                diagnostics: vec![style],
+                only: None,
+                trigger_kind: None,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        })?
+        .expect("no actions");
+    let titles: Vec<&str> = actions
+        .iter()
+        .map(|action| match action {
+            CodeActionOrCommand::CodeAction(a) => a.title.as_str(),
+            CodeActionOrCommand::Command(c) => c.title.as_str(),
+        })
+        .collect();
+    assert_eq!(titles, ["Change utilize to use"]);
+    Ok(())
+}
+
+#[test]
+fn test_typography() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let options = serde_json::json!({"typography": "smart"});
+    let client = TestClient::new_with_options(&tmp, Some(options))?;
+    let typography = |diags: PublishDiagnosticsParams| -> Vec<String> {
+        diags
+            .diagnostics
+            .into_iter()
+            .filter(|diag| diag.source.as_deref() == Some("typography"))
+            .map(|diag| diag.message)
+            .collect()
+    };
+
+    let prose = tmp.path().join("notes.md");
+    std::fs::write(&prose, "Wait... `a...b` is code\n")?;
+    let diags = client.open(Url::from_file_path(&prose).unwrap())?;
+    assert_eq!(typography(diags), ["Use … instead of ..."]);
+
+    // Comments in source code are spell checked, but their punctuation is left alone.
+    let code = tmp.path().join("main.rs");
+    std::fs::write(&code, "// Wait...\nfn main() {}\n")?;
+    let diags = client.open(Url::from_file_path(&code).unwrap())?;
+    assert_eq!(typography(diags), Vec::<String>::new());
+    Ok(())
+}
+
+#[test]
+fn test_variants() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    std::fs::write(tmp.path().join("words.txt"), "colour\ncentre\n")?;
+    let options = serde_json::json!({
+        "variant": "consistent",
+        "wordlists": [tmp.path().join("words.txt")],
+    });
+    let client = TestClient::new_with_options(&tmp, Some(options))?;
+    let path = tmp.path().join("notes.txt");
+    std::fs::write(&path, "The colour of the centre.\nColor it.\n")?;
+    let diags = client.open(Url::from_file_path(&path).unwrap())?;
+    let variants: Vec<(lsp_types::Range, &str)> = diags
+        .diagnostics
+        .iter()
+        .filter(|diag| diag.code == Some(lsp_types::NumberOrString::String("variant".into())))
+        .map(|diag| (diag.range, diag.message.as_str()))
+        .collect();
+    assert_eq!(
+        variants,
+        [(
+            lsp_types::Range::new(
+                lsp_types::Position::new(1, 0),
+                lsp_types::Position::new(1, 5)
+            ),
+            "Color is American spelling, but this document uses British spelling"
+        )]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_dictionaries() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    std::fs::write(
+        tmp.path().join(".spelgud.toml"),
+        "dictionaries = [\"dicts/custom\"]\n",
+    )?;
+    std::fs::create_dir(tmp.path().join("dicts"))?;
+    std::fs::write(
+        tmp.path().join("dicts/custom.aff"),
+        "SET UTF-8\nSFX S Y 1\nSFX S 0 s .\n",
+    )?;
+    std::fs::write(
+        tmp.path().join("dicts/custom.dic"),
+        "2\nacetaminophen/S\nibuprofen\n",
+    )?;
+    let client = TestClient::new_with_root(&tmp)?;
+    let path = tmp.path().join("notes.txt");
+    std::fs::write(&path, "Take acetaminophens or ibuprofin.\n")?;
+    let diags = client.open(Url::from_file_path(&path).unwrap())?;
+    let found: Vec<u32> = diags
+        .diagnostics
+        .iter()
+        .map(|diag| diag.range.start.character)
+        .collect();
+    // Only "ibuprofin" is reported, as "acetaminophens" is in the project's dictionary.
+    assert_eq!(found, [23]);
+    Ok(())
+}
+
+#[test]
+fn test_glossary() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    std::fs::write(
+        tmp.path().join(".spelgud.toml"),
+        "glossary_files = [\"terms.csv\"]\n\n[glossary]\n\"e-mail\" = \"email\"\n",
+    )?;
+    std::fs::write(tmp.path().join("terms.csv"), "repo,repository\n")?;
+    let client = TestClient::new_with_root(&tmp)?;
+    let path = tmp.path().join("notes.txt");
+    std::fs::write(&path, "Send an e-mail about the repo.\n")?;
+    let diags = client.open(Url::from_file_path(&path).unwrap())?;
+    let glossary: Vec<(u32, &str)> = diags
+        .diagnostics
+        .iter()
+        .filter(|diag| diag.code == Some(lsp_types::NumberOrString::String("glossary".into())))
+        .map(|diag| (diag.range.start.character, diag.message.as_str()))
+        .collect();
+    assert_eq!(
+        glossary,
+        [
+            (8, "Use 'email' instead of 'e-mail'"),
+            (25, "Use 'repository' instead of 'repo'"),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_proper_nouns() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let options = serde_json::json!({"proper_nouns": ["JavaScript", "macOS"]});
+    let client = TestClient::new_with_options(&tmp, Some(options))?;
+    let path = tmp.path().join("notes.txt");
+    std::fs::write(&path, "Write Javascript on macOS.\n")?;
+    let diags = client.open(Url::from_file_path(&path).unwrap())?;
+    // Names are accepted by the spell check in any case, so only the wrong case is reported.
+    let found: Vec<(u32, &str)> = diags
+        .diagnostics
+        .iter()
+        .map(|diag| (diag.range.start.character, diag.message.as_str()))
+        .collect();
+    assert_eq!(found, [(6, "Write 'Javascript' as 'JavaScript'")]);
+    Ok(())
+}
+
+#[test]
+fn test_forbidden() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let options = serde_json::json!({"forbidden": {"placeholder": "Replace the placeholder"}});
+    let client = TestClient::new_with_options(&tmp, Some(options))?;
+    let path = tmp.path().join("notes.txt");
+    std::fs::write(&path, "This is placeholder text.\n")?;
+    let diags = client.open(Url::from_file_path(&path).unwrap())?;
+    let found: Vec<(u32, &str, Option<lsp_types::DiagnosticSeverity>)> = diags
+        .diagnostics
+        .iter()
+        .map(|diag| {
+            (
+                diag.range.start.character,
+                diag.message.as_str(),
+                diag.severity,
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [(
+            8,
+            "Replace the placeholder",
+            Some(lsp_types::DiagnosticSeverity::ERROR)
+        )]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_inclusive() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let options = serde_json::json!({
+        "inclusive": {"enabled": true, "disabled": ["sanity check"]},
+    });
+    let client = TestClient::new_with_options(&tmp, Some(options))?;
+    let path = tmp.path().join("notes.txt");
+    std::fs::write(&path, "Do a sanity check of the whitelist.\n")?;
+    let diags = client.open(Url::from_file_path(&path).unwrap())?;
+    let found: Vec<(u32, &str)> = diags
+        .diagnostics
+        .iter()
+        .filter(|diag| diag.code == Some(lsp_types::NumberOrString::String("inclusive".into())))
+        .map(|diag| (diag.range.start.character, diag.message.as_str()))
+        .collect();
+    assert_eq!(found, [(25, "Consider 'allowlist' instead of 'whitelist'")]);
+    Ok(())
+}
+
+#[test]
+fn test_fix_on_save() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let capabilities = lsp_types::ClientCapabilities {
+        workspace: Some(lsp_types::WorkspaceClientCapabilities {
+            apply_edit: Some(true),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let options = serde_json::json!({"fix_on_save": true});
+    let client = TestClient::new_with_capabilities(&tmp, Some(options), capabilities)?;
+    let uri = Url::parse("untitled:Untitled-1")?;
+    client.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+        text_document: TextDocumentItem {
+            uri: uri.clone(),
+            language_id: "plaintext".into(),
+            version: 0,
+            text: "Keep them\nseperate, not teh same.\n".into(),
+        },
+    })?;
+    client.recv::<PublishDiagnostics>()?;
+    client.notify::<DidSaveTextDocument>(DidSaveTextDocumentParams {
+        text_document: TextDocumentIdentifier { uri: uri.clone() },
+        text: None,
+    })?;
+
+    let req = loop {
+        match client
+            .conn
+            .receiver
+            .recv_timeout(std::time::Duration::from_secs(5))?
+        {
+            Message::Request(req) => break req,
+            _ => continue,
+        }
+    };
+    assert_eq!(req.method, "workspace/applyEdit");
+    let params: lsp_types::ApplyWorkspaceEditParams = serde_json::from_value(req.params)?;
+    assert_eq!(
+        params.label.as_deref(),
+        Some("Fix typos: seperate -> separate, teh -> the")
+    );
+    let changes = params.edit.changes.unwrap();
+    assert_eq!(
+        changes[&uri],
+        [
+            TextEdit {
+                range: Range::new(Position::new(1, 0), Position::new(1, 8)),
+                new_text: "separate".into(),
+            },
+            TextEdit {
+                range: Range::new(Position::new(1, 14), Position::new(1, 17)),
+                new_text: "the".into(),
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_readability_lenses() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let path = tmp.path().join("guide.md");
+    let uri = Url::from_file_path(&path).unwrap();
+    std::fs::write(
+        &path,
+        "# Intro\n\nThe cat sat. It was happy.\n\n```sh\n# not a heading\n```\n\n## Detail\n\nConsiderable complexity necessitates comprehensive documentation.\n",
+    )?;
+    let options = serde_json::json!({"readability": true});
+    let mut client = TestClient::new_with_options(&tmp, Some(options))?;
+    client.open(uri.clone())?;
+
+    let lenses = client
+        .request::<lsp_types::request::CodeLensRequest>(lsp_types::CodeLensParams {
+            text_document: TextDocumentIdentifier { uri },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        })?
+        .expect("no lenses");
+    let lenses: Vec<(u32, String)> = lenses
+        .into_iter()
+        .map(|lens| (lens.range.start.line, lens.command.unwrap().title))
+        .collect();
+    assert_eq!(
+        lenses,
+        [
+            (0, "Grade -0.7, reading ease 105 (6 words)".into()),
+            (8, "Grade 40.6, reading ease -187 (5 words)".into()),
+        ]
+    );
+    Ok(())
+}
+
 #[test]
 fn test_document_symbols() -> spelgud::Result<()> {
     let mut client = TestClient::new()?;
@@ -500,19 +1192,271 @@ fn test_complete() -> spelgud::Result<()> {
     let resp = client.request::<Completion>(completion_params(
         example_uri(),
         Position {
-            line: 2,
-            character: 0,
+            line: 1,
+            character: 6,
         },
     ))?;
 
-    // TODO
-    return Ok(());
-
     let Some(lsp_types::CompletionResponse::Array(actual)) = resp else {
         panic!("Unexpected completion response {resp:?}");
     };
+    let labels: Vec<&str> = actual.iter().map(|item| item.label.as_str()).collect();
+    // Common words come first, if their frequencies are known.
+    if cfg!(feature = "bundled-frequencies") {
+        assert_eq!(labels[0], "quick", "{labels:?}");
+    }
+    let quick = actual
+        .iter()
+        .find(|item| item.label == "quick")
+        .ok_or(format!("No quick in {labels:?}"))?;
+    let Some(lsp_types::CompletionTextEdit::Edit(edit)) = &quick.text_edit else {
+        panic!("Unexpected completion {quick:?}");
+    };
+    assert_eq!(
+        edit.range,
+        Range::new(Position::new(1, 4), Position::new(1, 8))
+    );
+
+    // Correctly spelled words have no completions.
+    let resp = client.request::<Completion>(completion_params(
+        example_uri(),
+        Position {
+            line: 0,
+            character: 6,
+        },
+    ))?;
+    assert_eq!(resp, None);
+
+    Ok(())
+}
 
-    assert_elements_equal(actual, vec![], |s| s.label.clone());
+enum Status {}
 
+impl lsp_types::request::Request for Status {
+    type Params = ();
+    type Result = serde_json::Value;
+    const METHOD: &'static str = "spelgud/status";
+}
+
+#[test]
+fn test_status() -> spelgud::Result<()> {
+    let mut client = TestClient::new()?;
+
+    let status = client.request::<Status>(())?;
+    assert_eq!(status["open_files"], 0);
+    assert_eq!(status["session_words"], 0);
+    assert!(status["last_check_ms"].is_null());
+
+    client.open(example_uri())?;
+    let status = client.request::<Status>(())?;
+    assert_eq!(status["open_files"], 1);
+    assert!(status["last_check_ms"].is_u64());
+    let checkers = status["checkers"].as_array().unwrap();
+    assert_eq!(checkers[0]["name"], "codespell");
+    assert!(checkers.len() > 1);
+    Ok(())
+}
+
+enum Statistics {}
+
+impl lsp_types::request::Request for Statistics {
+    type Params = ();
+    type Result = serde_json::Value;
+    const METHOD: &'static str = "spelgud/statistics";
+}
+
+#[test]
+fn test_statistics() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let good = tmp.path().join("good.txt");
+    let bad = tmp.path().join("bad.txt");
+    let mut client = TestClient::new_with_options(&tmp, None)?;
+
+    std::fs::write(&good, "This is fine.")?;
+    std::fs::write(&bad, "Thiss duz not work. It duz not.")?;
+    client.open(Url::from_file_path(&good).unwrap())?;
+    client.open(Url::from_file_path(&bad).unwrap())?;
+
+    let stats = client.request::<Statistics>(())?;
+    assert_eq!(stats["words"], 10);
+    assert_eq!(stats["misspellings"], 3);
+    assert_eq!(stats["unique_misspellings"], 2);
+
+    // The file with the most misspellings comes first.
+    let files = stats["files"].as_array().unwrap();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0]["uri"], Url::from_file_path(&bad).unwrap().as_str());
+    assert_eq!(files[0]["words"], 7);
+    assert_eq!(files[0]["misspellings"], 3);
+    assert_eq!(files[1]["misspellings"], 0);
+    Ok(())
+}
+
+#[test]
+fn test_typos() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let first = tmp.path().join("first.txt");
+    let second = tmp.path().join("second.txt");
+    let mut client = TestClient::new_with_options(&tmp, None)?;
+
+    std::fs::write(&first, "Thiss duz not work. It duz not.")?;
+    std::fs::write(&second, "It still duz not work.")?;
+    client.open(Url::from_file_path(&first).unwrap())?;
+    client.open(Url::from_file_path(&second).unwrap())?;
+
+    let typos =
+        client.request::<lsp_types::request::ExecuteCommand>(lsp_types::ExecuteCommandParams {
+            command: "spelgud.typos".into(),
+            arguments: vec![serde_json::json!(1)],
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        })?;
+    let typos = typos.unwrap();
+    let typos = typos.as_array().unwrap();
+    assert_eq!(typos.len(), 1);
+    assert_eq!(typos[0]["word"], "duz");
+    assert_eq!(typos[0]["count"], 3);
+    assert_eq!(typos[0]["files"], 2);
+    assert!(typos[0]["fixes"].is_array());
+    Ok(())
+}
+
+#[test]
+fn test_untitled() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let options = serde_json::json!({"evict_after_secs": 1});
+    let mut client = TestClient::new_with_options(&tmp, Some(options))?;
+
+    // Buffers that aren't saved to disk are checked like any other.
+    let uri = Url::parse("untitled:Untitled-1")?;
+    client.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+        text_document: TextDocumentItem {
+            uri: uri.clone(),
+            language_id: "markdown".into(),
+            version: 0,
+            text: "This duz not work.".into(),
+        },
+    })?;
+    let diags = client.recv::<PublishDiagnostics>()?;
+    assert_eq!(diags.uri, uri);
+    let diag = diags.diagnostics.first().unwrap().clone();
+    assert_eq!(diag.message, "duz");
+
+    let actions = client
+        .request::<CodeActionRequest>(CodeActionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            range: diag.range,
+            context: CodeActionContext {
+                diagnostics: vec![diag.clone()],
+                only: None,
+                trigger_kind: None,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        })?
+        .expect("no actions");
+    assert!(!actions.is_empty());
+
+    // They are never unloaded, as they can't be reloaded from disk.
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    client.notify::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+        text_document: lsp_types::VersionedTextDocumentIdentifier {
+            uri: uri.clone(),
+            version: 1,
+        },
+        content_changes: vec![TextDocumentContentChangeEvent {
+            text: "does".into(),
+            range: Some(diag.range),
+            range_length: None,
+        }],
+    })?;
+    client.notify::<DidSaveTextDocument>(DidSaveTextDocumentParams {
+        text_document: TextDocumentIdentifier { uri: uri.clone() },
+        text: None,
+    })?;
+    let diags = client.recv::<PublishDiagnostics>()?;
+    assert_eq!(diags.uri, uri);
+    assert!(diags.diagnostics.is_empty());
+
+    let status = client.request::<Status>(())?;
+    assert_eq!(status["unloaded_files"], 0);
+    Ok(())
+}
+
+#[test]
+fn test_uri_schemes() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let root = std::fs::canonicalize(tmp.path())?;
+    std::fs::write(root.join(".spelgudignore"), "*.ipynb\n")?;
+    std::fs::write(root.join("notebook.ipynb"), "{}")?;
+    let client = TestClient::new_with_options(&tmp, None)?;
+
+    // Buffers that aren't local files are checked as they are in the editor, even if
+    // their path looks like that of a local file.
+    let cell = format!(
+        "vscode-notebook-cell:{}#W0sZmlsZQ",
+        root.join("notebook.ipynb").display()
+    );
+    for (uri, language_id) in [
+        (cell.as_str(), ""),
+        ("vscode-remote://ssh-remote+host/home/notes.md", "markdown"),
+    ] {
+        let uri = Url::parse(uri)?;
+        client.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: language_id.into(),
+                version: 0,
+                text: "This duz not work.".into(),
+            },
+        })?;
+        let diags = client.recv::<PublishDiagnostics>()?;
+        assert_eq!(diags.uri, uri);
+        assert_eq!(lines(diags), [0]);
+    }
+    Ok(())
+}
+
+// A checker for a project's terminology, which only knows one misspelling.
+struct Terms;
+
+impl spelgud::backend::Checker for Terms {
+    fn check(&mut self, text: &str) -> Result<Vec<spelgud::backend::Misspelling>> {
+        Ok(text
+            .match_indices("colour")
+            .map(|(offset, word)| spelgud::backend::Misspelling {
+                offset: text[..offset].chars().count(),
+                word: word.into(),
+                fixes: Some(vec!["color".into()]),
+            })
+            .collect())
+    }
+
+    fn suggest(&mut self, _word: &str) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    fn name(&self) -> String {
+        "terms".into()
+    }
+}
+
+#[test]
+fn test_run_with_checker() -> spelgud::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let client = TestClient::new_with_checker(&tmp, Box::new(Terms))?;
+    let uri = Url::parse("untitled:Untitled-1")?;
+    client.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+        text_document: TextDocumentItem {
+            uri: uri.clone(),
+            language_id: "plaintext".into(),
+            version: 0,
+            text: "Its colour is fine, but its colur isn't.".into(),
+        },
+    })?;
+    let diags = client.recv::<PublishDiagnostics>()?.diagnostics;
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].message, "colour");
+    assert_eq!(diags[0].source.as_deref(), Some("terms"));
+    assert_eq!(diags[0].range.start, Position::new(0, 4));
     Ok(())
 }